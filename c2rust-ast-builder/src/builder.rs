@@ -325,6 +325,11 @@ impl Builder {
         self.constness(Constness::Const)
     }
 
+    pub fn generic_over(mut self, param: GenericParam) -> Self {
+        self.generics.params.push(param);
+        self
+    }
+
     pub fn abi<A: Make<Abi>>(self, abi: A) -> Self {
         let abi = abi.make(&self);
         Builder {
@@ -1007,6 +1012,17 @@ impl Builder {
         })
     }
 
+    pub fn tuple_struct_pat<Pa, Pb>(self, path: Pa, pats: Vec<Pb>) -> P<Pat>
+        where Pa: Make<Path>, Pb: Make<P<Pat>> {
+        let path = path.make(&self);
+        let pats: Vec<P<Pat>> = pats.into_iter().map(|x| x.make(&self)).collect();
+        P(Pat {
+            id: self.id,
+            node: PatKind::TupleStruct(path, pats, None),
+            span: self.span,
+        })
+    }
+
     pub fn qpath_pat<Pa>(self, qself: Option<QSelf>, path: Pa) -> P<Pat>
         where Pa: Make<Path> {
         let path = path.make(&self);
@@ -1035,6 +1051,21 @@ impl Builder {
         })
     }
 
+    pub fn range_pat<L, H>(self, lo: L, hi: H) -> P<Pat>
+        where L: Make<P<Expr>>, H: Make<P<Expr>> {
+        let lo = lo.make(&self);
+        let hi = hi.make(&self);
+        let end = Spanned {
+            node: RangeEnd::Included(RangeSyntax::DotDotEq),
+            span: self.span,
+        };
+        P(Pat {
+            id: self.id,
+            node: PatKind::Range(lo, hi, end),
+            span: self.span,
+        })
+    }
+
     pub fn mac_pat<M>(self, mac: M) -> P<Pat>
         where M: Make<Mac> {
         let mac = mac.make(&self);
@@ -1312,6 +1343,14 @@ impl Builder {
                                     self.generics))
     }
 
+    pub fn tuple_struct_item<I>(self, name: I, fields: Vec<StructField>) -> P<Item>
+        where I: Make<Ident> {
+        let name = name.make(&self);
+        Self::item(name, self.attrs, self.vis, self.span, self.id,
+                   ItemKind::Struct(VariantData::Tuple(fields, DUMMY_NODE_ID),
+                                    self.generics))
+    }
+
     pub fn union_item<I>(self, name: I, fields: Vec<StructField>) -> P<Item>
         where I: Make<Ident> {
         let name = name.make(&self);
@@ -1403,6 +1442,24 @@ impl Builder {
                                   items))
     }
 
+    pub fn trait_impl_item<Pa, T>(self, trait_: Pa, ty: T, items: Vec<ImplItem>) -> P<Item>
+        where Pa: Make<Path>, T: Make<P<Ty>>
+    {
+        let trait_ref = TraitRef {
+            path: trait_.make(&self),
+            ref_id: DUMMY_NODE_ID,
+        };
+        let ty = ty.make(&self);
+        Self::item(keywords::Invalid.ident(), self.attrs, self.vis, self.span, self.id,
+                   ItemKind::Impl(self.unsafety,
+                                  ImplPolarity::Positive,
+                                  Defaultness::Final,
+                                  self.generics,
+                                  Some(trait_ref),
+                                  ty,
+                                  items))
+    }
+
     pub fn extern_crate_item<I>(self, name: I, rename: Option<I>) -> P<Item>
         where I: Make<Ident>
     {
@@ -1463,6 +1520,22 @@ impl Builder {
         ImplItem { id, ident, vis, defaultness, attrs, generics, node, span, tokens: None }
     }
 
+    pub fn fn_impl_item<I, D, B>(self, name: I, decl: D, block: B) -> ImplItem
+        where I: Make<Ident>, D: Make<P<FnDecl>>, B: Make<P<Block>> {
+        let name = name.make(&self);
+        let decl = decl.make(&self);
+        let block = block.make(&self);
+        let header = FnHeader {
+            unsafety: self.unsafety,
+            asyncness: IsAsync::NotAsync,
+            constness: dummy_spanned(self.constness),
+            abi: self.abi,
+        };
+        let sig = MethodSig { header, decl };
+        Self::impl_item_(name, self.attrs, self.vis, Defaultness::Final,
+                         self.generics, self.span, self.id, ImplItemKind::Method(sig, block))
+    }
+
     pub fn mac_impl_item<M>(self, mac: M) -> ImplItem
         where M: Make<Mac> {
         let mac = mac.make(&self);
@@ -1628,6 +1701,34 @@ impl Builder {
         }
     }
 
+    pub fn lt_param<L>(self, lifetime: L) -> GenericParam
+        where L: Make<Lifetime> {
+        let lifetime: Lifetime = lifetime.make(&self);
+        GenericParam {
+            attrs: self.attrs.into(),
+            ident: lifetime.ident,
+            id: self.id,
+            bounds: vec![],
+            kind: GenericParamKind::Lifetime,
+        }
+    }
+
+    pub fn bounded_ty_param<I, Pa>(self, ident: I, bounds: Vec<Pa>) -> GenericParam
+        where I: Make<Ident>, Pa: Make<Path> {
+        let ident = ident.make(&self);
+        let bounds = bounds.into_iter().map(|bound| {
+            let trait_ref = PolyTraitRef::new(vec![], bound.make(&self), self.span);
+            GenericBound::Trait(trait_ref, TraitBoundModifier::None)
+        }).collect();
+        GenericParam {
+            attrs: self.attrs.into(),
+            ident: ident,
+            id: self.id,
+            bounds: bounds,
+            kind: GenericParamKind::Type { default: None },
+        }
+    }
+
     pub fn ty<T>(self, node: TyKind) -> Ty {
         Ty {
             id: self.id,
@@ -1678,6 +1779,12 @@ impl Builder {
         dummy_spanned(kind)
     }
 
+    // Take the current internal list of outer attributes, e.g. to add them to
+    // an item that has already been built
+    pub fn into_attrs(self) -> Vec<Attribute> {
+        self.attrs
+    }
+
     // Convert the current internal list of outer attributes
     // into a vector of inner attributes, e.g.:
     // `#[foo]` => `#![foo]`
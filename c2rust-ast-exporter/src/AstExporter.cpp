@@ -52,6 +52,89 @@ namespace {
         cbor_encode_text_string(encoder, ptr, len);
     }
 
+    // Encode the attributes of a function or variable declaration that affect how it is linked
+    // as an array of [name, argument] pairs. The argument is null for attributes without one.
+    void cbor_encode_decl_attrs(CborEncoder *encoder, const Decl *D) {
+        std::vector<std::pair<std::string, std::string>> attrs;
+        std::vector<bool> has_arg;
+
+        if (auto A = D->getAttr<SectionAttr>()) {
+            attrs.emplace_back("section", A->getName().str());
+            has_arg.push_back(true);
+        }
+        if (D->hasAttr<WeakAttr>()) {
+            attrs.emplace_back("weak", "");
+            has_arg.push_back(false);
+        }
+        if (auto A = D->getAttr<AliasAttr>()) {
+            attrs.emplace_back("alias", A->getAliasee().str());
+            has_arg.push_back(true);
+        }
+        if (auto A = D->getAttr<ConstructorAttr>()) {
+            attrs.emplace_back("constructor", std::to_string(A->getPriority()));
+            has_arg.push_back(true);
+        }
+        if (auto A = D->getAttr<DestructorAttr>()) {
+            attrs.emplace_back("destructor", std::to_string(A->getPriority()));
+            has_arg.push_back(true);
+        }
+        if (auto A = D->getAttr<VisibilityAttr>()) {
+            const char *visibility = "default";
+            switch (A->getVisibility()) {
+                case VisibilityAttr::Default: visibility = "default"; break;
+                case VisibilityAttr::Hidden: visibility = "hidden"; break;
+                case VisibilityAttr::Protected: visibility = "protected"; break;
+            }
+            attrs.emplace_back("visibility", visibility);
+            has_arg.push_back(true);
+        }
+        // `__block` storage
+        if (D->hasAttr<BlocksAttr>()) {
+            attrs.emplace_back("blocks", "");
+            has_arg.push_back(false);
+        }
+        // C11 `_Noreturn` only shows up on the declaration, not in the function type
+        auto FD = dyn_cast<FunctionDecl>(D);
+        if (FD && FD->isNoReturn()) {
+            attrs.emplace_back("noreturn", "");
+            has_arg.push_back(false);
+        }
+        // Indices of the parameters declared `nonnull`, either on the function or on the
+        // parameter itself. A `nonnull` without arguments covers every parameter.
+        if (FD) {
+            std::string nonnull;
+            for (unsigned i = 0; i < FD->getNumParams(); i++) {
+                bool is_nonnull = FD->getParamDecl(i)->hasAttr<NonNullAttr>();
+                for (auto A : FD->specific_attrs<NonNullAttr>()) {
+                    is_nonnull = is_nonnull || A->isNonNull(i);
+                }
+                if (is_nonnull) {
+                    if (!nonnull.empty()) nonnull += ",";
+                    nonnull += std::to_string(i);
+                }
+            }
+            if (!nonnull.empty()) {
+                attrs.emplace_back("nonnull", nonnull);
+                has_arg.push_back(true);
+            }
+        }
+
+        CborEncoder array;
+        cbor_encoder_create_array(encoder, &array, attrs.size());
+        for (size_t i = 0; i < attrs.size(); i++) {
+            CborEncoder pair;
+            cbor_encoder_create_array(&array, &pair, 2);
+            cbor_encode_string(&pair, attrs[i].first);
+            if (has_arg[i]) {
+                cbor_encode_string(&pair, attrs[i].second);
+            } else {
+                cbor_encode_null(&pair);
+            }
+            cbor_encoder_close_container(&array, &pair);
+        }
+        cbor_encoder_close_container(encoder, &array);
+    }
+
     std::string make_realpath(std::string const &path) {
         if (auto abs_path = realpath(path.c_str(), nullptr)) {
             auto result = std::string(abs_path);
@@ -115,7 +198,11 @@ public:
 
         auto i = uintptr_t(s.Ty);
 
-        if (t.isConstQualified()) {
+        // Qualifiers on an array type apply to its elements (C11 6.7.3p9), so
+        // clang only reports them on the element type. Report arrays of const
+        // elements as const objects as well.
+        if (t.isConstQualified() ||
+            (t->isArrayType() && Context->getBaseElementType(t).isConstQualified())) {
           i |= 1;
         }
         if (t.isRestrictQualified()) {
@@ -577,7 +664,11 @@ class TranslateASTVisitor final
               childIds.push_back(x);
           }
 
-          encode_entry(CS, TagCompoundStmt, childIds);
+          // 8, 9, 10 - File number, line and column of the closing brace
+          encode_entry(CS, TagCompoundStmt, childIds,
+                       [this, CS](CborEncoder *array) {
+                           encodeSourcePos(array, CS->getRBracLoc());
+                       });
           return true;
       }
 
@@ -679,21 +770,42 @@ class TranslateASTVisitor final
           return true;
       }
 
+      /*
+       Describes a case statement, or a GNU case range (`case 1 ... 5:`)
+       Children: value, sub-statement, and the end of the range if there is one
+       Extras: value, and the end of the range if there is one
+       */
       bool VisitCaseStmt(CaseStmt *CS) {
           auto expr = CS->getLHS();
+          auto range_end = CS->getRHS();
 
           APSInt value;
           if (!expr->isIntegerConstantExpr(value, *Context)) {
               abort();
           }
 
+          APSInt end_value;
+          if (range_end && !range_end->isIntegerConstantExpr(end_value, *Context)) {
+              abort();
+          }
+
           std::vector<void*> childIds { expr, CS->getSubStmt() };
-          encode_entry(CS, TagCaseStmt, childIds, [value](CborEncoder *extra) {
+          if (range_end) {
+              childIds.push_back(range_end);
+          }
+
+          auto encode_value = [](CborEncoder *extra, const APSInt &value) {
               if (value.isSigned()) {
                   cbor_encode_int(extra, value.getSExtValue());
               } else {
                   cbor_encode_uint(extra, value.getZExtValue());
               }
+          };
+          encode_entry(CS, TagCaseStmt, childIds, [=](CborEncoder *extra) {
+              encode_value(extra, value);
+              if (range_end) {
+                  encode_value(extra, end_value);
+              }
           });
           return true;
       }
@@ -788,6 +900,24 @@ class TranslateASTVisitor final
           return true;
       }
 
+      /*
+       Describes a block literal (`^(int x) { ... }`)
+       Children: parameters, followed by the body
+       */
+      bool VisitBlockExpr(BlockExpr *E) {
+          auto block = E->getBlockDecl();
+
+          std::vector<void*> childIds;
+          for (auto x : block->parameters()) {
+              childIds.push_back(x);
+              TraverseDecl(x);
+          }
+          childIds.push_back(block->getBody());
+
+          encode_entry(E, TagBlockExpr, childIds);
+          return true;
+      }
+
       bool VisitOffsetOfExpr(OffsetOfExpr *E) {
           std::vector<void*> childIds;
 
@@ -1140,6 +1270,9 @@ class TranslateASTVisitor final
                                  auto bid = FD->getBuiltinID();
                                  cbor_encode_boolean(array,
                                          bid && !Context->BuiltinInfo.getHeaderName(bid));
+
+                                 // Attributes are inherited by later redeclarations
+                                 cbor_encode_decl_attrs(array, FD->getMostRecentDecl());
                              });
           typeEncoder.VisitQualType(functionType);
 
@@ -1192,6 +1325,9 @@ class TranslateASTVisitor final
                                  cbor_encode_boolean(array, is_extern);
 
                                  cbor_encode_boolean(array, is_defn);
+
+                                 // Attributes are inherited by later redeclarations
+                                 cbor_encode_decl_attrs(array, VD->getMostRecentDecl());
                              });
 
           typeEncoder.VisitQualType(T);
@@ -1403,6 +1539,24 @@ class TranslateASTVisitor final
           return true;
       }
 
+      bool VisitStaticAssertDecl(StaticAssertDecl *D)
+      {
+          std::vector<void*> childIds = { D->getAssertExpr() };
+
+          encode_entry(D, TagStaticAssertDecl, childIds, QualType(),
+          [D](CborEncoder *local){
+              // Wide messages can't be read back as UTF-8, so they are dropped
+              auto message = D->getMessage();
+              if (message && message->getCharByteWidth() == 1) {
+                  cbor_encode_string(local, message->getString().str());
+              } else {
+                  cbor_encode_null(local);
+              }
+          });
+
+          return true;
+      }
+
       //
       // Literals
       //
@@ -1464,9 +1618,18 @@ class TranslateASTVisitor final
 
                                 // String literals can contain arbitrary bytes, so
                                 // we encode these as byte strings rather than text.
-
-                                const uint8_t* bytes = reinterpret_cast<const uint8_t*>(SL->getBytes().data());
-                                cbor_encode_byte_string(array, bytes, SL->getByteLength());
+                                // Wide code units are written little-endian, whatever
+                                // the byte order of the host running clang.
+                                auto width = SL->getCharByteWidth();
+                                std::vector<uint8_t> bytes;
+                                bytes.reserve(SL->getByteLength());
+                                for (unsigned i = 0; i < SL->getLength(); i++) {
+                                    uint32_t unit = SL->getCodeUnit(i);
+                                    for (unsigned b = 0; b < width; b++) {
+                                        bytes.push_back((unit >> (8 * b)) & 0xff);
+                                    }
+                                }
+                                cbor_encode_byte_string(array, bytes.data(), bytes.size());
                              });
           return true;
       }
@@ -1626,10 +1789,16 @@ public:
             cbor_encoder_init(&encoder, buffer, len, 0);
 
             CborEncoder outer;
-            cbor_encoder_create_array(&encoder, &outer, 4);
+            cbor_encoder_create_array(&encoder, &outer, 6);
 
             CborEncoder array;
 
+            // 0. Encode the format header
+            cbor_encoder_create_array(&outer, &array, 2);
+            cbor_encode_text_stringz(&array, "c2rust-ast");
+            cbor_encode_uint(&array, AST_FORMAT_VERSION);
+            cbor_encoder_close_container(&outer, &array);
+
             // 1. Encode all of the reachable AST nodes and types
             cbor_encoder_create_array(&outer, &array, CborIndefiniteLength);
             TranslateASTVisitor visitor(&Context, &array, &sugared);
@@ -1669,6 +1838,16 @@ public:
             }
             cbor_encoder_close_container(&outer, &array);
 
+            // 5. Encode the target the translation unit was compiled for, so that the
+            // translator knows the sizes of target dependent types.
+            auto &target = Context.getTargetInfo();
+            cbor_encoder_create_array(&outer, &array, 4);
+            cbor_encode_string(&array, target.getTriple().str());
+            cbor_encode_uint(&array, target.getPointerWidth(0));
+            cbor_encode_uint(&array, target.getLongWidth());
+            cbor_encode_boolean(&array, Context.CharTy->isSignedIntegerType());
+            cbor_encoder_close_container(&outer, &array);
+
             cbor_encoder_close_container(&encoder, &outer);
         };
 
@@ -1781,31 +1960,32 @@ ExportResult *make_export_result(const Outputs &outputs) {
     return result;
 }
 
-// Extract clang AST for the source file specified in the argument vector.
-// Note: The arguments should only reference one source file at a time.
+// Extract clang ASTs for the source files specified in the argument vector.
+// All files are processed in a single run of the tool, and the output for
+// each file is keyed by its real path. Files that fail to parse have no
+// output.
 Outputs process(int argc, const char *argv[], int *result)
 {
-    static uint64_t source_path_count = 0;
+    static std::size_t source_path_count = 0;
     auto argv_ = augment_argv(argc, argv);
     int argc_ = argv_.size() - 1; // ignore the extra nullptr
     CommonOptionsParser OptionsParser(argc_, argv_.data(), MyToolCategory);
 
-    // the logic below assumes we're only translating one source file
-    assert(OptionsParser.getSourcePathList().size() - 1 == source_path_count++ &&
-        "Expected exactly one source path");
-
     // CommonOptionsParser is stateful so the vector returned by
     // getSourcePathList() includes paths from past invocations.
-    std::string sourcePath = OptionsParser.getSourcePathList().back();
-    // Make a new list with just the file we're currently translating
-    std::vector<std::string> sourcePathList(1, sourcePath);
+    auto const &allSourcePaths = OptionsParser.getSourcePathList();
+    assert(allSourcePaths.size() > source_path_count &&
+        "Expected at least one source path");
+    // Make a new list with just the files we're currently translating
+    std::vector<std::string> sourcePathList(
+        allSourcePaths.begin() + source_path_count, allSourcePaths.end());
+    source_path_count = allSourcePaths.size();
     ClangTool Tool(OptionsParser.getCompilations(), sourcePathList);
 
     Outputs outputs;
     MyFrontendActionFactory myFrontendActionFactory(&outputs);
 
     *result = Tool.run(&myFrontendActionFactory);
-    assert(outputs.size() == 1 && "Expected exactly one output.");
     return outputs;
 }
 
@@ -9,6 +9,11 @@
 #ifndef ast_tags_h
 #define ast_tags_h
 
+// Version of the CBOR format shared by the exporter and `clang_ast::process`. Exported ASTs start
+// with a ["c2rust-ast", AST_FORMAT_VERSION] header. Bump the version whenever tags are added or
+// removed or the layout of the exported entries changes.
+#define AST_FORMAT_VERSION 1
+
 enum ASTEntryTag {
     TagFunctionDecl = 0,
     TagParmVarDecl,
@@ -20,6 +25,7 @@ enum ASTEntryTag {
     TagEnumConstantDecl,
     TagTypedefDecl,
     TagUnionDecl,
+    TagStaticAssertDecl,
     
     
     TagCompoundStmt = 100,
@@ -69,6 +75,7 @@ enum ASTEntryTag {
     
     TagConvertVectorExpr,
     TagDesignatedInitExpr,
+    TagBlockExpr,
     
     TagIntegerLiteral = 300,
     TagStringLiteral,
@@ -2,8 +2,7 @@ use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use serde_bytes::ByteBuf;
 use serde_cbor::{Value, from_value};
-use serde_cbor::error;
-use std;
+use schema::{self, SchemaError};
 
 include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
 
@@ -52,12 +51,22 @@ impl TypeNode {
     pub const VOLATILE_MASK: u64 = 0b100;
 }
 
+/// The target a translation unit was compiled for
+#[derive(Debug, Clone, Default)]
+pub struct TargetInfo {
+    pub triple: String,
+    pub pointer_width: u64,
+    pub long_width: u64,
+    pub char_is_signed: bool,
+}
+
 #[derive(Debug, Clone)]
 pub struct AstContext {
     pub ast_nodes: HashMap<u64, AstNode>,
     pub type_nodes: HashMap<u64, TypeNode>,
     pub top_nodes: Vec<u64>,
     pub comments: Vec<CommentNode>,
+    pub target: TargetInfo,
 }
 
 pub fn expect_opt_str(val: &Value) -> Option<Option<&str>> {
@@ -76,30 +85,29 @@ pub fn expect_opt_u64(val: &Value) -> Option<Option<u64>> {
     }
 }
 
-fn import_ast_tag(tag: u64) -> ASTEntryTag {
-    unsafe {
-        return std::mem::transmute::<u32, ASTEntryTag>(tag as u32);
-    }
-}
-
-fn import_type_tag(tag: u64) -> TypeTag {
-    unsafe {
-        return std::mem::transmute::<u32, TypeTag>(tag as u32);
-    }
-}
-
-pub fn process(items: Value) -> error::Result<AstContext> {
+/// Import the nodes of an exported AST, checking that it follows the exporter's format first
+pub fn process(items: Value) -> Result<AstContext, SchemaError> {
+    schema::validate(&items)?;
 
     let mut asts: HashMap<u64, AstNode> = HashMap::new();
     let mut types: HashMap<u64, TypeNode> = HashMap::new();
     let mut comments: Vec<CommentNode> = vec![];
 
-    let (all_nodes, top_nodes, file_paths, raw_comments):
-        (Vec<Vec<Value>>,
+    let (_header, all_nodes, top_nodes, file_paths, raw_comments, raw_target):
+        (Value,
+         Vec<Vec<Value>>,
          Vec<u64>,
          Vec<String>,
          Vec<(u64, u64, u64, ByteBuf)>,
-        ) = from_value(items)?;
+         (String, u64, u64, bool),
+        ) = from_value(items).map_err(|e| SchemaError {
+            node_id: None,
+            tag: None,
+            message: format!("{}", e),
+        })?;
+
+    let (triple, pointer_width, long_width, char_is_signed) = raw_target;
+    let target = TargetInfo { triple, pointer_width, long_width, char_is_signed };
 
     for (fileid, line, column, bytes) in raw_comments {
         comments.push(CommentNode{
@@ -114,7 +122,8 @@ pub fn process(items: Value) -> error::Result<AstContext> {
         let entry_id = entry[0].as_u64().unwrap();
         let tag = entry[1].as_u64().unwrap();
 
-        if tag < 400 {
+        // The layout of the entries was validated above
+        if let Some(ast_tag) = schema::ast_tag(tag) {
 
             let children =
                 entry[2].as_array().unwrap()
@@ -131,7 +140,7 @@ pub fn process(items: Value) -> error::Result<AstContext> {
             };
 
             let node = AstNode {
-                tag: import_ast_tag(tag),
+                tag: ast_tag,
                 children,
                 fileid,
                 line: entry[4].as_u64().unwrap(),
@@ -143,9 +152,9 @@ pub fn process(items: Value) -> error::Result<AstContext> {
             };
 
             asts.insert(entry_id, node);
-        } else {
+        } else if let Some(type_tag) = schema::type_tag(tag) {
             let node = TypeNode {
-                tag: import_type_tag(tag),
+                tag: type_tag,
                 extras: entry[2..].to_vec(),
             };
 
@@ -157,5 +166,6 @@ pub fn process(items: Value) -> error::Result<AstContext> {
         ast_nodes: asts,
         type_nodes: types,
         comments,
+        target,
     })
 }
@@ -7,22 +7,42 @@ use serde_cbor::{Value, from_slice};
 use std::collections::HashMap;
 use std::ffi::{CString,CStr};
 use std::io::{Error, ErrorKind};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::slice;
 
 pub mod clang_ast;
+pub mod schema;
+
+/// Export the AST of a C file. The file is parsed for the host unless a `target` triple is given.
+pub fn get_untyped_ast(
+    file_path: &Path,
+    cc_db: &Path,
+    extra_args: &[&str],
+    target: Option<&str>,
+) -> Result<clang_ast::AstContext, Error> {
+    let buffer = get_ast_cbor(file_path, cc_db, extra_args, target)?;
+    parse_ast_cbor(&buffer)
+}
 
-pub fn get_untyped_ast(file_path: &Path, cc_db: &Path, extra_args: &[&str]) -> Result<clang_ast::AstContext, Error> {
-    let cbors = get_ast_cbors(file_path, cc_db, extra_args);
-    let buffer = cbors.values().next()
-        .ok_or(Error::new(ErrorKind::InvalidData, "Could not parse input file"))?;
-
-    // let cbor_path = file_path.with_extension("cbor");
-    // let mut cbor_file = File::create(&cbor_path)?;
-    // cbor_file.write_all(&buffer[..])?;
-    // eprintln!("Dumped CBOR to {}", cbor_path.to_string_lossy());
+/// Export the AST of a C file as CBOR, which can be saved to disk and imported with
+/// `parse_ast_cbor` later on.
+pub fn get_ast_cbor(
+    file_path: &Path,
+    cc_db: &Path,
+    extra_args: &[&str],
+    target: Option<&str>,
+) -> Result<Vec<u8>, Error> {
+    let cbors = get_ast_cbors(&[file_path], cc_db, extra_args, target);
+    cbors.into_iter().map(|(_, buffer)| buffer).next()
+        .ok_or(Error::new(ErrorKind::InvalidData, "Could not parse input file"))
+}
 
-    let items: Value = from_slice(&buffer[..]).unwrap();
+/// Import an AST exported as CBOR
+pub fn parse_ast_cbor(buffer: &[u8]) -> Result<clang_ast::AstContext, Error> {
+    let items: Value = match from_slice(buffer) {
+        Ok(items) => items,
+        Err(e) => return Err(Error::new(ErrorKind::InvalidData, format!("{:}", e))),
+    };
 
     match clang_ast::process(items) {
         Ok(cxt) => Ok(cxt),
@@ -30,11 +50,40 @@ pub fn get_untyped_ast(file_path: &Path, cc_db: &Path, extra_args: &[&str]) -> R
     }
 }
 
-fn get_ast_cbors(file_path: &Path, cc_db: &Path, extra_args: &[&str]) -> HashMap<String, Vec<u8>> {
+/// Export and import the ASTs of several C files in a single run of the exporter, keyed by the
+/// canonical path of each file. Files that could not be parsed are missing from the result.
+pub fn get_untyped_asts(
+    file_paths: &[&Path],
+    cc_db: &Path,
+    extra_args: &[&str],
+    target: Option<&str>,
+) -> HashMap<PathBuf, Result<clang_ast::AstContext, Error>> {
+    get_ast_cbors(file_paths, cc_db, extra_args, target)
+        .into_iter()
+        .map(|(path, buffer)| {
+            let ast = parse_ast_cbor(&buffer);
+            (path, ast)
+        })
+        .collect()
+}
+
+/// Export the ASTs of several C files as CBOR in a single run of the exporter, keyed by the
+/// canonical path of each file. Files that could not be parsed are missing from the result.
+///
+/// Unlike `get_untyped_asts`, this keeps the ASTs encoded, so that they can be saved and only
+/// take the memory of the decoded AST while each one is imported.
+pub fn get_ast_cbors(
+    file_paths: &[&Path],
+    cc_db: &Path,
+    extra_args: &[&str],
+    target: Option<&str>,
+) -> HashMap<PathBuf, Vec<u8>> {
     let mut res = 0;
 
     let mut args_owned = vec![CString::new("ast_exporter").unwrap()];
-    args_owned.push(CString::new(file_path.to_str().unwrap()).unwrap());
+    for file_path in file_paths {
+        args_owned.push(CString::new(file_path.to_str().unwrap()).unwrap());
+    }
     args_owned.push(CString::new("-p").unwrap());
     args_owned.push(CString::new(cc_db.to_str().unwrap()).unwrap());
 
@@ -42,6 +91,11 @@ fn get_ast_cbors(file_path: &Path, cc_db: &Path, extra_args: &[&str]) -> HashMap
         args_owned.push(CString::new(["-extra-arg=", arg].join("")).unwrap())
     }
 
+    // Comes after the compile command's arguments, so it overrides any target given there
+    if let Some(triple) = target {
+        args_owned.push(CString::new(["-extra-arg=--target=", triple].join("")).unwrap())
+    }
+
     let args_ptrs: Vec<*const libc::c_char> = args_owned.iter().map(|x| x.as_ptr()).collect();
 
     let hashmap;
@@ -66,7 +120,7 @@ extern "C" {
     fn drop_export_result(ptr: *mut ExportResult);
 }
 
-unsafe fn marshal_result(result: *const ExportResult) -> HashMap<String, Vec<u8>> {
+unsafe fn marshal_result(result: *const ExportResult) -> HashMap<PathBuf, Vec<u8>> {
     let mut output = HashMap::new();
 
     let n = (*result).entries as isize;
@@ -75,7 +129,7 @@ unsafe fn marshal_result(result: *const ExportResult) -> HashMap<String, Vec<u8>
 
         // Convert name field
         let cname = CStr::from_ptr(*res.names.offset(i));
-        let name = cname.to_str().unwrap().to_owned();
+        let name = PathBuf::from(cname.to_str().unwrap());
 
         // Convert CBOR bytes
         let csize = *res.sizes.offset(i);
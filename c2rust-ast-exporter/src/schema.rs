@@ -0,0 +1,345 @@
+//! This module checks that CBOR exported by `AstExporter.cpp` has the layout `clang_ast::process`
+//! expects, so that a mismatch between the exporter and the transpiler is reported up front, with
+//! the offending node, instead of as a panic somewhere in the conversion of the AST.
+//!
+//! An exported AST is an array of
+//!
+//!   0. the header `["c2rust-ast", AST_FORMAT_VERSION]`,
+//!   1. the AST and type nodes,
+//!   2. the ids of the top-level declarations,
+//!   3. the paths of the source files, indexed by file id,
+//!   4. the comments, as `[fileid, line, column, bytes]`, and
+//!   5. the target, as `[triple, pointer width, long width, char is signed]`.
+//!
+//! AST nodes are arrays `[id, tag, children, fileid, line, column, type id, is rvalue, extras...]`
+//! where each child and the type id may be null. Type nodes are arrays `[id, tag, extras...]`
+//! with as many extras as their tag calls for.
+
+use std::fmt;
+
+use serde_cbor::Value;
+
+use clang_ast::{ASTEntryTag, TypeTag, AST_FORMAT_VERSION};
+
+/// Magic string at the start of the format header
+pub const FORMAT_MAGIC: &str = "c2rust-ast";
+
+/// A malformed part of an exported AST
+#[derive(Debug, Clone)]
+pub struct SchemaError {
+    /// Id of the malformed node, if the error is in a node
+    pub node_id: Option<u64>,
+    /// Tag of the malformed node, if it has a valid one
+    pub tag: Option<u64>,
+    pub message: String,
+}
+
+impl SchemaError {
+    fn new(message: String) -> SchemaError {
+        SchemaError { node_id: None, tag: None, message }
+    }
+
+    fn in_node(node_id: u64, tag: Option<u64>, message: String) -> SchemaError {
+        SchemaError { node_id: Some(node_id), tag, message }
+    }
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.node_id, self.tag) {
+            (Some(id), Some(tag)) => write!(f, "node {} ({}): {}", id, tag_name(tag), self.message),
+            (Some(id), None) => write!(f, "node {}: {}", id, self.message),
+            _ => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// The first and last tags of each group of AST node tags in `ast_tags.hpp`. New tags are
+/// appended to the end of their group.
+const AST_TAG_RANGES: [(ASTEntryTag, ASTEntryTag); 4] = [
+    (ASTEntryTag::TagFunctionDecl, ASTEntryTag::TagStaticAssertDecl),
+    (ASTEntryTag::TagCompoundStmt, ASTEntryTag::TagAttributedStmt),
+    (ASTEntryTag::TagBinaryOperator, ASTEntryTag::TagBlockExpr),
+    (ASTEntryTag::TagIntegerLiteral, ASTEntryTag::TagFloatingLiteral),
+];
+
+/// The first and last tags of each group of type node tags in `ast_tags.hpp`
+const TYPE_TAG_RANGES: [(TypeTag, TypeTag); 2] = [
+    (TypeTag::TagTypeUnknown, TypeTag::TagTypeUnknown),
+    (TypeTag::TagInt, TypeTag::TagHalf),
+];
+
+/// The AST node tag with the given number, if there is one
+pub fn ast_tag(tag: u64) -> Option<ASTEntryTag> {
+    let known = AST_TAG_RANGES
+        .iter()
+        .any(|&(first, last)| first as u64 <= tag && tag <= last as u64);
+    if known {
+        // Safe since the tags of each group are consecutive
+        Some(unsafe { ::std::mem::transmute::<u32, ASTEntryTag>(tag as u32) })
+    } else {
+        None
+    }
+}
+
+/// The type node tag with the given number, if there is one
+pub fn type_tag(tag: u64) -> Option<TypeTag> {
+    let known = TYPE_TAG_RANGES
+        .iter()
+        .any(|&(first, last)| first as u64 <= tag && tag <= last as u64);
+    if known {
+        // Safe since the tags of each group are consecutive
+        Some(unsafe { ::std::mem::transmute::<u32, TypeTag>(tag as u32) })
+    } else {
+        None
+    }
+}
+
+/// Human readable name of a tag number
+pub fn tag_name(tag: u64) -> String {
+    match (ast_tag(tag), type_tag(tag)) {
+        (Some(tag), _) => format!("{:?}", tag),
+        (None, Some(tag)) => format!("{:?}", tag),
+        (None, None) => format!("unknown tag {}", tag),
+    }
+}
+
+/// Check that an exported AST has the layout described in the module documentation
+pub fn validate(items: &Value) -> Result<(), SchemaError> {
+    let parts = items.as_array()
+        .ok_or_else(|| SchemaError::new(format!("Exported AST is not an array")))?;
+    validate_header(parts)?;
+    if parts.len() != 6 {
+        return Err(SchemaError::new(format!("Expected 6 parts in exported AST, found {}", parts.len())))
+    }
+
+    let files = array(&parts[3], "file paths")?;
+    if let Some(path) = files.iter().find(|path| path.as_string().is_none()) {
+        return Err(SchemaError::new(format!("File path is not a string: {:?}", path)))
+    }
+    let num_files = files.len() as u64;
+
+    for node in array(&parts[1], "nodes")? {
+        validate_node(node, num_files)?;
+    }
+
+    for top in array(&parts[2], "top-level declarations")? {
+        top.as_u64().ok_or_else(|| {
+            SchemaError::new(format!("Top-level declaration id is not an integer: {:?}", top))
+        })?;
+    }
+
+    for comment in array(&parts[4], "comments")? {
+        let valid = match comment.as_array() {
+            Some(fields) => fields.len() == 4
+                && fields[..3].iter().all(|x| x.as_u64().is_some())
+                && fields[3].as_bytes().is_some(),
+            None => false,
+        };
+        if !valid {
+            return Err(SchemaError::new(format!("Malformed comment: {:?}", comment)))
+        }
+    }
+
+    let valid_target = match parts[5].as_array() {
+        Some(fields) => fields.len() == 4
+            && fields[0].as_string().is_some()
+            && fields[1].as_u64().is_some()
+            && fields[2].as_u64().is_some()
+            && fields[3].as_boolean().is_some(),
+        None => false,
+    };
+    if !valid_target {
+        return Err(SchemaError::new(format!("Malformed target: {:?}", parts[5])))
+    }
+
+    Ok(())
+}
+
+fn validate_header(parts: &[Value]) -> Result<(), SchemaError> {
+    let header = parts.first().and_then(|header| header.as_array());
+    let (magic, version) = match header {
+        Some(header) if header.len() == 2 => (header[0].as_string(), header[1].as_u64()),
+        _ => (None, None),
+    };
+    if magic.map(String::as_str) != Some(FORMAT_MAGIC) {
+        return Err(SchemaError::new(format!(
+            "Exported AST has no format header; it was probably exported by an older version \
+             of c2rust"
+        )))
+    }
+    match version {
+        Some(version) if version == AST_FORMAT_VERSION as u64 => Ok(()),
+        Some(version) => Err(SchemaError::new(format!(
+            "Exported AST has format version {}, but this version of c2rust reads version {}",
+            version, AST_FORMAT_VERSION
+        ))),
+        None => Err(SchemaError::new(format!("Exported AST has a malformed format header"))),
+    }
+}
+
+fn validate_node(node: &Value, num_files: u64) -> Result<(), SchemaError> {
+    let entry = node.as_array()
+        .ok_or_else(|| SchemaError::new(format!("Node is not an array: {:?}", node)))?;
+    let id = entry.get(0).and_then(Value::as_u64)
+        .ok_or_else(|| SchemaError::new(format!("Node has no id: {:?}", node)))?;
+    let tag = entry.get(1).and_then(Value::as_u64)
+        .ok_or_else(|| SchemaError::in_node(id, None, format!("Node has no tag")))?;
+
+    if let Some(type_tag) = type_tag(tag) {
+        let arity = type_arity(type_tag);
+        if entry.len() != 2 + arity {
+            return Err(SchemaError::in_node(id, Some(tag), format!(
+                "Expected {} extra fields, found {}", arity, entry.len() - 2
+            )))
+        }
+        return Ok(())
+    }
+    if ast_tag(tag).is_none() {
+        return Err(SchemaError::in_node(id, None, format!("Unknown tag {}", tag)))
+    }
+
+    let malformed = |what: &str| SchemaError::in_node(id, Some(tag), format!("Malformed {}", what));
+    if entry.len() < 8 {
+        return Err(SchemaError::in_node(id, Some(tag), format!(
+            "Expected at least 8 fields, found {}", entry.len()
+        )))
+    }
+
+    let children = entry[2].as_array().ok_or_else(|| malformed("children"))?;
+    if !children.iter().all(is_opt_u64) {
+        return Err(malformed("children"))
+    }
+
+    let fileid = entry[3].as_u64().ok_or_else(|| malformed("file id"))?;
+    if fileid >= num_files {
+        return Err(SchemaError::in_node(id, Some(tag), format!("Unknown file id {}", fileid)))
+    }
+    entry[4].as_u64().ok_or_else(|| malformed("line"))?;
+    entry[5].as_u64().ok_or_else(|| malformed("column"))?;
+
+    if !is_opt_u64(&entry[6]) {
+        return Err(malformed("type id"))
+    }
+    entry[7].as_boolean().ok_or_else(|| malformed("rvalue flag"))?;
+
+    Ok(())
+}
+
+/// Number of extra fields `AstExporter.cpp` emits after the id and tag of a type node
+fn type_arity(tag: TypeTag) -> usize {
+    use clang_ast::TypeTag::*;
+    match tag {
+        TagFunctionType => 4,
+        TagAttributedType | TagConstantArrayType | TagVariableArrayType | TagVectorType => 2,
+        TagPointer | TagBlockPointer | TagComplexType | TagStructType | TagUnionType
+        | TagTypedefType | TagEnumType | TagDecayedType | TagElaboratedType | TagParenType
+        | TagTypeOfType | TagIncompleteArrayType => 1,
+        _ => 0,
+    }
+}
+
+fn array<'a>(value: &'a Value, what: &str) -> Result<&'a Vec<Value>, SchemaError> {
+    value.as_array().ok_or_else(|| SchemaError::new(format!("Exported {} are not an array", what)))
+}
+
+fn is_opt_u64(value: &Value) -> bool {
+    match *value {
+        Value::Null | Value::U64(_) => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(version: u64) -> Value {
+        Value::Array(vec![Value::String(FORMAT_MAGIC.to_owned()), Value::U64(version)])
+    }
+
+    fn exported(header: Option<Value>, nodes: Vec<Value>) -> Value {
+        let parts = vec![
+            Value::Array(nodes),
+            Value::Array(vec![]),
+            Value::Array(vec![Value::String("a.c".to_owned())]),
+            Value::Array(vec![]),
+            Value::Array(vec![
+                Value::String("x86_64-unknown-linux-gnu".to_owned()),
+                Value::U64(64),
+                Value::U64(64),
+                Value::Bool(true),
+            ]),
+        ];
+        Value::Array(header.into_iter().chain(parts).collect())
+    }
+
+    fn type_node(id: u64, tag: TypeTag, extras: Vec<Value>) -> Value {
+        let fields = vec![Value::U64(id), Value::U64(tag as u64)];
+        Value::Array(fields.into_iter().chain(extras).collect())
+    }
+
+    fn if_stmt(id: u64) -> Value {
+        Value::Array(vec![
+            Value::U64(id),
+            Value::U64(ASTEntryTag::TagIfStmt as u64),
+            Value::Array(vec![Value::U64(4), Value::U64(5), Value::Null]),
+            Value::U64(0),
+            Value::U64(1),
+            Value::U64(1),
+            Value::Null,
+            Value::Bool(false),
+        ])
+    }
+
+    fn error(items: &Value) -> SchemaError {
+        validate(items).expect_err("malformed AST was accepted")
+    }
+
+    #[test]
+    fn accepts_well_formed_ast() {
+        let nodes = vec![
+            type_node(1, TypeTag::TagInt, vec![]),
+            type_node(2, TypeTag::TagPointer, vec![Value::U64(1)]),
+            if_stmt(3),
+        ];
+        validate(&exported(Some(header(AST_FORMAT_VERSION as u64)), nodes)).unwrap();
+    }
+
+    #[test]
+    fn rejects_missing_header() {
+        let err = error(&exported(None, vec![]));
+        assert_eq!(err.node_id, None);
+        assert!(err.message.contains("no format header"), "{}", err);
+    }
+
+    #[test]
+    fn rejects_wrong_version() {
+        let err = error(&exported(Some(header(AST_FORMAT_VERSION as u64 + 1)), vec![]));
+        assert!(err.message.contains("format version"), "{}", err);
+    }
+
+    #[test]
+    fn rejects_bad_type_arity() {
+        let nodes = vec![type_node(2, TypeTag::TagPointer, vec![])];
+        let err = error(&exported(Some(header(AST_FORMAT_VERSION as u64)), nodes));
+        assert_eq!(err.node_id, Some(2));
+        assert_eq!(err.tag, Some(TypeTag::TagPointer as u64));
+        assert_eq!(err.message, "Expected 1 extra fields, found 0");
+    }
+
+    #[test]
+    fn rejects_short_ast_node() {
+        let node = match if_stmt(3) {
+            Value::Array(mut fields) => {
+                fields.truncate(4);
+                Value::Array(fields)
+            }
+            _ => unreachable!(),
+        };
+        let err = error(&exported(Some(header(AST_FORMAT_VERSION as u64)), vec![node]));
+        assert_eq!(err.node_id, Some(3));
+        assert_eq!(err.tag, Some(ASTEntryTag::TagIfStmt as u64));
+        assert_eq!(err.message, "Expected at least 8 fields, found 4");
+    }
+}
@@ -140,12 +140,16 @@ fn emit_lib_rs(tcfg: &TranspilerConfig, reg: &Handlebars, build_dir: &Path,
 fn emit_cargo_toml(tcfg: &TranspilerConfig, reg: &Handlebars, build_dir: &Path) {
     // rust_checks_path is gone because we don't want to refer to the source
     // path but instead want the cross-check libs to be installed via cargo.
+    let features: Vec<&str> = tcfg.configurations.iter().map(|c| c.name.as_str()).collect();
     let json = json!({
         "crate_name": "c2rust-build",
         "root_rs_file": get_root_rs_file_name(tcfg),
         "main_module": tcfg.main,
         "cross_checks": tcfg.cross_checks,
         "cross_check_backend": tcfg.cross_check_backend,
+        "features": features,
+        "default_feature": features.first(),
+        "target": tcfg.target,
     });
     let file_name = "Cargo.toml";
     let output_path = build_dir.join(file_name);
@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::fmt;
 use std::vec::Vec;
 use c_ast::*;
 use c2rust_ast_exporter::clang_ast::*;
@@ -111,8 +112,8 @@ fn not_located<T>(t: T) -> Located<T> {
     }
 }
 
-fn parse_cast_kind(kind: &str) -> CastKind {
-    match kind {
+fn parse_cast_kind(kind: &str) -> Result<CastKind, String> {
+    let kind = match kind {
         "BitCast" => CastKind::BitCast,
         "LValueToRValue" => CastKind::LValueToRValue,
         "NoOp" => CastKind::NoOp,
@@ -143,7 +144,76 @@ fn parse_cast_kind(kind: &str) -> CastKind {
         "BuiltinFnToFnPtr" => CastKind::BuiltinFnToFnPtr,
         "ConstCast" => CastKind::ConstCast,
         "VectorSplat" => CastKind::VectorSplat,
-        k => panic!("Unsupported implicit cast: {}", k),
+        // Block pointers are translated to raw pointers too
+        "AnyPointerToBlockPointerCast" => CastKind::BitCast,
+        k => return Err(format!("Unsupported implicit cast: {}", k)),
+    };
+    Ok(kind)
+}
+
+/// Linkage attributes come as `[name, argument]` pairs; ones we don't know are skipped
+fn parse_decl_attrs(attrs: &Value) -> Result<Vec<DeclAttribute>, String> {
+    // Clang reports the lowest priority (65535) when none was given
+    let priority = |arg: Option<&str>| arg
+        .and_then(|p| p.parse::<u64>().ok())
+        .filter(|&p| p != 65535);
+
+    let attrs = attrs.as_array().ok_or("Expected attribute array on declaration")?;
+    let mut decl_attrs = vec![];
+    for attr in attrs {
+        let pair = match attr.as_array() {
+            Some(pair) if pair.len() == 2 => pair,
+            _ => return Err(format!("Expected attribute to be a name and argument pair")),
+        };
+        let name = pair[0].as_string().ok_or("Expected attribute name")?;
+        let arg = expect_opt_str(&pair[1]).ok_or("Expected attribute argument")?;
+        let decl_attr = match (name.as_str(), arg) {
+            ("section", Some(section)) => Some(DeclAttribute::Section(section.to_owned())),
+            ("weak", _) => Some(DeclAttribute::Weak),
+            ("alias", Some(target)) => Some(DeclAttribute::Alias(target.to_owned())),
+            ("constructor", arg) => Some(DeclAttribute::Constructor(priority(arg))),
+            ("destructor", arg) => Some(DeclAttribute::Destructor(priority(arg))),
+            ("visibility", Some(visibility)) => Some(DeclAttribute::Visibility(visibility.to_owned())),
+            ("blocks", _) => Some(DeclAttribute::BlockByref),
+            ("noreturn", _) => Some(DeclAttribute::NoReturn),
+            ("nonnull", Some(indices)) => Some(DeclAttribute::NonNull(
+                indices.split(',').filter_map(|i| i.parse().ok()).collect()
+            )),
+            _ => None,
+        };
+        decl_attrs.extend(decl_attr);
+    }
+    Ok(decl_attrs)
+}
+
+/// A node of the exported AST that could not be converted
+#[derive(Debug, Clone)]
+pub struct ConversionError {
+    /// Clang ID of the node
+    pub node_id: ClangId,
+    /// Name of the node's tag, if the node exists
+    pub tag: Option<String>,
+    pub message: String,
+}
+
+impl ConversionError {
+    fn new(untyped_context: &AstContext, node_id: ClangId, message: String) -> ConversionError {
+        let tag = match untyped_context.ast_nodes.get(&node_id) {
+            Some(node) => Some(format!("{:?}", node.tag)),
+            None => untyped_context.type_nodes
+                .get(&(node_id & TypeNode::ID_MASK))
+                .map(|node| format!("{:?}", node.tag)),
+        };
+        ConversionError { node_id, tag, message }
+    }
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.tag {
+            Some(ref tag) => write!(f, "node {} ({}): {}", self.node_id, tag, self.message),
+            None => write!(f, "node {}: {}", self.node_id, self.message),
+        }
     }
 }
 
@@ -263,7 +333,7 @@ impl ConversionContext {
         new_id: ImporterId,
         node: &AstNode,
         expr: CExprKind,
-    ) -> () {
+    ) -> Result<(), String> {
         if expected_ty & node_types::STMT != 0 {
             // This is going to be an extra node not present in the Clang AST
             let new_expr_id = self.id_mapper.fresh_id();
@@ -279,15 +349,21 @@ impl ConversionContext {
             self.add_expr(new_id, located(node, expr));
             self.processed_nodes.insert(new_id, node_types::EXPR);
         } else {
-            panic!("'expr_possibly_as_stmt' expects 'expected_ty' to be either 'EXPR' or 'STMT'");
+            return Err(format!("Expected a node of type {}, found an expression", expected_ty))
         }
+        Ok(())
     }
 
     /// Convert the contents of an `AstContext`, starting from the top-level declarations passed
     /// into the `ConversionContext` on creation.
     ///
     /// This populates the `typed_context` of the `ConversionContext` it is called on.
-    pub fn convert(&mut self, untyped_context: &AstContext) -> () {
+    ///
+    /// Fails with the first node whose children, type or extra fields don't have the shape its
+    /// tag calls for.
+    pub fn convert(&mut self, untyped_context: &AstContext) -> Result<(), ConversionError> {
+
+        self.typed_context.target = untyped_context.target.clone();
 
         for raw_comment in &untyped_context.comments {
             let comment = Located {
@@ -312,7 +388,8 @@ impl ConversionContext {
                 if ty & expected_ty != 0 {
                     continue;
                 }
-                panic!("Expected {} to be a node of type {}, not {}", &node_id, expected_ty, ty);
+                let msg = format!("Expected a node of type {}, not {}", expected_ty, ty);
+                return Err(ConversionError::new(untyped_context, node_id, msg))
             }
 
             // Create a `NewId` for this node
@@ -323,8 +400,10 @@ impl ConversionContext {
                 self.typed_context.c_decls_top.push(CDeclId(new_id));
             }
 
-            self.visit_node(untyped_context, node_id, new_id, expected_ty)
+            self.visit_node(untyped_context, node_id, new_id, expected_ty)?;
         }
+
+        Ok(())
     }
 
 
@@ -335,16 +414,21 @@ impl ConversionContext {
         node_id: ClangId,                 // Clang ID of node to visit
         new_id: ImporterId,                    // New ID of node to visit
         expected_ty: NodeType             // Expected type of node to visit
-    ) -> () {
+    ) -> Result<(), ConversionError> {
         use self::node_types::*;
 
+        // Nodes that don't have the shape their tag calls for are reported as errors naming them
+        let err = |msg: &str| ConversionError::new(untyped_context, node_id, msg.to_owned());
+
         if expected_ty & TYPE != 0 {
 
             // Convert the node
             let ty_node: &TypeNode = match untyped_context.type_nodes.get(&node_id) {
                 Some(x) => x,
-                None => return,
+                None => return Ok(()),
             };
+            let type_extra = |i: usize| ty_node.extras.get(i)
+                .ok_or_else(|| err(&format!("Expected at least {} extra fields", i + 1)));
 
             match ty_node.tag {
                 TypeTag::TagBool if expected_ty & OTHER_TYPE != 0 => {
@@ -443,8 +527,8 @@ impl ConversionContext {
                 }
 
                 TypeTag::TagPointer if expected_ty & OTHER_TYPE != 0 => {
-                    let pointed = ty_node.extras[0].as_u64()
-                        .expect("Pointer child not found");
+                    let pointed = type_extra(0)?.as_u64()
+                        .ok_or_else(|| err("Pointer child not found"))?;
                     let pointed_new = self.visit_qualified_type( pointed);
 
                     let pointer_ty = CTypeKind::Pointer(pointed_new);
@@ -453,8 +537,8 @@ impl ConversionContext {
                 }
 
                 TypeTag::TagBlockPointer if expected_ty & OTHER_TYPE != 0 => {
-                    let pointed = ty_node.extras[0].as_u64()
-                        .expect("Block pointer child not found");
+                    let pointed = type_extra(0)?.as_u64()
+                        .ok_or_else(|| err("Block pointer child not found"))?;
                     let pointed_new = self.visit_qualified_type( pointed);
 
                     let pointer_ty = CTypeKind::BlockPointer(pointed_new);
@@ -463,8 +547,8 @@ impl ConversionContext {
                 }
 
                 TypeTag::TagComplexType if expected_ty & OTHER_TYPE != 0 => {
-                    let subelt = ty_node.extras[0].as_u64()
-                        .expect("Complex child not found");
+                    let subelt = type_extra(0)?.as_u64()
+                        .ok_or_else(|| err("Complex child not found"))?;
                     let subelt_new = self.visit_type(subelt);
 
                     let complex_ty = CTypeKind::Complex(subelt_new);
@@ -473,8 +557,8 @@ impl ConversionContext {
                 }
 
                 TypeTag::TagStructType if expected_ty & OTHER_TYPE != 0 => {
-                    let decl = ty_node.extras[0].as_u64()
-                        .expect("Struct decl not found");
+                    let decl = type_extra(0)?.as_u64()
+                        .ok_or_else(|| err("Struct decl not found"))?;
                     let decl_new = CDeclId(self.visit_node_type(decl, RECORD_DECL));
 
                     let record_ty = CTypeKind::Struct(decl_new);
@@ -483,8 +567,8 @@ impl ConversionContext {
                 }
 
                 TypeTag::TagUnionType if expected_ty & OTHER_TYPE != 0 => {
-                    let decl = ty_node.extras[0].as_u64()
-                        .expect("Union decl not found");
+                    let decl = type_extra(0)?.as_u64()
+                        .ok_or_else(|| err("Union decl not found"))?;
                     let decl_new = CDeclId(self.visit_node_type(decl, RECORD_DECL));
 
                     let record_ty = CTypeKind::Union(decl_new);
@@ -493,27 +577,28 @@ impl ConversionContext {
                 }
 
                 TypeTag::TagFunctionType if expected_ty & FUNC_TYPE != 0 => {
-                    let mut arguments: Vec<CQualTypeId> = ty_node.extras[0].as_array()
-                        .expect("Function type expects array argument")
+                    let mut arguments: Vec<CQualTypeId> = type_extra(0)?.as_array()
+                        .ok_or_else(|| err("Function type expects array argument"))?
                         .iter()
                         .map(|cbor| {
-                            let arg = cbor.as_u64().expect("Bad function type child id");
-                            let arg_new = self.visit_qualified_type(arg);
-
-                            arg_new
+                            let arg = cbor.as_u64().ok_or_else(|| err("Bad function type child id"))?;
+                            Ok(self.visit_qualified_type(arg))
                         })
-                        .collect();
+                        .collect::<Result<_, ConversionError>>()?;
+                    if arguments.is_empty() {
+                        return Err(err("Function type has no return type"))
+                    }
                     let ret = arguments.remove(0);
-                    let is_variadic = ty_node.extras[1].as_boolean().expect("Variadicity of function type not found");
-                    let is_noreturn = ty_node.extras[2].as_boolean().expect("NoReturn of function type not found");
-                    let has_proto = ty_node.extras[3].as_boolean().expect("HasProto of function type not found");
+                    let is_variadic = type_extra(1)?.as_boolean().ok_or_else(|| err("Variadicity of function type not found"))?;
+                    let is_noreturn = type_extra(2)?.as_boolean().ok_or_else(|| err("NoReturn of function type not found"))?;
+                    let has_proto = type_extra(3)?.as_boolean().ok_or_else(|| err("HasProto of function type not found"))?;
                     let function_ty = CTypeKind::Function(ret, arguments, is_variadic, is_noreturn, has_proto);
                     self.add_type(new_id, not_located(function_ty));
                     self.processed_nodes.insert(new_id, FUNC_TYPE);
                 }
 
                 TypeTag::TagTypeOfType if expected_ty & TYPE != 0 => {
-                    let type_of_old = ty_node.extras[0].as_u64().expect("Type of (type) child not found");
+                    let type_of_old = type_extra(0)?.as_u64().ok_or_else(|| err("Type of (type) child not found"))?;
                     let type_of = self.visit_type(type_of_old);
 
                     let type_of_ty = CTypeKind::TypeOf(type_of);
@@ -522,8 +607,8 @@ impl ConversionContext {
                 }
 
                 TypeTag::TagTypedefType => {
-                    let decl = ty_node.extras[0].as_u64()
-                        .expect("Typedef decl not found");
+                    let decl = type_extra(0)?.as_u64()
+                        .ok_or_else(|| err("Typedef decl not found"))?;
                     let decl_new = CDeclId(self.visit_node_type(decl, TYPDEF_DECL));
 
                     let typedef_ty = CTypeKind::Typedef(decl_new);
@@ -532,8 +617,8 @@ impl ConversionContext {
                 }
 
                 TypeTag::TagEnumType if expected_ty & OTHER_TYPE != 0 => {
-                    let decl = ty_node.extras[0].as_u64()
-                        .expect("Enum decl not found");
+                    let decl = type_extra(0)?.as_u64()
+                        .ok_or_else(|| err("Enum decl not found"))?;
                     let decl_new = CDeclId(self.visit_node_type(decl, ENUM_DECL));
 
                     let enum_ty = CTypeKind::Enum(decl_new);
@@ -542,7 +627,7 @@ impl ConversionContext {
                 }
 
                 TypeTag::TagDecayedType if expected_ty & OTHER_TYPE != 0 => {
-                    let decayed_id = ty_node.extras[0].as_u64().expect("Decayed type child not found");
+                    let decayed_id = type_extra(0)?.as_u64().ok_or_else(|| err("Decayed type child not found"))?;
                     let decayed = self.visit_type(decayed_id);
 
                     let decayed_ty = CTypeKind::Decayed(decayed);
@@ -551,7 +636,7 @@ impl ConversionContext {
                 }
 
                 TypeTag::TagElaboratedType if expected_ty & OTHER_TYPE != 0 => {
-                    let elaborated_id = ty_node.extras[0].as_u64().expect("Elaborated type child not found");
+                    let elaborated_id = type_extra(0)?.as_u64().ok_or_else(|| err("Elaborated type child not found"))?;
                     let elaborated = self.visit_type(elaborated_id);
 
                     let elaborated_ty = CTypeKind::Elaborated(elaborated);
@@ -560,7 +645,7 @@ impl ConversionContext {
                 }
 
                 TypeTag::TagParenType => {
-                    let paren_id = ty_node.extras[0].as_u64().expect("Paren type child not found");
+                    let paren_id = type_extra(0)?.as_u64().ok_or_else(|| err("Paren type child not found"))?;
                     let paren = self.visit_type(paren_id);
 
                     let paren_ty = CTypeKind::Paren(paren);
@@ -569,16 +654,16 @@ impl ConversionContext {
                 }
 
                 TypeTag::TagAttributedType => {
-                    let ty_id = ty_node.extras[0].as_u64().expect("Attributed type child not found");
+                    let ty_id = type_extra(0)?.as_u64().ok_or_else(|| err("Attributed type child not found"))?;
                     let ty = self.visit_qualified_type(ty_id);
 
-                    let kind = match expect_opt_str(&ty_node.extras[1]).expect("Attributed type kind not found")
+                    let kind = match expect_opt_str(type_extra(1)?).ok_or_else(|| err("Attributed type kind not found"))?
                         {
                             None => None,
                             Some("noreturn") => Some(Attribute::NoReturn),
                             Some("nullable") => Some(Attribute::Nullable),
                             Some("notnull") => Some(Attribute::NotNull),
-                            Some(other) => panic!("Unknown type attribute: {}", other),
+                            Some(other) => return Err(err(&format!("Unknown type attribute: {}", other))),
                         };
 
                     let ty = CTypeKind::Attributed(ty, kind);
@@ -587,10 +672,10 @@ impl ConversionContext {
                 }
 
                 TypeTag::TagConstantArrayType => {
-                    let element_id = ty_node.extras[0].as_u64().expect("element id");
+                    let element_id = type_extra(0)?.as_u64().ok_or_else(|| err("element id"))?;
                     let element = self.visit_type(element_id);
 
-                    let count = ty_node.extras[1].as_u64().expect("count");
+                    let count = type_extra(1)?.as_u64().ok_or_else(|| err("count"))?;
 
                     let element_ty = CTypeKind::ConstantArray(element, count as usize);
                     self.add_type(new_id, not_located(element_ty));
@@ -598,7 +683,7 @@ impl ConversionContext {
                 }
 
                 TypeTag::TagIncompleteArrayType => {
-                    let element_id = ty_node.extras[0].as_u64().expect("element id");
+                    let element_id = type_extra(0)?.as_u64().ok_or_else(|| err("element id"))?;
                     let element = self.visit_type(element_id);
 
                     let element_ty = CTypeKind::IncompleteArray(element);
@@ -607,10 +692,10 @@ impl ConversionContext {
                 }
 
                 TypeTag::TagVariableArrayType => {
-                    let element_id = ty_node.extras[0].as_u64().expect("element id");
+                    let element_id = type_extra(0)?.as_u64().ok_or_else(|| err("element id"))?;
                     let element = self.visit_type(element_id);
 
-                    let count_id = expect_opt_u64(&ty_node.extras[1]).expect("count id");
+                    let count_id = expect_opt_u64(type_extra(1)?).ok_or_else(|| err("count id"))?;
                     let count = count_id.map(|x| self.visit_expr(x));
 
                     let element_ty = CTypeKind::VariableArray(element, count);
@@ -625,25 +710,32 @@ impl ConversionContext {
                 }
 
                 TypeTag::TagVectorType => {
-                    let elt = ty_node.extras[0].as_u64()
-                        .expect("Vector child not found");
+                    let elt = type_extra(0)?.as_u64()
+                        .ok_or_else(|| err("Vector child not found"))?;
                     let elt_new = self.visit_qualified_type(elt);
-                    let count = ty_node.extras[1].as_u64().expect("count");
+                    let count = type_extra(1)?.as_u64().ok_or_else(|| err("count"))?;
 
                     let vector_ty = CTypeKind::Vector(elt_new, count as usize);
                     self.add_type(new_id, not_located(vector_ty));
                     self.processed_nodes.insert(new_id, OTHER_TYPE);
                 }
 
-                t => panic!("Type conversion not implemented for {:?} expecting {:?}", t, expected_ty),
+                t => {
+                    let msg = format!("Type conversion not implemented for {:?} expecting {:?}", t, expected_ty);
+                    return Err(err(&msg))
+                }
             }
 
         } else {
             // Convert the node
             let node: &AstNode = match untyped_context.ast_nodes.get(&node_id) {
                 Some(x) => x,
-                None => return,
+                None => return Ok(()),
             };
+            let child = |i: usize| node.children.get(i).cloned()
+                .ok_or_else(|| err(&format!("Expected at least {} children", i + 1)));
+            let extra = |i: usize| node.extras.get(i)
+                .ok_or_else(|| err(&format!("Expected at least {} extra fields", i + 1)));
 
 
             match node.tag {
@@ -663,13 +755,25 @@ impl ConversionContext {
                     let constituent_stmts: Vec<CStmtId> = node.children
                         .iter()
                         .map(|id| {
-                            let arg_id = id.expect("Compound stmt child not found");
-                            self.visit_stmt(arg_id)
+                            let arg_id = id.ok_or_else(|| err("Compound stmt child not found"))?;
+                            Ok(self.visit_stmt(arg_id))
                         })
-                        .collect();
+                        .collect::<Result<_, ConversionError>>()?;
 
                     let compound_stmt = CStmtKind::Compound(constituent_stmts);
 
+                    // The closing brace is where comments at the end of the block get attached
+                    let end: Vec<u64> = node.extras.iter().filter_map(|v| v.as_u64()).collect();
+                    if end.len() == 3 {
+                        let end = SrcLoc {
+                            fileid: end[0],
+                            line: end[1],
+                            column: end[2],
+                            file_path: node.file_path.clone(),
+                        };
+                        self.typed_context.compound_ends.insert(CStmtId(new_id), end);
+                    }
+
                     self.add_stmt(new_id, located(node, compound_stmt));
                     self.processed_nodes.insert(new_id, OTHER_STMT);
                 }
@@ -678,10 +782,10 @@ impl ConversionContext {
                     let decls = node.children
                         .iter()
                         .map(|decl| {
-                            let decl_id = decl.expect("Decl not found in decl-statement");
-                            self.visit_decl(decl_id)
+                            let decl_id = decl.ok_or_else(|| err("Decl not found in decl-statement"))?;
+                            Ok(self.visit_decl(decl_id))
                         })
-                        .collect();
+                        .collect::<Result<_, ConversionError>>()?;
 
                     let decls_stmt = CStmtKind::Decls(decls);
 
@@ -690,7 +794,7 @@ impl ConversionContext {
                 }
 
                 ASTEntryTag::TagReturnStmt if expected_ty & OTHER_STMT != 0 => {
-                    let return_expr_opt = node.children[0]
+                    let return_expr_opt = child(0)?
                         .map(|id| self.visit_expr(id));
 
                     let return_stmt = CStmtKind::Return(return_expr_opt);
@@ -700,13 +804,13 @@ impl ConversionContext {
                 }
 
                 ASTEntryTag::TagIfStmt if expected_ty & OTHER_STMT != 0 => {
-                    let scrutinee_old = node.children[0].expect("If condition expression not found");
+                    let scrutinee_old = child(0)?.ok_or_else(|| err("If condition expression not found"))?;
                     let scrutinee = self.visit_expr(scrutinee_old);
 
-                    let true_variant_old = node.children[1].expect("If then body statement not found");
+                    let true_variant_old = child(1)?.ok_or_else(|| err("If then body statement not found"))?;
                     let true_variant = self.visit_stmt(true_variant_old);
 
-                    let false_variant = node.children[2]
+                    let false_variant = child(2)?
                         .map(|id| self.visit_stmt(id));
 
                     let if_stmt = CStmtKind::If { scrutinee, true_variant, false_variant };
@@ -716,7 +820,7 @@ impl ConversionContext {
                 }
 
                 ASTEntryTag::TagGotoStmt if expected_ty & OTHER_STMT != 0 => {
-                    let target_label_old = node.children[0].expect("Goto target label not found");
+                    let target_label_old = child(0)?.ok_or_else(|| err("Goto target label not found"))?;
                     let target_label = CStmtId(self.visit_node_type(target_label_old, LABEL_STMT));
 
                     let goto_stmt = CStmtKind::Goto(target_label);
@@ -733,13 +837,13 @@ impl ConversionContext {
                 }
 
                 ASTEntryTag::TagForStmt if expected_ty & OTHER_STMT != 0 => {
-                    let init = node.children[0].map(|id| self.visit_stmt(id));
+                    let init = child(0)?.map(|id| self.visit_stmt(id));
 
-                    let condition = node.children[1].map(|id| self.visit_expr(id));
+                    let condition = child(1)?.map(|id| self.visit_expr(id));
 
-                    let increment = node.children[2].map(|id| self.visit_expr(id));
+                    let increment = child(2)?.map(|id| self.visit_expr(id));
 
-                    let body_old = node.children[3].expect("For loop body not found");
+                    let body_old = child(3)?.ok_or_else(|| err("For loop body not found"))?;
                     let body = self.visit_stmt(body_old);
 
                     let for_stmt = CStmtKind::ForLoop { init, condition, increment, body };
@@ -749,10 +853,10 @@ impl ConversionContext {
                 }
 
                 ASTEntryTag::TagWhileStmt if expected_ty & OTHER_STMT != 0 => {
-                    let condition_old = node.children[0].expect("While loop condition not found");
+                    let condition_old = child(0)?.ok_or_else(|| err("While loop condition not found"))?;
                     let condition = self.visit_expr(condition_old);
 
-                    let body_old = node.children[1].expect("While loop body not found");
+                    let body_old = child(1)?.ok_or_else(|| err("While loop body not found"))?;
                     let body = self.visit_stmt(body_old);
 
                     let while_stmt = CStmtKind::While { condition, body };
@@ -763,10 +867,10 @@ impl ConversionContext {
 
                 ASTEntryTag::TagDoStmt if expected_ty & OTHER_STMT != 0 => {
 
-                    let body_old = node.children[0].expect("Do loop body not found");
+                    let body_old = child(0)?.ok_or_else(|| err("Do loop body not found"))?;
                     let body = self.visit_stmt(body_old);
 
-                    let condition_old = node.children[1].expect("Do loop condition not found");
+                    let condition_old = child(1)?.ok_or_else(|| err("Do loop condition not found"))?;
                     let condition = self.visit_expr(condition_old);
 
                     let do_stmt = CStmtKind::DoWhile { body, condition };
@@ -776,7 +880,7 @@ impl ConversionContext {
                 }
 
                 ASTEntryTag::TagLabelStmt if expected_ty & LABEL_STMT != 0 => {
-                    let substmt_old = node.children[0].expect("Label sub-statement not found");
+                    let substmt_old = child(0)?.ok_or_else(|| err("Label sub-statement not found"))?;
                     let substmt = self.visit_stmt(substmt_old);
 
                     let label_stmt = CStmtKind::Label(substmt);
@@ -786,10 +890,10 @@ impl ConversionContext {
                 }
 
                 ASTEntryTag::TagSwitchStmt if expected_ty & OTHER_STMT != 0 => {
-                    let scrutinee_old = node.children[0].expect("Switch expression not found");
+                    let scrutinee_old = child(0)?.ok_or_else(|| err("Switch expression not found"))?;
                     let scrutinee = self.visit_expr(scrutinee_old);
 
-                    let body_old = node.children[1].expect("Switch body not found");
+                    let body_old = child(1)?.ok_or_else(|| err("Switch body not found"))?;
                     let body = self.visit_stmt(body_old);
 
                     let switch_stmt = CStmtKind::Switch { scrutinee, body };
@@ -799,26 +903,36 @@ impl ConversionContext {
                 }
 
                 ASTEntryTag::TagCaseStmt if expected_ty & OTHER_STMT != 0 => {
-                    let expr_old = node.children[0].expect("Case expression not found");
+                    let expr_old = child(0)?.ok_or_else(|| err("Case expression not found"))?;
                     let expr = self.visit_expr(expr_old);
 
-                    let substmt_old = node.children[1].expect("Case sub-statement not found");
+                    let substmt_old = child(1)?.ok_or_else(|| err("Case sub-statement not found"))?;
                     let substmt = self.visit_stmt(substmt_old);
 
-                    let cie = match node.extras[0] {
-                        Value::U64(n) => ConstIntExpr::U(n),
-                        Value::I64(n) => ConstIntExpr::I(n),
-                        _ => panic!("Expected constant int expr"),
+                    let const_int_expr = |value: &Value| match *value {
+                        Value::U64(n) => Ok(ConstIntExpr::U(n)),
+                        Value::I64(n) => Ok(ConstIntExpr::I(n)),
+                        _ => Err(err("Expected constant int expr")),
+                    };
+                    let cie = const_int_expr(extra(0)?)?;
+
+                    // GNU case ranges carry their upper bound as a third child
+                    let case_stmt = match node.children.get(2) {
+                        Some(&range_end_old) => {
+                            let range_end_old = range_end_old.ok_or_else(|| err("Case range end not found"))?;
+                            let range_end = self.visit_expr(range_end_old);
+                            let end_cie = const_int_expr(extra(1)?)?;
+                            CStmtKind::CaseRange(expr, range_end, substmt, cie, end_cie)
+                        }
+                        None => CStmtKind::Case(expr, substmt, cie),
                     };
-
-                    let case_stmt = CStmtKind::Case(expr, substmt, cie);
 
                     self.add_stmt(new_id, located(node, case_stmt));
                     self.processed_nodes.insert(new_id, OTHER_STMT);
                 }
 
                 ASTEntryTag::TagDefaultStmt if expected_ty & OTHER_STMT != 0 => {
-                    let substmt_old = node.children[0].expect("Default sub-statement not found");
+                    let substmt_old = child(0)?.ok_or_else(|| err("Default sub-statement not found"))?;
                     let substmt = self.visit_stmt(substmt_old);
 
                     let default_stmt = CStmtKind::Default(substmt);
@@ -829,28 +943,33 @@ impl ConversionContext {
 
                 ASTEntryTag::TagAsmStmt if expected_ty & OTHER_STMT != 0 => {
 
-                    let is_volatile = node.extras[0].as_boolean().expect("volatile flag");
-                    let asm = node.extras[1].as_string().expect("assembly string").to_owned();
-                    let raw_inputs = node.extras[2].as_array().expect("input constraints array");
-                    let raw_outputs = node.extras[3].as_array().expect("output constraints array");
-                    let raw_clobbers = node.extras[4].as_array().expect("clobber array");
+                    let is_volatile = extra(0)?.as_boolean().ok_or_else(|| err("volatile flag"))?;
+                    let asm = extra(1)?.as_string().ok_or_else(|| err("assembly string"))?.to_owned();
+                    let raw_inputs = extra(2)?.as_array().ok_or_else(|| err("input constraints array"))?;
+                    let raw_outputs = extra(3)?.as_array().ok_or_else(|| err("output constraints array"))?;
+                    let raw_clobbers = extra(4)?.as_array().ok_or_else(|| err("clobber array"))?;
 
+                    if node.children.len() != raw_inputs.len() + raw_outputs.len() {
+                        return Err(err("Expected an expression for each assembly operand"))
+                    }
                     let (input_children, output_children) = node.children.split_at(raw_inputs.len());
 
                     let inputs: Vec<AsmOperand> = raw_inputs.iter().zip(input_children).map(|(c,e)| {
-                        let constraints = c.as_string().expect("constraint string").to_owned();
-                        let expression = self.visit_expr(e.expect("expression"));
-                        AsmOperand { constraints, expression }
-                    }).collect();
+                        let constraints = c.as_string().ok_or_else(|| err("constraint string"))?.to_owned();
+                        let expression = self.visit_expr(e.ok_or_else(|| err("expression"))?);
+                        Ok(AsmOperand { constraints, expression })
+                    }).collect::<Result<_, ConversionError>>()?;
 
                     let outputs: Vec<AsmOperand> = raw_outputs.iter().zip(output_children).map(|(c,e)| {
-                        let constraints = c.as_string().expect("constraint string").to_owned();
-                        let expression = self.visit_expr(e.expect("expression"));
-                        AsmOperand { constraints, expression }
-                    }).collect();
+                        let constraints = c.as_string().ok_or_else(|| err("constraint string"))?.to_owned();
+                        let expression = self.visit_expr(e.ok_or_else(|| err("expression"))?);
+                        Ok(AsmOperand { constraints, expression })
+                    }).collect::<Result<_, ConversionError>>()?;
 
-                    let clobbers: Vec<String> =
-                         raw_clobbers.iter().map(|c| c.as_string().expect("clobber string").to_owned()).collect();
+                    let clobbers: Vec<String> = raw_clobbers
+                        .iter()
+                        .map(|c| c.as_string().map(String::to_owned).ok_or_else(|| err("clobber string")))
+                        .collect::<Result<_, ConversionError>>()?;
 
                     let stmt = CStmtKind::Asm { is_volatile, asm, inputs, outputs, clobbers };
                     self.add_stmt(new_id, located(node, stmt));
@@ -860,78 +979,81 @@ impl ConversionContext {
                 // Expressions
 
                 ASTEntryTag::TagParenExpr if expected_ty & (EXPR | STMT) != 0 => {
-                    let wrapped = node.children[0].expect("Expected wrapped paren expression");
+                    let wrapped = child(0)?.ok_or_else(|| err("Expected wrapped paren expression"))?;
+                    if self.id_mapper.get_new(wrapped).is_some() {
+                        return Err(err("Wrapped paren expression is shared with another node"))
+                    }
 
                     self.id_mapper.merge_old(node_id, wrapped);
                     self.visit_node_type(wrapped, expected_ty);
                 }
 
                 ASTEntryTag::TagOffsetOfExpr if expected_ty & (EXPR | STMT) != 0 => {
-                    let value = node.extras[0].as_u64().expect("Expected offset value");
+                    let value = extra(0)?.as_u64().ok_or_else(|| err("Expected offset value"))?;
 
-                    let ty_old = node.type_id.expect("Expected expression to have type");
+                    let ty_old = node.type_id.ok_or_else(|| err("Expected expression to have type"))?;
                     let ty = self.visit_qualified_type(ty_old);
 
                     let offset_of = CExprKind::OffsetOf(ty, value);
 
-                    self.expr_possibly_as_stmt(expected_ty, new_id, node, offset_of);
+                    self.expr_possibly_as_stmt(expected_ty, new_id, node, offset_of).map_err(|e| err(&e))?;
                 }
 
                 ASTEntryTag::TagIntegerLiteral if expected_ty & (EXPR | STMT) != 0 => {
-                    let value = node.extras[0].as_u64().expect("Expected integer literal value");
-                    let base = node.extras[1].as_u64().expect("Expected integer base value");
+                    let value = extra(0)?.as_u64().ok_or_else(|| err("Expected integer literal value"))?;
+                    let base = extra(1)?.as_u64().ok_or_else(|| err("Expected integer base value"))?;
 
                     let base = match base {
                         8  => IntBase::Oct,
                         10 => IntBase::Dec,
                         16 => IntBase::Hex,
-                        _  => panic!("Invalid base: {}", base),
+                        _  => return Err(err(&format!("Invalid base: {}", base))),
                     };
 
-                    let ty_old = node.type_id.expect("Expected expression to have type");
+                    let ty_old = node.type_id.ok_or_else(|| err("Expected expression to have type"))?;
                     let ty = self.visit_qualified_type(ty_old);
 
                     let integer_literal = CExprKind::Literal(ty, CLiteral::Integer(value, base));
 
-                    self.expr_possibly_as_stmt(expected_ty, new_id, node, integer_literal);
+                    self.expr_possibly_as_stmt(expected_ty, new_id, node, integer_literal).map_err(|e| err(&e))?;
                 }
 
                 ASTEntryTag::TagStringLiteral if expected_ty & (EXPR | STMT) != 0 => {
-                    let ty_old = node.type_id.expect("Expected expression to have type");
+                    let ty_old = node.type_id.ok_or_else(|| err("Expected expression to have type"))?;
                     let ty = self.visit_qualified_type(ty_old);
-                    let width = node.extras[1].as_u64().expect("string literal char width") as u8;
-                    let bytes = node.extras[2].as_bytes().expect("string literal bytes").to_owned();
+                    let width = extra(1)?.as_u64().ok_or_else(|| err("string literal char width"))? as u8;
+                    let bytes = extra(2)?.as_bytes().ok_or_else(|| err("string literal bytes"))?.to_owned();
                     let string_literal = CExprKind::Literal(ty, CLiteral::String(bytes, width));
-                    self.expr_possibly_as_stmt(expected_ty, new_id, node, string_literal);
+                    self.expr_possibly_as_stmt(expected_ty, new_id, node, string_literal).map_err(|e| err(&e))?;
                 }
 
                 ASTEntryTag::TagCharacterLiteral if expected_ty & (EXPR | STMT) != 0 => {
-                    let value = node.extras[0].as_u64().expect("Expected character literal value");
+                    let value = extra(0)?.as_u64().ok_or_else(|| err("Expected character literal value"))?;
 
-                    let ty_old = node.type_id.expect("Expected expression to have type");
+                    let ty_old = node.type_id.ok_or_else(|| err("Expected expression to have type"))?;
                     let ty = self.visit_qualified_type(ty_old);
 
                     let character_literal = CExprKind::Literal(ty, CLiteral::Character(value));
 
-                    self.expr_possibly_as_stmt(expected_ty, new_id, node, character_literal);
+                    self.expr_possibly_as_stmt(expected_ty, new_id, node, character_literal).map_err(|e| err(&e))?;
                 }
 
                 ASTEntryTag::TagFloatingLiteral if expected_ty & (EXPR | STMT) != 0 => {
-                    let value = node.extras[0].as_f64().expect("Expected float literal value");
-                    let c_str = node.extras[1].as_string().expect("Expected float literal string").to_owned();
-                    let ty_old = node.type_id.expect("Expected expression to have type");
+                    let value = extra(0)?.as_f64().ok_or_else(|| err("Expected float literal value"))?;
+                    let c_str = extra(1)?.as_string().ok_or_else(|| err("Expected float literal string"))?.to_owned();
+                    let ty_old = node.type_id.ok_or_else(|| err("Expected expression to have type"))?;
                     let ty = self.visit_qualified_type(ty_old);
 
                     let floating_literal = CExprKind::Literal(ty, CLiteral::Floating(value, c_str));
 
-                    self.expr_possibly_as_stmt(expected_ty, new_id, node, floating_literal);
+                    self.expr_possibly_as_stmt(expected_ty, new_id, node, floating_literal).map_err(|e| err(&e))?;
                 }
 
                 ASTEntryTag::TagUnaryOperator if expected_ty & (EXPR | STMT) != 0 => {
 
-                    let prefix = node.extras[1].as_boolean().expect("Expected prefix information");
+                    let prefix = extra(1)?.as_boolean().ok_or_else(|| err("Expected prefix information"))?;
 
-                    let operator = match node.extras[0].as_string().expect("Expected operator").as_str() {
+                    let operator = match extra(0)?.as_string().ok_or_else(|| err("Expected operator"))?.as_str() {
                         "&" => UnOp::AddressOf,
                         "*" => UnOp::Deref,
                         "+" => UnOp::Plus,
@@ -944,48 +1066,50 @@ impl ConversionContext {
                         "__imag" => UnOp::Imag,
                         "__extension__" => UnOp::Extension,
                         "co_await" => UnOp::Coawait,
-                        o => panic!("Unexpected operator: {}", o),
+                        o => return Err(err(&format!("Unexpected operator: {}", o))),
                     };
 
-                    let operand_old = node.children[0].expect("Expected operand");
+                    let operand_old = child(0)?.ok_or_else(|| err("Expected operand"))?;
                     let operand = self.visit_expr(operand_old);
 
-                    let ty_old = node.type_id.expect("Expected expression to have type");
+                    let ty_old = node.type_id.ok_or_else(|| err("Expected expression to have type"))?;
                     let ty = self.visit_qualified_type(ty_old);
 
 
                     let unary = CExprKind::Unary(ty, operator, operand, node.rvalue);
 
-                    self.expr_possibly_as_stmt(expected_ty, new_id, node, unary);
+                    self.expr_possibly_as_stmt(expected_ty, new_id, node, unary).map_err(|e| err(&e))?;
                 }
 
                 ASTEntryTag::TagImplicitCastExpr if expected_ty & (EXPR | STMT) != 0 => {
-                    let expression_old = node.children[0].expect("Expected expression for implicit cast");
+                    let expression_old = child(0)?.ok_or_else(|| err("Expected expression for implicit cast"))?;
                     let expression = self.visit_expr(expression_old);
 
-                    let typ_old = node.type_id.expect("Expected type for implicit cast");
+                    let typ_old = node.type_id.ok_or_else(|| err("Expected type for implicit cast"))?;
                     let typ = self.visit_qualified_type(typ_old);
 
 
-                    let kind = parse_cast_kind(node.extras[0].as_string().expect("Expected cast kind"));
+                    let kind = parse_cast_kind(extra(0)?.as_string().ok_or_else(|| err("Expected cast kind"))?)
+                        .map_err(|e| err(&e))?;
                     let implicit = CExprKind::ImplicitCast(typ, expression, kind, None, node.rvalue);
 
-                    self.expr_possibly_as_stmt(expected_ty, new_id, node, implicit);
+                    self.expr_possibly_as_stmt(expected_ty, new_id, node, implicit).map_err(|e| err(&e))?;
                 }
 
                 ASTEntryTag::TagCStyleCastExpr if expected_ty & (EXPR | STMT) != 0 => {
-                    let expression_old = node.children[0].expect("Expected expression for explicit cast");
+                    let expression_old = child(0)?.ok_or_else(|| err("Expected expression for explicit cast"))?;
                     let expression = self.visit_expr(expression_old);
 
-                    let typ_old = node.type_id.expect("Expected type for explicit cast");
+                    let typ_old = node.type_id.ok_or_else(|| err("Expected type for explicit cast"))?;
                     let typ = self.visit_qualified_type(typ_old);
 
 
-                    let kind = parse_cast_kind(node.extras[0].as_string().expect("Expected cast kind"));
+                    let kind = parse_cast_kind(extra(0)?.as_string().ok_or_else(|| err("Expected cast kind"))?)
+                        .map_err(|e| err(&e))?;
 
                     let opt_field_id = match kind {
                         CastKind::ToUnion => {
-                            let id = node.children[1].expect("Expected field for union cast");
+                            let id = child(1)?.ok_or_else(|| err("Expected field for union cast"))?;
                             Some(self.visit_decl(id))
                         }
                         _ => None,
@@ -993,51 +1117,51 @@ impl ConversionContext {
 
                     let implicit = CExprKind::ExplicitCast(typ, expression, kind, opt_field_id, node.rvalue);
 
-                    self.expr_possibly_as_stmt(expected_ty, new_id, node, implicit);
+                    self.expr_possibly_as_stmt(expected_ty, new_id, node, implicit).map_err(|e| err(&e))?;
                 }
 
                 ASTEntryTag::TagCallExpr if expected_ty & (EXPR | STMT) != 0 => {
-                    let func_old = node.children[0].expect("Expected function for function call");
+                    let func_old = child(0)?.ok_or_else(|| err("Expected function for function call"))?;
                     let func = self.visit_expr(func_old);
 
                     let args: Vec<CExprId> = node.children
                         .iter()
                         .skip(1)
                         .map(|id| {
-                            let arg_id = id.expect("Expected call expression argument");
-                            self.visit_expr(arg_id)
+                            let arg_id = id.ok_or_else(|| err("Expected call expression argument"))?;
+                            Ok(self.visit_expr(arg_id))
                         })
-                        .collect();
+                        .collect::<Result<_, ConversionError>>()?;
 
-                    let ty_old = node.type_id.expect("Expected expression to have type");
+                    let ty_old = node.type_id.ok_or_else(|| err("Expected expression to have type"))?;
                     let ty = self.visit_qualified_type(ty_old);
 
                     let call = CExprKind::Call(ty, func, args);
 
-                    self.expr_possibly_as_stmt(expected_ty, new_id, node, call);
+                    self.expr_possibly_as_stmt(expected_ty, new_id, node, call).map_err(|e| err(&e))?;
                 }
 
                 ASTEntryTag::TagMemberExpr if expected_ty & (EXPR | STMT) != 0 => {
-                    let base_old = node.children[0].expect("Expected base for member expression");
+                    let base_old = child(0)?.ok_or_else(|| err("Expected base for member expression"))?;
                     let base = self.visit_expr(base_old);
 
-                    let field_old = node.children[1].expect("Expected field for member expression");
+                    let field_old = child(1)?.ok_or_else(|| err("Expected field for member expression"))?;
                     let field = self.visit_decl(field_old);
 
-                    let ty_old = node.type_id.expect("Expected expression to have type");
+                    let ty_old = node.type_id.ok_or_else(|| err("Expected expression to have type"))?;
                     let ty = self.visit_qualified_type(ty_old);
 
                     let member_kind =
-                        if node.extras[0].as_boolean().expect("is arrow")
+                        if extra(0)?.as_boolean().ok_or_else(|| err("is arrow"))?
                             { MemberKind::Arrow } else { MemberKind::Dot };
 
                     let member = CExprKind::Member(ty, base, field, member_kind, node.rvalue);
 
-                    self.expr_possibly_as_stmt(expected_ty, new_id, node, member);
+                    self.expr_possibly_as_stmt(expected_ty, new_id, node, member).map_err(|e| err(&e))?;
                 }
 
                 ASTEntryTag::TagBinaryOperator if expected_ty & (EXPR | STMT) != 0 => {
-                    let operator = match node.extras[0].as_string().expect("Expected operator").as_str() {
+                    let operator = match extra(0)?.as_string().ok_or_else(|| err("Expected operator"))?.as_str() {
                         "*" => BinOp::Multiply,
                         "/" => BinOp::Divide,
                         "%" => BinOp::Modulus,
@@ -1068,135 +1192,135 @@ impl ConversionContext {
                         "&=" => BinOp::AssignBitAnd,
                         "=" => BinOp::Assign,
                         "," => BinOp::Comma,
-                        _ => unimplemented!(),
+                        o => return Err(err(&format!("Unexpected operator: {}", o))),
                     };
 
-                    let left_operand_old = node.children[0].expect("Expected left operand");
+                    let left_operand_old = child(0)?.ok_or_else(|| err("Expected left operand"))?;
                     let left_operand = self.visit_expr(left_operand_old);
 
-                    let right_operand_old = node.children[1].expect("Expected right operand");
+                    let right_operand_old = child(1)?.ok_or_else(|| err("Expected right operand"))?;
                     let right_operand = self.visit_expr(right_operand_old);
 
-                    let ty_old = node.type_id.expect("Expected expression to have type");
+                    let ty_old = node.type_id.ok_or_else(|| err("Expected expression to have type"))?;
                     let ty = self.visit_qualified_type(ty_old);
 
-                    let opt_lhs_type_id = expect_opt_u64(&node.extras[1]).expect("Expected compute lhs type");
+                    let opt_lhs_type_id = expect_opt_u64(extra(1)?).ok_or_else(|| err("Expected compute lhs type"))?;
                     let opt_lhs_type = opt_lhs_type_id.map(|x| self.visit_qualified_type(x));
 
-                    let opt_res_type_id = expect_opt_u64(&node.extras[2]).expect("Expected compute lhs type");
+                    let opt_res_type_id = expect_opt_u64(extra(2)?).ok_or_else(|| err("Expected compute lhs type"))?;
                     let opt_res_type = opt_res_type_id.map(|x| self.visit_qualified_type(x));
 
                     let binary = CExprKind::Binary(ty, operator, left_operand, right_operand, opt_lhs_type, opt_res_type);
 
-                    self.expr_possibly_as_stmt(expected_ty, new_id, node, binary);
+                    self.expr_possibly_as_stmt(expected_ty, new_id, node, binary).map_err(|e| err(&e))?;
                 }
 
                 ASTEntryTag::TagDeclRefExpr if expected_ty & (EXPR | STMT) != 0 => {
-                    let declaration_old = node.children[0].expect("Expected declaration on expression tag decl");
+                    let declaration_old = child(0)?.ok_or_else(|| err("Expected declaration on expression tag decl"))?;
                     let declaration = self.visit_decl(declaration_old);
 
-                    let ty_old = node.type_id.expect("Expected expression to have type");
+                    let ty_old = node.type_id.ok_or_else(|| err("Expected expression to have type"))?;
                     let ty = self.visit_qualified_type(ty_old);
 
                     let decl = CExprKind::DeclRef(ty, declaration, node.rvalue);
 
-                    self.expr_possibly_as_stmt(expected_ty, new_id, node, decl);
+                    self.expr_possibly_as_stmt(expected_ty, new_id, node, decl).map_err(|e| err(&e))?;
                 }
 
                 ASTEntryTag::TagArraySubscriptExpr if expected_ty & (EXPR | STMT) != 0 => {
-                    let lhs_old = node.children[0].expect("Expected LHS on array subscript expression");
+                    let lhs_old = child(0)?.ok_or_else(|| err("Expected LHS on array subscript expression"))?;
                     let lhs = self.visit_expr(lhs_old);
 
-                    let rhs_old = node.children[1].expect("Expected RHS on array subscript expression");
+                    let rhs_old = child(1)?.ok_or_else(|| err("Expected RHS on array subscript expression"))?;
                     let rhs = self.visit_expr(rhs_old);
 
-                    let ty_old = node.type_id.expect("Expected expression to have type");
+                    let ty_old = node.type_id.ok_or_else(|| err("Expected expression to have type"))?;
                     let ty = self.visit_qualified_type(ty_old);
 
                     let subscript = CExprKind::ArraySubscript(ty, lhs, rhs, node.rvalue);
 
-                    self.expr_possibly_as_stmt(expected_ty, new_id, node, subscript);
+                    self.expr_possibly_as_stmt(expected_ty, new_id, node, subscript).map_err(|e| err(&e))?;
                 }
 
                 ASTEntryTag::TagConditionalOperator if expected_ty & (EXPR | STMT) != 0 => {
-                    let cond_old = node.children[0].expect("Expected condition on if expression");
+                    let cond_old = child(0)?.ok_or_else(|| err("Expected condition on if expression"))?;
                     let cond = self.visit_expr(cond_old);
 
-                    let lhs_old = node.children[1].expect("Expected 'then' on if expression");
+                    let lhs_old = child(1)?.ok_or_else(|| err("Expected 'then' on if expression"))?;
                     let lhs = self.visit_expr(lhs_old);
 
-                    let rhs_old = node.children[2].expect("Expected 'else' on if expression");
+                    let rhs_old = child(2)?.ok_or_else(|| err("Expected 'else' on if expression"))?;
                     let rhs = self.visit_expr(rhs_old);
 
-                    let ty_old = node.type_id.expect("Expected expression to have type");
+                    let ty_old = node.type_id.ok_or_else(|| err("Expected expression to have type"))?;
                     let ty = self.visit_qualified_type(ty_old);
 
                     let conditional = CExprKind::Conditional(ty, cond, lhs, rhs);
 
-                    self.expr_possibly_as_stmt(expected_ty, new_id, node, conditional);
+                    self.expr_possibly_as_stmt(expected_ty, new_id, node, conditional).map_err(|e| err(&e))?;
                 }
 
                 ASTEntryTag::TagBinaryConditionalOperator if expected_ty & (EXPR | STMT) != 0 => {
-                    let lhs_old = node.children[0].expect("Expected condition on if expression");
+                    let lhs_old = child(0)?.ok_or_else(|| err("Expected condition on if expression"))?;
                     let lhs = self.visit_expr(lhs_old);
 
-                    let rhs_old = node.children[1].expect("Expected 'else' on if expression");
+                    let rhs_old = child(1)?.ok_or_else(|| err("Expected 'else' on if expression"))?;
                     let rhs = self.visit_expr(rhs_old);
 
-                    let ty_old = node.type_id.expect("Expected expression to have type");
+                    let ty_old = node.type_id.ok_or_else(|| err("Expected expression to have type"))?;
                     let ty = self.visit_qualified_type(ty_old);
 
                     let conditional = CExprKind::BinaryConditional(ty, lhs, rhs);
 
-                    self.expr_possibly_as_stmt(expected_ty, new_id, node, conditional);
+                    self.expr_possibly_as_stmt(expected_ty, new_id, node, conditional).map_err(|e| err(&e))?;
                 }
 
                 ASTEntryTag::TagUnaryExprOrTypeTraitExpr if expected_ty & (EXPR | STMT) != 0 => {
-                    let ty = node.type_id.expect("Expected expression to have type");
+                    let ty = node.type_id.ok_or_else(|| err("Expected expression to have type"))?;
                     let ty = self.visit_qualified_type(ty);
 
-                    let expr = node.children[0].map(|x| self.visit_expr(x));
+                    let expr = child(0)?.map(|x| self.visit_expr(x));
 
-                    let kind_name = node.extras[0].as_string().expect("expected kind").as_str();
+                    let kind_name = extra(0)?.as_string().ok_or_else(|| err("expected kind"))?.as_str();
                     let kind = match kind_name {
                         "sizeof" => UnTypeOp::SizeOf,
                         "alignof" => UnTypeOp::AlignOf,
-                        str => panic!("Unsupported operation: {}", str),
+                        str => return Err(err(&format!("Unsupported operation: {}", str))),
                     };
 
-                    let arg_ty = node.extras[1].as_u64().expect("expected type id");
+                    let arg_ty = extra(1)?.as_u64().ok_or_else(|| err("expected type id"))?;
                     let arg_ty = self.visit_qualified_type(arg_ty);
 
                     let operator = CExprKind::UnaryType(ty, kind, expr, arg_ty);
 
-                    self.expr_possibly_as_stmt(expected_ty, new_id, node, operator);
+                    self.expr_possibly_as_stmt(expected_ty, new_id, node, operator).map_err(|e| err(&e))?;
                 }
 
                 ASTEntryTag::TagCompoundLiteralExpr => {
-                    let ty_old = node.type_id.expect("Expected compound literal to have type");
+                    let ty_old = node.type_id.ok_or_else(|| err("Expected compound literal to have type"))?;
                     let ty = self.visit_qualified_type(ty_old);
 
-                    let val_old = node.children[0].expect("Expected child on compound literal");
+                    let val_old = child(0)?.ok_or_else(|| err("Expected child on compound literal"))?;
                     let val = self.visit_expr(val_old);
 
-                    self.expr_possibly_as_stmt(expected_ty, new_id, node, CExprKind::CompoundLiteral(ty, val))
+                    self.expr_possibly_as_stmt(expected_ty, new_id, node, CExprKind::CompoundLiteral(ty, val)).map_err(|e| err(&e))?;
                 }
 
                 ASTEntryTag::TagPredefinedExpr => {
-                    let ty_old = node.type_id.expect("Expected predefined expr to have type");
+                    let ty_old = node.type_id.ok_or_else(|| err("Expected predefined expr to have type"))?;
                     let ty = self.visit_qualified_type(ty_old);
 
-                    let val_old = node.children[0].expect("Expected child on predefined expr");
+                    let val_old = child(0)?.ok_or_else(|| err("Expected child on predefined expr"))?;
                     let val = self.visit_expr(val_old);
 
-                    self.expr_possibly_as_stmt(expected_ty, new_id, node, CExprKind::Predefined(ty, val))
+                    self.expr_possibly_as_stmt(expected_ty, new_id, node, CExprKind::Predefined(ty, val)).map_err(|e| err(&e))?;
                 }
 
                 ASTEntryTag::TagImplicitValueInitExpr => {
-                    let ty_old = node.type_id.expect("Expected expression to have type");
+                    let ty_old = node.type_id.ok_or_else(|| err("Expected expression to have type"))?;
                     let ty = self.visit_qualified_type(ty_old);
 
-                    self.expr_possibly_as_stmt(expected_ty, new_id, node, CExprKind::ImplicitValueInit(ty))
+                    self.expr_possibly_as_stmt(expected_ty, new_id, node, CExprKind::ImplicitValueInit(ty)).map_err(|e| err(&e))?;
                 }
 
                 ASTEntryTag::TagInitListExpr => {
@@ -1204,67 +1328,91 @@ impl ConversionContext {
                     let exprs: Vec<CExprId> = node.children
                         .iter()
                         .map(|id| {
-                            let expr_id = id.expect("init expression id");
-                            self.visit_expr(expr_id)
+                            let expr_id = id.ok_or_else(|| err("init expression id"))?;
+                            Ok(self.visit_expr(expr_id))
                         })
-                        .collect();
+                        .collect::<Result<_, ConversionError>>()?;
 
-                    let ty_old = node.type_id.expect("Expected expression to have type");
+                    let ty_old = node.type_id.ok_or_else(|| err("Expected expression to have type"))?;
                     let ty = self.visit_qualified_type(ty_old);
 
-                    let union_field_id = expect_opt_u64(&node.extras[0]).expect("Bad union field ID entry").map(|x| self.visit_decl(x));
-                    let syntax_id = expect_opt_u64(&node.extras[1]).expect("Bad syntax ID entry").map(|x| self.visit_expr(x));
+                    let union_field_id = expect_opt_u64(extra(0)?).ok_or_else(|| err("Bad union field ID entry"))?.map(|x| self.visit_decl(x));
+                    let syntax_id = expect_opt_u64(extra(1)?).ok_or_else(|| err("Bad syntax ID entry"))?.map(|x| self.visit_expr(x));
 
                     let kind = CExprKind::InitList(ty, exprs, union_field_id, syntax_id);
-                    self.expr_possibly_as_stmt(expected_ty, new_id, node, kind)
+                    self.expr_possibly_as_stmt(expected_ty, new_id, node, kind).map_err(|e| err(&e))?;
                 }
 
                 ASTEntryTag::TagDesignatedInitExpr => {
-                    let ty_old = node.type_id.expect("Expected expression to have type");
+                    let ty_old = node.type_id.ok_or_else(|| err("Expected expression to have type"))?;
                     let ty = self.visit_qualified_type(ty_old);
 
-                    let designator_cbors = node.extras[0].as_array().expect("Expected designators array");
+                    let designator_cbors = extra(0)?.as_array().ok_or_else(|| err("Expected designators array"))?;
                     let designators = designator_cbors.into_iter().map(|x| {
-                        let entry = x.as_array().expect("expected designator array");
-                        match entry[0].as_u64().expect("expected designator tag") {
-                            1 => Designator::Index(entry[1].as_u64().expect("expected array index")),
-                            2 => Designator::Field(CDeclId(entry[1].as_u64().expect("expected field id"))),
-                            3 => Designator::Range(entry[1].as_u64().expect("expected array start"),
-                                                   entry[2].as_u64().expect("expected array end")),
-                            n => panic!("invalid designator tag: {}", n),
-                        }
-                    } ).collect();
+                        let entry = x.as_array().ok_or_else(|| err("expected designator array"))?;
+                        let field = |i: usize, msg: &str| entry.get(i).and_then(Value::as_u64).ok_or_else(|| err(msg));
+                        let designator = match field(0, "expected designator tag")? {
+                            1 => Designator::Index(field(1, "expected array index")?),
+                            2 => Designator::Field(CDeclId(field(1, "expected field id")?)),
+                            3 => Designator::Range(field(1, "expected array start")?,
+                                                   field(2, "expected array end")?),
+                            n => return Err(err(&format!("invalid designator tag: {}", n))),
+                        };
+                        Ok(designator)
+                    } ).collect::<Result<_, ConversionError>>()?;
 
-                    let init_id = node.children[0].expect("Expected initializer expression on designated init expr");
+                    let init_id = child(0)?.ok_or_else(|| err("Expected initializer expression on designated init expr"))?;
                     let init_expr = self.visit_expr(init_id);
 
                     let kind = CExprKind::DesignatedInitExpr(ty, designators, init_expr);
 
-                    self.expr_possibly_as_stmt(expected_ty, new_id, node, kind)
+                    self.expr_possibly_as_stmt(expected_ty, new_id, node, kind).map_err(|e| err(&e))?;
                 }
 
                 ASTEntryTag::TagStmtExpr => {
-                    let child_id = node.children[0].expect("Expected compound statement ID");
+                    let child_id = child(0)?.ok_or_else(|| err("Expected compound statement ID"))?;
                     let child = self.visit_stmt(child_id);
 
-                    let ty_old = node.type_id.expect("Expected expression to have type");
+                    let ty_old = node.type_id.ok_or_else(|| err("Expected expression to have type"))?;
                     let ty = self.visit_qualified_type(ty_old);
 
                     let stmt_expr = CExprKind::Statements(ty, child);
 
-                    self.expr_possibly_as_stmt(expected_ty, new_id, node, stmt_expr)
+                    self.expr_possibly_as_stmt(expected_ty, new_id, node, stmt_expr).map_err(|e| err(&e))?;
+                }
+
+                ASTEntryTag::TagBlockExpr => {
+                    let (body_id, parameter_ids) = node.children.split_last()
+                        .ok_or_else(|| err("Expected to find a block body"))?;
+                    let body_id = body_id.ok_or_else(|| err("Expected block body"))?;
+                    let body = self.visit_stmt(body_id);
+
+                    let params = parameter_ids
+                        .iter()
+                        .map(|id| {
+                            let param = id.ok_or_else(|| err("Block parameter not found"))?;
+                            Ok(CDeclId(self.visit_node_type(param, VAR_DECL)))
+                        })
+                        .collect::<Result<_, ConversionError>>()?;
+
+                    let ty_old = node.type_id.ok_or_else(|| err("Expected expression to have type"))?;
+                    let ty = self.visit_qualified_type(ty_old);
+
+                    let block = CExprKind::Block(ty, params, body);
+
+                    self.expr_possibly_as_stmt(expected_ty, new_id, node, block).map_err(|e| err(&e))?;
                 }
 
                 ASTEntryTag::TagVAArgExpr => {
-                    let child_id = node.children[0].expect("Expected subexpression");
+                    let child_id = child(0)?.ok_or_else(|| err("Expected subexpression"))?;
                     let child = self.visit_expr(child_id);
 
-                    let ty_old = node.type_id.expect("Expected expression to have type");
+                    let ty_old = node.type_id.ok_or_else(|| err("Expected expression to have type"))?;
                     let ty = self.visit_qualified_type(ty_old);
 
                     let vaarg_expr = CExprKind::VAArg(ty, child);
 
-                    self.expr_possibly_as_stmt(expected_ty, new_id, node, vaarg_expr)
+                    self.expr_possibly_as_stmt(expected_ty, new_id, node, vaarg_expr).map_err(|e| err(&e))?;
                 }
 
                 ASTEntryTag::TagShuffleVectorExpr => {
@@ -1272,17 +1420,17 @@ impl ConversionContext {
                     let kids: Vec<CExprId> = node.children
                         .iter()
                         .map(|id| {
-                            let child_id = id.expect("Missing shuffle argument");
-                            self.visit_expr(child_id)
+                            let child_id = id.ok_or_else(|| err("Missing shuffle argument"))?;
+                            Ok(self.visit_expr(child_id))
                         })
-                        .collect();
+                        .collect::<Result<_, ConversionError>>()?;
 
-                    let ty_old = node.type_id.expect("Expected expression to have type");
+                    let ty_old = node.type_id.ok_or_else(|| err("Expected expression to have type"))?;
                     let ty = self.visit_qualified_type(ty_old);
 
                     let e = CExprKind::ShuffleVector(ty, kids);
 
-                    self.expr_possibly_as_stmt(expected_ty, new_id, node, e)
+                    self.expr_possibly_as_stmt(expected_ty, new_id, node, e).map_err(|e| err(&e))?;
                 }
 
                 ASTEntryTag::TagConvertVectorExpr => {
@@ -1290,63 +1438,64 @@ impl ConversionContext {
                     let kids: Vec<CExprId> = node.children
                         .iter()
                         .map(|id| {
-                            let child_id = id.expect("Missing convert argument");
-                            self.visit_expr(child_id)
+                            let child_id = id.ok_or_else(|| err("Missing convert argument"))?;
+                            Ok(self.visit_expr(child_id))
                         })
-                        .collect();
+                        .collect::<Result<_, ConversionError>>()?;
 
-                    let ty_old = node.type_id.expect("Expected expression to have type");
+                    let ty_old = node.type_id.ok_or_else(|| err("Expected expression to have type"))?;
                     let ty = self.visit_qualified_type(ty_old);
 
                     let e = CExprKind::ConvertVector(ty, kids);
 
-                    self.expr_possibly_as_stmt(expected_ty, new_id, node, e)
+                    self.expr_possibly_as_stmt(expected_ty, new_id, node, e).map_err(|e| err(&e))?;
                 }
 
                 // Declarations
 
                 ASTEntryTag::TagFunctionDecl if expected_ty & OTHER_DECL != 0 => {
-                    let name = node.extras[0].as_string()
-                        .expect("Expected to find function name").to_owned();
+                    let name = extra(0)?.as_string()
+                        .ok_or_else(|| err("Expected to find function name"))?.to_owned();
 
-                    let is_extern = node.extras[1].as_boolean().expect("Expected to find visibility");
-                    let is_inline = node.extras[2].as_boolean().expect("Expected to find inline");
+                    let is_extern = extra(1)?.as_boolean().ok_or_else(|| err("Expected to find visibility"))?;
+                    let is_inline = extra(2)?.as_boolean().ok_or_else(|| err("Expected to find inline"))?;
 
-                    let is_main = node.extras[3].as_boolean().expect("Expected to find main");
+                    let is_main = extra(3)?.as_boolean().ok_or_else(|| err("Expected to find main"))?;
                     if is_main {
                         self.typed_context.c_main = Some(CDeclId(new_id));
                     }
 
-                    let is_implicit = node.extras[4].as_boolean().expect("Expected to find implicit");
+                    let is_implicit = extra(4)?.as_boolean().ok_or_else(|| err("Expected to find implicit"))?;
+                    let attrs = parse_decl_attrs(extra(5)?).map_err(|e| err(&e))?;
 
-                    let typ_old = node.type_id.expect("Expected to find a type on a function decl");
+                    let typ_old = node.type_id.ok_or_else(|| err("Expected to find a type on a function decl"))?;
                     let typ = CTypeId(self.visit_node_type(typ_old, TYPE));
 
                     let (body_id, parameter_ids) = node.children.split_last()
-                        .expect("Expected to find a function body");
+                        .ok_or_else(|| err("Expected to find a function body"))?;
 
                     let body = body_id.map(|b| self.visit_stmt(b));
 
                     let parameters = parameter_ids
                         .iter()
                         .map(|id| {
-                            let param = id.expect("Param field decl not found");
-                            CDeclId(self.visit_node_type(param, VAR_DECL))
+                            let param = id.ok_or_else(|| err("Param field decl not found"))?;
+                            Ok(CDeclId(self.visit_node_type(param, VAR_DECL)))
                         })
-                        .collect();
+                        .collect::<Result<_, ConversionError>>()?;
 
                     let function_decl =
-                        CDeclKind::Function { is_extern, is_inline, is_implicit, typ, name, parameters, body };
+                        CDeclKind::Function { is_extern, is_inline, is_implicit, typ, name, parameters, body, attrs };
 
                     self.add_decl(new_id, located(node, function_decl));
                     self.processed_nodes.insert(new_id, OTHER_DECL);
                 }
 
                 ASTEntryTag::TagTypedefDecl if expected_ty & TYPDEF_DECL != 0 => {
-                    let name = node.extras[0].as_string().expect("Expected to find typedef name").to_owned();
-                    let is_implicit = node.extras[1].as_boolean().expect("Expected to find implicit");
+                    let name = extra(0)?.as_string().ok_or_else(|| err("Expected to find typedef name"))?.to_owned();
+                    let is_implicit = extra(1)?.as_boolean().ok_or_else(|| err("Expected to find implicit"))?;
 
-                    let typ_old = node.type_id.expect("Expected to find type on typedef declaration");
+                    let typ_old = node.type_id.ok_or_else(|| err("Expected to find type on typedef declaration"))?;
                     let typ = self.visit_qualified_type(typ_old);
 
                     let typdef_decl = CDeclKind::Typedef { name, typ, is_implicit };
@@ -1355,18 +1504,30 @@ impl ConversionContext {
                     self.processed_nodes.insert(new_id, TYPDEF_DECL);
                 }
 
+                ASTEntryTag::TagStaticAssertDecl if expected_ty & OTHER_DECL != 0 => {
+                    let assert_expr = child(0)?
+                        .map(|id| self.visit_expr(id))
+                        .ok_or_else(|| err("Expected to find static assertion condition"))?;
+                    let message = expect_opt_str(extra(0)?).ok_or_else(|| err("Expected name"))?.map(str::to_string);
+
+                    let static_assert_decl = CDeclKind::StaticAssert { assert_expr, message };
+
+                    self.add_decl(new_id, located(node, static_assert_decl));
+                    self.processed_nodes.insert(new_id, OTHER_DECL);
+                }
+
                 ASTEntryTag::TagEnumDecl if expected_ty & ENUM_DECL != 0 => {
-                    let name = expect_opt_str(&node.extras[0]).unwrap().map(str::to_string);
+                    let name = expect_opt_str(extra(0)?).ok_or_else(|| err("Expected name"))?.map(str::to_string);
 
                     let variants = node.children
                         .iter()
                         .map(|id| {
-                            let con = id.expect("Enum constant not found");
+                            let con = id.ok_or_else(|| err("Enum constant not found"))?;
                             let id = CDeclId(self.visit_node_type(con, ENUM_CON));
                             self.typed_context.parents.insert(id, CDeclId(new_id));
-                            id
+                            Ok(id)
                         })
-                        .collect();
+                        .collect::<Result<_, ConversionError>>()?;
 
                     let integral_type = node.type_id.map(|x| self.visit_qualified_type(x));
 
@@ -1377,11 +1538,11 @@ impl ConversionContext {
                 }
 
                 ASTEntryTag::TagEnumConstantDecl if expected_ty & ENUM_CON != 0 => {
-                    let name = node.extras[0].as_string().expect("Expected to find enum constant name").to_owned();
-                    let value = match node.extras[1] {
+                    let name = extra(0)?.as_string().ok_or_else(|| err("Expected to find enum constant name"))?.to_owned();
+                    let value = match *extra(1)? {
                         Value::U64(n) => ConstIntExpr::U(n),
                         Value::I64(n) => ConstIntExpr::I(n),
-                        _ => panic!("Expected constant int expr"),
+                        _ => return Err(err("Expected constant int expr")),
                     };
 
                     let enum_constant_decl = CDeclKind::EnumConstant { name, value };
@@ -1391,52 +1552,55 @@ impl ConversionContext {
                 }
 
                 ASTEntryTag::TagVarDecl if expected_ty & VAR_DECL != 0 => {
-                    let ident = node.extras[0].as_string().expect("Expected to find variable name").to_owned();
-
-                    let is_static = node.extras[1].as_boolean().expect("Expected to find duration");
-                    let is_extern = node.extras[2].as_boolean().expect("Expected to find visibility");
-                    let is_defn   = node.extras[3].as_boolean().expect("Expected to find whether decl is definition");
-                    assert!(if is_extern { is_static } else { true }, "Something cannot be extern without also being static");
+                    let ident = extra(0)?.as_string().ok_or_else(|| err("Expected to find variable name"))?.to_owned();
+
+                    let is_static = extra(1)?.as_boolean().ok_or_else(|| err("Expected to find duration"))?;
+                    let is_extern = extra(2)?.as_boolean().ok_or_else(|| err("Expected to find visibility"))?;
+                    let is_defn   = extra(3)?.as_boolean().ok_or_else(|| err("Expected to find whether decl is definition"))?;
+                    let attrs = parse_decl_attrs(extra(4)?).map_err(|e| err(&e))?;
+                    if is_extern && !is_static {
+                        return Err(err("Something cannot be extern without also being static"))
+                    }
 
-                    let initializer = node.children[0]
+                    let initializer = child(0)?
                         .map(|id| self.visit_expr(id));
 
-                    let typ_id = node.type_id.expect("Expected to find type on variable declaration");
+                    let typ_id = node.type_id.ok_or_else(|| err("Expected to find type on variable declaration"))?;
                     let typ = self.visit_qualified_type(typ_id);
 
-                    let variable_decl = CDeclKind::Variable { is_static, is_extern, is_defn, ident, initializer, typ };
+                    let variable_decl = CDeclKind::Variable { is_static, is_extern, is_defn, ident, initializer, typ, attrs };
 
                     self.add_decl(new_id, located(node, variable_decl));
                     self.processed_nodes.insert(new_id, VAR_DECL);
                 }
 
                 ASTEntryTag::TagStructDecl if expected_ty & RECORD_DECL != 0 => {
-                    let name = expect_opt_str(&node.extras[0]).unwrap().map(str::to_string);
-                    let has_def = node.extras[1].as_boolean().expect("Expected has_def flag on struct");
-                    let attrs = node.extras[2].as_array().expect("Expected attribute array on record");
-                    let manual_alignment = expect_opt_u64(&node.extras[3]).expect("Expected struct alignment");
-                    let max_field_alignment = expect_opt_u64(&node.extras[4]).expect("Expected struct field align");
-                    let platform_byte_size = node.extras[5].as_u64().expect("Expected struct size");
-                    let platform_alignment = node.extras[6].as_u64().expect("Expected struct alignment");
+                    let name = expect_opt_str(extra(0)?).ok_or_else(|| err("Expected name"))?.map(str::to_string);
+                    let has_def = extra(1)?.as_boolean().ok_or_else(|| err("Expected has_def flag on struct"))?;
+                    let attrs = extra(2)?.as_array().ok_or_else(|| err("Expected attribute array on record"))?;
+                    let manual_alignment = expect_opt_u64(extra(3)?).ok_or_else(|| err("Expected struct alignment"))?;
+                    let max_field_alignment = expect_opt_u64(extra(4)?).ok_or_else(|| err("Expected struct field align"))?;
+                    let platform_byte_size = extra(5)?.as_u64().ok_or_else(|| err("Expected struct size"))?;
+                    let platform_alignment = extra(6)?.as_u64().ok_or_else(|| err("Expected struct alignment"))?;
 
                     let fields: Option<Vec<CDeclId>> =
                     if has_def {
                         Some(node.children
                             .iter()
                             .map(|id| {
-                                let field = id.expect("Record field decl not found");
+                                let field = id.ok_or_else(|| err("Record field decl not found"))?;
                                 let id = CDeclId(self.visit_node_type(field, FIELD_DECL));
                                 self.typed_context.parents.insert(id, CDeclId(new_id));
-                                id
+                                Ok(id)
                             })
-                            .collect())
+                            .collect::<Result<_, ConversionError>>()?)
                     } else {
                         None
                     };
 
                     let mut is_packed = false;
                     for attr in attrs {
-                        match attr.as_string().expect("Records attributes should be strings").as_str() {
+                        match attr.as_string().ok_or_else(|| err("Records attributes should be strings"))?.as_str() {
                             "packed" => is_packed = true,
                             _ => {}
                         }
@@ -1457,44 +1621,48 @@ impl ConversionContext {
                 },
 
                 ASTEntryTag::TagUnionDecl if expected_ty & RECORD_DECL != 0 => {
-                    let name = expect_opt_str(&node.extras[0]).unwrap().map(str::to_string);
-                    let has_def = node.extras[1].as_boolean().expect("Expected has_def flag on struct");
+                    let name = expect_opt_str(extra(0)?).ok_or_else(|| err("Expected name"))?.map(str::to_string);
+                    let has_def = extra(1)?.as_boolean().ok_or_else(|| err("Expected has_def flag on struct"))?;
+                    let platform_byte_size = extra(5)?.as_u64().ok_or_else(|| err("Expected union size"))?;
+                    let platform_alignment = extra(6)?.as_u64().ok_or_else(|| err("Expected union alignment"))?;
                     let fields: Option<Vec<CDeclId>> =
                         if has_def {
                             Some(node.children
                                 .iter()
                                 .map(|id| {
-                                    let field = id.expect("Record field decl not found");
+                                    let field = id.ok_or_else(|| err("Record field decl not found"))?;
                                     let id = CDeclId(self.visit_node_type(field, FIELD_DECL));
                                     self.typed_context.parents.insert(id, CDeclId(new_id));
-                                    id
+                                    Ok(id)
                                 })
-                                .collect())
+                                .collect::<Result<_, ConversionError>>()?)
                         } else {
                             None
                         };
 
-                    let record = CDeclKind::Union { name, fields };
+                    let record = CDeclKind::Union { name, fields, platform_byte_size, platform_alignment };
 
                     self.add_decl(new_id, located(node, record));
                     self.processed_nodes.insert(new_id, RECORD_DECL);
                 },
 
                 ASTEntryTag::TagFieldDecl if expected_ty & FIELD_DECL != 0 => {
-                    let name = node.extras[0].as_string().expect("A field needs a name").to_owned();
-                    let typ_id = node.type_id.expect("Expected to find type on field declaration");
+                    let name = extra(0)?.as_string().ok_or_else(|| err("A field needs a name"))?.to_owned();
+                    let typ_id = node.type_id.ok_or_else(|| err("Expected to find type on field declaration"))?;
                     let typ = self.visit_qualified_type(typ_id);
-                    let bitfield_width = node.extras[1].as_u64();
-                    let platform_bit_offset = node.extras[2].as_u64().expect("Did not find field bit offset");
-                    let platform_type_bitwidth = node.extras[3].as_u64().expect("Did not find field bitwidth");
+                    let bitfield_width = extra(1)?.as_u64();
+                    let platform_bit_offset = extra(2)?.as_u64().ok_or_else(|| err("Did not find field bit offset"))?;
+                    let platform_type_bitwidth = extra(3)?.as_u64().ok_or_else(|| err("Did not find field bitwidth"))?;
                     let field = CDeclKind::Field { name, typ, bitfield_width, platform_bit_offset, platform_type_bitwidth };
                     self.add_decl(new_id, located(node, field));
                     self.processed_nodes.insert(new_id, FIELD_DECL);
                 }
 
-                t => panic!("Could not translate node {:?} as type {}", t, expected_ty),
+                t => return Err(err(&format!("Could not translate node {:?} as type {}", t, expected_ty))),
             }
         }
+
+        Ok(())
     }
 }
 
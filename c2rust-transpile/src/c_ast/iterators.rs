@@ -57,6 +57,11 @@ fn immediate_expr_children(kind: &CExprKind) -> Vec<SomeId> {
         ImplicitCast(_, e, _, _, _) | ExplicitCast(_, e, _, _, _) |
         Member(_, e, _, _, _) | CompoundLiteral(_, e) | Predefined(_, e) | VAArg(_,e) => intos![e],
         Statements(_, s) => vec![s.into()],
+        Block(_, ref params, body) => {
+            let mut res: Vec<SomeId> = params.iter().map(|&x| x.into()).collect();
+            res.push(body.into());
+            res
+        }
     }
 }
 
@@ -93,6 +98,11 @@ fn immediate_expr_children_all_types(kind: &CExprKind) -> Vec<SomeId> {
         ExplicitCast(qty, e, _, _, _) | CompoundLiteral(qty, e) |
         VAArg(qty, e) => intos![qty.ctype, e],
         Statements(_, s) => vec![s.into()],
+        Block(_, ref params, body) => {
+            let mut res: Vec<SomeId> = params.iter().map(|&x| x.into()).collect();
+            res.push(body.into());
+            res
+        }
     }
 }
 
@@ -122,6 +132,7 @@ fn immediate_decl_children(kind: &CDeclKind) -> Vec<SomeId> {
         Union { ref fields, .. } =>
             fields.iter().flat_map(|x| x).map(|&x| x.into()).collect(),
         Field { typ, .. } => intos![typ.ctype],
+        StaticAssert { assert_expr, .. } => intos![assert_expr],
     }
 }
 
@@ -131,6 +142,7 @@ fn immediate_stmt_children(kind: &CStmtKind) -> Vec<SomeId> {
         Expr(e) => intos![e],
         Label(s) => intos![s],
         Case(e, s, _) => intos![e,s],
+        CaseRange(lo, hi, s, _, _) => intos![lo,hi,s],
         Default(s) => intos![s],
 
         // Compound statements (6.8.2)
@@ -222,7 +234,8 @@ fn immediate_type_children(kind: &CTypeKind) -> Vec<SomeId> {
     }
 }
 
-fn immediate_children(context: &TypedAstContext, s_or_e: SomeId) -> Vec<SomeId> {
+/// The nodes directly below a node, not including the types of expressions
+pub fn immediate_children(context: &TypedAstContext, s_or_e: SomeId) -> Vec<SomeId> {
     match s_or_e {
         SomeId::Stmt(stmt_id) => immediate_stmt_children(&context[stmt_id].kind),
         SomeId::Expr(expr_id) => immediate_expr_children(&context[expr_id].kind),
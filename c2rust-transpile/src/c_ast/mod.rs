@@ -1,4 +1,5 @@
-use c2rust_ast_exporter::clang_ast::LRValue;
+use c2rust_ast_exporter::clang_ast::{LRValue, TargetInfo};
+use std::cmp::Ordering;
 use std::collections::{HashMap,HashSet,BTreeMap};
 use indexmap::IndexMap;
 use std::ops::Index;
@@ -52,16 +53,44 @@ pub struct TypedAstContext {
 
     pub comments: Vec<Located<String>>,
 
+    // Positions of the closing braces of compound statements
+    pub compound_ends: HashMap<CStmtId, SrcLoc>,
+
     // The key is the typedef decl being squashed away,
     // and the value is the decl id to the corresponding structure
     pub prenamed_decls: IndexMap<CDeclId, CDeclId>,
+
+    // The target the translation unit was compiled for
+    pub target: TargetInfo,
 }
 
 /// Comments associated with a typed AST context
 #[derive(Debug, Clone)]
 pub struct CommentContext {
-    decl_comments: HashMap<CDeclId, Vec<String>>,
-    stmt_comments: HashMap<CStmtId, Vec<String>>,
+    decl_comments: HashMap<CDeclId, Vec<Located<String>>>,
+    stmt_comments: HashMap<CStmtId, Vec<Located<String>>>,
+    expr_comments: HashMap<CExprId, Vec<Located<String>>>,
+
+    /// Comments between the last statement of a compound statement and its closing brace
+    block_end_comments: HashMap<CStmtId, Vec<Located<String>>>,
+
+    /// Number of comments per file that have been handed out for translation
+    preserved: HashMap<u64, usize>,
+
+    /// Number of comments per file that could not be attached to any node
+    unattached: HashMap<u64, usize>,
+
+    file_paths: HashMap<u64, PathBuf>,
+}
+
+/// The kinds of nodes a comment can be attached to, in order of preference when several start at
+/// the same position
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum CommentTarget {
+    Decl(CDeclId),
+    Stmt(CStmtId),
+    BlockEnd(CStmtId),
+    Expr(CExprId),
 }
 
 impl TypedAstContext {
@@ -78,7 +107,9 @@ impl TypedAstContext {
             parents: HashMap::new(),
 
             comments: vec![],
+            compound_ends: HashMap::new(),
             prenamed_decls: IndexMap::new(),
+            target: TargetInfo::default(),
         }
     }
 
@@ -161,6 +192,7 @@ impl TypedAstContext {
             CExprKind::ImplicitValueInit { .. } |
             CExprKind::Predefined(..) |
             CExprKind::Statements(..) | // TODO: more precision
+            CExprKind::Block(..) | // allocates the block
             CExprKind::VAArg(..) => false,
 
             CExprKind::Literal(_, _) |
@@ -184,27 +216,140 @@ impl TypedAstContext {
         }
     }
 
+    /// Is this a function type that never returns, either through its `noreturn` flag or a
+    /// `noreturn` type attribute somewhere along the way to the function type?
+    pub fn is_noreturn_function_type(&self, typ: CTypeId) -> bool {
+        match self.index(typ).kind {
+            CTypeKind::Attributed(_, Some(Attribute::NoReturn)) => true,
+            CTypeKind::Attributed(ty, _) => self.is_noreturn_function_type(ty.ctype),
+            CTypeKind::Elaborated(ty) |
+            CTypeKind::TypeOf(ty) |
+            CTypeKind::Paren(ty) => self.is_noreturn_function_type(ty),
+            CTypeKind::Typedef(decl) => match self.index(decl).kind {
+                CDeclKind::Typedef { typ: ty, .. } => self.is_noreturn_function_type(ty.ctype),
+                _ => false,
+            },
+            CTypeKind::Function(_, _, _, no_return, _) => no_return,
+            _ => false,
+        }
+    }
+
+    /// Does this function declaration never return? This also covers C11 `_Noreturn`, which is
+    /// a property of the declaration rather than of its type.
+    pub fn is_noreturn_function(&self, decl_id: CDeclId) -> bool {
+        match self.index(decl_id).kind {
+            CDeclKind::Function { typ, ref attrs, .. } =>
+                attrs.contains(&DeclAttribute::NoReturn) || self.is_noreturn_function_type(typ),
+            _ => false,
+        }
+    }
+
+    /// Functions that are referred to other than as the callee of a direct call, for instance to
+    /// take their address
+    pub fn address_taken_functions(&self) -> HashSet<CDeclId> {
+        let mut direct_callees: HashSet<CExprId> = HashSet::new();
+        for expr in self.c_exprs.values() {
+            if let CExprKind::Call(_, func, _) = expr.kind {
+                if let CExprKind::ImplicitCast(_, callee, CastKind::FunctionToPointerDecay, _, _) =
+                    self.index(func).kind {
+                    direct_callees.insert(callee);
+                }
+            }
+        }
+
+        self.c_exprs.iter().filter_map(|(&id, expr)| match expr.kind {
+            CExprKind::DeclRef(_, decl_id, _) if !direct_callees.contains(&id) => {
+                match self.index(decl_id).kind {
+                    CDeclKind::Function { .. } => Some(decl_id),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }).collect()
+    }
+
+    /// Variables that are referred to other than to access a single object through them with
+    /// `*p` or `p->field`, or to test them against null, for instance to subscript, offset,
+    /// reassign or pass them on
+    pub fn escaping_pointer_variables(&self) -> HashSet<CDeclId> {
+        let mut single_object_uses: Vec<CExprId> = vec![];
+        for expr in self.c_exprs.values() {
+            match expr.kind {
+                CExprKind::Unary(_, UnOp::Deref, pointer, _) |
+                CExprKind::Member(_, pointer, _, MemberKind::Arrow, _) |
+                CExprKind::Unary(_, UnOp::Not, pointer, _) |
+                CExprKind::Conditional(_, pointer, _, _) => single_object_uses.push(pointer),
+                CExprKind::Binary(_, BinOp::EqualEqual, lhs, rhs, _, _) |
+                CExprKind::Binary(_, BinOp::NotEqual, lhs, rhs, _, _) |
+                CExprKind::Binary(_, BinOp::And, lhs, rhs, _, _) |
+                CExprKind::Binary(_, BinOp::Or, lhs, rhs, _, _) => {
+                    single_object_uses.push(lhs);
+                    single_object_uses.push(rhs);
+                }
+                _ => {}
+            }
+        }
+        for stmt in self.c_stmts.values() {
+            match stmt.kind {
+                CStmtKind::If { scrutinee: condition, .. } |
+                CStmtKind::While { condition, .. } |
+                CStmtKind::DoWhile { condition, .. } |
+                CStmtKind::ForLoop { condition: Some(condition), .. } =>
+                    single_object_uses.push(condition),
+                _ => {}
+            }
+        }
+
+        let single_object_refs: HashSet<CExprId> = single_object_uses.into_iter()
+            .filter_map(|pointer| match self.index(pointer).kind {
+                CExprKind::ImplicitCast(_, var, CastKind::LValueToRValue, _, _) => Some(var),
+                _ => None,
+            })
+            .collect();
+
+        self.c_exprs.iter().filter_map(|(&id, expr)| match expr.kind {
+            CExprKind::DeclRef(_, decl_id, _) if !single_object_refs.contains(&id) => {
+                match self.index(decl_id).kind {
+                    CDeclKind::Variable { .. } => Some(decl_id),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }).collect()
+    }
+
     // Pessimistically try to check if an expression doesn't return. If it does, or we can't tell
     /// that it doesn't, return `false`.
     pub fn expr_diverges(&self, expr_id: CExprId) -> bool {
         let func_id = match self.index(expr_id).kind {
             CExprKind::Call(_, func_id, _) => func_id,
+            // `(void)abort()` diverges just as well
+            CExprKind::ImplicitCast(_, sub_id, CastKind::ToVoid, _, _) |
+            CExprKind::ExplicitCast(_, sub_id, CastKind::ToVoid, _, _) =>
+                return self.expr_diverges(sub_id),
             _ => return false,
         };
 
+        // Calls to declared functions can check the declaration for `_Noreturn`
+        if let CExprKind::ImplicitCast(_, callee, CastKind::FunctionToPointerDecay, _, _) =
+            self.index(func_id).kind {
+            if let CExprKind::DeclRef(_, decl_id, _) = self.index(callee).kind {
+                if self.is_noreturn_function(decl_id) {
+                    return true;
+                }
+            }
+        }
+
         let type_id = match self[func_id].kind.get_type() {
                 None => return false,
                 Some(t) => t,
         };
-        let pointed_id = match self.index(type_id).kind {
+        let pointed_id = match self.resolve_type(type_id).kind {
             CTypeKind::Pointer(pointer_qualtype) => pointer_qualtype.ctype,
             _ => return false,
         };
 
-        match self.index(pointed_id).kind {
-            CTypeKind::Function(_, _, _, no_return, _) => no_return,
-            _ => false,
-        }
+        self.is_noreturn_function_type(pointed_id)
     }
 
 
@@ -219,7 +364,7 @@ impl TypedAstContext {
         let mut used: HashSet<CDeclId> = HashSet::new();
 
         // Mark all the roots as used.  Roots are all top-level functions and variables that might
-        // be visible from another compilation unit.
+        // be visible from another compilation unit, and the static assertions checking them.
         for &decl_id in &self.c_decls_top {
             let decl = self.index(decl_id);
             match decl.kind {
@@ -227,7 +372,8 @@ impl TypedAstContext {
                     to_walk.push(decl_id);
                     used.insert(decl_id);
                 },
-                CDeclKind::Variable { is_defn: true, is_extern: true, .. } => {
+                CDeclKind::Variable { is_defn: true, is_extern: true, .. } |
+                CDeclKind::StaticAssert { .. } => {
                     to_walk.push(decl_id);
                     used.insert(decl_id);
                 },
@@ -317,98 +463,212 @@ impl CommentContext {
         CommentContext {
             decl_comments: HashMap::new(),
             stmt_comments: HashMap::new(),
+            expr_comments: HashMap::new(),
+            block_end_comments: HashMap::new(),
+            preserved: HashMap::new(),
+            unattached: HashMap::new(),
+            file_paths: HashMap::new(),
         }
     }
 
 
-    // Try to match up every comment with a declaration or a statement
+    // Try to match up every comment with a declaration, a statement, an expression or the end of
+    // a compound statement
     pub fn new(
         ast_context: &mut TypedAstContext
     ) -> CommentContext {
+        use self::iterators::{immediate_children, SomeId};
+
+        let mut file_paths: HashMap<u64, PathBuf> = HashMap::new();
 
-        // Group and sort declarations by file and by position
-        let mut decls: HashMap<u64, Vec<(SrcLoc, CDeclId)>> = HashMap::new();
-        for (decl_id, ref loc_decl) in &ast_context.c_decls {
-            if let Some(ref loc) = loc_decl.loc {
-                decls.entry(loc.fileid).or_insert(vec![]).push((loc.clone(), *decl_id));
+        // Group the nodes comments can be attached to by file
+        let mut targets: HashMap<u64, Vec<((u64, u64), CommentTarget)>> = HashMap::new();
+        {
+            let mut add_target = |loc: &SrcLoc, target: CommentTarget| {
+                if let Some(ref path) = loc.file_path {
+                    file_paths.entry(loc.fileid).or_insert_with(|| path.clone());
+                }
+                targets.entry(loc.fileid).or_insert(vec![]).push(((loc.line, loc.column), target));
+            };
+
+            for (decl_id, loc_decl) in &ast_context.c_decls {
+                if let Some(ref loc) = loc_decl.loc {
+                    add_target(loc, CommentTarget::Decl(*decl_id));
+                }
+            }
+            for (stmt_id, loc_stmt) in &ast_context.c_stmts {
+                if let Some(ref loc) = loc_stmt.loc {
+                    add_target(loc, CommentTarget::Stmt(*stmt_id));
+                }
+            }
+            for (stmt_id, loc) in &ast_context.compound_ends {
+                add_target(loc, CommentTarget::BlockEnd(*stmt_id));
             }
-        }
-        decls.iter_mut().for_each(|(_, v)| v.sort());
 
-        // Group and sort statements by file and by position
-        let mut stmts: HashMap<u64, Vec<(SrcLoc, CStmtId)>> = HashMap::new();
-        for (stmt_id, ref loc_stmt) in &ast_context.c_stmts {
-            if let Some(ref loc) = loc_stmt.loc {
-                stmts.entry(loc.fileid).or_insert(vec![]).push((loc.clone(), *stmt_id));
+            // Of the expressions starting at the same position, only the outermost one is a
+            // target, since the inner ones are often folded into it during translation
+            let mut inner_exprs: HashSet<CExprId> = HashSet::new();
+            for (expr_id, loc_expr) in &ast_context.c_exprs {
+                for child in immediate_children(ast_context, SomeId::Expr(*expr_id)) {
+                    if let SomeId::Expr(child_id) = child {
+                        let same_start = match (&loc_expr.loc, &ast_context[child_id].loc) {
+                            (&Some(ref l1), &Some(ref l2)) => (l1.line, l1.column) == (l2.line, l2.column),
+                            _ => false,
+                        };
+                        if same_start {
+                            inner_exprs.insert(child_id);
+                        }
+                    }
+                }
+            }
+            for (expr_id, loc_expr) in &ast_context.c_exprs {
+                if let Some(ref loc) = loc_expr.loc {
+                    if !inner_exprs.contains(expr_id) {
+                        add_target(loc, CommentTarget::Expr(*expr_id));
+                    }
+                }
             }
         }
-        stmts.iter_mut().for_each(|(_, v)| v.sort());
+        targets.iter_mut().for_each(|(_, v)| v.sort());
 
+        let mut comments_map: HashMap<CommentTarget, BTreeMap<SrcLoc, String>> = HashMap::new();
+        let mut unattached: HashMap<u64, usize> = HashMap::new();
 
-        let mut decl_comments_map: HashMap<CDeclId, BTreeMap<SrcLoc, String>> = HashMap::new();
-        let mut stmt_comments_map: HashMap<CStmtId, BTreeMap<SrcLoc, String>> = HashMap::new();
+        let empty_vec = &vec![];
 
-        let empty_vec1 = &vec![];
-        let empty_vec2 = &vec![];
-
-
-        // Match comments to declarations and statements
+        // Match comments to their targets
         while let Some(Located { loc, kind: str }) = ast_context.comments.pop() {
             if let Some(loc) = loc {
-                let this_file_decls = decls.get(&loc.fileid).unwrap_or(empty_vec1);
-                let this_file_stmts = stmts.get(&loc.fileid).unwrap_or(empty_vec2);
-
-                // Find the closest declaration and statement
-                let decl_ix = this_file_decls
-                    .binary_search_by_key(&loc.line, |&(ref l,_)| l.line)
-                    .unwrap_or_else(|x| x);
-                let stmt_ix = this_file_stmts
-                    .binary_search_by_key(&loc.line, |&(ref l,_)| l.line)
-                    .unwrap_or_else(|x| x);
-
-                // Prefer the one that is higher up (biasing towards declarations if there is a tie)
-                match (this_file_decls.get(decl_ix), this_file_stmts.get(stmt_ix)) {
-                    (Some(&(ref l1, d)), Some(&(ref l2, s))) => {
-                        if l1 > l2 {
-                            stmt_comments_map.entry(s).or_insert(BTreeMap::new()).insert(loc, str);
-                        } else {
-                            decl_comments_map.entry(d).or_insert(BTreeMap::new()).insert(loc, str);
-                        }
-                    }
-                    (Some(&(_, d)), None) => {
-                        decl_comments_map.entry(d).or_insert(BTreeMap::new()).insert(loc, str);
-                    }
-                    (None, Some(&(_, s))) => {
-                        stmt_comments_map.entry(s).or_insert(BTreeMap::new()).insert(loc, str);
+                let this_file_targets = targets.get(&loc.fileid).unwrap_or(empty_vec);
+                let pos = (loc.line, loc.column);
+
+                // Index of the first target starting at or after a position
+                let first_from = |pos: (u64, u64)| -> usize {
+                    this_file_targets
+                        .binary_search_by(|&(p, _)| if p < pos { Ordering::Less } else { Ordering::Greater })
+                        .unwrap_or_else(|x| x)
+                };
+
+                // A target following the comment on the same line, like in `f(a, /* b */ b)`,
+                // wins. Otherwise a comment trailing a declaration or statement on the same line
+                // belongs to it, and failing that, the comment precedes whatever comes next.
+                let after = this_file_targets.get(first_from(pos));
+                let trailed = this_file_targets[first_from((loc.line, 0))..]
+                    .iter()
+                    .take_while(|&&(p, _)| p < pos)
+                    .find(|&&(_, target)| match target {
+                        CommentTarget::Decl(_) | CommentTarget::Stmt(_) => true,
+                        _ => false,
+                    });
+                let target = match (after, trailed) {
+                    (Some(&((line, _), target)), _) if line == loc.line => Some(target),
+                    (_, Some(&(_, target))) => Some(target),
+                    (Some(&(_, target)), None) => Some(target),
+                    (None, None) => None,
+                };
+
+                match target {
+                    Some(target) => {
+                        comments_map.entry(target).or_insert(BTreeMap::new()).insert(loc, str);
                     }
-                    (None, None) => {
+                    None => {
                         diag!(Diagnostic::Comments, "Didn't find a target node for the comment '{}'", str);
-                    },
-                };
+                        *unattached.entry(loc.fileid).or_insert(0) += 1;
+                    }
+                }
             }
         }
 
         // Flatten out the nested comment maps
-        let decl_comments = decl_comments_map
-          .into_iter()
-          .map(|(decl_id, map)| (decl_id, map.into_iter().map(|(_, v)| v).collect()))
-          .collect();
-        let stmt_comments = stmt_comments_map
-          .into_iter()
-          .map(|(decl_id, map)| (decl_id, map.into_iter().map(|(_, v)| v).collect()))
-          .collect();
-
-        CommentContext { decl_comments, stmt_comments }
+        let mut context = CommentContext::empty();
+        context.unattached = unattached;
+        context.file_paths = file_paths;
+        for (target, map) in comments_map {
+            let comments = map
+                .into_iter()
+                .map(|(loc, v)| Located { loc: Some(loc), kind: v })
+                .collect();
+            match target {
+                CommentTarget::Decl(id) => { context.decl_comments.insert(id, comments); }
+                CommentTarget::Stmt(id) => { context.stmt_comments.insert(id, comments); }
+                CommentTarget::BlockEnd(id) => { context.block_end_comments.insert(id, comments); }
+                CommentTarget::Expr(id) => { context.expr_comments.insert(id, comments); }
+            }
+        }
+        context
+    }
+
+    /// Count the comments being handed out for translation and strip their locations
+    fn take(&mut self, comments: Option<Vec<Located<String>>>) -> Vec<String> {
+        let comments = comments.unwrap_or(vec![]);
+        for comment in &comments {
+            if let Some(ref loc) = comment.loc {
+                *self.preserved.entry(loc.fileid).or_insert(0) += 1;
+            }
+        }
+        comments.into_iter().map(|c| c.kind).collect()
     }
 
     // Extract the comment for a given declaration
     pub fn remove_decl_comment(&mut self, decl_id: CDeclId) -> Vec<String> {
-        self.decl_comments.remove(&decl_id).unwrap_or(vec![])
+        let comments = self.decl_comments.remove(&decl_id);
+        self.take(comments)
     }
 
     // Extract the comment for a given statement
     pub fn remove_stmt_comment(&mut self, stmt_id: CStmtId) -> Vec<String> {
-        self.stmt_comments.remove(&stmt_id).unwrap_or(vec![])
+        let comments = self.stmt_comments.remove(&stmt_id);
+        self.take(comments)
+    }
+
+    // Extract the comment for a given expression
+    pub fn remove_expr_comment(&mut self, expr_id: CExprId) -> Vec<String> {
+        let comments = self.expr_comments.remove(&expr_id);
+        self.take(comments)
+    }
+
+    // Extract the comments at the end of a given compound statement
+    pub fn remove_block_end_comment(&mut self, stmt_id: CStmtId) -> Vec<String> {
+        let comments = self.block_end_comments.remove(&stmt_id);
+        self.take(comments)
+    }
+
+    // Move the comments of a statement onto another one, ahead of its own comments
+    pub fn move_stmt_comment(&mut self, from: CStmtId, to: CStmtId) {
+        if let Some(mut comments) = self.stmt_comments.remove(&from) {
+            comments.extend(self.stmt_comments.remove(&to).unwrap_or(vec![]));
+            self.stmt_comments.insert(to, comments);
+        }
+    }
+
+    /// Report, per file, how many comments were preserved in the translation and list the ones
+    /// that were dropped because whatever they were attached to was not translated
+    pub fn report(&self) {
+        let mut dropped_counts: HashMap<u64, usize> = self.unattached.clone();
+        let leftovers = self.decl_comments.values()
+            .chain(self.stmt_comments.values())
+            .chain(self.expr_comments.values())
+            .chain(self.block_end_comments.values())
+            .flat_map(|comments| comments.iter());
+        for comment in leftovers {
+            if let Some(ref loc) = comment.loc {
+                diag!(Diagnostic::Comments, "Dropped the comment at line {}: '{}'", loc.line, comment.kind);
+                *dropped_counts.entry(loc.fileid).or_insert(0) += 1;
+            }
+        }
+
+        let mut files: Vec<u64> = self.preserved.keys().chain(dropped_counts.keys()).cloned().collect();
+        files.sort();
+        files.dedup();
+        for fileid in files {
+            let preserved = self.preserved.get(&fileid).cloned().unwrap_or(0);
+            let dropped = dropped_counts.get(&fileid).cloned().unwrap_or(0);
+            let file = self.file_paths
+                .get(&fileid)
+                .map_or_else(|| format!("file {}", fileid), |path| path.display().to_string());
+            diag!(Diagnostic::Comments, "{}: preserved {} of {} comments, dropped {}",
+                  file, preserved, preserved + dropped, dropped);
+        }
     }
 }
 
@@ -490,6 +750,7 @@ pub enum CDeclKind {
         name: String,
         parameters: Vec<CParamId>,
         body: Option<CStmtId>,
+        attrs: Vec<DeclAttribute>,
     },
 
     // http://clang.llvm.org/doxygen/classclang_1_1VarDecl.html
@@ -500,6 +761,7 @@ pub enum CDeclKind {
         ident: String,
         initializer: Option<CExprId>,
         typ: CQualTypeId,
+        attrs: Vec<DeclAttribute>,
     },
 
     // Enum (http://clang.llvm.org/doxygen/classclang_1_1EnumDecl.html)
@@ -536,6 +798,8 @@ pub enum CDeclKind {
     Union {
         name: Option<String>,
         fields: Option<Vec<CFieldId>>,
+        platform_byte_size: u64,
+        platform_alignment: u64,
     },
 
     // Field
@@ -546,6 +810,12 @@ pub enum CDeclKind {
         platform_bit_offset: u64,
         platform_type_bitwidth: u64,
     },
+
+    // Static assertion
+    StaticAssert {
+        assert_expr: CExprId,
+        message: Option<String>,
+    },
 }
 
 impl CDeclKind {
@@ -638,6 +908,9 @@ pub enum CExprKind {
     // From syntactic form of initializer list expressions
     DesignatedInitExpr(CQualTypeId, Vec<Designator>, CExprId),
 
+    // Block literal (`^(params) { body }`), with its parameters and body
+    Block(CQualTypeId, Vec<CDeclId>, CStmtId),
+
     BadExpr,
 }
 
@@ -681,6 +954,7 @@ impl CExprKind {
             CExprKind::CompoundLiteral(ty, _) |
             CExprKind::Predefined(ty, _) |
             CExprKind::Statements(ty, _) |
+            CExprKind::Block(ty, _, _) |
             CExprKind::VAArg(ty, _) |
             CExprKind::ShuffleVector(ty, _) |
             CExprKind::ConvertVector(ty, _) |
@@ -844,7 +1118,7 @@ pub enum CLiteral {
     Integer(u64, IntBase), // value and base
     Character(u64),
     Floating(f64, String),
-    String(Vec<u8>, u8), // Literal bytes and unit byte width
+    String(Vec<u8>, u8), // Literal bytes (little-endian code units) and unit byte width
 }
 
 impl CLiteral {
@@ -877,6 +1151,8 @@ pub enum CStmtKind {
     // All of these have a `CStmtId` to represent the substatement that comes after them
     Label(CStmtId),
     Case(CExprId, CStmtId, ConstIntExpr),
+    /// GNU case range (`case lo ... hi:`), holding both bounds and their values
+    CaseRange(CExprId, CExprId, CStmtId, ConstIntExpr, ConstIntExpr),
     Default(CStmtId),
 
     // Compound statements (6.8.2)
@@ -1092,6 +1368,29 @@ pub enum Attribute {
     Nullable,
 }
 
+/// Attributes of function and variable declarations that control linking and storage
+#[derive(Clone, Debug, PartialEq)]
+pub enum DeclAttribute {
+    /// `section("name")`
+    Section(String),
+    /// `weak`
+    Weak,
+    /// `alias("target")`
+    Alias(String),
+    /// `constructor`, with its priority when one was given
+    Constructor(Option<u64>),
+    /// `destructor`, with its priority when one was given
+    Destructor(Option<u64>),
+    /// `visibility("hidden")` and friends
+    Visibility(String),
+    /// `__block` storage, which lets blocks capture a variable by reference
+    BlockByref,
+    /// C11 `_Noreturn` or `__attribute__((noreturn))` on a function
+    NoReturn,
+    /// `nonnull` on a function or its parameters, with the indices of the parameters it covers
+    NonNull(Vec<usize>),
+}
+
 impl CTypeKind {
 
     pub fn is_pointer(&self) -> bool {
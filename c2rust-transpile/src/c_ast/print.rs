@@ -197,6 +197,26 @@ impl<W: Write> Printer<W> {
             Some(&CExprKind::VAArg(_,val)) =>
                 self.print_expr(val, context),
 
+            Some(&CExprKind::Block(_, ref params, body)) => {
+                self.writer.write_all(b"^(")?;
+
+                let mut first: bool = true;
+                for param in params {
+                    if !first {
+                        self.writer.write_all(b", ")?;
+                    }
+                    first = false;
+                    match context.c_decls.get(param).map(|l| &l.kind) {
+                        Some(&CDeclKind::Variable { ref ident, ref typ, .. }) =>
+                            self.print_qtype(*typ, Some(ident.as_str()), context)?,
+                        _ => panic!("Block argument is not VarDecl"),
+                    }
+                }
+
+                self.writer.write_all(b") ")?;
+                self.print_stmt(body, false, false, context)
+            }
+
             None => panic!("Could not find expression with ID {:?}", expr_id),
            // _ => unimplemented!("Printer::print_expr"),
         }
@@ -584,6 +604,20 @@ impl<W: Write> Printer<W> {
                 Ok(())
             },
 
+            Some(&CDeclKind::StaticAssert { assert_expr, ref message }) => {
+                self.writer.write_all(b"_Static_assert(")?;
+                self.print_expr(assert_expr, context)?;
+                if let Some(ref message) = *message {
+                    self.writer.write_fmt(format_args!(", {:?}", message))?;
+                }
+                self.writer.write_all(b");")?;
+                if newline {
+                    self.writer.write_all(b"\n")?;
+                }
+
+                Ok(())
+            },
+
             None => panic!("Could not find declaration with ID {:?}", decl_id),
 
            // _ => unimplemented!("Printer::print_decl"),
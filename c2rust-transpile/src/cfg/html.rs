@@ -0,0 +1,355 @@
+//! This module writes a self-contained HTML page per function for debugging the relooper.
+//!
+//! The page has four panels, cross-linked by block label:
+//!
+//!   - the C source of the function, with the lines of C labels marked
+//!   - the CFG handed to the relooper, one box per basic block
+//!   - the tree of `Structure`s the relooper produced
+//!   - the Rust statements emitted for the function
+//!
+//! Every mention of a label links to its basic block, and hovering over one highlights all the
+//! other mentions of the same label.
+
+use super::*;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Accumulates the panels of the HTML page for one CFG as translation progresses
+pub struct CfgHtml {
+    name: String,
+
+    /// The C source lines, numbered
+    c_source: Option<(PathBuf, Vec<(u64, String)>)>,
+
+    /// Lines of the C labels in the CFG
+    c_label_lines: IndexMap<Label, u64>,
+
+    /// Tokens in the Rust output that refer to labels (loop labels and `current_block` values)
+    label_tokens: IndexMap<String, Label>,
+
+    cfg: String,
+    structures: String,
+    rust: String,
+}
+
+impl CfgHtml {
+
+    /// Start a page for the CFG of `name`, translated from the C statements `stmt_ids`
+    pub fn new(ast_context: &TypedAstContext, name: &str, stmt_ids: &[CStmtId]) -> Self {
+        let mut file_path: Option<PathBuf> = None;
+        let mut lines: Option<(u64, u64)> = None;
+        for id in stmt_ids.iter().flat_map(|&stmt_id| DFExpr::new(ast_context, stmt_id.into())) {
+            let loc = match id {
+                SomeId::Stmt(x) => ast_context.c_stmts.get(&x).and_then(|s| s.loc.as_ref()),
+                SomeId::Expr(x) => ast_context.c_exprs.get(&x).and_then(|e| e.loc.as_ref()),
+                SomeId::Decl(x) => ast_context.c_decls.get(&x).and_then(|d| d.loc.as_ref()),
+                SomeId::Type(_) => None,
+            };
+            if let Some(loc) = loc {
+                if file_path.is_none() {
+                    file_path = loc.file_path.clone();
+                }
+                if file_path.is_some() && loc.file_path == file_path {
+                    lines = Some(match lines {
+                        None => (loc.line, loc.line),
+                        Some((first, last)) => (first.min(loc.line), last.max(loc.line)),
+                    });
+                }
+            }
+        }
+
+        let c_source = match (file_path, lines) {
+            (Some(path), Some((first, last))) => fs::read_to_string(&path).ok().map(|text| {
+                let lines = text
+                    .lines()
+                    .enumerate()
+                    .map(|(i, line)| (i as u64 + 1, line.to_owned()))
+                    .filter(|&(n, _)| n + 1 >= first && n <= last + 1)
+                    .collect();
+                (path, lines)
+            }),
+            _ => None,
+        };
+
+        CfgHtml {
+            name: name.to_owned(),
+            c_source,
+            c_label_lines: IndexMap::new(),
+            label_tokens: IndexMap::new(),
+            cfg: String::new(),
+            structures: String::new(),
+            rust: String::new(),
+        }
+    }
+
+    /// Render the CFG handed to the relooper
+    pub fn add_cfg(
+        &mut self,
+        cfg: &Cfg<Label, StmtOrDecl>,
+        ast_context: &TypedAstContext,
+        store: &DeclStmtStore,
+        show_liveness: bool,
+    ) {
+        for lbl in cfg.nodes.keys() {
+            if let Label::FromC(stmt_id) = *lbl {
+                if let Some(line) = ast_context.c_stmts.get(&stmt_id).and_then(|s| s.loc.as_ref()) {
+                    self.c_label_lines.insert(*lbl, line.line);
+                }
+            }
+
+            self.label_tokens.insert(lbl.pretty_print(), *lbl);
+            self.label_tokens.insert(lbl.debug_print(), *lbl);
+            self.label_tokens.insert(pprust::expr_to_string(&lbl.to_num_expr()), *lbl);
+        }
+
+        let decl_names = |decls: &IndexSet<CDeclId>| -> String {
+            decls
+                .iter()
+                .filter_map(|decl| ast_context.index(*decl).kind.get_name())
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        let mut out = String::new();
+        out.push_str(&format!("<p>entry: {}</p>\n", label_link(cfg.entries)));
+        for (lbl, bb) in &cfg.nodes {
+            out.push_str(&format!("<div class=\"block\" id=\"{}\">\n", block_anchor(*lbl)));
+            out.push_str(&format!("<div class=\"header\">{}", label_link(*lbl)));
+            if let Some(line) = self.c_label_lines.get(lbl) {
+                out.push_str(&format!(" <a class=\"line\" href=\"#c-{0}\">line {0}</a>", line));
+            }
+            out.push_str("</div>\n");
+
+            if show_liveness {
+                out.push_str(&format!(
+                    "<div class=\"liveness\">live in: {{{}}}<br>defined: {{{}}}</div>\n",
+                    escape(&decl_names(&bb.live)),
+                    escape(&decl_names(&bb.defined)),
+                ));
+            }
+
+            let body: Vec<String> = bb.body.iter().flat_map(|sd| sd.to_string(store)).collect();
+            if !body.is_empty() {
+                out.push_str(&format!("<pre>{}</pre>\n", escape(&body.join("\n"))));
+            }
+
+            let terminator = match bb.terminator {
+                End => String::from("end"),
+                Jump(tgt) => format!("jump {}", label_link(tgt)),
+                Branch(ref cond, tru, fal) => format!(
+                    "if <code>{}</code><br>then {}<br>else {}",
+                    escape(&pprust::expr_to_string(cond)),
+                    label_link(tru),
+                    label_link(fal),
+                ),
+                Switch { ref expr, ref cases } => {
+                    let mut s = format!("match <code>{}</code>", escape(&pprust::expr_to_string(expr)));
+                    for &(ref pats, tgt) in cases {
+                        let pats: Vec<String> = pats.iter().map(|p| pprust::pat_to_string(p)).collect();
+                        s.push_str(&format!(
+                            "<br><code>{}</code> =&gt; {}",
+                            escape(&pats.join(" | ")),
+                            label_link(tgt),
+                        ));
+                    }
+                    s
+                }
+            };
+            out.push_str(&format!("<div class=\"terminator\">{}</div>\n</div>\n", terminator));
+        }
+        self.cfg = out;
+    }
+
+    /// Render the structures produced by the relooper
+    pub fn add_structures(&mut self, structures: &Vec<Structure<StmtOrComment>>) {
+        let mut out = String::new();
+        render_structures(structures, &mut out);
+        self.structures = out;
+    }
+
+    /// Render the emitted Rust statements
+    pub fn add_rust(&mut self, stmts: &[Stmt]) {
+        let rust: Vec<String> = stmts.iter().map(|s| pprust::stmt_to_string(s)).collect();
+        self.rust = link_label_tokens(&escape(&rust.join("\n")), &self.label_tokens);
+    }
+
+    /// Write the page into `dir`
+    pub fn write(&self, dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+
+        let file_name: String = self.name
+            .chars()
+            .map(|c| if c.is_alphanumeric() || c == '_' { c } else { '_' })
+            .collect();
+        let mut file = File::create(dir.join(format!("cfg_{}.html", file_name)))?;
+
+        let c_source = match self.c_source {
+            Some((ref path, ref lines)) => {
+                let labelled_lines: IndexSet<u64> = self.c_label_lines.values().cloned().collect();
+                let mut s = format!("<p>{}</p>\n<pre>", escape(&path.display().to_string()));
+                for &(n, ref line) in lines {
+                    let class = if labelled_lines.contains(&n) { " labelled" } else { "" };
+                    s.push_str(&format!(
+                        "<span class=\"c-line{}\" id=\"c-{}\"><span class=\"lineno\">{:5}</span> {}</span>\n",
+                        class, n, n, escape(line),
+                    ));
+                }
+                s.push_str("</pre>\n");
+                s
+            }
+            None => String::from("<p>C source unavailable</p>\n"),
+        };
+
+        write!(file, "{}", PAGE_HEAD.replace("{title}", &escape(&self.name)))?;
+        write!(file, "<h1>{}</h1>\n<div class=\"panels\">\n", escape(&self.name))?;
+        write!(file, "<section><h2>C source</h2>\n{}</section>\n", c_source)?;
+        write!(file, "<section><h2>CFG</h2>\n{}</section>\n", self.cfg)?;
+        write!(file, "<section><h2>Structures</h2>\n{}</section>\n", self.structures)?;
+        write!(file, "<section><h2>Rust</h2>\n<pre>{}</pre>\n</section>\n", self.rust)?;
+        write!(file, "</div>\n{}", PAGE_TAIL)?;
+        Ok(())
+    }
+}
+
+fn render_structures(structures: &Vec<Structure<StmtOrComment>>, out: &mut String) {
+    let entries = |entries: &IndexSet<Label>| -> String {
+        entries.iter().map(|l| label_link(*l)).collect::<Vec<_>>().join(", ")
+    };
+
+    out.push_str("<ul>\n");
+    for structure in structures {
+        out.push_str("<li>");
+        match *structure {
+            Structure::Simple { entries: ref es, ref body, ref terminator } => {
+                out.push_str(&format!("<b>Simple</b> {}", entries(es)));
+                let body: Vec<String> = body
+                    .iter()
+                    .map(|s| match *s {
+                        StmtOrComment::Stmt(ref s) => pprust::stmt_to_string(s),
+                        StmtOrComment::Comment(ref c) => c.clone(),
+                    })
+                    .collect();
+                if !body.is_empty() {
+                    out.push_str(&format!("<pre>{}</pre>", escape(&body.join("\n"))));
+                }
+                let targets = terminator.get_labels();
+                if targets.is_empty() {
+                    out.push_str("<div class=\"terminator\">end</div>");
+                }
+                for target in targets {
+                    match *target {
+                        StructureLabel::GoTo(l) =>
+                            out.push_str(&format!("<div class=\"terminator\">go to {}</div>", label_link(l))),
+                        StructureLabel::ExitTo(l) =>
+                            out.push_str(&format!("<div class=\"terminator\">exit to {}</div>", label_link(l))),
+                        StructureLabel::Nested(ref nested) => {
+                            out.push_str("<div class=\"terminator\">nested</div>");
+                            render_structures(nested, out);
+                        }
+                    }
+                }
+            }
+            Structure::Loop { entries: ref es, ref body } => {
+                out.push_str(&format!("<b>Loop</b> {}", entries(es)));
+                render_structures(body, out);
+            }
+            Structure::Multiple { entries: ref es, ref branches, ref then } => {
+                out.push_str(&format!("<b>Multiple</b> {}<ul>\n", entries(es)));
+                for (lbl, branch) in branches {
+                    out.push_str(&format!("<li>branch {}", label_link(*lbl)));
+                    render_structures(branch, out);
+                    out.push_str("</li>\n");
+                }
+                out.push_str("<li>then");
+                render_structures(then, out);
+                out.push_str("</li>\n</ul>");
+            }
+        }
+        out.push_str("</li>\n");
+    }
+    out.push_str("</ul>\n");
+}
+
+fn block_anchor(lbl: Label) -> String {
+    format!("block-{}", lbl.debug_print())
+}
+
+fn label_link(lbl: Label) -> String {
+    format!(
+        "<a class=\"label\" data-label=\"{0}\" href=\"#block-{0}\">{0}</a>",
+        lbl.debug_print(),
+    )
+}
+
+/// Link the tokens of already escaped text that refer to labels
+fn link_label_tokens(text: &str, label_tokens: &IndexMap<String, Label>) -> String {
+    let mut out = String::new();
+    let mut token = String::new();
+
+    let flush = |token: &mut String, out: &mut String| {
+        match label_tokens.get(token.as_str()) {
+            Some(lbl) => out.push_str(&format!(
+                "<a class=\"label\" data-label=\"{}\" href=\"#{}\">{}</a>",
+                lbl.debug_print(), block_anchor(*lbl), token,
+            )),
+            None => out.push_str(token),
+        }
+        token.clear();
+    };
+
+    for c in text.chars() {
+        if c.is_alphanumeric() || c == '_' || (c == '\'' && token.is_empty()) {
+            token.push(c);
+        } else {
+            flush(&mut token, &mut out);
+            out.push(c);
+        }
+    }
+    flush(&mut token, &mut out);
+    out
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+const PAGE_HEAD: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{title}</title>
+<style>
+body { font-family: sans-serif; margin: 1em; }
+.panels { display: flex; align-items: flex-start; }
+section { flex: 1; min-width: 0; margin-right: 1em; overflow-x: auto; }
+pre { margin: 0.2em 0; }
+.block { border: 1px solid #888; margin: 0.5em 0; padding: 0.3em; }
+.block .header { font-weight: bold; }
+.liveness { color: #555; font-size: smaller; }
+.terminator { color: #036; }
+.c-line.labelled { background: #eef; }
+.lineno { color: #999; }
+a.label { color: #a30; text-decoration: none; }
+a.label.highlight { background: #ff8; }
+:target { background: #ffd; outline: 2px solid #fc0; }
+</style>
+</head>
+<body>
+"#;
+
+const PAGE_TAIL: &str = r#"<script>
+document.querySelectorAll('a.label').forEach(function (a) {
+  var same = '[data-label="' + a.dataset.label + '"]';
+  a.addEventListener('mouseover', function () {
+    document.querySelectorAll(same).forEach(function (b) { b.classList.add('highlight'); });
+  });
+  a.addEventListener('mouseout', function () {
+    document.querySelectorAll(same).forEach(function (b) { b.classList.remove('highlight'); });
+  });
+});
+</script>
+</body>
+</html>
+"#;
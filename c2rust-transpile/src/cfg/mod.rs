@@ -9,6 +9,7 @@
 //!
 //!   - given an entry point C statement, translate it into a CFG consisting of `BasicBlock<Label>`
 //!   - simplify this CFG (by eliminating empty blocks that jump unconditionally to the next block)
+//!   - optionally, split nodes to make irreducible control flow reducible
 //!   - use the _Relooper algorithm_ to convert this CFG into a sequence of `Structure<StmtOrDecl>`s
 //!   - place the declarations in the right place and produce a sequence of `Structure<Stmt>`s
 //!   - simplify that sequence of `Structure<Stmt>`s into another such sequence
@@ -18,7 +19,7 @@
 use syntax;
 use syntax::ast::{Arm, Expr, ExprKind, LitIntType, Pat, Stmt, StmtKind, Lit, LitKind};
 use syntax::ptr::P;
-use syntax_pos::DUMMY_SP;
+use syntax_pos::{DUMMY_SP, Span};
 use c_ast::CLabelId;
 use c_ast::iterators::{DFExpr, SomeId};
 use std::ops::Index;
@@ -39,6 +40,7 @@ use serde_json;
 
 use translator::*;
 use with_stmts::WithStmts;
+use rust_ast::source_map::SourceMapKind;
 use c_ast::*;
 use c2rust_ast_builder::mk;
 
@@ -46,6 +48,8 @@ pub mod relooper;
 pub mod structures;
 pub mod loops;
 pub mod multiples;
+pub mod node_splitting;
+pub mod html;
 mod inc_cleanup;
 
 use cfg::loops::*;
@@ -271,7 +275,7 @@ pub enum GenTerminator<Lbl> {
     /// Multi-way branch. The patterns are expected to match the type of the expression.
     Switch {
         expr: P<Expr>,
-        cases: Vec<(Vec<P<Pat>>, Lbl)>, // TODO: support ranges of expressions
+        cases: Vec<(Vec<P<Pat>>, Lbl)>,
     }
 }
 
@@ -380,13 +384,31 @@ impl GenTerminator<StructureLabel<StmtOrDecl>> {
 }
 
 /// The sole purpose of this structure is to accumulate information about what cases/default have
-/// been seen which translating the body of the switch.
+/// been seen which translating the body of the switch. GNU case ranges are recorded as range
+/// patterns alongside the single-value cases.
 #[derive(Clone, Debug, Default)]
 pub struct SwitchCases {
     cases: Vec<(P<Pat>,Label)>,
     default: Option<Label>,
 }
 
+/// Build the literal expression for the value of a `case` label
+fn const_int_expr(cie: ConstIntExpr) -> P<Expr> {
+    match cie {
+        ConstIntExpr::U(n) =>
+            mk().lit_expr(mk().int_lit(n as u128, LitIntType::Unsuffixed)),
+
+        ConstIntExpr::I(n) if n >= 0 =>
+            mk().lit_expr(mk().int_lit(n as u128, LitIntType::Unsuffixed)),
+
+        ConstIntExpr::I(n) =>
+            mk().unary_expr(
+                syntax::ast::UnOp::Neg,
+                mk().lit_expr(mk().int_lit((-n) as u128, LitIntType::Unsuffixed))
+            ),
+    }
+}
+
 /// A Rust statement, or a C declaration, or a comment
 #[derive(Clone, Debug)]
 pub enum StmtOrDecl {
@@ -496,6 +518,9 @@ pub enum ImplicitReturnType {
     /// > used by the caller, the behavior is undefined."
     NoImplicitReturnType,
 
+    /// `noreturn` functions can't fall off the end of their bodies
+    NoReturn,
+
     /// This is for handling statement expressions
     ///
     /// TODO: document
@@ -553,6 +578,11 @@ impl Cfg<Label, StmtOrDecl> {
                             translator.panic("Reached end of non-void function without returning");
                         wip.body.push(StmtOrDecl::Stmt(mk().semi_stmt(ret_expr)));
                     },
+                    ImplicitReturnType::NoReturn => {
+                        let ret_expr: P<Expr> =
+                            translator.panic("Reached end of noreturn function");
+                        wip.body.push(StmtOrDecl::Stmt(mk().semi_stmt(ret_expr)));
+                    },
                     ImplicitReturnType::StmtExpr(ctx, expr_id, brk_label) => {
                         let WithStmts { mut stmts, val } = translator.convert_expr(
                             ctx,
@@ -1235,9 +1265,22 @@ impl CfgBuilder {
 
         let mut wip = self.new_wip_block(entry);
 
-        // Add statement comment into current block right before the current statement
-        for cmmt in translator.comment_context.borrow_mut().remove_stmt_comment(stmt_id) {
-            wip.push_comment(cmmt);
+        // Add statement comment into current block right before the current statement. Comments
+        // on labels and cases go to the statement they label instead, since the current block is
+        // often unreachable (after a `break`, for instance).
+        match translator.ast_context.index(stmt_id).kind {
+            CStmtKind::Label(sub_stmt) |
+            CStmtKind::Case(_, sub_stmt, _) |
+            CStmtKind::CaseRange(_, _, sub_stmt, _, _) |
+            CStmtKind::Default(sub_stmt) =>
+                translator.comment_context.borrow_mut().move_stmt_comment(stmt_id, sub_stmt),
+            _ => for cmmt in translator.comment_context.borrow_mut().remove_stmt_comment(stmt_id) {
+                wip.push_comment(cmmt);
+            },
+        }
+        let stmt_loc = translator.ast_context.index(stmt_id).loc.as_ref();
+        if let Some(marker) = translator.source_map_marker(stmt_loc, SourceMapKind::Stmt) {
+            wip.push_comment(marker);
         }
 
         let out_wip: Result<Option<WipBlock>, String> = match translator.ast_context.index(stmt_id).kind {
@@ -1516,7 +1559,14 @@ impl CfgBuilder {
                     comp_entry
                 )?;
 
-                Ok(next_lbl.map(|l| self.new_wip_block(l)))
+                // Comments at the end of the block go where control leaves it
+                Ok(next_lbl.map(|l| {
+                    let mut wip = self.new_wip_block(l);
+                    for cmmt in translator.comment_context.borrow_mut().remove_block_end_comment(stmt_id) {
+                        wip.push_comment(cmmt);
+                    }
+                    wip
+                }))
             },
 
             CStmtKind::Expr(expr) => 'case_blk: {
@@ -1581,19 +1631,7 @@ impl CfgBuilder {
                 self.add_wip_block(wip, Jump(this_label));
 
                 // Case
-                let branch = match cie {
-                    ConstIntExpr::U(n) =>
-                        mk().lit_expr(mk().int_lit(n as u128, LitIntType::Unsuffixed)),
-
-                    ConstIntExpr::I(n) if n >= 0 =>
-                        mk().lit_expr(mk().int_lit(n as u128, LitIntType::Unsuffixed)),
-
-                    ConstIntExpr::I(n) =>
-                        mk().unary_expr(
-                            syntax::ast::UnOp::Neg,
-                            mk().lit_expr(mk().int_lit((-n) as u128, LitIntType::Unsuffixed))
-                        ),
-                };
+                let branch = const_int_expr(cie);
                 self.switch_expr_cases
                     .last_mut()
                     .ok_or(format!(
@@ -1608,6 +1646,39 @@ impl CfgBuilder {
                 Ok(sub_stmt_next.map(|l| self.new_wip_block(l)))
             }
 
+            CStmtKind::CaseRange(_lo_expr, _hi_expr, sub_stmt, lo, hi) => {
+                self.last_per_stmt_mut().saw_unmatched_case = true;
+                let this_label = Label::FromC(stmt_id);
+                self.add_wip_block(wip, Jump(this_label));
+
+                {
+                    let cases = &mut self.switch_expr_cases
+                        .last_mut()
+                        .ok_or(format!(
+                            "Cannot find the 'switch' wrapping this ({:?}) 'case' statement",
+                            stmt_id,
+                        ))?
+                        .cases;
+
+                    // An empty range (`case 5 ... 1:`) matches nothing, and Rust rejects it as a
+                    // pattern, so only the label is kept for any fallthrough into it
+                    let is_empty = match (lo, hi) {
+                        (ConstIntExpr::U(lo), ConstIntExpr::U(hi)) => lo > hi,
+                        (ConstIntExpr::I(lo), ConstIntExpr::I(hi)) => lo > hi,
+                        (ConstIntExpr::I(lo), ConstIntExpr::U(hi)) => lo >= 0 && lo as u64 > hi,
+                        (ConstIntExpr::U(lo), ConstIntExpr::I(hi)) => hi < 0 || lo > hi as u64,
+                    };
+                    if !is_empty {
+                        let pat = mk().range_pat(const_int_expr(lo), const_int_expr(hi));
+                        cases.push((pat, this_label));
+                    }
+                }
+
+                // Sub stmt
+                let sub_stmt_next = self.convert_stmt_help(translator, ctx, sub_stmt, in_tail, this_label)?;
+                Ok(sub_stmt_next.map(|l| self.new_wip_block(l)))
+            }
+
             CStmtKind::Default(sub_stmt) => {
                 self.last_per_stmt_mut().saw_unmatched_default = true;
                 let this_label = Label::FromC(stmt_id);
@@ -1776,6 +1847,7 @@ impl CfgBuilder {
         // Run relooper
         let mut stmts = translator.convert_cfg(
             &format!("<substmt_{:?}>", stmt_id),
+            &[stmt_id],
             graph,
             store,
             live_in,
@@ -125,3 +125,32 @@ impl<Lbl: Hash + Ord + Clone> MultipleInfo<Lbl> {
     }
 }
 
+
+/// Recognize the `Multiple` left behind by forward `goto`s to a common epilogue, as in the C
+/// cleanup idiom:
+///
+/// ```c
+/// for (i = 0; i < n; i++) {
+///     if (!init(i)) goto out;
+/// }
+/// finish();
+/// out:
+/// return;
+/// ```
+///
+/// The structure before the `Multiple` either falls through to the code in its only branch
+/// (`finish()`) or skips straight to the join (`out`). Instead of dispatching on `current_block`,
+/// we can wrap both in a labeled block and translate the jumps to the join as `break`s out of it.
+///
+/// Returns the label of the branch and the join label.
+pub fn early_exit_join<S>(multiple: &Structure<S>) -> Option<(Label, Label)> {
+    match *multiple {
+        Structure::Multiple { ref entries, ref branches, ref then }
+        if entries.len() == 2 && branches.len() == 1 && then.is_empty() => {
+            let branch = *branches.keys().next()?;
+            let join = *entries.iter().find(|&&entry| entry != branch)?;
+            if entries.contains(&branch) { Some((branch, join)) } else { None }
+        }
+        _ => None,
+    }
+}
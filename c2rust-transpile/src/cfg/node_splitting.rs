@@ -0,0 +1,264 @@
+//! This module makes irreducible CFGs reducible by splitting nodes before relooping.
+//!
+//! A CFG is irreducible when it contains a cycle that can be entered at more than one block, as
+//! happens with `goto`s into the middle of loops. The relooper can only handle such a region with
+//! a `Multiple` inside a `Loop`, dispatching on `current_block`. Instead, we pick one entry of the
+//! region as its header and give every other entry a private copy of the blocks it can reach
+//! without going through that header. The region then only has a single entry and reloops into
+//! ordinary loops and labeled breaks.
+//!
+//! Splitting duplicates code and can blow up exponentially, so it is bounded by a budget on how
+//! many statements may be copied, relative to the size of the function.
+
+use super::*;
+
+/// The outcome of splitting the nodes of a CFG
+#[derive(Copy, Clone, Debug, Default)]
+pub struct NodeSplitting {
+    /// Whether the CFG had irreducible control flow to begin with
+    pub was_irreducible: bool,
+
+    /// Whether the CFG is reducible after splitting
+    pub is_reducible: bool,
+
+    /// Number of blocks that were copied
+    pub split_blocks: usize,
+
+    /// Number of statements in the copied blocks
+    pub copied_stmts: usize,
+}
+
+impl Cfg<Label, StmtOrDecl> {
+
+    /// Split nodes until the CFG is reducible, copying at most `budget_percent` percent of the
+    /// statements in the CFG. Blocks declaring variables are never copied, since a declaration
+    /// can only be placed once.
+    pub fn split_irreducible_nodes(&mut self, budget_percent: u32) -> NodeSplitting {
+        let total_size: usize = self.nodes.values().map(block_size).sum();
+        let mut budget = total_size * budget_percent as usize / 100;
+        let mut report = NodeSplitting::default();
+
+        let mut next_label = self.nodes
+            .keys()
+            .filter_map(|lbl| match *lbl {
+                Label::Synthetic(id) => Some(id + 1),
+                Label::FromC(_) => None,
+            })
+            .max()
+            .unwrap_or(0);
+
+        loop {
+            let predecessors = self.predecessor_map();
+            let all_nodes: IndexSet<Label> = self.nodes.keys().cloned().collect();
+            let (region, entries) = match self.find_multi_entry_region(&all_nodes, &predecessors) {
+                Some(region) => region,
+                None => {
+                    report.is_reducible = true;
+                    return report
+                }
+            };
+            report.was_irreducible = true;
+
+            // Pick the header that requires copying the fewest statements
+            let best = entries
+                .iter()
+                .filter_map(|&header| {
+                    let copies: Vec<IndexSet<Label>> = entries
+                        .iter()
+                        .filter(|&&entry| entry != header)
+                        .map(|&entry| self.reachable_avoiding(entry, header, &region))
+                        .collect();
+                    let copies_decls = copies
+                        .iter()
+                        .flat_map(|copy| copy.iter())
+                        .any(|lbl| self.nodes[lbl].body.iter().any(|s| match *s {
+                            StmtOrDecl::Decl(_) => true,
+                            _ => false,
+                        }));
+                    if copies_decls {
+                        return None
+                    }
+                    let cost: usize = copies
+                        .iter()
+                        .flat_map(|copy| copy.iter())
+                        .map(|lbl| block_size(&self.nodes[lbl]))
+                        .sum();
+                    Some((cost, header, copies))
+                })
+                .min_by_key(|&(cost, _, _)| cost);
+
+            let (cost, header, copies) = match best {
+                Some(best) if best.0 <= budget => best,
+                _ => return report,
+            };
+            budget -= cost;
+
+            let other_entries = entries.iter().cloned().filter(|&entry| entry != header);
+            for (entry, copy) in other_entries.zip(copies) {
+                let renamed: IndexMap<Label, Label> = copy
+                    .iter()
+                    .map(|&lbl| {
+                        let new_lbl = Label::Synthetic(next_label);
+                        next_label += 1;
+                        (lbl, new_lbl)
+                    })
+                    .collect();
+
+                for (lbl, new_lbl) in &renamed {
+                    let bb = self.nodes[lbl].clone();
+                    let terminator = bb.terminator.map_labels(|l| *renamed.get(l).unwrap_or(l));
+                    report.split_blocks += 1;
+                    report.copied_stmts += bb.body.len();
+                    self.nodes.insert(*new_lbl, BasicBlock { terminator, ..bb });
+                }
+
+                // Send the edges entering the region at `entry` to its copy
+                let new_entry = renamed[&entry];
+                for pred in predecessors.get(&entry).into_iter().flatten() {
+                    if region.contains(pred) {
+                        continue
+                    }
+                    let bb = self.nodes.get_mut(pred).expect("predecessor not found");
+                    for lbl in bb.terminator.get_labels_mut() {
+                        if *lbl == entry {
+                            *lbl = new_entry;
+                        }
+                    }
+                }
+                if self.entries == entry {
+                    self.entries = new_entry;
+                }
+            }
+        }
+    }
+
+    /// Map each block to the blocks that jump to it
+    fn predecessor_map(&self) -> IndexMap<Label, IndexSet<Label>> {
+        let mut predecessors: IndexMap<Label, IndexSet<Label>> = IndexMap::new();
+        for (lbl, bb) in &self.nodes {
+            for succ in bb.terminator.get_labels() {
+                predecessors.entry(*succ).or_insert(IndexSet::new()).insert(*lbl);
+            }
+        }
+        predecessors
+    }
+
+    /// Find a strongly connected region within `nodes` that can be entered at more than one block,
+    /// returning the region and its entries. Single-entry regions are searched for nested
+    /// multi-entry regions once their entry is removed.
+    fn find_multi_entry_region(
+        &self,
+        nodes: &IndexSet<Label>,
+        predecessors: &IndexMap<Label, IndexSet<Label>>,
+    ) -> Option<(IndexSet<Label>, IndexSet<Label>)> {
+        for region in self.strongly_connected_components(nodes) {
+            if region.len() < 2 {
+                continue
+            }
+
+            let entries: IndexSet<Label> = region
+                .iter()
+                .cloned()
+                .filter(|lbl| {
+                    *lbl == self.entries || predecessors
+                        .get(lbl)
+                        .map_or(false, |preds| preds.iter().any(|pred| !region.contains(pred)))
+                })
+                .collect();
+
+            if entries.len() > 1 {
+                return Some((region, entries))
+            }
+
+            let inner: IndexSet<Label> = region.difference(&entries).cloned().collect();
+            if let Some(found) = self.find_multi_entry_region(&inner, predecessors) {
+                return Some(found)
+            }
+        }
+        None
+    }
+
+    /// Tarjan's algorithm, restricted to the subgraph induced by `nodes`
+    fn strongly_connected_components(&self, nodes: &IndexSet<Label>) -> Vec<IndexSet<Label>> {
+        struct Tarjan<'a> {
+            cfg: &'a Cfg<Label, StmtOrDecl>,
+            nodes: &'a IndexSet<Label>,
+            index: IndexMap<Label, usize>,
+            low_link: IndexMap<Label, usize>,
+            stack: IndexSet<Label>,
+            components: Vec<IndexSet<Label>>,
+        }
+
+        impl<'a> Tarjan<'a> {
+            fn visit(&mut self, lbl: Label) {
+                let index = self.index.len();
+                self.index.insert(lbl, index);
+                self.low_link.insert(lbl, index);
+                self.stack.insert(lbl);
+
+                let successors: Vec<Label> = self.cfg.nodes[&lbl].terminator
+                    .get_labels()
+                    .into_iter()
+                    .cloned()
+                    .filter(|succ| self.nodes.contains(succ))
+                    .collect();
+                for succ in successors {
+                    if !self.index.contains_key(&succ) {
+                        self.visit(succ);
+                        let low = self.low_link[&lbl].min(self.low_link[&succ]);
+                        self.low_link.insert(lbl, low);
+                    } else if self.stack.contains(&succ) {
+                        let low = self.low_link[&lbl].min(self.index[&succ]);
+                        self.low_link.insert(lbl, low);
+                    }
+                }
+
+                if self.low_link[&lbl] == self.index[&lbl] {
+                    let mut component = IndexSet::new();
+                    while let Some(member) = self.stack.pop() {
+                        component.insert(member);
+                        if member == lbl {
+                            break
+                        }
+                    }
+                    self.components.push(component);
+                }
+            }
+        }
+
+        let mut tarjan = Tarjan {
+            cfg: self,
+            nodes,
+            index: IndexMap::new(),
+            low_link: IndexMap::new(),
+            stack: IndexSet::new(),
+            components: vec![],
+        };
+        for &lbl in nodes {
+            if !tarjan.index.contains_key(&lbl) {
+                tarjan.visit(lbl);
+            }
+        }
+        tarjan.components
+    }
+
+    /// The blocks of `region` reachable from `start` without going through `avoid`
+    fn reachable_avoiding(&self, start: Label, avoid: Label, region: &IndexSet<Label>) -> IndexSet<Label> {
+        let mut visited: IndexSet<Label> = IndexSet::new();
+        let mut to_visit: Vec<Label> = vec![start];
+
+        while let Some(lbl) = to_visit.pop() {
+            if lbl == avoid || !region.contains(&lbl) || !visited.insert(lbl) {
+                continue
+            }
+            to_visit.extend(self.nodes[&lbl].terminator.get_labels().into_iter().cloned());
+        }
+
+        visited
+    }
+}
+
+/// Size of a block for the purposes of the code-growth budget
+fn block_size<L, S>(bb: &BasicBlock<L, S>) -> usize {
+    bb.body.len() + 1
+}
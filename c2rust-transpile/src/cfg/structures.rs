@@ -125,6 +125,12 @@ pub trait StructuredStatement: Sized {
         exit_style: ExitStyle,            // `break` or a `continue`
         label: Option<Self::L>,           // which loop are we breaking
     ) -> Self;
+
+    /// Make a labeled block that can be exited early with a labeled `break`
+    fn mk_labeled_block(
+        lbl: Self::L,
+        body: Self
+    ) -> Self;
 }
 
 
@@ -140,6 +146,7 @@ pub enum StructuredAST<E, P, L, S> {
     GotoTable(Vec<(L, StructuredAST<E,P,L,S>)>, Box<StructuredAST<E,P,L,S>>),
     Loop(Option<L>, Box<StructuredAST<E,P,L,S>>),
     Exit(ExitStyle, Option<L>),
+    LabeledBlock(L, Box<StructuredAST<E,P,L,S>>),
 }
 
 impl<E, P, L, S> StructuredStatement for StructuredAST<E, P, L, S> {
@@ -183,25 +190,62 @@ impl<E, P, L, S> StructuredStatement for StructuredAST<E, P, L, S> {
     fn mk_exit(exit_style: ExitStyle, label: Option<Self::L>) -> Self {
         StructuredAST::Exit(exit_style, label)
     }
+
+    fn mk_labeled_block(lbl: Self::L, body: Self) -> Self {
+        StructuredAST::LabeledBlock(lbl, Box::new(body))
+    }
 }
 
 
 /// Recursive helper for `structured_cfg`
 ///
+/// `exits` lists the structures that can be exited from, innermost first: each has a label, a
+/// flag for whether it is a labeled block rather than a loop, and what each exit label does.
+///
 /// TODO: move this into `structured_cfg`?
 fn structured_cfg_help<S: StructuredStatement<E=P<Expr>, P=P<Pat>, L=Label, S=StmtOrComment>>(
-    exits: Vec<(Label, IndexMap<Label, (IndexSet<Label>, ExitStyle)>)>,
+    exits: Vec<(Label, bool, IndexMap<Label, (IndexSet<Label>, ExitStyle)>)>,
     next: &IndexSet<Label>,
     root: &Vec<Structure<StmtOrComment>>,
     used_loop_labels: &mut IndexSet<Label>,
 ) -> Result<S, String> {
 
+    // Structures followed by a `Multiple` that can be translated as a labeled block, along with
+    // the branch they fall through to instead of what follows the `Multiple`
+    let early_exits = early_exits(root);
+    let early_exit_nexts: Vec<Option<IndexSet<Label>>> = early_exits
+        .iter()
+        .map(|early_exit| early_exit.map(|(branch, _)| indexset![branch]))
+        .collect();
+
     let mut next: &IndexSet<Label> = next;
     let mut rest: S = S::empty();
 
-    for structure in root.iter().rev() {
+    // The translated branch of an early exit `Multiple`, waiting for the structure before it,
+    // along with the labels that can follow the `Multiple`
+    let mut early_exit_branch: Option<(S, bool, IndexSet<Label>)> = None;
+
+    for (i, structure) in root.iter().enumerate().rev() {
         let mut new_rest: S = S::empty();
 
+        // Jumps to the join of a following early exit `Multiple` break out of a labeled block.
+        // What follows the block may still dispatch on `current_block`, so the break has to
+        // record the join whenever more than one label can follow the `Multiple`.
+        let early_exit = early_exits[i];
+        let exits = match early_exit {
+            Some((_, join)) => {
+                next = early_exit_nexts[i].as_ref().expect("early exit without next");
+                let follow = early_exit_branch.as_ref()
+                    .map(|&(_, _, ref follow)| follow.clone())
+                    .expect("early exit branch not translated");
+                let block_exits = indexmap!{ join => (follow, ExitStyle::Break) };
+                let mut exits_new = vec![(join, true, block_exits)];
+                exits_new.extend(exits.clone());
+                exits_new
+            }
+            None => exits.clone(),
+        };
+
         match structure {
             &Structure::Simple { ref body, ref terminator, .. } => {
 
@@ -230,10 +274,12 @@ fn structured_cfg_help<S: StructuredStatement<E=P<Expr>, P=P<Pat>, L=Label, S=St
                         &StructureLabel::ExitTo(to) => {
 
                             let mut immediate = true;
-                            for &(label, ref local) in &exits {
+                            for &(label, is_block, ref local) in &exits {
                                 if let Some(&(ref follow, exit_style)) = local.get(&to) {
 
-                                    let lbl = if immediate {
+                                    // Unlabeled `break`s and `continue`s aren't allowed to leave
+                                    // labeled blocks
+                                    let lbl = if immediate && !is_block {
                                         None
                                     } else {
                                         used_loop_labels.insert(label);
@@ -251,10 +297,28 @@ fn structured_cfg_help<S: StructuredStatement<E=P<Expr>, P=P<Pat>, L=Label, S=St
                             Err(format!("Not a valid exit: {:?} has nothing to exit to", to))
                         }
 
-                        &StructureLabel::GoTo(to) => Err(format!(
-                            "Not a valid exit: {:?} (GoTo isn't falling through to {:?})",
-                            to, next
-                        )),
+                        &StructureLabel::GoTo(to) => {
+
+                            // Without crossing a loop, we can still jump forward out of the
+                            // labeled blocks we are in
+                            for &(label, is_block, ref local) in &exits {
+                                if !is_block {
+                                    break;
+                                }
+                                if let Some(&(ref follow, exit_style)) = local.get(&to) {
+                                    used_loop_labels.insert(label);
+                                    return Ok(S::mk_append(
+                                        insert_goto(to, follow),
+                                        S::mk_exit(exit_style, Some(label)),
+                                    ))
+                                }
+                            }
+
+                            Err(format!(
+                                "Not a valid exit: {:?} (GoTo isn't falling through to {:?})",
+                                to, next
+                            ))
+                        }
                     }
                 };
 
@@ -273,6 +337,25 @@ fn structured_cfg_help<S: StructuredStatement<E=P<Expr>, P=P<Pat>, L=Label, S=St
                 });
             }
 
+            &Structure::Multiple { ref branches, .. }
+            if i > 0 && early_exits[i - 1].is_some() => {
+                let (branch, join) = early_exits[i - 1].expect("early exit not found");
+
+                // Jumps to the join from before this structure are only known to be breaks out of
+                // the labeled block once we get there
+                let join_was_used = used_loop_labels.remove(&join);
+
+                let mut branch_exits = vec![(join, true, IndexMap::new())];
+                branch_exits.extend(exits.clone());
+                let stmts = structured_cfg_help(
+                    branch_exits,
+                    next,
+                    &branches[&branch],
+                    used_loop_labels,
+                )?;
+                early_exit_branch = Some((stmts, join_was_used, next.clone()));
+            }
+
             &Structure::Multiple { ref branches, ref then, .. } => {
                 let cases: Vec<(Label, S)> = branches
                     .iter()
@@ -311,7 +394,7 @@ fn structured_cfg_help<S: StructuredStatement<E=P<Expr>, P=P<Pat>, L=Label, S=St
                     .map(|e| (*e, (next.clone(), ExitStyle::Break)))
                 );
 
-                let mut exits_new = vec![(*label, these_exits)];
+                let mut exits_new = vec![(*label, false, these_exits)];
                 exits_new.extend(exits.clone());
 
                 let body = structured_cfg_help(
@@ -325,6 +408,20 @@ fn structured_cfg_help<S: StructuredStatement<E=P<Expr>, P=P<Pat>, L=Label, S=St
             }
         }
 
+        // Wrap this structure and the branch of the early exit `Multiple` after it in a labeled
+        // block, unless nothing actually jumps straight to the join
+        if let Some((_, join)) = early_exit {
+            let (branch_stmts, join_was_used, _) = early_exit_branch.take()
+                .expect("early exit branch not translated");
+            new_rest = S::mk_append(new_rest, branch_stmts);
+            if used_loop_labels.remove(&join) {
+                new_rest = S::mk_labeled_block(join, new_rest);
+            }
+            if join_was_used {
+                used_loop_labels.insert(join);
+            }
+        }
+
         new_rest = S::mk_append(new_rest, rest);
 
         rest = new_rest;
@@ -334,22 +431,52 @@ fn structured_cfg_help<S: StructuredStatement<E=P<Expr>, P=P<Pat>, L=Label, S=St
     Ok(rest)
 }
 
-/// Checks if there are any `Multiple` structures anywhere. Only if so will there be any need for a
-/// `current_block` variable.
+/// For each structure, the branch and join labels of the `Multiple` right after it, if that
+/// `Multiple` is translated as a labeled block around the two of them (see `early_exit_join`).
+fn early_exits<Stmt>(root: &[Structure<Stmt>]) -> Vec<Option<(Label, Label)>> {
+    let mut early_exits: Vec<Option<(Label, Label)>> = vec![];
+    for i in 0..root.len() {
+        // A `Multiple` that is itself wrapped into a labeled block can't wrap another one
+        let is_wrapped = i > 0 && early_exits[i - 1].is_some();
+        let early_exit = if is_wrapped { None } else { root.get(i + 1).and_then(early_exit_join) };
+        early_exits.push(early_exit);
+    }
+    early_exits
+}
+
+/// Checks if there are any `Multiple` structures anywhere that need to dispatch on the label to
+/// go to next. Only if so will there be any need for a `current_block` variable.
 pub fn has_multiple<Stmt>(root: &Vec<Structure<Stmt>>) -> bool {
-    root.iter().any(|structure| {
+    find_multiple(root, false)
+}
+
+/// Checks if there are any `Multiple` structures anywhere that are translated as labeled blocks.
+pub fn has_early_exit_block<Stmt>(root: &Vec<Structure<Stmt>>) -> bool {
+    find_multiple(root, true)
+}
+
+/// Checks for `Multiple` structures that either are (`early_exit`) or aren't translated as
+/// labeled blocks
+fn find_multiple<Stmt>(root: &Vec<Structure<Stmt>>, early_exit: bool) -> bool {
+    let early_exits = early_exits(root);
+    root.iter().enumerate().any(|(i, structure)| {
         match structure {
             &Structure::Simple { ref terminator, .. } => terminator
                 .get_labels()
                 .into_iter()
                 .any(|structure_label|
                     match structure_label {
-                        &StructureLabel::Nested(ref nested) => has_multiple(nested),
+                        &StructureLabel::Nested(ref nested) => find_multiple(nested, early_exit),
                         _ => false,
                     }
                 ),
-            &Structure::Multiple { .. } => return true,
-            &Structure::Loop { ref body, .. } => has_multiple(body),
+            &Structure::Multiple { ref branches, ref then, .. } => {
+                let is_early_exit = i > 0 && early_exits[i - 1].is_some();
+                is_early_exit == early_exit
+                    || branches.values().any(|branch| find_multiple(branch, early_exit))
+                    || find_multiple(then, early_exit)
+            }
+            &Structure::Loop { ref body, .. } => find_multiple(body, early_exit),
         }
     })
 }
@@ -362,6 +489,22 @@ struct StructureState {
 }
 
 impl StructureState {
+
+    /// Translate a structured AST into the statements of a nested block, returning them along
+    /// with the `Span` to give the block. Comments left over at the end of the block are printed
+    /// right before its closing brace.
+    fn into_block(
+        &self,
+        ast: StructuredAST<P<Expr>, P<Pat>, Label, StmtOrComment>,
+        comment_store: &mut comment_store::CommentStore,
+        queued_comments: &mut Vec<String>,
+    ) -> (Vec<Stmt>, Span) {
+        let mut output = vec![];
+        self.into_stmt(ast, comment_store, queued_comments, &mut output);
+        let end = comment_store.add_block_end_comment_lines(queued_comments.drain(..).collect());
+        (output, end)
+    }
+
     pub fn into_stmt(
         &self,
         ast: StructuredAST<P<Expr>, P<Pat>, Label, StmtOrComment>,
@@ -404,13 +547,9 @@ impl StructureState {
                 let arms: Vec<Arm> = cases
                     .into_iter()
                     .map(|(pats, stmts)| -> Arm {
-                        let stmts = {
-                            let mut output = vec![];
-                            self.into_stmt(stmts, comment_store, queued_comments, &mut output);
-                            output
-                        };
+                        let (stmts, end) = self.into_block(stmts, comment_store, queued_comments);
 
-                        let body = mk().block_expr(mk().block(stmts));
+                        let body = mk().block_expr(mk().span(end).block(stmts));
                         mk().arm(pats, None as Option<P<Expr>>, body)
                     })
                     .collect();
@@ -430,27 +569,22 @@ impl StructureState {
 
                 let s = comment_store.add_comment_lines(queued_comments.drain(..).collect());
 
-                let then = {
-                    let mut output = vec![];
-                    self.into_stmt(*then, comment_store, queued_comments, &mut output);
-                    output
-                };
+                let (then, then_end) = self.into_block(*then, comment_store, queued_comments);
+                let (mut els, els_end) = self.into_block(*els, comment_store, queued_comments);
 
-                let mut els = {
-                    let mut output = vec![];
-                    self.into_stmt(*els, comment_store, queued_comments, &mut output);
-                    output
-                };
+                // A branch with nothing but comments in it is kept for the sake of the comments
+                let then_is_empty = then.is_empty() && then_end == DUMMY_SP;
+                let els_is_empty = els.is_empty() && els_end == DUMMY_SP;
 
-                let mut if_stmt = match (then.is_empty(), els.is_empty()) {
+                let mut if_stmt = match (then_is_empty, els_is_empty) {
                     (true, true) => mk().semi_stmt(cond),
                     (false, true) => {
-                        let if_expr = mk().ifte_expr(cond, mk().block(then), None as Option<P<Expr>>);
+                        let if_expr = mk().ifte_expr(cond, mk().span(then_end).block(then), None as Option<P<Expr>>);
                         mk().expr_stmt(if_expr)
                     },
                     (true, false) => {
                         let negated_cond = not(&cond);
-                        let if_expr = mk().ifte_expr(negated_cond, mk().block(els), None as Option<P<Expr>>);
+                        let if_expr = mk().ifte_expr(negated_cond, mk().span(els_end).block(els), None as Option<P<Expr>>);
                         mk().expr_stmt(if_expr)
                     },
                     (false, false) => {
@@ -462,7 +596,7 @@ impl StructureState {
                             }
                         }
 
-                        let is_els_expr = els.len() == 1 && is_expr(&els[0].node);
+                        let is_els_expr = els.len() == 1 && is_expr(&els[0].node) && els_end == DUMMY_SP;
 
                         let els_branch = if is_els_expr {
                             match els.swap_remove(0).node {
@@ -470,10 +604,10 @@ impl StructureState {
                                 _ => panic!("is_els_expr out of sync"),
                             }
                         } else {
-                            mk().block_expr(mk().block(els))
+                            mk().block_expr(mk().span(els_end).block(els))
                         };
 
-                        let if_expr = mk().ifte_expr(cond, mk().block(then), Some(els_branch));
+                        let if_expr = mk().ifte_expr(cond, mk().span(then_end).block(then), Some(els_branch));
                         mk().expr_stmt(if_expr)
                     }
                 };
@@ -490,29 +624,21 @@ impl StructureState {
                 let mut arms: Vec<Arm> = cases
                     .into_iter()
                     .map(|(lbl, stmts)| -> Arm {
-                        let stmts = {
-                            let mut output = vec![];
-                            self.into_stmt(stmts, comment_store, queued_comments, &mut output);
-                            output
-                        };
+                        let (stmts, end) = self.into_block(stmts, comment_store, queued_comments);
 
                         let lbl_expr = if self.debug_labels { lbl.to_string_expr() } else { lbl.to_num_expr() };
                         let pat = mk().lit_pat(lbl_expr);
-                        let body = mk().block_expr(mk().block(stmts));
+                        let body = mk().block_expr(mk().span(end).block(stmts));
                         mk().arm(vec![pat], None as Option<P<Expr>>, body)
                     })
                     .collect();
 
-                let then = {
-                    let mut output = vec![];
-                    self.into_stmt(*then, comment_store, queued_comments, &mut output);
-                    output
-                };
+                let (then, then_end) = self.into_block(*then, comment_store, queued_comments);
 
                 arms.push(mk().arm(
                     vec![mk().wild_pat()],
                     None as Option<P<Expr>>,
-                    mk().block_expr(mk().block(then))
+                    mk().block_expr(mk().span(then_end).block(then))
                 ));
 
                 let e = mk().match_expr(self.current_block.clone(), arms);
@@ -528,11 +654,7 @@ impl StructureState {
 
                 let s = comment_store.add_comment_lines(queued_comments.drain(..).collect());
 
-                let body = {
-                    let mut output = vec![];
-                    self.into_stmt(*body, comment_store, queued_comments, &mut output);
-                    output
-                };
+                let (body, body_end) = self.into_block(*body, comment_store, queued_comments);
 
 
                 // TODO: this is ugly but it needn't be. We are just pattern matching on particular ASTs.
@@ -544,7 +666,7 @@ impl StructureState {
                                     if let syntax::ast::ExprKind::Break(None, None) = expr.node {
                                         let e = mk().while_expr(
                                             not(cond),
-                                            mk().block(body.iter().skip(1).cloned().collect()),
+                                            mk().span(body_end).block(body.iter().skip(1).cloned().collect()),
                                             lbl.map(|l| l.pretty_print()),
                                         );
                                         output.push(mk().span(s).expr_stmt(e));
@@ -556,7 +678,7 @@ impl StructureState {
                     }
                 }
 
-                let e = mk().loop_expr(mk().block(body), lbl.map(|l| l.pretty_print()));
+                let e = mk().loop_expr(mk().span(body_end).block(body), lbl.map(|l| l.pretty_print()));
 
                 output.push(mk().span(s).expr_stmt(e));
             },
@@ -574,6 +696,18 @@ impl StructureState {
 
                 output.push(mk().span(s).semi_stmt(e));
             },
+
+            LabeledBlock(lbl, body) => {
+                // Make a labeled block that `break`s can jump to the end of.
+
+                let s = comment_store.add_comment_lines(queued_comments.drain(..).collect());
+
+                let (body, body_end) = self.into_block(*body, comment_store, queued_comments);
+
+                let e = mk().labelled_block_expr(mk().span(body_end).block(body), lbl.pretty_print());
+
+                output.push(mk().span(s).expr_stmt(e));
+            },
         }
     }
 }
@@ -0,0 +1,363 @@
+//! This module merges the translations of a C file under several preprocessor configurations into
+//! a single Rust file.
+//!
+//! Each configuration is a named set of extra clang arguments (e.g. `ssl=-DWITH_SSL`), and its name
+//! becomes a Cargo feature of the translated crate. The file is translated once per configuration,
+//! and the pretty-printed outputs are split into top-level items. Items that come out identical in
+//! every configuration are emitted once; all others are emitted once per distinct text, gated by
+//! `#[cfg(feature = "...")]` on the configurations producing that text. Exactly one of the
+//! features must be enabled when building the translated crate. Cargo features are additive, so
+//! each merged file starts with `compile_error!` guards rejecting builds that enable none or
+//! several of them; a configuration other than the default one is built with
+//! `--no-default-features --features NAME`.
+//!
+//! Merging works on the printed text rather than on the AST since each translation runs in its own
+//! session, with its own comments and spans.
+
+use std::str::FromStr;
+
+use indexmap::IndexSet;
+
+/// A named set of extra clang arguments to translate under
+#[derive(Debug, Clone)]
+pub struct Configuration {
+    /// Name of the configuration, which is also the name of its Cargo feature
+    pub name: String,
+    /// Extra arguments to pass to clang when translating this configuration
+    pub clang_args: Vec<String>,
+}
+
+impl FromStr for Configuration {
+    type Err = String;
+
+    /// Parse `NAME=ARGS`, where `ARGS` is a whitespace-separated list of clang arguments
+    fn from_str(s: &str) -> Result<Configuration, String> {
+        let mut parts = s.splitn(2, '=');
+        let name = parts.next().unwrap_or("").trim();
+        let args = parts.next().unwrap_or("");
+
+        if name.is_empty() {
+            return Err(format!("Missing configuration name in `{}`", s))
+        }
+        if name == "default" {
+            return Err(format!("`{}` can't be used as a configuration name", name))
+        }
+        if !name.chars().all(|c: char| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+            return Err(format!("Configuration name `{}` is not a valid Cargo feature name", name))
+        }
+
+        Ok(Configuration {
+            name: name.to_owned(),
+            clang_args: args.split_whitespace().map(String::from).collect(),
+        })
+    }
+}
+
+/// Merge the translations of one file under each of the given configurations, given as pairs of
+/// configuration name and translated source, into a single source.
+pub fn merge_translations(translations: &[(String, String)]) -> String {
+    let num_configs = translations.len();
+
+    let mut attrs: IndexSet<String> = IndexSet::new();
+    let mut features: IndexSet<String> = IndexSet::new();
+    let mut items: Vec<(String, Vec<usize>)> = vec![];
+
+    for (config, &(_, ref source)) in translations.iter().enumerate() {
+        let (config_attrs, config_items) = split_items(source);
+
+        // Inner attributes can't be gated on a feature, so we take their union
+        for attr in config_attrs {
+            match feature_names(&attr) {
+                Some(names) => {
+                    attrs.insert(String::from(FEATURE_ATTR));
+                    features.extend(names);
+                }
+                None => {
+                    attrs.insert(attr);
+                }
+            }
+        }
+
+        // Line up the items of this configuration with those seen so far. Items only found in this
+        // configuration go right after the last item matched.
+        let mut pos = 0;
+        for item in config_items {
+            let found = items[pos..]
+                .iter()
+                .position(|&(ref text, ref configs)| *text == item && !configs.contains(&config));
+            match found {
+                Some(offset) => {
+                    items[pos + offset].1.push(config);
+                    pos += offset + 1;
+                }
+                None => {
+                    items.insert(pos, (item, vec![config]));
+                    pos += 1;
+                }
+            }
+        }
+    }
+
+    let mut features: Vec<String> = features.into_iter().collect();
+    features.sort();
+    let feature_attr = format!("#![feature({})]", features.join(", "));
+
+    let mut out: Vec<String> = attrs
+        .into_iter()
+        .map(|attr| if attr == FEATURE_ATTR { feature_attr.clone() } else { attr })
+        .collect();
+
+    let names: Vec<&str> = translations.iter().map(|&(ref name, _)| name.as_str()).collect();
+    out.extend(exclusive_feature_guards(&names));
+
+    for (text, configs) in items {
+        if configs.len() == num_configs {
+            out.push(text);
+            continue
+        }
+        let mut gated: Vec<String> = configs
+            .iter()
+            .map(|&config| format!("feature = \"{}\"", translations[config].0))
+            .collect();
+        let cfg = if gated.len() == 1 {
+            gated.pop().unwrap()
+        } else {
+            format!("any({})", gated.join(", "))
+        };
+        out.push(format!("#[cfg({})]\n{}", cfg, text));
+    }
+
+    let mut merged = out.join("\n");
+    merged.push('\n');
+    merged
+}
+
+/// `compile_error!`s for builds enabling several of the configuration features, or none of them
+fn exclusive_feature_guards(names: &[&str]) -> Vec<String> {
+    let mut guards = vec![];
+    if names.len() < 2 {
+        return guards
+    }
+
+    let features: Vec<String> = names.iter().map(|name| format!("feature = \"{}\"", name)).collect();
+    guards.push(format!(
+        "#[cfg(not(any({})))]\ncompile_error!(\"Enable one of the features {}\");",
+        features.join(", "),
+        names.join(", "),
+    ));
+    let pairs = names.iter().zip(&features);
+    for (i, (first, first_feature)) in pairs.clone().enumerate() {
+        for (second, second_feature) in pairs.clone().skip(i + 1) {
+            guards.push(format!(
+                "#[cfg(all({}, {}))]\ncompile_error!(\"Features {} and {} are mutually exclusive, \
+                 build with --no-default-features --features NAME\");",
+                first_feature, second_feature, first, second,
+            ));
+        }
+    }
+    guards
+}
+
+/// Placeholder for the merged `#![feature(...)]` attribute
+const FEATURE_ATTR: &str = "#![feature]";
+
+/// The feature names enabled by a `#![feature(...)]` attribute
+fn feature_names(attr: &str) -> Option<Vec<String>> {
+    let attr = attr.trim();
+    if !attr.starts_with("#![feature(") || !attr.ends_with(")]") {
+        return None
+    }
+    let names = &attr["#![feature(".len()..attr.len() - ")]".len()];
+    Some(names
+        .split(',')
+        .map(|name| name.trim())
+        .filter(|name| !name.is_empty())
+        .map(String::from)
+        .collect())
+}
+
+/// Split a pretty-printed Rust file into its leading inner attributes and its top-level items.
+/// Comments and outer attributes are kept with the item they precede.
+fn split_items(source: &str) -> (Vec<String>, Vec<String>) {
+    let mut attrs: Vec<String> = vec![];
+    let mut items: Vec<String> = vec![];
+    let mut scanner = Scanner::default();
+    let mut chunk: Vec<&str> = vec![];
+    let mut in_attrs = true;
+
+    for line in source.lines() {
+        let at_boundary = scanner.is_at_boundary();
+        if chunk.is_empty() && at_boundary && line.trim().is_empty() {
+            continue
+        }
+        if chunk.is_empty() && !line.starts_with("#![") {
+            in_attrs = false;
+        }
+
+        chunk.push(line);
+        let last = scanner.scan_line(line);
+
+        let complete = scanner.is_at_boundary() && match last {
+            Some(']') => in_attrs,
+            Some(';') | Some('}') => !in_attrs,
+            _ => false,
+        };
+        if complete {
+            let text = chunk.join("\n");
+            chunk.clear();
+            if in_attrs { attrs.push(text) } else { items.push(text) }
+        }
+    }
+
+    // Trailing comments
+    if !chunk.is_empty() {
+        items.push(chunk.join("\n"));
+    }
+
+    (attrs, items)
+}
+
+/// Tracks nesting and literals across lines, so that item boundaries can be told apart from the
+/// ends of nested statements and from text inside strings or comments
+#[derive(Default)]
+struct Scanner {
+    depth: usize,
+    in_str: bool,
+    block_comments: usize,
+}
+
+impl Scanner {
+    fn is_at_boundary(&self) -> bool {
+        self.depth == 0 && !self.in_str && self.block_comments == 0
+    }
+
+    /// Scan one line, returning its last character outside of comments
+    fn scan_line(&mut self, line: &str) -> Option<char> {
+        let chars: Vec<char> = line.chars().collect();
+        let mut last = None;
+        let mut i = 0;
+
+        while i < chars.len() {
+            let c = chars[i];
+            let next = chars.get(i + 1).cloned();
+
+            if self.block_comments > 0 {
+                if c == '*' && next == Some('/') {
+                    self.block_comments -= 1;
+                    i += 1;
+                } else if c == '/' && next == Some('*') {
+                    self.block_comments += 1;
+                    i += 1;
+                }
+            } else if self.in_str {
+                if c == '\\' {
+                    i += 1;
+                } else if c == '"' {
+                    self.in_str = false;
+                }
+                last = Some(c);
+            } else if c == '/' && next == Some('/') {
+                break
+            } else if c == '/' && next == Some('*') {
+                self.block_comments += 1;
+                i += 1;
+            } else if c == '"' {
+                self.in_str = true;
+                last = Some(c);
+            } else if c == '\'' {
+                // Skip character literals, but not lifetimes
+                if next == Some('\\') {
+                    i += 3;
+                    while i < chars.len() && chars[i] != '\'' {
+                        i += 1;
+                    }
+                } else if chars.get(i + 2) == Some(&'\'') {
+                    i += 2;
+                }
+                last = Some('\'');
+            } else {
+                match c {
+                    '{' | '(' | '[' => self.depth += 1,
+                    '}' | ')' | ']' => self.depth = self.depth.saturating_sub(1),
+                    _ => {}
+                }
+                if !c.is_whitespace() {
+                    last = Some(c);
+                }
+            }
+            i += 1;
+        }
+
+        last
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn configs(sources: &[(&str, &str)]) -> Vec<(String, String)> {
+        sources.iter().map(|&(name, src)| (name.to_owned(), src.to_owned())).collect()
+    }
+
+    #[test]
+    fn splits_items() {
+        let source = "#![allow(dead_code)]\n#![feature(libc)]\nextern crate libc;\n// c: a.c:1\n#[no_mangle]\npub unsafe extern \"C\" fn f(mut x: i32)\n -> i32 {\n    if x > 0 { return 1; }\n    let s = b\"}\\\"{\\x00\";\n    return '}' as i32;\n}\npub static mut g: [i32; 2] =\n    [1, 2];\n";
+        let (attrs, items) = split_items(source);
+        assert_eq!(attrs, vec!["#![allow(dead_code)]", "#![feature(libc)]"]);
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0], "extern crate libc;");
+        assert!(items[1].starts_with("// c: a.c:1\n#[no_mangle]"));
+        assert!(items[2].starts_with("pub static mut g"));
+    }
+
+    #[test]
+    fn gates_differing_items() {
+        let merged = merge_translations(&configs(&[
+            ("ssl", "#![feature(libc)]\npub type T = i32;\npub fn f() -> i32 { 1 }\n"),
+            ("debug", "#![feature(libc, asm)]\npub type T = i32;\npub fn f() -> i32 { 2 }\npub fn log() { }\n"),
+        ]));
+        let expected = "#![feature(asm, libc)]\n\
+                        #[cfg(not(any(feature = \"ssl\", feature = \"debug\")))]\n\
+                        compile_error!(\"Enable one of the features ssl, debug\");\n\
+                        #[cfg(all(feature = \"ssl\", feature = \"debug\"))]\n\
+                        compile_error!(\"Features ssl and debug are mutually exclusive, \
+                        build with --no-default-features --features NAME\");\n\
+                        pub type T = i32;\n\
+                        #[cfg(feature = \"debug\")]\n\
+                        pub fn f() -> i32 { 2 }\n\
+                        #[cfg(feature = \"debug\")]\n\
+                        pub fn log() { }\n\
+                        #[cfg(feature = \"ssl\")]\n\
+                        pub fn f() -> i32 { 1 }\n";
+        assert_eq!(merged, expected);
+    }
+
+    #[test]
+    fn gates_items_shared_by_some_configurations() {
+        let merged = merge_translations(&configs(&[
+            ("a", "pub fn f() { }\n"),
+            ("b", "pub fn f() { }\n"),
+            ("c", "pub fn g() { }\n"),
+        ]));
+        let guards = exclusive_feature_guards(&["a", "b", "c"]);
+        assert_eq!(guards.len(), 4);
+        assert!(guards[3].starts_with("#[cfg(all(feature = \"b\", feature = \"c\"))]"));
+        let expected = guards.join("\n") + "\n\
+                        #[cfg(feature = \"c\")]\n\
+                        pub fn g() { }\n\
+                        #[cfg(any(feature = \"a\", feature = \"b\"))]\n\
+                        pub fn f() { }\n";
+        assert_eq!(merged, expected);
+    }
+
+    #[test]
+    fn parses_configurations() {
+        let config: Configuration = "ssl=-DWITH_SSL  -DSSL_VERSION=3".parse().unwrap();
+        assert_eq!(config.name, "ssl");
+        assert_eq!(config.clang_args, vec!["-DWITH_SSL", "-DSSL_VERSION=3"]);
+        assert!("=-DX".parse::<Configuration>().is_err());
+        assert!("default=-DX".parse::<Configuration>().is_err());
+        assert!("with ssl=-DX".parse::<Configuration>().is_err());
+    }
+}
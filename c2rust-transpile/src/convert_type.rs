@@ -6,15 +6,23 @@ use std::ops::Index;
 use renamer::*;
 use std::collections::{HashSet,HashMap};
 use c_ast::CDeclId;
+use c2rust_ast_exporter::clang_ast::TargetInfo;
+
+/// Name of the `#[repr(C)]` struct describing the layout of a block
+pub const BLOCK_LITERAL: &str = "BlockLiteral";
 
 pub struct TypeConverter {
     pub translate_valist: bool,
+    /// Target to translate target-dependent integer types for. Without one, they are translated
+    /// to the `libc` types for whatever target the Rust code is built for.
+    pub target: Option<TargetInfo>,
     renamer: Renamer<CDeclId>,
     fields: HashMap<CDeclId, Renamer<CFieldId>>,
     features: HashSet<&'static str>,
+    blocks_used: bool,
 }
 
-static RESERVED_NAMES: [&str; 101] = [
+static RESERVED_NAMES: [&str; 104] = [
     // Keywords currently in use
     "as", "break", "const", "continue", "crate", "else", "enum", "extern", "false", "fn",
     "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
@@ -44,6 +52,8 @@ static RESERVED_NAMES: [&str; 101] = [
     "u8", "u16", "u32", "u64", "u128", "usize",
     "str",
 
+    // Helper types emitted by the translator
+    "BlockLiteral", "BlockDescriptor", "RustBlock",
 ];
 
 impl TypeConverter {
@@ -51,9 +61,11 @@ impl TypeConverter {
     pub fn new() -> TypeConverter {
         TypeConverter {
             translate_valist: false,
+            target: None,
             renamer: Renamer::new(&RESERVED_NAMES),
             fields: HashMap::new(),
             features: HashSet::new(),
+            blocks_used: false,
         }
     }
 
@@ -61,6 +73,11 @@ impl TypeConverter {
         &self.features
     }
 
+    /// Whether a block pointer type was converted, requiring the block ABI definitions
+    pub fn blocks_used(&self) -> bool {
+        self.blocks_used
+    }
+
     pub fn declare_decl_name(&mut self, decl_id: CDeclId, name: &str) -> String {
         self.renamer.insert(decl_id, name).expect("Name already assigned")
     }
@@ -119,6 +136,24 @@ impl TypeConverter {
         return Ok(mk().unsafe_().abi("C").barefn_ty(fn_ty));
     }
 
+    /// Convert a (possibly attributed or typedef'd) function type, which gets the `!` return type
+    /// if `is_noreturn`. Function types pass `is_noreturn_function_type`, the same predicate
+    /// function declarations use for their type.
+    pub fn convert_function_type(
+      &mut self,
+      ctxt: &TypedAstContext,
+      ctype: CTypeId,
+      is_noreturn: bool,
+    ) -> Result<P<Ty>, String> {
+        match ctxt.resolve_type(ctype).kind {
+            CTypeKind::Function(ret, ref params, is_var, _, _) => {
+                let opt_ret = if is_noreturn { None } else { Some(ret) };
+                self.convert_function(ctxt, opt_ret, params, is_var)
+            }
+            ref k => Err(format!("Expected a function type, got {:?}", k)),
+        }
+    }
+
     pub fn convert_pointer(&mut self, ctxt: &TypedAstContext, qtype: CQualTypeId) -> Result<P<Ty>, String> {
 
         match ctxt.resolve_type(qtype.ctype).kind {
@@ -141,14 +176,13 @@ impl TypeConverter {
 
             // Function pointers are translated to Option applied to the function type
             // in order to support NULL function pointers natively
-            CTypeKind::Function(ret, ref params, is_var, is_noreturn, has_proto) => {
+            CTypeKind::Function(.., has_proto) => {
 
                 if !has_proto {
                     return Err(format!("Unable to convert function pointer type without prototype"))
                 }
 
-                let opt_ret = if is_noreturn { None } else { Some(ret) };
-                let fn_ty = self.convert_function(ctxt, opt_ret, params, is_var)?;
+                let fn_ty = self.convert_function_type(ctxt, qtype.ctype, ctxt.is_noreturn_function_type(qtype.ctype))?;
                 let param = mk().angle_bracketed_args(vec![fn_ty]);
                 let optn_ty = mk().path_ty(vec![mk().path_segment_with_args("Option", param)]);
                 return Ok(optn_ty)
@@ -176,6 +210,31 @@ impl TypeConverter {
         Ok(mk().set_mutbl(mutbl).ptr_ty(child_ty))
     }
 
+    /// The type of `long` or `unsigned long`, whose width differs between data models
+    fn long_ty(&self, signed: bool) -> P<Ty> {
+        match self.target {
+            Some(ref target) => {
+                let prefix = if signed { "i" } else { "u" };
+                mk().path_ty(mk().path(vec![format!("{}{}", prefix, target.long_width)]))
+            }
+            None => {
+                let name = if signed { "c_long" } else { "c_ulong" };
+                mk().path_ty(mk().path(vec!["libc", name]))
+            }
+        }
+    }
+
+    /// The type of plain `char`, whose signedness differs between targets
+    fn char_ty(&self) -> P<Ty> {
+        match self.target {
+            Some(ref target) => {
+                let name = if target.char_is_signed { "i8" } else { "u8" };
+                mk().path_ty(mk().path(vec![name]))
+            }
+            None => mk().path_ty(mk().path(vec!["libc","c_char"])),
+        }
+    }
+
     /// Convert a `C` type to a `Rust` one. For the moment, these are expected to have compatible
     /// memory layouts.
     pub fn convert(&mut self, ctxt: &TypedAstContext, ctype: CTypeId) -> Result<P<Ty>, String> {
@@ -185,15 +244,15 @@ impl TypeConverter {
             CTypeKind::Bool => Ok(mk().path_ty(mk().path(vec!["bool"]))),
             CTypeKind::Short => Ok(mk().path_ty(mk().path(vec!["libc","c_short"]))),
             CTypeKind::Int => Ok(mk().path_ty(mk().path(vec!["libc","c_int"]))),
-            CTypeKind::Long => Ok(mk().path_ty(mk().path(vec!["libc","c_long"]))),
+            CTypeKind::Long => Ok(self.long_ty(true)),
             CTypeKind::LongLong => Ok(mk().path_ty(mk().path(vec!["libc","c_longlong"]))),
             CTypeKind::UShort => Ok(mk().path_ty(mk().path(vec!["libc","c_ushort"]))),
             CTypeKind::UInt => Ok(mk().path_ty(mk().path(vec!["libc","c_uint"]))),
-            CTypeKind::ULong => Ok(mk().path_ty(mk().path(vec!["libc","c_ulong"]))),
+            CTypeKind::ULong => Ok(self.long_ty(false)),
             CTypeKind::ULongLong => Ok(mk().path_ty(mk().path(vec!["libc","c_ulonglong"]))),
             CTypeKind::SChar => Ok(mk().path_ty(mk().path(vec!["libc","c_schar"]))),
             CTypeKind::UChar => Ok(mk().path_ty(mk().path(vec!["libc","c_uchar"]))),
-            CTypeKind::Char => Ok(mk().path_ty(mk().path(vec!["libc","c_char"]))),
+            CTypeKind::Char => Ok(self.char_ty()),
             CTypeKind::Double => Ok(mk().path_ty(mk().path(vec!["libc","c_double"]))),
             CTypeKind::LongDouble => Ok(mk().path_ty(mk().path(vec!["libc","c_double"]))),
             CTypeKind::Float => Ok(mk().path_ty(mk().path(vec!["libc","c_float"]))),
@@ -246,14 +305,22 @@ impl TypeConverter {
                 Ok(mk().mutbl().ptr_ty(child_ty))
             }
 
-            CTypeKind::Attributed(ty, _) => self.convert(ctxt, ty.ctype),
-
-            CTypeKind::Function(ret, ref params, is_var, is_noreturn, true) => {
-                let opt_ret = if is_noreturn { None } else { Some(ret) };
-                let fn_ty = self.convert_function(ctxt, opt_ret, params, is_var)?;
-                Ok(fn_ty)
+            // A `noreturn` attribute on a function type makes it diverge
+            CTypeKind::Attributed(ty, _) => match ctxt.resolve_type(ty.ctype).kind {
+                CTypeKind::Function(.., true) if ctxt.is_noreturn_function_type(ctype) =>
+                    self.convert_function_type(ctxt, ctype, true),
+                _ => self.convert(ctxt, ty.ctype),
+            },
+
+            // Blocks are called through the `invoke` function in their block literal
+            CTypeKind::BlockPointer(_) => {
+                self.blocks_used = true;
+                Ok(mk().mutbl().ptr_ty(mk().path_ty(vec![BLOCK_LITERAL])))
             }
 
+            CTypeKind::Function(_, _, _, is_noreturn, true) =>
+                self.convert_function_type(ctxt, ctype, is_noreturn),
+
             CTypeKind::TypeOf(ty) => self.convert(ctxt, ty),
 
             ref t => Err(format!("Unsupported type {:?}", t)),
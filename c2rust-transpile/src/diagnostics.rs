@@ -5,12 +5,20 @@ use fern::colors::ColoredLevelConfig;
 use log::Level;
 
 const DEFAULT_WARNINGS: &[Diagnostic] = &[
+    Diagnostic::Attributes,
+    Diagnostic::Enums,
+    Diagnostic::StaticInitializers,
+    Diagnostic::NodeSplitting,
 ];
 
 #[derive(PartialEq, Eq, Hash, Debug, Display, EnumString, Clone)]
 #[strum(serialize_all = "kebab_case")]
 pub enum Diagnostic {
+    Attributes,
     Comments,
+    Enums,
+    NodeSplitting,
+    StaticInitializers,
 }
 
 macro_rules! diag {
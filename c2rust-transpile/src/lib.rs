@@ -30,6 +30,7 @@ mod diagnostics;
 pub mod build_files;
 pub mod c_ast;
 pub mod cfg;
+pub mod configurations;
 pub mod convert_type;
 pub mod renamer;
 pub mod rust_ast;
@@ -38,22 +39,26 @@ pub mod with_stmts;
 
 use std::collections::HashSet;
 use std::error::Error;
-use std::fs::File;
+use std::fs::{self, DirBuilder, File};
 use std::io::prelude::*;
 use std::io;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 use std::process;
 
+use indexmap::IndexMap;
 use regex::Regex;
 
 use c2rust_ast_exporter as ast_exporter;
+use c2rust_ast_exporter::clang_ast::AstContext;
 use c_ast::Printer;
 use c_ast::*;
 pub use diagnostics::Diagnostic;
 
+use rust_ast::source_map::{SourceMap, SourceMapEntry};
 use build_files::{get_build_dir, emit_build_files, BuildDirectoryContents};
 use std::prelude::v1::Vec;
-pub use translator::ReplaceMode;
+pub use translator::{ReplaceMode, VlaLowering, OverflowMode};
+pub use configurations::Configuration;
 
 /// Configuration settings for the translation process
 #[derive(Debug)]
@@ -65,6 +70,9 @@ pub struct TranspilerConfig {
     pub dump_function_cfgs: bool,
     pub json_function_cfgs: bool,
     pub dump_cfg_liveness: bool,
+    /// Write an HTML page per function cross-linking its C source, CFG, relooper structures and
+    /// Rust output into this directory
+    pub dump_cfg_html: Option<PathBuf>,
     pub dump_structures: bool,
     // Options that control translation
     pub incremental_relooper: bool,
@@ -87,6 +95,30 @@ pub struct TranspilerConfig {
     pub overwrite_existing: bool,
     pub reduce_type_annotations: bool,
     pub reorganize_definitions: bool,
+    /// Write a `.srcmap.json` sidecar mapping the generated Rust back to the C source. Not
+    /// supported together with `configurations`.
+    pub emit_source_map: bool,
+    /// Precede translated items and statements with `// c: file.c:123` comments
+    pub annotate_source_locations: bool,
+    /// How to allocate local variable-length arrays
+    pub vla_lowering: VlaLowering,
+    /// How integer arithmetic that might overflow is translated
+    pub overflow_mode: OverflowMode,
+    /// Pass `nonnull` and `_Nullable` pointer parameters as references
+    pub nonnull_references: bool,
+    /// Check the field offsets of translated structs at compile time, not only their size and
+    /// alignment
+    pub assert_field_offsets: bool,
+    /// Split nodes to make irreducible control flow reducible before relooping, copying at most
+    /// this percentage of each function's statements
+    pub node_splitting_budget: Option<u32>,
+    /// Translate each file once per preprocessor configuration and merge the results, gating the
+    /// items that differ on a Cargo feature named after their configuration
+    pub configurations: Vec<Configuration>,
+    /// Translate for this target triple instead of the host
+    pub target: Option<String>,
+    /// Save the CBOR encoding of each exported AST into this directory, for `transpile_from_ast`
+    pub save_ast: Option<PathBuf>,
     pub enabled_warnings: HashSet<Diagnostic>,
 
     // Options that control build files
@@ -110,6 +142,12 @@ Directory `/usr/include` was not found! Please install the following package:
 pub fn transpile(tcfg: TranspilerConfig, cc_db: &Path, extra_clang_args: &[&str]) {
     diagnostics::init(tcfg.enabled_warnings.clone());
 
+    // Merged translations don't keep track of where their lines came from
+    if tcfg.emit_source_map && !tcfg.configurations.is_empty() {
+        eprintln!("Error: Source maps can't be emitted for translations of several configurations");
+        return;
+    }
+
     // TODO: bindgen may have a more elegant solution to this issue
     // MacOS Mojave does not have `/usr/include` even if Xcode or the
     // command line developer tools are installed.
@@ -137,21 +175,39 @@ pub fn transpile(tcfg: TranspilerConfig, cc_db: &Path, extra_clang_args: &[&str]
 
     let build_dir = get_build_dir(&tcfg, cc_db);
 
+    // Translate each file once, even if it has several compile commands that spell its path
+    // differently
+    let mut input_paths = IndexMap::new();
+    for CompileCmd { directory: d, file: f, .. } in cmds {
+        let input_path = d.join(f);
+        let real_path = fs::canonicalize(&input_path).unwrap_or_else(|_| input_path.clone());
+        input_paths.entry(real_path).or_insert(input_path);
+    }
+    let outputs = input_paths
+        .values()
+        .map(|input_path| {
+            let output_path = get_output_path(&tcfg, input_path, &build_dir);
+            let skip = output_path.exists() && !tcfg.overwrite_existing;
+            (input_path.as_path(), output_path, skip)
+        })
+        .collect::<Vec<_>>();
+
+    let to_translate = outputs
+        .iter()
+        .filter(|&&(_, _, skip)| !skip)
+        .map(|&(input_path, ref output_path, _)| (input_path, output_path.as_path()))
+        .collect::<Vec<_>>();
+
     let mut modules = Vec::<PathBuf>::new();
-    for mut cmd in cmds {
-        let CompileCmd { directory: d, file: f, ..} = cmd;
-        println!("Transpiling {}", f.to_str().unwrap());
-        let input_file_abs = d.join(f);
-        if let Some(m) = transpile_single(
-            &tcfg,
-            input_file_abs.as_path(),
-            cc_db,
-            &build_dir,
-            extra_clang_args) {
-            modules.push(m);
-        };
+    for &(_, ref output_path, skip) in &outputs {
+        if skip {
+            println!("Skipping existing file {}", output_path.display());
+        }
+        modules.push(output_path.clone());
     }
 
+    translate_files(&tcfg, &to_translate, cc_db, extra_clang_args);
+
     if tcfg.emit_build_files {
         let crate_file = emit_build_files(&tcfg, &build_dir, modules);
         // We only run the reorganization refactoring if we emitted a fresh crate file
@@ -163,6 +219,68 @@ pub fn transpile(tcfg: TranspilerConfig, cc_db: &Path, extra_clang_args: &[&str]
     }
 }
 
+/// Translate a C file from its AST, as saved with `--save-ast`, without invoking clang. The Rust
+/// file is written next to the saved AST, e.g. `foo.rs` for `foo.c.cbor` or `foo.c.ssl.cbor`. The
+/// translation is for the target the AST was exported for, which is only treated as an explicit
+/// `--target` if it differs from the host.
+pub fn transpile_from_ast(mut tcfg: TranspilerConfig, ast_path: &Path) {
+    diagnostics::init(tcfg.enabled_warnings.clone());
+
+    let cbor = match fs::read(ast_path) {
+        Ok(cbor) => cbor,
+        Err(e) => {
+            eprintln!("Error: could not read {}: {}", ast_path.display(), e);
+            process::exit(1);
+        }
+    };
+
+    let untyped_context = parse_cbor(&cbor);
+    drop(cbor);
+
+    // The AST was laid out for its own target. Only translate in target mode if that isn't the
+    // host, so that an AST exported for the host is translated just as it was when it was saved.
+    let ast_triple = untyped_context.target.triple.clone();
+    match tcfg.target {
+        Some(ref target) if *target != ast_triple => {
+            eprintln!("Error: {} was exported for {}, not {}", ast_path.display(), ast_triple, target);
+            process::exit(1);
+        }
+        Some(_) => {}
+        None if translator::is_host_target(&untyped_context.target) => {}
+        None => tcfg.target = Some(ast_triple),
+    }
+
+    let input_path = saved_ast_input_path(ast_path);
+    let output_dir = ast_path.parent().unwrap_or(Path::new("."));
+    let output_path = get_output_path(&tcfg, &input_path, output_dir);
+    if output_path.exists() && !tcfg.overwrite_existing {
+        println!("Skipping existing file {}", output_path.display());
+        return;
+    }
+
+    println!("Transpiling {}", ast_path.display());
+    let (translated_string, source_map) = translate_ast(&tcfg, &input_path, untyped_context);
+    write_translation(&tcfg, &output_path, &translated_string, source_map);
+}
+
+/// The path of the C file whose AST `save_ast` saved at `ast_path`: `foo.c` for `foo.c.cbor` and
+/// for `foo.c.ssl.cbor`, the AST of `foo.c` under the `ssl` configuration
+fn saved_ast_input_path(ast_path: &Path) -> PathBuf {
+    let input_path = ast_path.with_extension("");
+    let is_c_file = input_path
+        .extension()
+        .map_or(false, |ext| C_FILE_EXTENSIONS.iter().any(|c_ext| ext == *c_ext));
+    let has_config_suffix = input_path.file_stem().map_or(false, |stem| Path::new(stem).extension().is_some());
+    if !is_c_file && has_config_suffix {
+        input_path.with_extension("")
+    } else {
+        input_path
+    }
+}
+
+/// Extensions of C files, which configuration names can't be mistaken for in saved AST file names
+const C_FILE_EXTENSIONS: &[&str] = &["c", "h", "i"];
+
 fn invoke_refactor(build_dir: &PathBuf, crate_path: &PathBuf) {
     // Assumes the subcommand executable is in the same directory as this program.
     let cmd_path = std::env::current_exe().expect("Cannot get current executable path");
@@ -227,28 +345,114 @@ fn get_compile_commands(compile_commands: &Path) -> Result<Vec<CompileCmd>, Box<
     Ok(v)
 }
 
-fn transpile_single(
+/// Export the ASTs of the given C files in a single run of clang per configuration, saving them
+/// if requested, and write the translation of each file to its output path. Each AST is translated
+/// and dropped as soon as it is exported, so at most the ASTs of one configuration are in memory.
+fn translate_files(
     tcfg: &TranspilerConfig,
-    input_path: &Path,
+    files: &[(&Path, &Path)],
     cc_db: &Path,
-    build_dir: &Path,
     extra_clang_args: &[&str],
-) -> Option<PathBuf> {
+) {
+    if files.is_empty() {
+        return;
+    }
 
-    let output_path = get_output_path(tcfg, input_path, build_dir);
-    if output_path.exists() && !tcfg.overwrite_existing {
-        println!("Skipping existing file {}", output_path.display());
-        return Some(output_path);
+    let configs: Vec<Option<&Configuration>> = if tcfg.configurations.is_empty() {
+        vec![None]
+    } else {
+        tcfg.configurations.iter().map(Some).collect()
+    };
+    let target = tcfg.target.as_ref().map(String::as_str);
+
+    // Saved ASTs mirror the paths of their files relative to the compilation database
+    let cc_dir = match cc_db.parent() {
+        Some(dir) if dir != Path::new("") => dir,
+        _ => Path::new("."),
+    };
+    let ast_base_dir = fs::canonicalize(cc_dir).unwrap_or_else(|_| cc_dir.to_path_buf());
+
+    // Translations of each file under the configurations exported so far
+    let mut translations: Vec<Vec<(String, String)>> = vec![vec![]; files.len()];
+
+    for (i, &config) in configs.iter().enumerate() {
+        let is_last_config = i + 1 == configs.len();
+        let mut clang_args = extra_clang_args.to_vec();
+        if let Some(config) = config {
+            clang_args.extend(config.clang_args.iter().map(String::as_str));
+        }
+        let config_name = config.map(|config| config.name.as_str());
+        let mut cbors = ast_exporter::get_ast_cbors(
+            &files.iter().map(|&(input_path, _)| input_path).collect::<Vec<_>>(),
+            cc_db,
+            &clang_args,
+            target,
+        );
+
+        for (&(input_path, output_path), file_translations) in files.iter().zip(&mut translations) {
+            // The exporter keys each AST by the real path of its file
+            let real_path = fs::canonicalize(input_path).ok();
+            let cbor = real_path.as_ref().and_then(|path| cbors.remove(path));
+            let (real_path, cbor) = match (real_path, cbor) {
+                (Some(real_path), Some(cbor)) => (real_path, cbor),
+                _ => {
+                    eprintln!("Error: Could not parse {}", input_path.display());
+                    process::exit(1);
+                }
+            };
+
+            if let Some(ref ast_dir) = tcfg.save_ast {
+                let ast_path = saved_ast_path(ast_dir, &ast_base_dir, &real_path, config_name);
+                save_ast(&ast_path, &cbor);
+            }
+
+            match config {
+                None => {
+                    println!("Transpiling {}", input_path.display());
+                    let (translated_string, source_map) = translate_cbor(tcfg, input_path, &cbor);
+                    write_translation(tcfg, output_path, &translated_string, source_map);
+                }
+                Some(config) => {
+                    println!("Transpiling {} with configuration {}", input_path.display(), config.name);
+                    let (translated_string, _) = translate_cbor(tcfg, input_path, &cbor);
+                    file_translations.push((config.name.clone(), translated_string));
+                    if is_last_config {
+                        // `transpile` rejects source maps for merged translations
+                        let merged = configurations::merge_translations(file_translations);
+                        write_translation(tcfg, output_path, &merged, vec![]);
+                    }
+                }
+            }
+        }
     }
+}
 
-    // Extract the untyped AST from the CBOR file
-    let untyped_context = match ast_exporter::get_untyped_ast(input_path, cc_db, extra_clang_args) {
+/// Translate a C file from the CBOR encoding of its AST
+fn translate_cbor(
+    tcfg: &TranspilerConfig,
+    input_path: &Path,
+    cbor: &[u8],
+) -> (String, Vec<SourceMapEntry>) {
+    translate_ast(tcfg, input_path, parse_cbor(cbor))
+}
+
+/// Extract the untyped AST from the CBOR encoding of an exported AST
+fn parse_cbor(cbor: &[u8]) -> AstContext {
+    match ast_exporter::parse_ast_cbor(cbor) {
         Err(e) => {
             eprintln!("Error: {:}", e);
             process::exit(1);
         }
         Ok(cxt) => cxt,
-    };
+    }
+}
+
+/// Translate a C file from its untyped AST
+fn translate_ast(
+    tcfg: &TranspilerConfig,
+    input_path: &Path,
+    untyped_context: AstContext,
+) -> (String, Vec<SourceMapEntry>) {
 
     if tcfg.dump_untyped_context {
         println!("CBOR Clang AST");
@@ -258,7 +462,10 @@ fn transpile_single(
     // Convert this into a typed AST
     let typed_context = {
         let mut conv = ConversionContext::new(&untyped_context);
-        conv.convert(&untyped_context);
+        if let Err(e) = conv.convert(&untyped_context) {
+            eprintln!("Error: failed to convert the AST of {}: {}", input_path.display(), e);
+            process::exit(1);
+        }
         conv.typed_context
     };
 
@@ -274,8 +481,58 @@ fn transpile_single(
 
     // Perform the translation
     let main_file = input_path.with_extension("");
-    let translated_string = translator::translate(typed_context, &tcfg, main_file);
+    translator::translate(typed_context, &tcfg, main_file)
+}
+
+/// Where to save the AST of a C file: `<file name>.cbor`, or `<file name>.<configuration>.cbor`
+/// when translating one of several configurations, in the subdirectory of `ast_dir` that mirrors
+/// the file's path relative to `base_dir`. Files outside of `base_dir` mirror their full path, so
+/// files with the same name in different directories never share a saved AST.
+fn saved_ast_path(ast_dir: &Path, base_dir: &Path, input_path: &Path, config_name: Option<&str>) -> PathBuf {
+    let relative_path = match input_path.strip_prefix(base_dir) {
+        Ok(relative_path) => relative_path.to_path_buf(),
+        Err(_) => input_path
+            .components()
+            .filter(|component| match *component {
+                Component::Normal(_) => true,
+                _ => false,
+            })
+            .collect(),
+    };
+
+    let mut file_name = relative_path.file_name().unwrap().to_os_string();
+    if let Some(config_name) = config_name {
+        file_name.push(".");
+        file_name.push(config_name);
+    }
+    file_name.push(".cbor");
+    ast_dir.join(relative_path).with_file_name(file_name)
+}
+
+/// Save the CBOR encoding of the AST of a C file at `ast_path`, as given by `saved_ast_path`
+fn save_ast(ast_path: &Path, cbor: &[u8]) {
+    let ast_dir = ast_path.parent().unwrap_or(Path::new("."));
+    if let Err(e) = DirBuilder::new().recursive(true).create(ast_dir) {
+        panic!("Unable to create AST directory {}: {}", ast_dir.display(), e);
+    }
 
+    let mut file = match File::create(&ast_path) {
+        Ok(file) => file,
+        Err(e) => panic!("Unable to open file for writing: {}", e),
+    };
+    if let Err(e) = file.write_all(cbor) {
+        panic!("Unable to write AST to file: {}", e);
+    }
+    println!("Saved AST to {}", ast_path.display());
+}
+
+/// Write a translated file and, if requested, its source map
+fn write_translation(
+    tcfg: &TranspilerConfig,
+    output_path: &Path,
+    translated_string: &str,
+    source_map: Vec<SourceMapEntry>,
+) {
     let mut file = match File::create(&output_path) {
         Ok(file) => file,
         Err(e) => panic!("Unable to open file for writing: {}", e),
@@ -286,7 +543,20 @@ fn transpile_single(
         Err(e) => panic!("Unable to write translation to file: {}", e),
     };
 
-    Some(output_path)
+    if tcfg.emit_source_map {
+        let source_map = SourceMap {
+            rust_file: output_path.to_path_buf(),
+            entries: source_map,
+        };
+        let source_map_path = output_path.with_extension("srcmap.json");
+        let file = match File::create(&source_map_path) {
+            Ok(file) => file,
+            Err(e) => panic!("Unable to open file for writing: {}", e),
+        };
+        if let Err(e) = serde_json::to_writer_pretty(file, &source_map) {
+            panic!("Unable to write source map to file: {}", e);
+        }
+    }
 }
 
 fn get_output_path(
@@ -320,3 +590,25 @@ fn get_output_path(
         path_buf
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_configuration_from_saved_ast_paths() {
+        assert_eq!(saved_ast_input_path(Path::new("ast/foo.c.cbor")), Path::new("ast/foo.c"));
+        assert_eq!(saved_ast_input_path(Path::new("ast/foo.c.ssl.cbor")), Path::new("ast/foo.c"));
+        assert_eq!(saved_ast_input_path(Path::new("ast/foo.bar.c.cbor")), Path::new("ast/foo.bar.c"));
+    }
+
+    #[test]
+    fn mirrors_relative_paths_of_saved_asts() {
+        let base = Path::new("/work/proj");
+        let saved = |path: &str, config: Option<&str>| saved_ast_path(Path::new("ast"), base, Path::new(path), config);
+        assert_eq!(saved("/work/proj/src/a/util.c", None), Path::new("ast/src/a/util.c.cbor"));
+        assert_eq!(saved("/work/proj/src/b/util.c", None), Path::new("ast/src/b/util.c.cbor"));
+        assert_eq!(saved("/work/proj/main.c", Some("ssl")), Path::new("ast/main.c.ssl.cbor"));
+        assert_eq!(saved("/usr/src/lib.c", None), Path::new("ast/usr/src/lib.c.cbor"));
+    }
+}
@@ -35,6 +35,10 @@ pub struct CommentStore {
     /// `Span` of whatever is associated with the comment.
     output_comments: BTreeMap<Span, comments::Comment>,
 
+    /// Comments to print right before the closing brace of a block. The `Span` keys are those of
+    /// the blocks.
+    block_end_comments: BTreeMap<Span, comments::Comment>,
+
     /// Monotonically increasing source of new byte positions.
     span_source: u32,
 }
@@ -44,6 +48,7 @@ impl CommentStore {
     pub fn new() -> Self {
         CommentStore {
             output_comments: BTreeMap::new(),
+            block_end_comments: BTreeMap::new(),
             span_source: 0,
         }
     }
@@ -51,6 +56,7 @@ impl CommentStore {
     pub fn into_comment_traverser(self) -> CommentTraverser {
         CommentTraverser {
             old_comments: self.output_comments,
+            old_block_end_comments: self.block_end_comments,
             store: CommentStore::new(),
         }
     }
@@ -84,17 +90,13 @@ impl CommentStore {
     /// Add a comment at the current position, then return the `Span` that should be given to
     /// something we want associated with this comment.
     pub fn add_comment_lines(&mut self, lines: Vec<String>) -> Span {
-        let lines: Vec<String> = lines
-            .into_iter()
-            .map(|mut comment| {
-                if comment.starts_with("//!") || comment.starts_with("///") ||
-                    comment.starts_with("/**") || comment.starts_with("/*!") {
-                    comment.insert(2,' ');
-                }
-                comment
-            })
-            .collect();
+        let lines = lines.into_iter().map(as_plain_comment).collect();
+        self.add_doc_comment_lines(lines)
+    }
 
+    /// Like `add_comment_lines`, but lines in Rust doc comment syntax are emitted as written, so
+    /// that they document whatever is given the returned `Span`.
+    pub fn add_doc_comment_lines(&mut self, lines: Vec<String>) -> Span {
         if lines.is_empty() {
             DUMMY_SP
         } else {
@@ -107,11 +109,43 @@ impl CommentStore {
             )
         }
     }
+
+    /// Add a comment to print at the end of a block, then return the `Span` that should be given
+    /// to the block.
+    pub fn add_block_end_comment_lines(&mut self, lines: Vec<String>) -> Span {
+        if lines.is_empty() {
+            return DUMMY_SP
+        }
+
+        self.span_source += 1;
+        let sp = Span::new(
+            BytePos(self.span_source),
+            BytePos(self.span_source),
+            SyntaxContext::empty(),
+        );
+        let cmmt = comments::Comment {
+            style: comments::CommentStyle::Isolated,
+            lines: lines.into_iter().map(as_plain_comment).collect(),
+            pos: BytePos(0), // overwritten when the block is traversed
+        };
+        self.block_end_comments.insert(sp, cmmt);
+        sp
+    }
 }
 
+/// Keep a comment from being parsed as a Rust doc comment by separating its leading `//` or `/*`
+/// from the rest.
+pub fn as_plain_comment(mut comment: String) -> String {
+    if comment.starts_with("//!") || comment.starts_with("///") ||
+        comment.starts_with("/**") || comment.starts_with("/*!") {
+        comment.insert(2,' ');
+    }
+    comment
+}
 
 pub struct CommentTraverser {
     old_comments: BTreeMap<Span, comments::Comment>,
+    old_block_end_comments: BTreeMap<Span, comments::Comment>,
     store: CommentStore
 }
 impl CommentTraverser {
@@ -153,8 +187,17 @@ impl traverse::Traversal for CommentTraverser {
     }
 
     fn traverse_block(&mut self, mut b: Block) -> Block {
+        let block_end = self.old_block_end_comments.remove(&b.span);
         b.span = self.reinsert_comment_at(b.span);
-        traverse::traverse_block_def(self, b)
+        let mut b = traverse::traverse_block_def(self, b);
+
+        // The pretty-printer prints the comments positioned before the end of the block's span
+        // right before its closing brace
+        if let Some(cmmt) = block_end {
+            let sp = self.store.add_comment(cmmt);
+            b.span = b.span.with_hi(sp.hi());
+        }
+        b
     }
 
     fn traverse_local(&mut self, mut l: Local) -> Local {
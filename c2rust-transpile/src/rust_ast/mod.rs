@@ -1,3 +1,4 @@
 pub mod comment_store;
 pub mod item_store;
+pub mod source_map;
 pub mod traverse;
@@ -0,0 +1,265 @@
+//! This module tracks where translated items and statements came from in the C source.
+//!
+//! We have no control over the line numbers chosen by the Rust pretty-printer, so we can't record
+//! output positions while translating. Instead, every translated item or statement that should
+//! appear in the source map gets a marker comment (routed through the `CommentStore` like any other
+//! comment). Once the output has been pretty-printed, `SourceMapBuilder::finish` finds the markers,
+//! records the Rust line range following each one, and either drops the marker line or replaces it
+//! with a human readable `// c: file.c:123` annotation.
+
+use std::path::PathBuf;
+
+use c_ast::SrcLoc;
+
+const MARKER_PREFIX: &str = "// c2rust-source-map: ";
+
+/// What kind of C construct a source map entry originates from
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceMapKind {
+    Decl,
+    Stmt,
+}
+
+/// One mapping from a range of lines in the generated Rust to a C source location
+#[derive(Serialize, Debug, Clone)]
+pub struct SourceMapEntry {
+    /// First line of the Rust output belonging to this entry (1-based)
+    pub rust_start_line: usize,
+    /// Last line of the Rust output belonging to this entry (1-based, inclusive)
+    pub rust_end_line: usize,
+    pub c_file: PathBuf,
+    pub c_line: u64,
+    pub c_column: u64,
+    pub kind: SourceMapKind,
+}
+
+/// Source map for a single generated Rust file
+#[derive(Serialize, Debug, Clone)]
+pub struct SourceMap {
+    pub rust_file: PathBuf,
+    pub entries: Vec<SourceMapEntry>,
+}
+
+pub struct SourceMapBuilder {
+    locations: Vec<(SrcLoc, SourceMapKind)>,
+}
+
+impl SourceMapBuilder {
+    pub fn new() -> Self {
+        SourceMapBuilder {
+            locations: vec![],
+        }
+    }
+
+    /// Register a C source location, returning the comment line that should be attached to the
+    /// Rust node translated from it. Locations without a file path can't be mapped back to
+    /// anything useful, so they produce no marker.
+    pub fn marker(&mut self, loc: &SrcLoc, kind: SourceMapKind) -> Option<String> {
+        if loc.file_path.is_none() {
+            return None;
+        }
+        let idx = self.locations.len();
+        self.locations.push((loc.clone(), kind));
+        Some(format!("{}{}", MARKER_PREFIX, idx))
+    }
+
+    /// Strip the markers out of the pretty-printed output and compute the source map. When
+    /// `annotate` is set, each marker is replaced by a `// c: file.c:123` comment instead of being
+    /// removed.
+    pub fn finish(self, output: &str, annotate: bool) -> (String, Vec<SourceMapEntry>) {
+        struct Anchor {
+            idx: usize,
+            indent: usize,
+            start: usize,
+        }
+
+        let mut lines: Vec<String> = vec![];
+        // For every output line, the indentation of a mapped construct starting (or annotated)
+        // there. Any such line ends the ranges of enclosing-or-sibling entries before it.
+        let mut boundaries: Vec<Option<usize>> = vec![];
+        let mut pending: Vec<(usize, usize)> = vec![];
+        let mut anchors: Vec<Anchor> = vec![];
+
+        for line in output.lines() {
+            let trimmed = line.trim_start();
+            let indent = line.len() - trimmed.len();
+            let marker = if trimmed.starts_with(MARKER_PREFIX) {
+                trimmed[MARKER_PREFIX.len()..].trim().parse::<usize>().ok()
+                    .filter(|idx| *idx < self.locations.len())
+            } else {
+                None
+            };
+
+            match marker {
+                Some(idx) => {
+                    if annotate {
+                        let loc = &self.locations[idx].0;
+                        let file = loc.file_path.as_ref().unwrap();
+                        lines.push(format!("{:indent$}// c: {}:{}", "", file.display(), loc.line,
+                                           indent = indent));
+                        boundaries.push(Some(indent));
+                    }
+                    pending.push((idx, indent));
+                }
+                None => {
+                    lines.push(line.to_owned());
+                    let start = lines.len();
+                    let mut boundary = None;
+                    for (idx, indent) in pending.drain(..) {
+                        boundary = Some(boundary.map_or(indent, |b: usize| b.min(indent)));
+                        anchors.push(Anchor { idx, indent, start });
+                    }
+                    boundaries.push(if annotate { None } else { boundary });
+                }
+            }
+        }
+
+        let is_blank = |line: usize| lines[line - 1].trim().is_empty();
+        let line_indent = |line: usize| {
+            let l = &lines[line - 1];
+            l.len() - l.trim_start().len()
+        };
+
+        let mut entries = vec![];
+        for Anchor { idx, indent, start } in anchors {
+            let mut end = start;
+            for line in start + 1..lines.len() + 1 {
+                let ends_here = match boundaries[line - 1] {
+                    Some(b) => b <= indent,
+                    None => !is_blank(line) && line_indent(line) < indent,
+                };
+                if ends_here {
+                    break;
+                }
+                end = line;
+            }
+            while end > start && is_blank(end) {
+                end -= 1;
+            }
+
+            let (ref loc, kind) = self.locations[idx];
+            entries.push(SourceMapEntry {
+                rust_start_line: start,
+                rust_end_line: end,
+                c_file: loc.file_path.clone().unwrap(),
+                c_line: loc.line,
+                c_column: loc.column,
+                kind,
+            });
+        }
+        entries.sort_by_key(|e| (e.rust_start_line, e.rust_end_line));
+
+        let mut output = lines.join("\n");
+        output.push('\n');
+        (output, entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn loc(line: u64) -> SrcLoc {
+        SrcLoc { fileid: 1, line, column: 5, file_path: Some(PathBuf::from("test.c")) }
+    }
+
+    /// A function with two statements, the second spanning several lines, and a static, with the
+    /// markers the translator would have attached to them
+    fn marked_output() -> (SourceMapBuilder, String) {
+        let mut builder = SourceMapBuilder::new();
+        let output = {
+            let mut marker = |line, kind| builder.marker(&loc(line), kind).unwrap();
+            format!(
+"{}
+pub unsafe extern \"C\" fn f() {{
+    {}
+    let mut x: libc::c_int = 1;
+    {}
+    if x != 0 {{
+        x = 2
+    }}
+}}
+
+{}
+pub static mut g: libc::c_int = 0;
+",
+            marker(1, SourceMapKind::Decl),
+            marker(2, SourceMapKind::Stmt),
+            marker(3, SourceMapKind::Stmt),
+            marker(10, SourceMapKind::Decl),
+            )
+        };
+        (builder, output)
+    }
+
+    fn ranges(entries: &[SourceMapEntry]) -> Vec<(usize, usize, u64)> {
+        entries.iter().map(|e| (e.rust_start_line, e.rust_end_line, e.c_line)).collect()
+    }
+
+    #[test]
+    fn markers_need_a_file() {
+        let mut builder = SourceMapBuilder::new();
+        let no_file = SrcLoc { file_path: None, .. loc(1) };
+        assert_eq!(builder.marker(&no_file, SourceMapKind::Decl), None);
+        assert_eq!(builder.marker(&loc(1), SourceMapKind::Decl),
+                   Some(format!("{}0", MARKER_PREFIX)));
+        assert_eq!(builder.marker(&loc(2), SourceMapKind::Stmt),
+                   Some(format!("{}1", MARKER_PREFIX)));
+    }
+
+    #[test]
+    fn strips_markers_and_records_ranges() {
+        let (builder, output) = marked_output();
+        let (output, entries) = builder.finish(&output, false);
+
+        assert_eq!(output,
+"pub unsafe extern \"C\" fn f() {
+    let mut x: libc::c_int = 1;
+    if x != 0 {
+        x = 2
+    }
+}
+
+pub static mut g: libc::c_int = 0;
+");
+        // The function ends at its closing brace, not at the blank line after it
+        assert_eq!(ranges(&entries), vec![(1, 6, 1), (2, 2, 2), (3, 5, 3), (8, 8, 10)]);
+        assert_eq!(entries[0].kind, SourceMapKind::Decl);
+        assert_eq!(entries[1].kind, SourceMapKind::Stmt);
+        assert_eq!(entries[0].c_file, PathBuf::from("test.c"));
+        assert_eq!(entries[0].c_column, 5);
+    }
+
+    #[test]
+    fn annotates_source_locations() {
+        let (builder, output) = marked_output();
+        let (output, entries) = builder.finish(&output, true);
+
+        assert_eq!(output,
+"// c: test.c:1
+pub unsafe extern \"C\" fn f() {
+    // c: test.c:2
+    let mut x: libc::c_int = 1;
+    // c: test.c:3
+    if x != 0 {
+        x = 2
+    }
+}
+
+// c: test.c:10
+pub static mut g: libc::c_int = 0;
+");
+        // Ranges start after the annotations and refer to the annotated output
+        assert_eq!(ranges(&entries), vec![(2, 9, 1), (4, 4, 2), (6, 8, 3), (12, 12, 10)]);
+    }
+
+    #[test]
+    fn keeps_unknown_markers() {
+        let builder = SourceMapBuilder::new();
+        let output = format!("{}7\nfn f() {{}}\n", MARKER_PREFIX);
+        let (stripped, entries) = builder.finish(&output, false);
+        assert_eq!(stripped, output);
+        assert!(entries.is_empty());
+    }
+}
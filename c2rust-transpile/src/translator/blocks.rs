@@ -0,0 +1,612 @@
+#![deny(missing_docs)]
+//! This module translates Clang blocks (`^(int x) { ... }`) into Rust closures.
+//!
+//! A block pointer is a `*mut BlockLiteral`, laid out like the block literal of the blocks ABI, so
+//! blocks can be passed to and received from C code. A block literal is translated into a `move`
+//! closure boxed together with its `BlockLiteral` header in a `RustBlock`. The `invoke` function
+//! of the header is a monomorphized shim that calls the closure.
+//!
+//! Captured variables are copied into the closure, just like C copies them into the block.
+//! Variables declared `__block` can be modified by the blocks capturing them, so they are stored
+//! in an `Arc<UnsafeCell<T>>` that every capturing closure shares. The reference count is atomic
+//! because the runtime may copy and release a block on any thread, but the variable itself is
+//! accessed through a raw pointer without synchronization, just like C's `__block` storage.
+//!
+//! Like a C block literal, which lives on the stack, a translated block literal belongs to the
+//! function evaluating it: each literal has a `BlockSlot` declared at the start of the enclosing
+//! function body, which owns the block it evaluated last. Evaluating the literal again frees the
+//! previous block, and so does leaving the function. The header carries copy and dispose helpers,
+//! so `_Block_copy` copies a block to the heap by cloning its closure, and `_Block_release` drops
+//! the closure of the heap copy before the runtime frees it. Only block literals in static
+//! initializers, which are evaluated once, are boxed for good and flagged as global. The `isa` of a
+//! block points at `_NSConcreteStackBlock` or `_NSConcreteGlobalBlock`, as the runtime expects.
+
+use super::*;
+
+use convert_type::BLOCK_LITERAL;
+
+/// Name of the descriptor struct pointed to by a `BlockLiteral`
+pub const BLOCK_DESCRIPTOR: &str = "BlockDescriptor";
+/// Name of the struct boxing a closure together with its block header
+pub const RUST_BLOCK: &str = "RustBlock";
+/// Name of the struct owning the last block evaluated by a block literal
+pub const BLOCK_SLOT: &str = "BlockSlot";
+/// Name of the function allocating a `RustBlock` owned by a `BlockSlot`
+pub const NEW_BLOCK: &str = "new_block";
+/// Name of the function allocating a `RustBlock` that is never freed
+pub const NEW_GLOBAL_BLOCK: &str = "new_global_block";
+/// Name of the copy helper of `RustBlock`s
+pub const COPY_BLOCK: &str = "copy_block";
+/// Name of the dispose helper of `RustBlock`s
+pub const DISPOSE_BLOCK: &str = "dispose_block";
+
+/// `BLOCK_HAS_COPY_DISPOSE`: the descriptor has copy and dispose helpers
+const BLOCK_HAS_COPY_DISPOSE: u128 = 1 << 25;
+/// `BLOCK_IS_GLOBAL`: the runtime treats the block as static data
+const BLOCK_IS_GLOBAL: u128 = 1 << 28;
+
+impl<'c> Translation<'c> {
+    /// Is this a local variable declared with `__block` storage?
+    pub fn is_block_byref(&self, decl_id: CDeclId) -> bool {
+        match self.ast_context[decl_id].kind {
+            CDeclKind::Variable { ref attrs, .. } => attrs.contains(&DeclAttribute::BlockByref),
+            _ => false,
+        }
+    }
+
+    /// Translate a block literal into a boxed closure behind a block header.
+    pub fn convert_block_literal(
+        &self,
+        ctx: ExprContext,
+        ty: CQualTypeId,
+        params: &[CDeclId],
+        body: CStmtId,
+    ) -> Result<WithStmts<P<Expr>>, String> {
+        let block_ptr_ty = self.convert_type(ty.ctype)?;
+        let ret = match self.ast_context.resolve_type(ty.ctype).kind {
+            CTypeKind::BlockPointer(pointee) => match self.ast_context.resolve_type(pointee.ctype).kind {
+                CTypeKind::Function(ret, ..) => ret,
+                ref k => return Err(format!("Block pointer to non-function type {:?}", k)),
+            },
+            ref k => return Err(format!("Block literal has non-block type {:?}", k)),
+        };
+        let ret_ty = match self.ast_context.resolve_type(ret.ctype).kind {
+            CTypeKind::Void => None,
+            _ => Some(self.convert_type(ret.ctype)?),
+        };
+        let fn_ret = |ret_ty: &Option<P<Ty>>| match *ret_ty {
+            Some(ref ty) => FunctionRetTy::Ty(ty.clone()),
+            None => FunctionRetTy::Default(DUMMY_SP),
+        };
+
+        let mut param_tys = vec![];
+        for &param in params {
+            match self.ast_context[param].kind {
+                CDeclKind::Variable { typ, .. } => param_tys.push(self.convert_type(typ.ctype)?),
+                _ => return Err(format!("Block parameter is not a variable")),
+            }
+        }
+
+        // Block literals in static initializers are only evaluated once
+        let slot_name = if ctx.is_static || self.block_slots.borrow().is_empty() {
+            None
+        } else {
+            let name = self.renamer.borrow_mut().pick_name_root("block_slot");
+            self.block_slots.borrow_mut().last_mut().unwrap().push(name.clone());
+            Some(name)
+        };
+
+        let invoke_name = self.renamer.borrow_mut().pick_name("invoke_block");
+        let make_name = self.renamer.borrow_mut().pick_name("make_block");
+        let block_name = self.renamer.borrow_mut().pick_name("block");
+        let slot_arg_name = self.renamer.borrow_mut().pick_name("slot");
+        let closure_name = self.renamer.borrow_mut().pick_name("closure");
+        let arg_names: Vec<String> = (0..params.len())
+            .map(|i| self.renamer.borrow_mut().pick_name(&format!("arg{}", i)))
+            .collect();
+
+        // F: FnMut(A0, A1, ...) -> R, plus Clone for the copy helper
+        let closure_param = |cloned: bool| {
+            let mut sig = mk().parenthesized_args(param_tys.clone());
+            sig.output = ret_ty.clone();
+            let mut bounds = vec![vec![mk().path_segment_with_args("FnMut", sig)]];
+            if cloned {
+                bounds.push(vec![mk().path_segment("Clone")]);
+            }
+            mk().bounded_ty_param("F", bounds)
+        };
+
+        // unsafe extern "C" fn invoke<F>(block: *mut BlockLiteral, arg0: A0, ...) -> R {
+        //     ((*(block as *mut RustBlock<F>)).closure)(arg0, ...)
+        // }
+        let mut invoke_args = vec![mk().arg(block_ptr_ty.clone(), mk().ident_pat(&block_name))];
+        invoke_args.extend(param_tys.iter().zip(&arg_names)
+            .map(|(ty, name)| mk().arg(ty.clone(), mk().ident_pat(name))));
+        let rust_block = mk().cast_expr(mk().path_expr(vec![&block_name]),
+                                        mk().mutbl().ptr_ty(rust_block_ty(mk().ident_ty("F"))));
+        let closure = mk().field_expr(mk().paren_expr(mk().unary_expr("*", mk().paren_expr(rust_block))), "closure");
+        let call = mk().call_expr(mk().paren_expr(closure),
+                                  arg_names.iter().map(|name| mk().path_expr(vec![name])).collect());
+        let invoke = mk().unsafe_().abi("C")
+            .generic_over(closure_param(false))
+            .fn_item(&invoke_name, mk().fn_decl(invoke_args, fn_ret(&ret_ty), false),
+                     mk().block(vec![mk().expr_stmt(call)]));
+
+        // unsafe fn make_block<F>(slot: &mut BlockSlot, closure: F) -> *mut BlockLiteral {
+        //     new_block(slot, closure, invoke::<F> as *mut libc::c_void)
+        // }
+        let invoke_ptr = fn_ptr_expr(&invoke_name);
+        let mut make_args = vec![];
+        let mut new_block_args = vec![];
+        if slot_name.is_some() {
+            let slot_ref_ty = mk().set_mutbl("mut").ref_ty(mk().path_ty(vec![BLOCK_SLOT]));
+            make_args.push(mk().arg(slot_ref_ty, mk().ident_pat(&slot_arg_name)));
+            new_block_args.push(mk().path_expr(vec![&slot_arg_name]));
+        }
+        make_args.push(mk().arg(mk().ident_ty("F"), mk().ident_pat(&closure_name)));
+        new_block_args.push(mk().path_expr(vec![&closure_name]));
+        new_block_args.push(invoke_ptr);
+        let new_block_fn = if slot_name.is_some() { NEW_BLOCK } else { NEW_GLOBAL_BLOCK };
+        let new_block = mk().call_expr(mk().path_expr(vec![new_block_fn]), new_block_args);
+        let make = mk().unsafe_()
+            .generic_over(closure_param(slot_name.is_some()))
+            .fn_item(&make_name, mk().fn_decl(make_args, FunctionRetTy::Ty(block_ptr_ty), false),
+                     mk().block(vec![mk().expr_stmt(new_block)]));
+
+        // Every capturing closure shares the cells of `__block` variables
+        let mut captured: IndexSet<String> = IndexSet::new();
+        for some_id in DFExpr::new(&self.ast_context, body.into()) {
+            if let SomeId::Expr(expr_id) = some_id {
+                if let CExprKind::DeclRef(_, decl_id, _) = self.ast_context[expr_id].kind {
+                    if self.is_block_byref(decl_id) {
+                        // Variables declared inside the block aren't in scope yet
+                        if let Some(name) = self.renamer.borrow().get(&decl_id) {
+                            captured.insert(name);
+                        }
+                    }
+                }
+            }
+        }
+
+        let closure = self.with_scope(|| {
+            let mut args = vec![];
+            for (&param, ty) in params.iter().zip(&param_tys) {
+                let (ident, typ) = match self.ast_context[param].kind {
+                    CDeclKind::Variable { ref ident, typ, .. } => (ident, typ),
+                    _ => return Err(format!("Block parameter is not a variable")),
+                };
+                let mutbl = if typ.qualifiers.is_const { Mutability::Immutable } else { Mutability::Mutable };
+                let pat = if ident.is_empty() {
+                    mk().wild_pat()
+                } else {
+                    let name = self.renamer.borrow_mut().insert(param, ident)
+                        .ok_or_else(|| format!("Failed to insert block parameter '{}'", ident))?;
+                    mk().set_mutbl(mutbl).ident_pat(name)
+                };
+                args.push(mk().arg(ty.clone(), pat));
+            }
+
+            let body_ids = match self.ast_context[body].kind {
+                CStmtKind::Compound(ref stmts) => stmts,
+                _ => return Err(format!("Block body is not a compound statement")),
+            };
+            let implicit_ret = match ret_ty {
+                Some(_) => cfg::ImplicitReturnType::NoImplicitReturnType,
+                None => cfg::ImplicitReturnType::Void,
+            };
+            let body = self.convert_function_body(ctx.not_static(), &invoke_name, body_ids, implicit_ret)?;
+
+            Ok(mk().closure_expr(
+                CaptureBy::Value,
+                Movability::Movable,
+                mk().fn_decl(args, fn_ret(&ret_ty), false),
+                mk().block_expr(stmts_block(body)),
+            ))
+        })?;
+
+        let mut stmts = vec![mk().item_stmt(invoke), mk().item_stmt(make)];
+        for name in captured {
+            let clone = mk().method_call_expr(mk().path_expr(vec![&name]), "clone", vec![] as Vec<P<Expr>>);
+            stmts.push(mk().local_stmt(P(mk().local(mk().ident_pat(&name), None as Option<P<Ty>>, Some(clone)))));
+        }
+        let mut make_call_args = vec![];
+        if let Some(ref slot_name) = slot_name {
+            make_call_args.push(mk().set_mutbl("mut").addr_of_expr(mk().path_expr(vec![slot_name])));
+        }
+        make_call_args.push(closure);
+        stmts.push(mk().expr_stmt(mk().call_expr(mk().path_expr(vec![&make_name]), make_call_args)));
+
+        Ok(WithStmts::new(mk().block_expr(mk().block(stmts))))
+    }
+
+    /// Translate a call through a block pointer into a call of its `invoke` function, which takes
+    /// the block itself as its first argument.
+    pub fn convert_block_call(
+        &self,
+        ctx: ExprContext,
+        func: CExprId,
+        args: &[CExprId],
+    ) -> Result<WithStmts<P<Expr>>, String> {
+        let block_ty = self.ast_context[func].kind.get_type()
+            .ok_or_else(|| format!("Block call has no callee type"))?;
+        let (ret, params) = match self.ast_context.resolve_type(block_ty).kind {
+            CTypeKind::BlockPointer(pointee) => match self.ast_context.resolve_type(pointee.ctype).kind {
+                CTypeKind::Function(ret, ref params, false, ..) => (ret, params.clone()),
+                CTypeKind::Function(..) => return Err(format!("Variadic blocks are not supported")),
+                ref k => return Err(format!("Block pointer to non-function type {:?}", k)),
+            },
+            ref k => return Err(format!("Called block has non-block type {:?}", k)),
+        };
+
+        let block_ptr_ty = self.convert_type(block_ty)?;
+        let mut invoke_args = vec![mk().arg(block_ptr_ty, mk().wild_pat())];
+        for param in params {
+            invoke_args.push(mk().arg(self.convert_type(param.ctype)?, mk().wild_pat()));
+        }
+        let invoke_ret = match self.ast_context.resolve_type(ret.ctype).kind {
+            CTypeKind::Void => FunctionRetTy::Default(DUMMY_SP),
+            _ => FunctionRetTy::Ty(self.convert_type(ret.ctype)?),
+        };
+        let invoke_ty = mk().unsafe_().abi("C").barefn_ty(mk().fn_decl(invoke_args, invoke_ret, false));
+
+        let WithStmts { mut stmts, val: block } = self.convert_expr(ctx.used(), func)?;
+        let block_name = self.renamer.borrow_mut().pick_name("block");
+        stmts.push(mk().local_stmt(P(mk().local(mk().ident_pat(&block_name), None as Option<P<Ty>>, Some(block)))));
+
+        let mut args_new = vec![mk().path_expr(vec![&block_name])];
+        for &arg in args {
+            let WithStmts { stmts: ss, val } = self.convert_expr(ctx.used(), arg)?;
+            stmts.extend(ss);
+            args_new.push(val);
+        }
+
+        // transmute::<*mut c_void, unsafe extern "C" fn(*mut BlockLiteral, ...) -> R>((*block).invoke)
+        let invoke_field = mk().field_expr(mk().paren_expr(mk().unary_expr("*", mk().path_expr(vec![&block_name]))), "invoke");
+        let void_ptr = mk().mutbl().ptr_ty(mk().path_ty(vec!["libc", "c_void"]));
+        let invoke = transmute_expr(void_ptr, invoke_ty, invoke_field);
+        let call_expr = mk().call_expr(invoke, args_new);
+
+        if ctx.is_unused() {
+            stmts.push(mk().semi_stmt(call_expr));
+            let val = self.panic("Block call expression is not supposed to be used");
+            Ok(WithStmts { stmts, val })
+        } else {
+            Ok(WithStmts { stmts, val: call_expr })
+        }
+    }
+
+    /// Declare a `__block` variable as a cell shared with the blocks that capture it.
+    pub fn convert_block_byref_decl(
+        &self,
+        ctx: ExprContext,
+        decl_id: CDeclId,
+        ident: &str,
+        initializer: Option<CExprId>,
+        typ: CQualTypeId,
+    ) -> Result<cfg::DeclStmtInfo, String> {
+        let has_self_reference = initializer
+            .map_or(false, |expr_id| self.has_decl_reference(decl_id, expr_id));
+
+        let rust_name = self.renamer.borrow_mut()
+            .insert(decl_id, ident)
+            .ok_or_else(|| format!("Failed to insert variable '{}'", ident))?;
+        let (ty, _, init) = self.convert_variable(ctx, initializer, typ)?;
+        let init = init?;
+        let ty = shared_cell_ty(ty);
+        let zeroed = shared_cell_new(self.implicit_default_expr(typ.ctype, false)?);
+
+        // A hoisted declaration gets a fresh cell each time the C declaration is reached
+        let local_mut = mk().local(mk().set_mutbl("mut").ident_pat(&rust_name), Some(ty.clone()), Some(zeroed.clone()));
+        let decl = vec![mk().local_stmt(P(local_mut))];
+
+        let mut assign = vec![mk().semi_stmt(mk().assign_expr(mk().path_expr(vec![&rust_name]), zeroed.clone()))];
+        assign.extend(init.stmts.clone());
+        assign.push(mk().semi_stmt(mk().assign_expr(block_byref_place(&rust_name), init.val.clone())));
+
+        // Blocks in the initializer can capture the variable being initialized
+        let decl_and_assign = if has_self_reference {
+            let local = mk().local(mk().ident_pat(&rust_name), Some(ty), Some(zeroed));
+            let mut stmts = vec![mk().local_stmt(P(local))];
+            stmts.extend(init.stmts);
+            stmts.push(mk().semi_stmt(mk().assign_expr(block_byref_place(&rust_name), init.val)));
+            stmts
+        } else {
+            let mut stmts = init.stmts;
+            let local = mk().local(mk().ident_pat(&rust_name), Some(ty), Some(shared_cell_new(init.val)));
+            stmts.push(mk().local_stmt(P(local)));
+            stmts
+        };
+
+        Ok(cfg::DeclStmtInfo::new(decl, assign, decl_and_assign))
+    }
+
+    /// The `BlockLiteral` and `BlockDescriptor` types, `RustBlock`, its copy and dispose helpers,
+    /// `new_block` and `new_global_block`
+    pub fn block_abi_items(&self) -> Vec<P<Item>> {
+        let void_ptr = || mk().mutbl().ptr_ty(mk().path_ty(vec!["libc", "c_void"]));
+        let descriptor_ptr = || mk().mutbl().ptr_ty(mk().path_ty(vec![BLOCK_DESCRIPTOR]));
+        let literal_ptr = || mk().mutbl().ptr_ty(mk().path_ty(vec![BLOCK_LITERAL]));
+        let c_int = || mk().path_ty(vec!["libc", "c_int"]);
+        let c_ulong = || mk().path_ty(vec!["libc", "c_ulong"]);
+        let f = || mk().ident_ty("F");
+        let block_ty = || rust_block_ty(f());
+        let rust_block_ptr = || mk().mutbl().ptr_ty(block_ty());
+        let int = |i: u128| mk().lit_expr(mk().int_lit(i, LitIntType::Unsuffixed));
+        let null = |ty: P<Ty>| mk().cast_expr(int(0), ty);
+        let var = |name: &str| mk().path_expr(vec![name]);
+        let deref = |name: &str| mk().paren_expr(mk().unary_expr("*", var(name)));
+        let cloned_f = || mk().bounded_ty_param("F", vec![vec![mk().path_segment("Clone")]]);
+
+        let literal = mk().pub_()
+            .call_attr("derive", vec!["Copy", "Clone"])
+            .call_attr("repr", vec!["C"])
+            .struct_item(BLOCK_LITERAL, vec![
+                mk().pub_().struct_field("isa", void_ptr()),
+                mk().pub_().struct_field("flags", c_int()),
+                mk().pub_().struct_field("reserved", c_int()),
+                mk().pub_().struct_field("invoke", void_ptr()),
+                mk().pub_().struct_field("descriptor", descriptor_ptr()),
+            ]);
+        let comment = String::from("// `copy` and `dispose` are only present if the block has BLOCK_HAS_COPY_DISPOSE");
+        let span = self.comment_store.borrow_mut().add_comment_lines(vec![comment]);
+        let descriptor = mk().span(span).pub_()
+            .call_attr("derive", vec!["Copy", "Clone"])
+            .call_attr("repr", vec!["C"])
+            .struct_item(BLOCK_DESCRIPTOR, vec![
+                mk().pub_().struct_field("reserved", c_ulong()),
+                mk().pub_().struct_field("size", c_ulong()),
+                mk().pub_().struct_field("copy", void_ptr()),
+                mk().pub_().struct_field("dispose", void_ptr()),
+            ]);
+        let rust_block = mk().pub_()
+            .call_attr("repr", vec!["C"])
+            .generic_over(mk().ty_param("F"))
+            .struct_item(RUST_BLOCK, vec![
+                mk().pub_().struct_field("literal", mk().path_ty(vec![BLOCK_LITERAL])),
+                mk().pub_().struct_field("descriptor", mk().path_ty(vec![BLOCK_DESCRIPTOR])),
+                mk().pub_().struct_field("closure", f()),
+            ]);
+
+        // (*dst).literal.descriptor = &mut (*dst).descriptor;
+        let fix_descriptor = |block: P<Expr>| mk().semi_stmt(mk().assign_expr(
+            mk().field_expr(mk().field_expr(block.clone(), "literal"), "descriptor"),
+            mk().set_mutbl("mut").addr_of_expr(mk().field_expr(block, "descriptor")),
+        ));
+
+        // Called by `_Block_copy` after it copied the bytes of a block to the heap
+        // unsafe extern "C" fn copy_block<F: Clone>(dst: *mut RustBlock<F>, src: *mut RustBlock<F>) {
+        //     ::std::ptr::write(&mut (*dst).closure, (*src).closure.clone());
+        //     (*dst).literal.descriptor = &mut (*dst).descriptor;
+        // }
+        let clone = mk().method_call_expr(mk().field_expr(deref("src"), "closure"), "clone", vec![] as Vec<P<Expr>>);
+        let write = mk().call_expr(mk().path_expr(vec!["", "std", "ptr", "write"]), vec![
+            mk().set_mutbl("mut").addr_of_expr(mk().field_expr(deref("dst"), "closure")),
+            clone,
+        ]);
+        let copy = mk().unsafe_().abi("C")
+            .generic_over(cloned_f())
+            .fn_item(COPY_BLOCK,
+                     mk().fn_decl(vec![mk().arg(rust_block_ptr(), mk().ident_pat("dst")),
+                                       mk().arg(rust_block_ptr(), mk().ident_pat("src"))],
+                                  FunctionRetTy::Default(DUMMY_SP), false),
+                     mk().block(vec![mk().semi_stmt(write), fix_descriptor(deref("dst"))]));
+
+        // Called by `_Block_release` before it frees a heap copy
+        // unsafe extern "C" fn dispose_block<F>(block: *mut RustBlock<F>) {
+        //     ::std::ptr::drop_in_place(&mut (*block).closure);
+        // }
+        let drop_closure = mk().call_expr(mk().path_expr(vec!["", "std", "ptr", "drop_in_place"]), vec![
+            mk().set_mutbl("mut").addr_of_expr(mk().field_expr(deref("block"), "closure")),
+        ]);
+        let dispose = mk().unsafe_().abi("C")
+            .generic_over(mk().ty_param("F"))
+            .fn_item(DISPOSE_BLOCK,
+                     mk().fn_decl(vec![mk().arg(rust_block_ptr(), mk().ident_pat("block"))],
+                                  FunctionRetTy::Default(DUMMY_SP), false),
+                     mk().block(vec![mk().semi_stmt(drop_closure)]));
+
+        // RustBlock { literal: BlockLiteral { ... }, descriptor: BlockDescriptor { ... }, closure }
+        let size_of_block = || mk().call_expr(mk().path_expr(vec![
+            mk().path_segment(""),
+            mk().path_segment("std"),
+            mk().path_segment("mem"),
+            mk().path_segment_with_args("size_of", mk().angle_bracketed_args(vec![block_ty()])),
+        ]), vec![] as Vec<P<Expr>>);
+        // {
+        //     extern "C" { static _NSConcreteStackBlock: [*mut libc::c_void; 32]; }
+        //     &_NSConcreteStackBlock as *const _ as *mut libc::c_void
+        // }
+        // Declared locally so a translated definition of the same symbol doesn't clash with it
+        let isa = |class: &str| {
+            let class_ty = mk().array_ty(void_ptr(), int(32));
+            let decl = mk().abi("C").foreign_items(vec![mk().static_foreign_item(class, class_ty)]);
+            let addr = mk().cast_expr(
+                mk().cast_expr(mk().addr_of_expr(var(class)), mk().ptr_ty(mk().infer_ty())),
+                void_ptr(),
+            );
+            mk().block_expr(mk().block(vec![mk().item_stmt(decl), mk().expr_stmt(addr)]))
+        };
+        let rust_block_expr = |flags: u128, helpers: bool| {
+            let helper = |name: &str| if helpers { fn_ptr_expr(name) } else { null(void_ptr()) };
+            let class = if flags & BLOCK_IS_GLOBAL != 0 {
+                "_NSConcreteGlobalBlock"
+            } else {
+                "_NSConcreteStackBlock"
+            };
+            let header = mk().struct_expr(vec![BLOCK_LITERAL], vec![
+                mk().field("isa", isa(class)),
+                mk().field("flags", int(flags)),
+                mk().field("reserved", int(0)),
+                mk().field("invoke", var("invoke")),
+                mk().field("descriptor", null(descriptor_ptr())),
+            ]);
+            let descriptor_init = mk().struct_expr(vec![BLOCK_DESCRIPTOR], vec![
+                mk().field("reserved", int(0)),
+                mk().field("size", mk().cast_expr(size_of_block(), c_ulong())),
+                mk().field("copy", helper(COPY_BLOCK)),
+                mk().field("dispose", helper(DISPOSE_BLOCK)),
+            ]);
+            mk().struct_expr(vec![RUST_BLOCK], vec![
+                mk().field("literal", header),
+                mk().field("descriptor", descriptor_init),
+                mk().field("closure", var("closure")),
+            ])
+        };
+        let closure_args = || vec![mk().arg(f(), mk().ident_pat("closure")),
+                                   mk().arg(void_ptr(), mk().ident_pat("invoke"))];
+
+        // pub struct BlockSlot(pub *mut BlockLiteral);
+        //
+        // impl Drop for BlockSlot {
+        //     fn drop(&mut self) {
+        //         unsafe {
+        //             if !self.0.is_null() {
+        //                 let dispose = transmute::<*mut libc::c_void, unsafe extern "C" fn(*mut BlockLiteral)>(
+        //                     (*(*self.0).descriptor).dispose);
+        //                 dispose(self.0);
+        //                 ::libc::free(self.0 as *mut libc::c_void);
+        //             }
+        //         }
+        //     }
+        // }
+        let slot = mk().pub_()
+            .tuple_struct_item(BLOCK_SLOT, vec![mk().pub_().enum_field(literal_ptr())]);
+        let slot_block = || mk().field_expr(mk().ident_expr("self"), "0");
+        let dispose_ty = mk().unsafe_().abi("C").barefn_ty(mk().fn_decl(
+            vec![mk().arg(literal_ptr(), mk().wild_pat())],
+            FunctionRetTy::Default(DUMMY_SP),
+            false,
+        ));
+        let dispose_field = mk().field_expr(
+            mk().paren_expr(mk().unary_expr("*", mk().field_expr(
+                mk().paren_expr(mk().unary_expr("*", slot_block())), "descriptor"))),
+            "dispose",
+        );
+        let dispose_fn = transmute_expr(void_ptr(), dispose_ty, dispose_field);
+        let free = mk().call_expr(mk().path_expr(vec!["", "libc", "free"]),
+                                  vec![mk().cast_expr(slot_block(), void_ptr())]);
+        let release = mk().block(vec![
+            mk().local_stmt(P(mk().local(mk().ident_pat("dispose"), None as Option<P<Ty>>, Some(dispose_fn)))),
+            mk().semi_stmt(mk().call_expr(var("dispose"), vec![slot_block()])),
+            mk().semi_stmt(free),
+        ]);
+        let is_null = mk().method_call_expr(slot_block(), "is_null", vec![] as Vec<P<Expr>>);
+        let release_if_set = mk().ifte_expr(mk().unary_expr("!", is_null), release, None as Option<P<Expr>>);
+        let drop_body = mk().block(vec![mk().expr_stmt(
+            mk().block_expr(mk().unsafe_().block(vec![mk().expr_stmt(release_if_set)])))]);
+        let drop_decl = mk().fn_decl(
+            vec![mk().self_arg(SelfKind::Region(None, Mutability::Mutable))],
+            FunctionRetTy::Default(DUMMY_SP),
+            false,
+        );
+        let slot_drop = mk().trait_impl_item(
+            vec!["Drop"],
+            mk().path_ty(vec![BLOCK_SLOT]),
+            vec![mk().fn_impl_item("drop", drop_decl, drop_body)],
+        );
+
+        // pub unsafe fn new_block<F: Clone>(slot: &mut BlockSlot, closure: F,
+        //                                   invoke: *mut libc::c_void) -> *mut BlockLiteral {
+        //     let block = ::libc::malloc(::std::mem::size_of::<RustBlock<F>>()) as *mut RustBlock<F>;
+        //     ::std::ptr::write(block, RustBlock { ... });
+        //     (*block).literal.descriptor = &mut (*block).descriptor;
+        //     *slot = BlockSlot(block as *mut BlockLiteral);
+        //     block as *mut BlockLiteral
+        // }
+        let malloc = mk().call_expr(mk().path_expr(vec!["", "libc", "malloc"]), vec![size_of_block()]);
+        let alloc = mk().local_stmt(P(mk().local(
+            mk().ident_pat("block"),
+            None as Option<P<Ty>>,
+            Some(mk().cast_expr(malloc, rust_block_ptr())),
+        )));
+        let init = mk().semi_stmt(mk().call_expr(mk().path_expr(vec!["", "std", "ptr", "write"]), vec![
+            var("block"),
+            rust_block_expr(BLOCK_HAS_COPY_DISPOSE, true),
+        ]));
+        // Frees the block evaluated before, like C reusing the stack slot of the literal
+        let store = mk().semi_stmt(mk().assign_expr(
+            mk().unary_expr("*", var("slot")),
+            mk().call_expr(var(BLOCK_SLOT), vec![mk().cast_expr(var("block"), literal_ptr())]),
+        ));
+        let ret = mk().expr_stmt(mk().cast_expr(var("block"), literal_ptr()));
+        let mut new_block_args = vec![mk().arg(mk().set_mutbl("mut").ref_ty(mk().path_ty(vec![BLOCK_SLOT])),
+                                              mk().ident_pat("slot"))];
+        new_block_args.extend(closure_args());
+        let new_block = mk().pub_().unsafe_()
+            .generic_over(cloned_f())
+            .fn_item(NEW_BLOCK,
+                     mk().fn_decl(new_block_args, FunctionRetTy::Ty(literal_ptr()), false),
+                     mk().block(vec![alloc, init, fix_descriptor(deref("block")), store, ret]));
+
+        // pub unsafe fn new_global_block<F>(closure: F, invoke: *mut libc::c_void) -> *mut BlockLiteral {
+        //     let block = Box::into_raw(Box::new(RustBlock { ... }));
+        //     (*block).literal.descriptor = &mut (*block).descriptor;
+        //     block as *mut BlockLiteral
+        // }
+        let boxed = mk().call_expr(mk().path_expr(vec!["Box", "new"]), vec![rust_block_expr(BLOCK_IS_GLOBAL, false)]);
+        let raw = mk().call_expr(mk().path_expr(vec!["Box", "into_raw"]), vec![boxed]);
+        let alloc = mk().local_stmt(P(mk().local(mk().ident_pat("block"), None as Option<P<Ty>>, Some(raw))));
+        let ret = mk().expr_stmt(mk().cast_expr(var("block"), literal_ptr()));
+        let comment = String::from("// Blocks of static initializers are created once and never freed");
+        let span = self.comment_store.borrow_mut().add_comment_lines(vec![comment]);
+        let new_global_block = mk().span(span).pub_().unsafe_()
+            .generic_over(mk().ty_param("F"))
+            .fn_item(NEW_GLOBAL_BLOCK,
+                     mk().fn_decl(closure_args(), FunctionRetTy::Ty(literal_ptr()), false),
+                     mk().block(vec![alloc, fix_descriptor(deref("block")), ret]));
+
+        vec![literal, descriptor, rust_block, copy, dispose, slot, slot_drop, new_block, new_global_block]
+    }
+}
+
+/// `let mut slot = BlockSlot(::std::ptr::null_mut());`, the slot owning the last block evaluated
+/// by one block literal
+pub fn block_slot_decl(name: &str) -> Stmt {
+    let null = mk().call_expr(mk().path_expr(vec!["", "std", "ptr", "null_mut"]), vec![] as Vec<P<Expr>>);
+    let empty = mk().call_expr(mk().path_expr(vec![BLOCK_SLOT]), vec![null]);
+    mk().local_stmt(P(mk().local(mk().set_mutbl("mut").ident_pat(name), None as Option<P<Ty>>, Some(empty))))
+}
+
+/// `RustBlock<F>`
+fn rust_block_ty(f: P<Ty>) -> P<Ty> {
+    mk().path_ty(vec![mk().path_segment_with_args(RUST_BLOCK, mk().angle_bracketed_args(vec![f]))])
+}
+
+/// `name::<F> as *mut libc::c_void`
+fn fn_ptr_expr(name: &str) -> P<Expr> {
+    mk().cast_expr(
+        mk().path_expr(vec![mk().path_segment_with_args(
+            name,
+            mk().angle_bracketed_args(vec![mk().ident_ty("F")]),
+        )]),
+        mk().mutbl().ptr_ty(mk().path_ty(vec!["libc", "c_void"])),
+    )
+}
+
+/// `::std::sync::Arc<::std::cell::UnsafeCell<ty>>`
+fn shared_cell_ty(ty: P<Ty>) -> P<Ty> {
+    let cell = mk().path_ty(vec![
+        mk().path_segment(""),
+        mk().path_segment("std"),
+        mk().path_segment("cell"),
+        mk().path_segment_with_args("UnsafeCell", mk().angle_bracketed_args(vec![ty])),
+    ]);
+    mk().path_ty(vec![
+        mk().path_segment(""),
+        mk().path_segment("std"),
+        mk().path_segment("sync"),
+        mk().path_segment_with_args("Arc", mk().angle_bracketed_args(vec![cell])),
+    ])
+}
+
+/// `::std::sync::Arc::new(::std::cell::UnsafeCell::new(val))`
+fn shared_cell_new(val: P<Expr>) -> P<Expr> {
+    let cell = mk().call_expr(mk().path_expr(vec!["", "std", "cell", "UnsafeCell", "new"]), vec![val]);
+    mk().call_expr(mk().path_expr(vec!["", "std", "sync", "Arc", "new"]), vec![cell])
+}
+
+/// `*name.get()`, the place a `__block` variable is stored in
+pub fn block_byref_place(name: &str) -> P<Expr> {
+    let ptr = mk().method_call_expr(mk().path_expr(vec![name]), "get", vec![] as Vec<P<Expr>>);
+    mk().unary_expr("*", ptr)
+}
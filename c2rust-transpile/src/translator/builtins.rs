@@ -64,6 +64,15 @@ impl<'c> Translation<'c> {
             }
             "__builtin_expect" => self.convert_expr(ctx.used(), args[0]),
 
+            "__builtin_unreachable" => Ok(WithStmts::new(mk().call_expr(
+                mk().path_expr(vec!["", "std", "hint", "unreachable_unchecked"]),
+                vec![] as Vec<P<Expr>>,
+            ))),
+            "__builtin_trap" => Ok(WithStmts::new(mk().call_expr(
+                mk().path_expr(vec!["", "std", "process", "abort"]),
+                vec![] as Vec<P<Expr>>,
+            ))),
+
             "__builtin_popcount" | "__builtin_popcountl" | "__builtin_popcountll" => {
                 let val = self.convert_expr(ctx.used(), args[0])?;
                 Ok(val.map(|x| {
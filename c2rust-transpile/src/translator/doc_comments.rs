@@ -0,0 +1,310 @@
+#![deny(missing_docs)]
+//! Doxygen comments (`/** ... */`, `/*! ... */`, `/// ...` and `//! ...`) preceding top-level
+//! declarations are translated into `///` rustdoc comments, so that `cargo doc` on the translated
+//! crate is useful from the start.
+//!
+//! `@brief` text becomes the summary, and the `@param` and `@return`/`@retval` commands are
+//! collected into `# Arguments` and `# Returns` sections. `@code` blocks become fenced `c` code
+//! blocks, so rustdoc does not try to run them as doctests. All other comments are still emitted
+//! as plain comments.
+
+use super::*;
+use rust_ast::comment_store::as_plain_comment;
+
+impl<'c> Translation<'c> {
+    /// Add the comments preceding a declaration, then `extra_comment`, then its source map marker,
+    /// to the comment store, returning the `Span` to give the translated declaration. Doxygen
+    /// comments become doc comments if the declaration is top-level.
+    pub fn convert_decl_comments(&self, decl_id: CDeclId, toplevel: bool, extra_comment: Option<String>) -> Span {
+        let mut comments = self.comment_context.borrow_mut().remove_decl_comment(decl_id);
+        comments.extend(extra_comment);
+        let decl_loc = self.ast_context.c_decls.get(&decl_id).and_then(|d| d.loc.as_ref());
+        let marker = self.source_map_marker(decl_loc, SourceMapKind::Decl);
+
+        if !toplevel {
+            let mut lines = comments;
+            lines.extend(marker);
+            return self.comment_store.borrow_mut().add_comment_lines(lines)
+        }
+
+        // Doc comments go last so that they directly precede the item they document
+        let mut lines: Vec<String> = vec![];
+        let mut docs: Vec<String> = vec![];
+        for comment in comments {
+            match doxygen_to_rustdoc(&comment) {
+                Some(doc) => {
+                    if !docs.is_empty() {
+                        docs.push(String::from("///"));
+                    }
+                    docs.extend(doc);
+                }
+                None => lines.push(as_plain_comment(comment)),
+            }
+        }
+        lines.extend(docs);
+        lines.extend(marker);
+        self.comment_store.borrow_mut().add_doc_comment_lines(lines)
+    }
+}
+
+/// The part of a Doxygen comment being collected
+enum Section {
+    Body,
+    Param,
+    Return,
+}
+
+/// Translate a Doxygen comment into the lines of a rustdoc comment, or return `None` if the
+/// comment is not a Doxygen comment.
+pub fn doxygen_to_rustdoc(comment: &str) -> Option<Vec<String>> {
+    let comment = comment.trim();
+
+    // Strip the comment delimiters, leaving one line of text per line of the comment
+    let text_lines: Vec<&str> = if comment.starts_with("/**") || comment.starts_with("/*!") {
+        if comment.starts_with("/***") || comment.len() < 5 || !comment.ends_with("*/") {
+            return None
+        }
+        let inner = &comment[3..comment.len() - 2];
+        let inner = if inner.starts_with('<') { &inner[1..] } else { inner };
+        inner
+            .lines()
+            .map(|line| {
+                let line = line.trim_left();
+                if line.starts_with('*') { &line[1..] } else { line }
+            })
+            .collect()
+    } else if comment.starts_with("///") || comment.starts_with("//!") {
+        if comment.starts_with("////") {
+            return None
+        }
+        let mut text_lines = vec![];
+        for line in comment.lines() {
+            let line = line.trim_left();
+            if !(line.starts_with("///") || line.starts_with("//!")) {
+                return None
+            }
+            let line = &line[3..];
+            text_lines.push(if line.starts_with('<') { &line[1..] } else { line });
+        }
+        text_lines
+    } else {
+        return None
+    };
+
+    let mut body: Vec<String> = vec![];
+    let mut params: Vec<(String, String)> = vec![];
+    let mut returns: Vec<String> = vec![];
+    let mut section = Section::Body;
+    let mut in_code = false;
+
+    for raw in text_lines {
+        let line = raw.trim();
+
+        if in_code {
+            if is_command(line, "endcode") {
+                body.push(String::from("```"));
+                in_code = false;
+            } else {
+                let code = if raw.starts_with(' ') { &raw[1..] } else { raw };
+                body.push(code.trim_right().to_owned());
+            }
+            continue
+        }
+
+        if line.is_empty() {
+            section = Section::Body;
+            body.push(String::new());
+            continue
+        }
+
+        let (command, rest) = split_command(line);
+        match command {
+            Some("brief") | Some("short") | Some("details") => {
+                section = Section::Body;
+                if !rest.is_empty() {
+                    body.push(rest.to_owned());
+                }
+            }
+            Some("param") | Some("tparam") => {
+                let rest = if rest.starts_with('[') {
+                    rest.find(']').map_or(rest, |end| rest[end + 1..].trim_left())
+                } else {
+                    rest
+                };
+                let mut words = rest.splitn(2, |c: char| c.is_whitespace());
+                let name = words.next().unwrap_or("").trim_right_matches(',');
+                let desc = words.next().unwrap_or("").trim();
+                params.push((name.to_owned(), desc.to_owned()));
+                section = Section::Param;
+            }
+            Some("return") | Some("returns") | Some("result") => {
+                returns.push(rest.to_owned());
+                section = Section::Return;
+            }
+            Some("retval") => {
+                let mut words = rest.splitn(2, |c: char| c.is_whitespace());
+                let value = words.next().unwrap_or("");
+                let desc = words.next().unwrap_or("").trim();
+                returns.push(format!("* `{}` - {}", value, desc));
+                section = Section::Return;
+            }
+            Some("code") => {
+                section = Section::Body;
+                body.push(String::from("```c"));
+                in_code = true;
+            }
+            Some("note") | Some("warning") | Some("see") | Some("sa") | Some("deprecated") => {
+                section = Section::Body;
+                let heading = match command {
+                    Some("note") => "Note",
+                    Some("warning") => "Warning",
+                    Some("deprecated") => "Deprecated",
+                    _ => "See also",
+                };
+                body.push(format!("{}: {}", heading, rest));
+            }
+            _ => match section {
+                Section::Param => {
+                    let desc = &mut params.last_mut().expect("no parameter to continue").1;
+                    desc.push(' ');
+                    desc.push_str(line);
+                }
+                Section::Return => {
+                    let ret = returns.last_mut().expect("no return to continue");
+                    ret.push(' ');
+                    ret.push_str(line);
+                }
+                Section::Body => body.push(line.to_owned()),
+            },
+        }
+    }
+    if in_code {
+        body.push(String::from("```"));
+    }
+
+    // Collapse runs of empty lines and drop leading and trailing ones
+    let mut doc: Vec<String> = vec![];
+    for line in body {
+        if !line.is_empty() || doc.last().map_or(false, |l: &String| !l.is_empty()) {
+            doc.push(line);
+        }
+    }
+    while doc.last().map_or(false, |l| l.is_empty()) {
+        doc.pop();
+    }
+
+    if !params.is_empty() {
+        if !doc.is_empty() {
+            doc.push(String::new());
+        }
+        doc.push(String::from("# Arguments"));
+        doc.push(String::new());
+        doc.extend(params.into_iter().map(|(name, desc)| format!("* `{}` - {}", name, desc.trim())));
+    }
+    if !returns.is_empty() {
+        if !doc.is_empty() {
+            doc.push(String::new());
+        }
+        doc.push(String::from("# Returns"));
+        doc.push(String::new());
+        doc.extend(returns.into_iter().map(|ret| ret.trim().to_owned()));
+    }
+
+    if doc.is_empty() {
+        return None
+    }
+    let mut in_code = false;
+    Some(doc
+        .into_iter()
+        .map(|line| {
+            if line.starts_with("```") {
+                in_code = !in_code;
+            } else if !in_code {
+                return format!("/// {}", inline_commands(&line)).trim_right().to_owned()
+            }
+            format!("/// {}", line).trim_right().to_owned()
+        })
+        .collect())
+}
+
+/// Render the inline commands `\p`, `\c` and `\a` (or `@p`, `@c` and `@a`), which mark the next
+/// word as code or as an argument, as inline code
+fn inline_commands(line: &str) -> String {
+    let mut words = line.split(' ');
+    let mut out: Vec<String> = vec![];
+    while let Some(word) = words.next() {
+        match word {
+            "\\p" | "@p" | "\\c" | "@c" | "\\a" | "@a" => match words.next() {
+                Some(arg) => {
+                    let code = arg.trim_right_matches(|c: char| ".,;:!?)".contains(c));
+                    out.push(format!("`{}`{}", code, &arg[code.len()..]));
+                }
+                None => out.push(word.to_owned()),
+            },
+            _ => out.push(word.to_owned()),
+        }
+    }
+    out.join(" ")
+}
+
+/// Split a line starting with a `@command` or `\command` into the command and the rest of the
+/// line
+fn split_command(line: &str) -> (Option<&str>, &str) {
+    if !(line.starts_with('@') || line.starts_with('\\')) {
+        return (None, line)
+    }
+    let end = line[1..]
+        .find(|c: char| !c.is_alphanumeric())
+        .map_or(line.len(), |i| i + 1);
+    if end == 1 {
+        return (None, line)
+    }
+    (Some(&line[1..end]), line[end..].trim())
+}
+
+fn is_command(line: &str, command: &str) -> bool {
+    split_command(line).0 == Some(command)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::doxygen_to_rustdoc;
+
+    #[test]
+    fn converts_sections() {
+        let comment = "/**\n * @brief Adds two numbers.\n *\n * @param a the first\n *        number\n * @param[in] b the second number\n * @return the sum\n */";
+        let expected = vec![
+            "/// Adds two numbers.",
+            "///",
+            "/// # Arguments",
+            "///",
+            "/// * `a` - the first number",
+            "/// * `b` - the second number",
+            "///",
+            "/// # Returns",
+            "///",
+            "/// the sum",
+        ];
+        assert_eq!(doxygen_to_rustdoc(comment).unwrap(), expected);
+    }
+
+    #[test]
+    fn converts_line_comments_and_code() {
+        let comment = "/// Frees the list \\p l.\n/// \\code\n///   list_free(l);\n/// \\endcode";
+        let expected = vec![
+            "/// Frees the list `l`.",
+            "/// ```c",
+            "///   list_free(l);",
+            "/// ```",
+        ];
+        assert_eq!(doxygen_to_rustdoc(comment).unwrap(), expected);
+    }
+
+    #[test]
+    fn ignores_plain_comments() {
+        assert_eq!(doxygen_to_rustdoc("/* not documentation */"), None);
+        assert_eq!(doxygen_to_rustdoc("// not documentation"), None);
+        assert_eq!(doxygen_to_rustdoc("/****** banner ******/"), None);
+        assert_eq!(doxygen_to_rustdoc("/**/"), None);
+    }
+}
@@ -0,0 +1,334 @@
+#![deny(missing_docs)]
+//! This module decides which C enums can be translated into Rust enums and produces the
+//! declarations and conversions for the ones that can.
+//!
+//! A C enum is "closed" when every value of the enum type we can see is produced from one of
+//! its constants, or from an integer that is explicitly converted to it. Integer conversions are
+//! checked at runtime through a generated `try_from` constructor. Anything that could sneak an
+//! arbitrary value into the enum without such a conversion (arithmetic on enum lvalues, unions,
+//! pointer casts, bit-fields, functions and globals visible to other translation units, including
+//! through the fields of the records they reach) leaves it "open", and those enums keep the
+//! integer type alias translation.
+
+use super::*;
+
+use std::collections::HashSet;
+
+/// Primitive integer type backing a closed enum
+#[derive(Debug, Copy, Clone)]
+pub struct EnumRepr {
+    name: &'static str,
+    bits: u32,
+    signed: bool,
+}
+
+impl EnumRepr {
+    fn from_integral_type(kind: &CTypeKind) -> Result<EnumRepr, String> {
+        let (name, bits, signed) = match *kind {
+            CTypeKind::SChar => ("i8", 8, true),
+            CTypeKind::UChar => ("u8", 8, false),
+            CTypeKind::Short => ("i16", 16, true),
+            CTypeKind::UShort => ("u16", 16, false),
+            CTypeKind::Int => ("i32", 32, true),
+            CTypeKind::UInt => ("u32", 32, false),
+            CTypeKind::LongLong => ("i64", 64, true),
+            CTypeKind::ULongLong => ("u64", 64, false),
+            ref k => return Err(format!("has a platform-dependent underlying type {:?}", k)),
+        };
+        Ok(EnumRepr { name, bits, signed })
+    }
+
+    /// Truncate a constant to the width of this representation
+    fn truncate(&self, value: ConstIntExpr) -> u64 {
+        let raw = match value {
+            ConstIntExpr::U(v) => v,
+            ConstIntExpr::I(v) => v as u64,
+        };
+        if self.bits == 64 { raw } else { raw & ((1 << self.bits) - 1) }
+    }
+
+    /// Literal expression (usable as a pattern) for a truncated constant
+    fn literal(&self, bits: u64) -> P<Expr> {
+        if self.signed {
+            let shift = 64 - self.bits;
+            signed_int_expr(((bits << shift) as i64) >> shift)
+        } else {
+            mk().lit_expr(mk().int_lit(bits as u128, LitIntType::Unsuffixed))
+        }
+    }
+
+    /// Rust type of the representation
+    pub fn ty(&self) -> P<Ty> {
+        mk().path_ty(vec![self.name])
+    }
+}
+
+impl<'c> Translation<'c> {
+    /// Look through every declaration and expression to decide, for each enum with a
+    /// definition, whether it is closed (`Ok` with its representation) or why it isn't.
+    pub fn classify_enums(&self) -> IndexMap<CEnumId, Result<EnumRepr, String>> {
+        let context = &self.ast_context;
+        let mut enums: IndexMap<CEnumId, Result<EnumRepr, String>> = IndexMap::new();
+
+        for (&decl_id, decl) in &context.c_decls {
+            if let CDeclKind::Enum { integral_type: Some(integral_type), ref variants, .. } = decl.kind {
+                enums.insert(decl_id, self.closed_enum_repr(integral_type, variants));
+            }
+        }
+
+        {
+            let mut open = |ty: CTypeId, reason: &str| {
+                let mut found = vec![];
+                self.enums_in_type(ty, &mut HashSet::new(), &mut found);
+                for enum_id in found {
+                    if let Some(entry) = enums.get_mut(&enum_id) {
+                        if entry.is_ok() {
+                            *entry = Err(reason.to_owned());
+                        }
+                    }
+                }
+            };
+
+            for decl in context.c_decls.values() {
+                match decl.kind {
+                    CDeclKind::Field { typ, bitfield_width: Some(_), .. } =>
+                        open(typ.ctype, "is used as the type of a bit-field"),
+                    CDeclKind::Union { fields: Some(ref fields), .. } => {
+                        for &field_id in fields {
+                            if let CDeclKind::Field { typ, .. } = context[field_id].kind {
+                                open(typ.ctype, "is stored in a union");
+                            }
+                        }
+                    }
+                    // Other translation units can pass or store any integer through functions
+                    // and globals they can see, whether or not they are defined here
+                    CDeclKind::Function { is_extern, body, typ, .. } if is_extern || body.is_none() => {
+                        if let CTypeKind::Function(ret, ref params, ..) = context.resolve_type(typ).kind {
+                            for ty in params.iter().chain(Some(&ret)) {
+                                open(ty.ctype, "crosses the signature of an externally visible function");
+                            }
+                        }
+                    }
+                    CDeclKind::Variable { is_extern: true, typ, .. } =>
+                        open(typ.ctype, "is the type of an externally visible variable"),
+                    _ => {}
+                }
+            }
+
+            for expr in context.c_exprs.values() {
+                match expr.kind {
+                    CExprKind::Unary(ty, UnOp::PreIncrement, _, _) |
+                    CExprKind::Unary(ty, UnOp::PostIncrement, _, _) |
+                    CExprKind::Unary(ty, UnOp::PreDecrement, _, _) |
+                    CExprKind::Unary(ty, UnOp::PostDecrement, _, _) =>
+                        if context.resolve_type(ty.ctype).kind.is_enum() {
+                            open(ty.ctype, "is incremented or decremented")
+                        },
+                    CExprKind::Binary(ty, op, _, _, _, _) if op.underlying_assignment().is_some() =>
+                        if context.resolve_type(ty.ctype).kind.is_enum() {
+                            open(ty.ctype, "is the target of a compound assignment")
+                        },
+                    CExprKind::VAArg(ty, _) =>
+                        if context.resolve_type(ty.ctype).kind.is_enum() {
+                            open(ty.ctype, "is read with va_arg")
+                        },
+                    CExprKind::ImplicitCast(ty, src, kind, _, _) |
+                    CExprKind::ExplicitCast(ty, src, kind, _, _) => {
+                        let src_ty = context[src].kind.get_type();
+                        match kind {
+                            CastKind::BitCast | CastKind::IntegralToPointer | CastKind::ToUnion => {
+                                let pointee = |ty: CTypeId| match context.resolve_type(ty).kind {
+                                    CTypeKind::Pointer(p) => Some(p.ctype),
+                                    _ => None,
+                                };
+                                let tys = pointee(ty.ctype).into_iter()
+                                    .chain(src_ty.and_then(&pointee));
+                                for ty in tys {
+                                    open(ty, "is accessed through a pointer cast");
+                                }
+                            }
+                            CastKind::LValueToRValue | CastKind::NoOp | CastKind::ConstCast |
+                            CastKind::ToVoid | CastKind::IntegralCast |
+                            CastKind::FloatingToIntegral => {}
+                            _ => if context.resolve_type(ty.ctype).kind.is_enum() {
+                                open(ty.ctype, "is produced by an unsupported cast")
+                            },
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        enums
+    }
+
+    /// Check that the constants of an enum can all be discriminants of a Rust enum.
+    fn closed_enum_repr(&self, integral_type: CQualTypeId, variants: &[CEnumConstantId]) -> Result<EnumRepr, String> {
+        let repr = EnumRepr::from_integral_type(&self.ast_context.resolve_type(integral_type.ctype).kind)?;
+
+        let mut seen = HashSet::new();
+        for &variant_id in variants {
+            if let CDeclKind::EnumConstant { value, .. } = self.ast_context[variant_id].kind {
+                let bits = repr.truncate(value);
+                if !seen.insert(bits) {
+                    return Err(format!("has several constants with the value {}", bits));
+                }
+            }
+        }
+
+        // Zero-initialized storage of the enum type has to hold a valid variant
+        if !seen.contains(&0) {
+            return Err(format!("has no constant with the value 0"));
+        }
+
+        Ok(repr)
+    }
+
+    /// Find the enums that a value of this type could hold, looking through pointers, arrays
+    /// and the fields of structs and unions. `records` guards against recursive records.
+    fn enums_in_type(&self, ty: CTypeId, records: &mut HashSet<CRecordId>, found: &mut Vec<CEnumId>) {
+        match self.ast_context.resolve_type(ty).kind {
+            CTypeKind::Enum(enum_id) => found.push(enum_id),
+            CTypeKind::Pointer(qty) => self.enums_in_type(qty.ctype, records, found),
+            CTypeKind::ConstantArray(elt, _) |
+            CTypeKind::IncompleteArray(elt) |
+            CTypeKind::VariableArray(elt, _) => self.enums_in_type(elt, records, found),
+            CTypeKind::Struct(record_id) |
+            CTypeKind::Union(record_id) => {
+                if !records.insert(record_id) {
+                    return;
+                }
+                let fields = match self.ast_context[record_id].kind {
+                    CDeclKind::Struct { fields: Some(ref fields), .. } |
+                    CDeclKind::Union { fields: Some(ref fields), .. } => fields,
+                    _ => return,
+                };
+                for &field_id in fields {
+                    if let CDeclKind::Field { typ, .. } = self.ast_context[field_id].kind {
+                        self.enums_in_type(typ.ctype, records, found);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Representation of an enum, if it is translated to a Rust enum.
+    pub fn closed_enum(&self, enum_id: CEnumId) -> Option<EnumRepr> {
+        match self.closed_enums.borrow().get(&enum_id) {
+            Some(&Ok(repr)) => Some(repr),
+            _ => None,
+        }
+    }
+
+    /// Representation of an enum type, if it is translated to a Rust enum.
+    pub fn closed_enum_type(&self, ty: CTypeId) -> Option<EnumRepr> {
+        match self.ast_context.resolve_type(ty).kind {
+            CTypeKind::Enum(enum_id) => self.closed_enum(enum_id),
+            _ => None,
+        }
+    }
+
+    /// Generate the declaration of a closed enum along with its `try_from` constructor.
+    pub fn convert_closed_enum(
+        &self,
+        span: Span,
+        enum_id: CEnumId,
+        repr: EnumRepr,
+    ) -> Result<ConvertedDecl, String> {
+        let enum_name = self.type_converter.borrow().resolve_decl_name(enum_id)
+            .expect("Enums should already be renamed");
+        let variant_ids = match self.ast_context[enum_id].kind {
+            CDeclKind::Enum { ref variants, .. } => variants,
+            _ => return Err(format!("{:?} does not point to an `enum` declaration", enum_id)),
+        };
+
+        let mut variants = vec![];
+        let mut arms = vec![];
+        for &variant_id in variant_ids {
+            let value = match self.ast_context[variant_id].kind {
+                CDeclKind::EnumConstant { value, .. } => value,
+                _ => return Err(format!("{:?} does not point to an enum variant", variant_id)),
+            };
+            let name = self.renamer.borrow().get(&variant_id).expect("Enum constant not named");
+            let discriminant = repr.literal(repr.truncate(value));
+
+            let variant = mk().path_expr(vec![enum_name.as_str(), name.as_str()]);
+            arms.push(mk().arm(
+                vec![mk().lit_pat(discriminant.clone())],
+                None as Option<P<Expr>>,
+                mk().call_expr(mk().ident_expr("Ok"), vec![variant]),
+            ));
+            variants.push(mk().unit_variant(name, Some(discriminant)));
+        }
+        arms.push(mk().arm(
+            vec![mk().wild_pat()],
+            None as Option<P<Expr>>,
+            mk().call_expr(mk().ident_expr("Err"), vec![mk().ident_expr("value")]),
+        ));
+
+        let ret_ty = mk().path_ty(vec![mk().path_segment_with_args(
+            "Result",
+            mk().angle_bracketed_args(vec![mk().ident_ty(&enum_name), repr.ty()]),
+        )]);
+        let decl = mk().fn_decl(
+            vec![mk().arg(repr.ty(), mk().ident_pat("value"))],
+            FunctionRetTy::Ty(ret_ty),
+            false,
+        );
+        let body = mk().block(vec![mk().expr_stmt(mk().match_expr(mk().ident_expr("value"), arms))]);
+        let try_from = mk().pub_().fn_impl_item("try_from", decl, body);
+        let conversions = mk().impl_item(mk().ident_ty(&enum_name), vec![try_from]);
+
+        let decl_file_path = self.ast_context[enum_id].loc.as_ref()
+            .and_then(|loc| loc.file_path.as_ref());
+        self.insert_item(conversions, decl_file_path, &self.main_file);
+
+        Ok(ConvertedDecl::Item(mk().span(span).pub_()
+            .call_attr("derive", vec!["Copy", "Clone", "PartialEq", "Eq", "Debug"])
+            .call_attr("repr", vec![repr.name])
+            .enum_item(enum_name, variants)))
+    }
+
+    /// Convert an integer into a closed enum, panicking on values without a matching constant.
+    pub fn closed_enum_from_int(&self, enum_id: CEnumId, repr: EnumRepr, val: P<Expr>) -> P<Expr> {
+        let enum_name = self.type_converter.borrow().resolve_decl_name(enum_id)
+            .expect("Enums should already be renamed");
+        let val = mk().cast_expr(val, repr.ty());
+        let conversion = mk().call_expr(mk().path_expr(vec![enum_name, "try_from".to_owned()]), vec![val]);
+        mk().method_call_expr(conversion, "unwrap", vec![] as Vec<P<Expr>>)
+    }
+
+    /// Does this cast to a closed enum need a runtime conversion, i.e. is its source something
+    /// other than one of the enum's own constants?
+    pub fn is_checked_enum_cast(&self, target_ty: CTypeId, expr: CExprId) -> bool {
+        let enum_id = match self.ast_context.resolve_type(target_ty).kind {
+            CTypeKind::Enum(enum_id) if self.closed_enum(enum_id).is_some() => enum_id,
+            _ => return false,
+        };
+        if let Some(src_ty) = self.ast_context[expr].kind.get_type() {
+            if self.ast_context.resolve_type(src_ty).kind == CTypeKind::Enum(enum_id) {
+                return false;
+            }
+        }
+        let variants = match self.ast_context[enum_id].kind {
+            CDeclKind::Enum { ref variants, .. } => variants,
+            _ => return true,
+        };
+        let literal_value = match self.ast_context[expr].kind {
+            CExprKind::DeclRef(_, decl_id, _) => return !variants.contains(&decl_id),
+            CExprKind::Literal(_, CLiteral::Integer(i, _)) => i as i64,
+            CExprKind::Unary(_, UnOp::Negate, subexpr_id, _) => match self.ast_context[subexpr_id].kind {
+                CExprKind::Literal(_, CLiteral::Integer(i, _)) => -(i as i64),
+                _ => return true,
+            },
+            _ => return true,
+        };
+        !variants.iter().any(|&variant_id| match self.ast_context[variant_id].kind {
+            CDeclKind::EnumConstant { value, .. } =>
+                value == ConstIntExpr::I(literal_value) || value == ConstIntExpr::U(literal_value as u64),
+            _ => false,
+        })
+    }
+}
@@ -0,0 +1,260 @@
+#![deny(missing_docs)]
+//! Structs ending in a flexible array member (`T data[];`) keep their `#[repr(C)]` layout, with
+//! the member translated to a zero-length array `[T; 0]`. `size_of` such a struct excludes the
+//! tail just like C's `sizeof`, so size computations carry over unchanged.
+//!
+//! To make the tail usable without pointer arithmetic, each such struct gets an `impl` with
+//!
+//!   * `data(this: *const S, len) -> &[T]` and `data_mut(this: *mut S, len) -> &mut [T]`, which
+//!     view the first `len` elements of the tail as a slice, and
+//!   * `alloc_with_data(len) -> *mut S`, which `malloc`s a struct with room for `len` elements,
+//!     or returns null if that size overflows `usize`.
+//!
+//! The accessors take raw pointers rather than `&self`: a reference to the struct only covers
+//! `size_of::<S>()` bytes, so a slice of the tail derived from it would reach outside the memory
+//! the reference may access. The tail is instead found by offsetting the raw pointer itself.
+//!
+//! Calls of the form `malloc(sizeof(S) + n * sizeof(T))` are translated as calls to the
+//! allocation helper.
+
+use super::*;
+
+impl<'c> Translation<'c> {
+    /// The flexible array member of a struct and its element type, if the struct ends in one
+    pub fn flexible_array_member(&self, record_id: CRecordId) -> Option<(CFieldId, CTypeId)> {
+        let fields = match self.ast_context[record_id].kind {
+            CDeclKind::Struct { fields: Some(ref fields), .. } => fields,
+            _ => return None,
+        };
+        let &last = fields.last()?;
+        match self.ast_context[last].kind {
+            CDeclKind::Field { typ, bitfield_width: None, .. } =>
+                match self.ast_context.resolve_type(typ.ctype).kind {
+                    CTypeKind::IncompleteArray(elt) => Some((last, elt)),
+                    _ => None,
+                },
+            _ => None,
+        }
+    }
+
+    /// Emit the slice accessors and the allocation helper for a struct with a flexible array
+    /// member next to the struct's declaration.
+    pub fn add_flexible_array_impl(
+        &self,
+        record_id: CRecordId,
+        name: &str,
+        field_name: &str,
+        elt: CTypeId,
+    ) -> Result<(), String> {
+        let elt_ty = self.convert_type(elt)?;
+        let len_arg = || mk().arg(mk().path_ty(vec!["usize"]), mk().ident_pat("len"));
+
+        // pub unsafe fn data<'a>(this: *const S, len: usize) -> &'a [T] {
+        //     let offset = &(*this).data as *const _ as usize - this as usize;
+        //     ::std::slice::from_raw_parts((this as *const u8).offset(offset as isize) as *const T, len)
+        // }
+        let accessor = |is_mut: bool| {
+            let (method, from_raw_parts) = if is_mut {
+                (format!("{}_mut", field_name), "from_raw_parts_mut")
+            } else {
+                (field_name.to_owned(), "from_raw_parts")
+            };
+            let ptr_ty = |ty: P<Ty>| if is_mut { mk().mutbl().ptr_ty(ty) } else { mk().ptr_ty(ty) };
+            let slice_ty = mk().slice_ty(elt_ty.clone());
+            let ret_ty = if is_mut {
+                mk().mutbl().ref_lt_ty("'a", slice_ty)
+            } else {
+                mk().ref_lt_ty("'a", slice_ty)
+            };
+
+            let this = || mk().ident_expr("this");
+            let field = mk().field_expr(mk().paren_expr(mk().unary_expr("*", this())), field_name);
+            let field_addr = mk().cast_expr(
+                mk().cast_expr(mk().addr_of_expr(field), mk().ptr_ty(mk().infer_ty())),
+                mk().path_ty(vec!["usize"]),
+            );
+            let offset = mk().binary_expr(
+                BinOpKind::Sub,
+                field_addr,
+                mk().cast_expr(this(), mk().path_ty(vec!["usize"])),
+            );
+            let offset = mk().local(mk().ident_pat("offset"), None as Option<P<Ty>>, Some(offset));
+
+            let bytes = mk().cast_expr(this(), ptr_ty(mk().path_ty(vec!["u8"])));
+            let tail = mk().method_call_expr(
+                bytes,
+                "offset",
+                vec![mk().cast_expr(mk().ident_expr("offset"), mk().path_ty(vec!["isize"]))],
+            );
+            let slice = mk().call_expr(
+                mk().path_expr(vec!["", "std", "slice", from_raw_parts]),
+                vec![mk().cast_expr(tail, ptr_ty(elt_ty.clone())), mk().ident_expr("len")],
+            );
+
+            let decl = mk().fn_decl(
+                vec![mk().arg(ptr_ty(mk().ident_ty(name)), mk().ident_pat("this")), len_arg()],
+                FunctionRetTy::Ty(ret_ty),
+                false,
+            );
+            let body = mk().block(vec![mk().local_stmt(P(offset)), mk().expr_stmt(slice)]);
+            mk().pub_().unsafe_().generic_over(mk().lt_param("'a")).fn_impl_item(method, decl, body)
+        };
+
+        // pub unsafe fn alloc_with_data(len: usize) -> *mut S {
+        //     match len.checked_mul(size_of::<T>()).and_then(|n| n.checked_add(size_of::<S>())) {
+        //         Some(size) => ::libc::malloc(size) as *mut S,
+        //         None => ::std::ptr::null_mut(),
+        //     }
+        // }
+        let record_ptr_ty = mk().mutbl().ptr_ty(mk().ident_ty(name));
+        let tail_size = mk().method_call_expr(
+            mk().ident_expr("len"),
+            "checked_mul",
+            vec![std_mem_size_of(elt_ty.clone())],
+        );
+        let add_head = mk().closure_expr(
+            CaptureBy::Ref,
+            Movability::Movable,
+            mk().fn_decl(
+                vec![mk().arg(mk().infer_ty(), mk().ident_pat("n"))],
+                FunctionRetTy::Default(DUMMY_SP),
+                false,
+            ),
+            mk().method_call_expr(
+                mk().ident_expr("n"),
+                "checked_add",
+                vec![std_mem_size_of(mk().ident_ty(name))],
+            ),
+        );
+        let size = mk().method_call_expr(tail_size, "and_then", vec![add_head]);
+        let malloc = mk().call_expr(
+            mk().path_expr(vec!["", "libc", "malloc"]),
+            vec![mk().ident_expr("size")],
+        );
+        let null = mk().call_expr(
+            mk().path_expr(vec!["", "std", "ptr", "null_mut"]),
+            vec![] as Vec<P<Expr>>,
+        );
+        let alloc = mk().match_expr(size, vec![
+            mk().arm(
+                vec![mk().tuple_struct_pat(vec!["Some"], vec![mk().ident_pat("size")])],
+                None as Option<P<Expr>>,
+                mk().cast_expr(malloc, record_ptr_ty.clone()),
+            ),
+            mk().arm(
+                vec![mk().ident_pat("None")],
+                None as Option<P<Expr>>,
+                null,
+            ),
+        ]);
+        let decl = mk().fn_decl(vec![len_arg()], FunctionRetTy::Ty(record_ptr_ty), false);
+        let body = mk().block(vec![mk().expr_stmt(alloc)]);
+        let alloc = mk().pub_().unsafe_()
+            .fn_impl_item(format!("alloc_with_{}", field_name), decl, body);
+
+        let item = mk().impl_item(mk().ident_ty(name), vec![accessor(false), accessor(true), alloc]);
+
+        let decl_file_path = self.ast_context[record_id].loc.as_ref()
+            .and_then(|loc| loc.file_path.as_ref());
+        self.insert_item(item, decl_file_path, &self.main_file);
+        Ok(())
+    }
+
+    /// Translate `malloc(sizeof(S) + n * sizeof(T))`, where `S` ends in a flexible array member
+    /// of `T`, as `S::alloc_with_data(n)`. Returns `None` for any other call.
+    pub fn convert_flexible_array_alloc(
+        &self,
+        ctx: ExprContext,
+        callee: CDeclId,
+        args: &[CExprId],
+    ) -> Result<Option<WithStmts<P<Expr>>>, String> {
+        match self.ast_context[callee].kind {
+            CDeclKind::Function { ref name, .. } if name == "malloc" => {}
+            _ => return Ok(None),
+        }
+        if args.len() != 1 {
+            return Ok(None)
+        }
+        let (record_id, len) = match self.flexible_array_alloc_size(args[0]) {
+            Some(alloc) => alloc,
+            None => return Ok(None),
+        };
+
+        let name = self.type_converter.borrow().resolve_decl_name(record_id)
+            .ok_or_else(|| format!("Unnamed struct {:?}", record_id))?;
+        let (field_id, _) = self.flexible_array_member(record_id)
+            .ok_or_else(|| format!("Struct {:?} has no flexible array member", record_id))?;
+        let field_name = self.type_converter.borrow().resolve_field_name(Some(record_id), field_id)
+            .ok_or_else(|| format!("Unnamed field {:?}", field_id))?;
+
+        let alloc = mk().path_expr(vec![name, format!("alloc_with_{}", field_name)]);
+        let void_ptr = mk().mutbl().ptr_ty(mk().path_ty(vec!["libc", "c_void"]));
+        Ok(Some(self.convert_expr(ctx.used(), len)?.map(|len| {
+            let call = mk().call_expr(alloc, vec![cast_int(len, "usize")]);
+            mk().cast_expr(call, void_ptr)
+        })))
+    }
+
+    /// Match `sizeof(S) + n * sizeof(T)` (in either operand order) against a struct `S` ending in
+    /// a flexible array member of `T`, returning the struct and `n`
+    fn flexible_array_alloc_size(&self, size: CExprId) -> Option<(CRecordId, CExprId)> {
+        let (lhs, rhs) = match self.ast_context[self.strip_implicit_casts(size)].kind {
+            CExprKind::Binary(_, c_ast::BinOp::Add, lhs, rhs, _, _) => (lhs, rhs),
+            _ => return None,
+        };
+
+        let record = |e: CExprId| {
+            let record_id = match self.ast_context.resolve_type(self.size_of_argument(e)?).kind {
+                CTypeKind::Struct(record_id) => record_id,
+                _ => return None,
+            };
+            self.flexible_array_member(record_id).map(|(_, elt)| (record_id, elt))
+        };
+        let (record_id, elt, tail) = match (record(lhs), record(rhs)) {
+            (Some((record_id, elt)), _) => (record_id, elt, rhs),
+            (None, Some((record_id, elt))) => (record_id, elt, lhs),
+            (None, None) => return None,
+        };
+
+        let (lhs, rhs) = match self.ast_context[self.strip_implicit_casts(tail)].kind {
+            CExprKind::Binary(_, c_ast::BinOp::Multiply, lhs, rhs, _, _) => (lhs, rhs),
+            _ => return None,
+        };
+        let is_elt_size = |e: CExprId| self.size_of_argument(e).map_or(false, |ty| {
+            self.ast_context.resolve_type_id(ty) == self.ast_context.resolve_type_id(elt)
+        });
+        if is_elt_size(rhs) {
+            Some((record_id, lhs))
+        } else if is_elt_size(lhs) {
+            Some((record_id, rhs))
+        } else {
+            None
+        }
+    }
+
+    /// The type measured by a `sizeof` expression
+    fn size_of_argument(&self, expr: CExprId) -> Option<CTypeId> {
+        match self.ast_context[self.strip_implicit_casts(expr)].kind {
+            CExprKind::UnaryType(_, UnTypeOp::SizeOf, _, arg_ty) => Some(arg_ty.ctype),
+            _ => None,
+        }
+    }
+
+    fn strip_implicit_casts(&self, mut expr: CExprId) -> CExprId {
+        while let CExprKind::ImplicitCast(_, inner, _, _, _) = self.ast_context[expr].kind {
+            expr = inner;
+        }
+        expr
+    }
+}
+
+/// `::std::mem::size_of::<ty>()`
+fn std_mem_size_of(ty: P<Ty>) -> P<Expr> {
+    let path = vec![
+        mk().path_segment(""),
+        mk().path_segment("std"),
+        mk().path_segment("mem"),
+        mk().path_segment_with_args("size_of", mk().angle_bracketed_args(vec![ty])),
+    ];
+    mk().call_expr(mk().path_expr(path), vec![] as Vec<P<Expr>>)
+}
@@ -0,0 +1,89 @@
+#![deny(missing_docs)]
+//! This module emits compile-time checks that the translated structs and unions have the size,
+//! alignment and field offsets clang computed for the C declarations.
+//!
+//! Each record gets one `const` whose type lists the expected values as array lengths, e.g.
+//! `([(); 16], [(); 8], [(); 4])`, and whose value builds the same arrays from
+//! `size_of`, `align_of` and, with `--assert-field-offsets`, the field offsets of the Rust type.
+//! A mismatch in any of them is a type error, so a record with the wrong layout fails the build
+//! instead of corrupting memory. Computing field offsets in a `const` needs nightly features the
+//! translation otherwise doesn't, which is why they are only checked on request.
+
+use super::*;
+
+impl<'c> Translation<'c> {
+    /// Emit the layout assertions for a translated record next to its declaration.
+    /// `field_offsets` pairs the Rust field names with their expected byte offsets, which are only
+    /// checked with `--assert-field-offsets`; records whose fields get repacked (bit-fields) or
+    /// always live at offset 0 (unions) pass none. Bit-field structs are made of byte arrays, so
+    /// they don't get an alignment check either.
+    pub fn add_layout_assertions(
+        &self,
+        record_id: CRecordId,
+        name: &str,
+        byte_size: u64,
+        alignment: Option<u64>,
+        field_offsets: Vec<(String, u64)>,
+    ) {
+        let record_ty = || mk().ident_ty(name);
+
+        let mut expected = vec![byte_size];
+        let mut actual = vec![std_mem_call("size_of", record_ty())];
+
+        if let Some(alignment) = alignment {
+            expected.push(alignment);
+            actual.push(std_mem_call("align_of", record_ty()));
+        }
+
+        let field_offsets = if self.tcfg.assert_field_offsets { field_offsets } else { vec![] };
+        if !field_offsets.is_empty() {
+            self.use_feature("const_raw_ptr_deref");
+            self.use_feature("const_raw_ptr_to_usize_cast");
+        }
+        for (field, offset) in field_offsets {
+            expected.push(offset);
+            actual.push(field_offset_expr(record_ty(), &field));
+        }
+
+        let ty = mk().tuple_ty(expected.into_iter()
+            .map(|n| mk().array_ty(mk().tuple_ty(vec![] as Vec<P<Ty>>), usize_lit(n)))
+            .collect());
+        let init = mk().tuple_expr(actual.into_iter()
+            .map(|n| mk().repeat_expr(mk().tuple_expr(vec![] as Vec<P<Expr>>), n))
+            .collect());
+
+        let const_name = self.renamer.borrow_mut().pick_name_root(&format!("{}_LAYOUT", name));
+        let item = mk()
+            .call_attr("allow", vec!["dead_code", "non_upper_case_globals"])
+            .const_item(const_name, ty, init);
+
+        let decl_file_path = self.ast_context[record_id].loc.as_ref()
+            .and_then(|loc| loc.file_path.as_ref());
+        self.insert_item(item, decl_file_path, &self.main_file);
+    }
+}
+
+/// `::std::mem::name::<ty>()`
+fn std_mem_call(name: &str, ty: P<Ty>) -> P<Expr> {
+    let path = vec![
+        mk().path_segment(""),
+        mk().path_segment("std"),
+        mk().path_segment("mem"),
+        mk().path_segment_with_args(name, mk().angle_bracketed_args(vec![ty])),
+    ];
+    mk().call_expr(mk().path_expr(path), vec![] as Vec<P<Expr>>)
+}
+
+/// `unsafe { &(*(0 as *const ty)).field as *const _ as usize }`
+fn field_offset_expr(ty: P<Ty>, field: &str) -> P<Expr> {
+    let null = mk().cast_expr(usize_lit(0), mk().ptr_ty(ty));
+    let record = mk().unary_expr("*", mk().paren_expr(null));
+    let field = mk().addr_of_expr(mk().field_expr(mk().paren_expr(record), field));
+    let ptr = mk().cast_expr(field, mk().ptr_ty(mk().infer_ty()));
+    let offset = mk().cast_expr(ptr, mk().path_ty(vec!["usize"]));
+    mk().block_expr(mk().unsafe_().block(vec![mk().expr_stmt(offset)]))
+}
+
+fn usize_lit(n: u64) -> P<Expr> {
+    mk().lit_expr(mk().int_lit(n as u128, LitIntType::Unsuffixed))
+}
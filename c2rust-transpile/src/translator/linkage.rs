@@ -0,0 +1,185 @@
+#![deny(missing_docs)]
+//! This module translates the GNU attributes that control how functions and variables are
+//! linked: `section`, `weak`, `alias`, `constructor`, `destructor` and `visibility`.
+//!
+//! Sections map onto `#[link_section]`, weak definitions onto the nightly-only
+//! `#[linkage = "weak"]`, and function aliases onto exported functions calling their target.
+//! Constructors and destructors are registered by placing a pointer to them in the platform's
+//! init/fini array, the same way `run_static_initializers` is. The arrays are only known on Linux,
+//! macOS and Windows, so each registration comes with a `compile_error!` for other targets.
+
+use super::*;
+
+/// The target of an `alias` attribute, if there is one
+pub fn alias_target(attrs: &[DeclAttribute]) -> Option<&str> {
+    attrs.iter().filter_map(|attr| match *attr {
+        DeclAttribute::Alias(ref target) => Some(target.as_str()),
+        _ => None,
+    }).next()
+}
+
+impl<'c> Translation<'c> {
+    /// Translate a function declaration aliasing a function defined in this translation unit into
+    /// an exported function of the alias name that calls the target. Rust can't give a second
+    /// symbol name to a function, but this way C callers of the alias still link.
+    pub fn convert_function_alias(
+        &self,
+        ctx: ExprContext,
+        span: Span,
+        typ: CFuncTypeId,
+        new_name: &str,
+        name: &str,
+        target: &str,
+    ) -> Result<ConvertedDecl, String> {
+        let target_id = self.ast_context.c_decls.iter()
+            .filter(|&(_, decl)| match decl.kind {
+                CDeclKind::Function { ref name, body: Some(_), .. } => name == target,
+                _ => false,
+            })
+            .map(|(&decl_id, _)| decl_id)
+            .next()
+            .ok_or_else(|| format!("Alias target {} of {} is not a function defined in this file", target, name))?;
+        let target_name = self.renamer.borrow().get(&target_id)
+            .ok_or_else(|| format!("Alias target {} was not renamed", target))?;
+
+        let (ret, params, is_variadic) = match self.ast_context.resolve_type(typ).kind {
+            CTypeKind::Function(ret, ref params, is_variadic, ..) => (ret, params.clone(), is_variadic),
+            ref k => return Err(format!("Type of alias {} was not a function type, got {:?}", name, k)),
+        };
+        if is_variadic {
+            return Err(format!("Can't forward the variadic alias {} to {}", name, target))
+        }
+
+        let mut args = vec![];
+        let mut call_args = vec![];
+        for (index, &param) in params.iter().enumerate() {
+            let arg_name = format!("arg{}", index);
+            let (ty, _, _) = self.convert_variable(ctx, None, param)?;
+            args.push(mk().arg(ty, mk().ident_pat(&arg_name)));
+            call_args.push(mk().ident_expr(&arg_name));
+        }
+        let ret = if self.ast_context.resolve_type(ret.ctype).kind == CTypeKind::Void {
+            FunctionRetTy::Default(DUMMY_SP)
+        } else {
+            FunctionRetTy::Ty(self.convert_type(ret.ctype)?)
+        };
+        let decl = mk().fn_decl(args, ret, false);
+
+        // pub unsafe extern "C" fn sum_alias(arg0: c_int) -> c_int { sum_tagged(arg0) }
+        let call = mk().call_expr(mk().path_expr(vec![target_name]), call_args);
+        let block = mk().block(vec![mk().expr_stmt(call)]);
+
+        Ok(ConvertedDecl::Item(mk_linkage(false, new_name, name)
+            .span(span)
+            .abi("C")
+            .pub_()
+            .unsafe_()
+            .fn_item(new_name, decl, block)))
+    }
+
+    /// Add the linkage attributes of a function or variable definition to its translation, and
+    /// register it in the init/fini array if it is a constructor or destructor.
+    pub fn add_link_attrs(
+        &self,
+        decl_id: CDeclId,
+        name: &str,
+        attrs: &[DeclAttribute],
+        converted: ConvertedDecl,
+    ) -> ConvertedDecl {
+        let item = match converted {
+            ConvertedDecl::Item(item) => item,
+            ConvertedDecl::ForeignItem(item) => {
+                if attrs.contains(&DeclAttribute::Weak) {
+                    diag!(Diagnostic::Attributes,
+                          "Dropping weak attribute on declaration of {}: Rust has no weak imports",
+                          name);
+                }
+                return ConvertedDecl::ForeignItem(item);
+            }
+            ConvertedDecl::NoItem => return ConvertedDecl::NoItem,
+        };
+
+        let mut builder = mk();
+        for attr in attrs {
+            builder = match *attr {
+                DeclAttribute::Section(ref section) => builder.str_attr("link_section", section),
+                DeclAttribute::Weak => {
+                    self.use_feature("linkage");
+                    diag!(Diagnostic::Attributes,
+                          "Weak definition of {} needs the nightly-only `linkage` feature", name);
+                    builder.str_attr("linkage", "weak")
+                }
+                DeclAttribute::Constructor(priority) => {
+                    self.add_init_array_entry(decl_id, name, ".init_array", priority);
+                    builder
+                }
+                DeclAttribute::Destructor(priority) => {
+                    self.add_init_array_entry(decl_id, name, ".fini_array", priority);
+                    builder
+                }
+                DeclAttribute::Visibility(ref visibility) if visibility != "default" => {
+                    diag!(Diagnostic::Attributes,
+                          "Ignoring visibility(\"{}\") on {}: Rust can't restrict the visibility \
+                           of exported symbols", visibility, name);
+                    builder
+                }
+                DeclAttribute::Visibility(_) | DeclAttribute::Alias(_) | DeclAttribute::BlockByref |
+                DeclAttribute::NoReturn | DeclAttribute::NonNull(_) => builder,
+            };
+        }
+
+        let attrs = builder.into_attrs();
+        ConvertedDecl::Item(item.map(|mut item| {
+            item.attrs.extend(attrs);
+            item
+        }))
+    }
+
+    /// Place a pointer to the function `name` in `.init_array` or `.fini_array` (or their
+    /// equivalents on macOS and Windows), so the loader runs it before or after `main`. Building
+    /// for any other OS is a compile error rather than silently never running the function.
+    fn add_init_array_entry(&self, decl_id: CDeclId, name: &str, section: &str, priority: Option<u64>) {
+        let is_init = section == ".init_array";
+        let linux_section = match priority {
+            Some(priority) => format!("{}.{:05}", section, priority),
+            None => section.to_owned(),
+        };
+        let macos_section = if is_init { "__DATA,__mod_init_func" } else { "__DATA,__mod_term_func" };
+
+        // The CRT runs the initializers in `.CRT$XC*` and the terminators in `.CRT$XT*`
+        let windows_section = if is_init { ".CRT$XCU" } else { ".CRT$XTU" };
+
+        let attrs = mk()
+            .single_attr("used")
+            .call_attr("cfg_attr", vec!["target_os = \"linux\"".to_owned(),
+                                        format!("link_section = \"{}\"", linux_section)])
+            .call_attr("cfg_attr", vec!["target_os = \"macos\"".to_owned(),
+                                        format!("link_section = \"{}\"", macos_section)])
+            .call_attr("cfg_attr", vec!["target_os = \"windows\"".to_owned(),
+                                        format!("link_section = \"{}\"", windows_section)]);
+
+        let fn_decl = mk().fn_decl(vec![], FunctionRetTy::Default(DUMMY_SP), false);
+        let ty = mk().unsafe_().abi("C").barefn_ty(fn_decl);
+        let suffix = if is_init { "CONSTRUCTOR" } else { "DESTRUCTOR" };
+        let static_name = self.renamer.borrow_mut().pick_name_root(&format!("{}_{}", name, suffix));
+        let item = attrs.static_item(static_name, ty, mk().path_expr(vec![name]));
+
+        // #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+        // compile_error!("...");
+        let when = if is_init { "before" } else { "after" };
+        let msg = format!("`{}` can only be registered to run {} main on Linux, macOS and Windows",
+                          name, when);
+        let msg = vec![
+            Token::interpolated(Nonterminal::NtExpr(mk().lit_expr(mk().str_lit(&msg)))),
+        ].into_iter().collect::<TokenStream>();
+        let unsupported = mk()
+            .call_attr("cfg", vec!["not(any(target_os = \"linux\", target_os = \"macos\", \
+                                    target_os = \"windows\"))"])
+            .mac_item(mk().mac(vec!["compile_error"], msg, MacDelimiter::Parenthesis));
+
+        let decl_file_path = self.ast_context[decl_id].loc.as_ref()
+            .and_then(|loc| loc.file_path.as_ref());
+        self.insert_item(item, decl_file_path, &self.main_file);
+        self.insert_item(unsupported, decl_file_path, &self.main_file);
+    }
+}
@@ -53,6 +53,9 @@ impl<'c> Translation<'c> {
         }
 
         let underlying_type_id = underlying_type_id.expect("Attempt to construct value of forward declared enum");
+        if let Some(repr) = self.closed_enum(def_id) {
+            return self.closed_enum_from_int(def_id, repr, signed_int_expr(value))
+        }
         let value = match self.ast_context.resolve_type(underlying_type_id.ctype).kind {
             CTypeKind::UInt => mk().lit_expr(mk().int_lit((value as u32) as u128, LitIntType::Unsuffixed)),
             CTypeKind::ULong => mk().lit_expr(mk().int_lit((value as u64) as u128, LitIntType::Unsuffixed)),
@@ -75,20 +78,24 @@ impl<'c> Translation<'c> {
             CLiteral::Integer(val, base) => Ok(WithStmts::new(self.mk_int_lit(ty, val, base))),
 
             CLiteral::Character(val) => {
+                // Plain character constants are `int`, while `L'x'`, `u'x'` and `U'x'` have the
+                // type of `wchar_t`, `char16_t` and `char32_t` respectively
+                let char_ty = match self.ast_context.resolve_type(ty.ctype).kind {
+                    CTypeKind::Int => mk().path_ty(vec!["i32"]),
+                    _ => self.convert_type(ty.ctype)?,
+                };
                 let expr = match char::from_u32(val as u32) {
                     Some(c) => {
                         let lit = mk().char_lit(c);
-                        let expr = mk().lit_expr(lit);
-                        let i32_type = mk().path_ty(vec!["i32"]);
-                        mk().cast_expr(expr, i32_type)
+                        mk().lit_expr(lit)
                     }
                     None => {
                         // Fallback for characters outside of the valid Unicode range
-                        let lit = mk().int_lit(val as u128, LitIntType::Signed(IntTy::I32));
+                        let lit = mk().int_lit(val as u128, LitIntType::Unsuffixed);
                         mk().lit_expr(lit)
                     }
                 };
-                Ok(WithStmts::new(expr))
+                Ok(WithStmts::new(mk().cast_expr(expr, char_ty)))
             }
 
             CLiteral::Floating(val, ref c_str) => {
@@ -109,6 +116,10 @@ impl<'c> Translation<'c> {
                 Ok(WithStmts::new(mk().lit_expr(mk().float_lit(str, float_ty))))
             }
 
+            CLiteral::String(ref val, width) if width > 1 => {
+                self.convert_wide_string_literal(is_static, ty, val, width)
+            }
+
             CLiteral::String(ref val, width) => {
                 let mut val = val.to_owned();
 
@@ -151,6 +162,53 @@ impl<'c> Translation<'c> {
         }
     }
 
+    /// Convert a `L"..."`, `u"..."` or `U"..."` literal into an array of its code units. The
+    /// exporter writes each code unit as `width` little-endian bytes.
+    fn convert_wide_string_literal(
+        &self,
+        is_static: bool,
+        ty: CQualTypeId,
+        bytes: &[u8],
+        width: u8,
+    ) -> Result<WithStmts<P<Expr>>, String> {
+        let mut units: Vec<u64> = bytes
+            .chunks(width as usize)
+            .map(|unit| unit.iter().rev().fold(0, |acc, &b| (acc << 8) | b as u64))
+            .collect();
+
+        let elem_ty = match self.ast_context.resolve_type(ty.ctype).kind {
+            // Match the literal size to the expected size padding with zeros as needed
+            CTypeKind::ConstantArray(elem, size) => {
+                units.resize(size, 0);
+                self.convert_type(elem)?
+            }
+            ref t => return Err(format!("Unexpected type for wide string literal: {:?}", t)),
+        };
+
+        let vals: Vec<P<Expr>> = units
+            .into_iter()
+            .map(|unit| mk().lit_expr(mk().int_lit(unit as u128, LitIntType::Unsuffixed)))
+            .collect();
+        let len = mk().lit_expr(mk().int_lit(vals.len() as u128, LitIntType::Unsuffixed));
+        let array = mk().array_expr(vals);
+
+        if is_static {
+            Ok(WithStmts::new(array))
+        } else {
+            // Borrowing the constant array promotes it to a `'static`, so pointers to the
+            // literal stay valid just like they do in C
+            let source_ty = mk().ref_ty(mk().array_ty(elem_ty, len));
+            let mutbl = if ty.qualifiers.is_const {
+                Mutability::Immutable
+            } else {
+                Mutability::Mutable
+            };
+            let target_ty = mk().set_mutbl(mutbl).ref_ty(self.convert_type(ty.ctype)?);
+            let pointer = transmute_expr(source_ty, target_ty, mk().addr_of_expr(array));
+            Ok(WithStmts::new(mk().unary_expr(ast::UnOp::Deref, pointer)))
+        }
+    }
+
     /// Convert an initialization list into an expresion. These initialization lists can be
     /// used as array literals, struct literals, and union literals in code.
     pub fn convert_init_list(
@@ -186,7 +244,13 @@ impl<'c> Translation<'c> {
                     x.val
                 } else {
                     let mut vals: Vec<P<Expr>> = vec![];
-                    for &v in ids {
+                    let mut remaining = ids;
+                    while let Some(&v) = remaining.first() {
+                        // A GNU range designator (`[2 ... 5] = x`) shows up as the same
+                        // initializer repeated for every index in the range
+                        let count = remaining.iter().take_while(|&&w| w == v).count();
+                        remaining = &remaining[count..];
+
                         let mut x = self.convert_expr(ctx.used(), v)?;
 
                         // Array literals require all of their elements to be the correct type; they
@@ -202,7 +266,22 @@ impl<'c> Translation<'c> {
                         }
 
                         stmts.append(&mut x.stmts);
-                        vals.push(x.val);
+
+                        // The initializer of a range is only evaluated once, so anything with
+                        // side effects is bound to a local before being repeated
+                        if count > 1 && !ctx.is_static && !self.ast_context.is_expr_pure(v) {
+                            let elem_name = self.renamer.borrow_mut().fresh();
+                            stmts.push(mk().local_stmt(P(mk().local(
+                                mk().ident_pat(&elem_name),
+                                None as Option<P<Ty>>,
+                                Some(x.val),
+                            ))));
+                            x.val = mk().path_expr(vec![elem_name]);
+                        }
+
+                        for _ in 0..count {
+                            vals.push(x.val.clone());
+                        }
                     }
                     // Pad out the array literal with default values to the desired size
                     for _i in ids.len()..n {
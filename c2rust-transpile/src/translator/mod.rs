@@ -1,4 +1,5 @@
 use std::cell::RefCell;
+use std::collections::HashSet;
 use std::{char,io,mem};
 use std::ops::Index;
 use std::path::{self, PathBuf};
@@ -17,10 +18,12 @@ use syntax_pos::{DUMMY_SP, Span};
 
 use rust_ast::comment_store::CommentStore;
 use rust_ast::item_store::ItemStore;
+use rust_ast::source_map::{SourceMapBuilder, SourceMapEntry, SourceMapKind};
 use rust_ast::traverse::Traversal;
 use c2rust_ast_builder::{mk, Builder};
 
 use TranspilerConfig;
+use diagnostics::Diagnostic;
 use c_ast::*;
 use c_ast::iterators::{DFExpr, SomeId};
 use c_ast;
@@ -32,12 +35,23 @@ use with_stmts::WithStmts;
 
 mod assembly;
 mod bitfields;
+mod blocks;
 mod builtins;
+mod doc_comments;
+mod enums;
+mod flexible_arrays;
+mod layout;
+mod linkage;
 mod literals;
 mod main_function;
 mod named_references;
 mod operators;
+mod reference_params;
 mod simd;
+mod static_assert;
+mod statics;
+mod target;
+mod variable_arrays;
 mod variadic;
 
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -79,6 +93,10 @@ impl From<bool> for DecayRef {
     }
 }
 
+pub use self::variable_arrays::VlaLowering;
+pub use self::operators::OverflowMode;
+pub use self::target::is_host_target;
+
 #[derive(Debug, Copy, Clone)]
 pub enum ReplaceMode {
     None,
@@ -126,11 +144,22 @@ pub struct Translation<'c> {
     type_converter: RefCell<TypeConverter>,
     renamer: RefCell<Renamer<CDeclId>>,
     zero_inits: RefCell<IndexMap<CDeclId, Result<P<Expr>, String>>>,
+    closed_enums: RefCell<IndexMap<CEnumId, Result<enums::EnumRepr, String>>>,
+    immutable_globals: RefCell<IndexMap<CDeclId, statics::ImmutableGlobal>>,
+    // Functions that keep their C signature under `--nonnull-references`
+    address_taken_functions: HashSet<CDeclId>,
+    // Pointer parameters used as more than a single object under `--nonnull-references`
+    escaping_pointer_params: HashSet<CDeclId>,
+    // Slots for the block literals of each function body being translated
+    block_slots: RefCell<Vec<Vec<String>>>,
 
     // Comment support
     pub comment_context: RefCell<CommentContext>, // Incoming comments
     pub comment_store: RefCell<CommentStore>, // Outgoing comments
 
+    // Source map support
+    source_map: RefCell<SourceMapBuilder>,
+
     // Mod block defintion reorganization
     mod_blocks: RefCell<IndexMap<PathBuf, ItemStore>>,
 
@@ -247,7 +276,8 @@ pub fn signed_int_expr(value: i64) -> P<Expr> {
 fn prefix_names(translation: &mut Translation, prefix: String) {
     for (&decl_id, ref mut decl) in &mut translation.ast_context.c_decls {
         match decl.kind {
-            CDeclKind::Function { ref mut name, ref body, .. } if body.is_some() => {
+            CDeclKind::Function { ref mut name, ref body, ref attrs, .. }
+                if body.is_some() || linkage::alias_target(attrs).is_some() => {
                 // SIMD types are imported and do not need to be renamed
                 if name.starts_with("_mm") {
                     continue;
@@ -260,6 +290,19 @@ fn prefix_names(translation: &mut Translation, prefix: String) {
             CDeclKind::Variable { ref mut ident, is_static: true, .. } => ident.insert_str(0, &prefix),
             _ => (),
         }
+
+        // Aliases refer to their (renamed) targets by name
+        match decl.kind {
+            CDeclKind::Function { ref mut attrs, .. } |
+            CDeclKind::Variable { ref mut attrs, .. } => {
+                for attr in attrs.iter_mut() {
+                    if let DeclAttribute::Alias(ref mut target) = *attr {
+                        target.insert_str(0, &prefix);
+                    }
+                }
+            }
+            _ => (),
+        }
     }
 }
 
@@ -308,7 +351,11 @@ pub fn translate_failure(tcfg: &TranspilerConfig, msg: &str) {
     }
 }
 
-pub fn translate(ast_context: TypedAstContext, tcfg: &TranspilerConfig, main_file: PathBuf) -> String {
+pub fn translate(
+    ast_context: TypedAstContext,
+    tcfg: &TranspilerConfig,
+    main_file: PathBuf,
+) -> (String, Vec<SourceMapEntry>) {
 
     let mut t = Translation::new(ast_context, tcfg, main_file);
     let ctx = ExprContext {
@@ -329,6 +376,14 @@ pub fn translate(ast_context: TypedAstContext, tcfg: &TranspilerConfig, main_fil
     // we simplify the translator output by omitting those.
     t.ast_context.prune_unused_decls();
 
+    // Decide which enums can become Rust enums before anything refers to them
+    let closed_enums = t.classify_enums();
+    t.closed_enums.replace(closed_enums);
+
+    // Globals that are never written don't need to be `static mut`
+    let immutable_globals = t.classify_immutable_globals();
+    t.immutable_globals.replace(immutable_globals);
+
     enum Name<'a> {
         VarName(&'a str),
         TypeName(&'a str),
@@ -448,6 +503,7 @@ pub fn translate(ast_context: TypedAstContext, tcfg: &TranspilerConfig, main_fil
             let needs_export = match t.ast_context.c_decls[top_id].kind {
                 CDeclKind::Function { is_implicit, .. } => !is_implicit,
                 CDeclKind::Variable { .. } => true,
+                CDeclKind::StaticAssert { .. } => true,
                 _ => false,
             };
             if needs_export {
@@ -487,6 +543,23 @@ pub fn translate(ast_context: TypedAstContext, tcfg: &TranspilerConfig, main_fil
             }
         }
 
+        // Immutable statics holding raw pointers need a wrapper to be `Sync`
+        if t.immutable_globals.borrow().values().any(|g| *g == statics::ImmutableGlobal::SyncStatic) {
+            let items = t.sync_static_items();
+            t.item_store.borrow_mut().items.extend(items);
+        }
+
+        // Block pointers point to block literals laid out by the blocks ABI
+        if t.type_converter.borrow().blocks_used() {
+            let items = t.block_abi_items();
+            t.item_store.borrow_mut().items.extend(items);
+        }
+
+        // Refuse to build for a target other than the one the code was translated for
+        if let Some(item) = t.target_check_item() {
+            t.item_store.borrow_mut().items.insert(0, item);
+        }
+
         // Initialize global statics when necessary
         if !t.sectioned_static_initializers.borrow().is_empty() {
             let (initializer_fn, initializer_static) = t.generate_global_static_init();
@@ -496,8 +569,14 @@ pub fn translate(ast_context: TypedAstContext, tcfg: &TranspilerConfig, main_fil
             items.push(initializer_static);
         }
 
+        // Every comment that is going to be translated has been handed out by now
+        t.comment_context.borrow().report();
+
+        // All source locations are known by now; the markers get resolved after printing
+        let source_map = mem::replace(&mut *t.source_map.borrow_mut(), SourceMapBuilder::new());
+
         // pass all converted items to the Rust pretty printer
-        to_string(|s| {
+        let output = to_string(|s| {
 
             print_header(s,&t)?;
 
@@ -549,7 +628,13 @@ pub fn translate(ast_context: TypedAstContext, tcfg: &TranspilerConfig, main_fil
             }
 
             Ok(())
-        })
+        });
+
+        if tcfg.emit_source_map || tcfg.annotate_source_locations {
+            source_map.finish(&output, tcfg.annotate_source_locations)
+        } else {
+            (output, vec![])
+        }
     })
 }
 
@@ -717,6 +802,13 @@ impl<'c> Translation<'c> {
         let mut type_converter = TypeConverter::new();
 
         if tcfg.translate_valist { type_converter.translate_valist = true }
+        if tcfg.target.is_some() { type_converter.target = Some(ast_context.target.clone()) }
+
+        let (address_taken_functions, escaping_pointer_params) = if tcfg.nonnull_references {
+            (ast_context.address_taken_functions(), ast_context.escaping_pointer_variables())
+        } else {
+            (HashSet::new(), HashSet::new())
+        };
 
         Translation {
             features: RefCell::new(IndexSet::new()),
@@ -741,10 +833,24 @@ impl<'c> Translation<'c> {
 
                 // prelude names
                 "drop", "Some", "None", "Ok", "Err",
+
+                // Helper types emitted by the translator
+                statics::SYNC_STATIC,
+                blocks::BLOCK_SLOT,
+                blocks::NEW_BLOCK,
+                blocks::NEW_GLOBAL_BLOCK,
+                blocks::COPY_BLOCK,
+                blocks::DISPOSE_BLOCK,
             ])),
             zero_inits: RefCell::new(IndexMap::new()),
+            closed_enums: RefCell::new(IndexMap::new()),
+            immutable_globals: RefCell::new(IndexMap::new()),
+            address_taken_functions,
+            escaping_pointer_params,
+            block_slots: RefCell::new(vec![]),
             comment_context,
             comment_store: RefCell::new(CommentStore::new()),
+            source_map: RefCell::new(SourceMapBuilder::new()),
             sectioned_static_initializers: RefCell::new(Vec::new()),
             mod_blocks: RefCell::new(IndexMap::new()),
             mod_names: RefCell::new(IndexMap::new()),
@@ -753,6 +859,15 @@ impl<'c> Translation<'c> {
         }
     }
 
+    /// Produce the comment line that ties the Rust translation of a C node back to its source
+    /// location, if source maps or source annotations were requested.
+    pub fn source_map_marker(&self, loc: Option<&SrcLoc>, kind: SourceMapKind) -> Option<String> {
+        if !self.tcfg.emit_source_map && !self.tcfg.annotate_source_locations {
+            return None;
+        }
+        loc.and_then(|loc| self.source_map.borrow_mut().marker(loc, kind))
+    }
+
     /// Called when translation makes use of a language feature that will require a feature-gate.
     pub fn use_feature(&self, feature: &'static str) {
         self.features.borrow_mut().insert(feature);
@@ -789,7 +904,8 @@ impl<'c> Translation<'c> {
             };
 
             match self.ast_context[expr_id].kind {
-                CExprKind::DeclRef(_, _, LRValue::LValue) => return true,
+                CExprKind::DeclRef(_, _, LRValue::LValue) |
+                CExprKind::Block(..) => return true,
                 CExprKind::InitList(CQualTypeId { ctype, .. }, ..) => {
                     if let CTypeKind::Vector(..) = self.ast_context.resolve_type(ctype).kind {
                         return true
@@ -805,13 +921,18 @@ impl<'c> Translation<'c> {
     /// The purpose of this function is to decide on whether or not a static initializer's
     /// translation is able to be compiled as a valid rust static initializer
     fn static_initializer_is_uncompilable(&self, expr_id: Option<CExprId>) -> bool {
-        use c_ast::UnOp::{AddressOf, Negate};
+        self.static_initializer_runtime_reason(expr_id).is_some()
+    }
+
+    /// Explain why a static initializer can't be evaluated at compile time and has to be run
+    /// from `run_static_initializers` instead, if that is the case.
+    fn static_initializer_runtime_reason(&self, expr_id: Option<CExprId>) -> Option<&'static str> {
         use c_ast::CastKind::PointerToIntegral;
         use c_ast::BinOp::{Add, Subtract, Multiply, Divide, Modulus};
 
         let expr_id = match expr_id {
             Some(expr_id) => expr_id,
-            None => return false,
+            None => return None,
         };
 
         let iter = DFExpr::new(&self.ast_context, expr_id.into());
@@ -823,18 +944,47 @@ impl<'c> Translation<'c> {
             };
 
             match self.ast_context[expr_id].kind {
-                // Technically we're being conservative here, but it's only the most
-                // contrived array indexing initializers that would be accepted
-                CExprKind::ArraySubscript(..) => return true,
-                CExprKind::Member(..) => return true,
-
-                CExprKind::Conditional(..) => return true,
-                CExprKind::Unary(typ, Negate, _, _) => {
-                    if self.ast_context.resolve_type(typ.ctype).kind.is_unsigned_integral_type() {
-                        return true;
+                // Indexing is fine as long as it is a constant index into an array we can name;
+                // going through a pointer needs `offset`, which isn't a `const fn`
+                CExprKind::ArraySubscript(_, lhs, rhs, _) => {
+                    if !self.is_constant_array_index(lhs, rhs) && !self.is_constant_array_index(rhs, lhs) {
+                        return Some("it indexes through a pointer");
                     }
-                },
-                CExprKind::ImplicitCast(_, _, PointerToIntegral, _, _) => return true,
+                }
+                CExprKind::Member(_, base, field, kind, _) => {
+                    if let MemberKind::Arrow = kind {
+                        return Some("it accesses a field through a pointer");
+                    }
+                    let is_union = self.ast_context[base].kind.get_type()
+                        .map_or(false, |ty| match self.ast_context.resolve_type(ty).kind {
+                            CTypeKind::Union(..) => true,
+                            _ => false,
+                        });
+                    if is_union {
+                        return Some("it accesses a union field");
+                    }
+                    if let CDeclKind::Field { bitfield_width: Some(_), .. } = self.ast_context[field].kind {
+                        return Some("it accesses a bit-field");
+                    }
+                }
+
+                CExprKind::Conditional(..) => return Some("it contains a conditional expression"),
+                // Statics can't refer to the interior of other statics
+                CExprKind::Unary(_, c_ast::UnOp::AddressOf, arg, _) if self.is_static_interior(arg) =>
+                    return Some("it takes the address of part of another static"),
+                CExprKind::ImplicitCast(_, expr, CastKind::FunctionToPointerDecay, _, _)
+                    if self.noreturn_decl_ref_type(expr).is_some() =>
+                    return Some("it transmutes a pointer to a `_Noreturn` function"),
+                // Blocks are allocated on the heap
+                CExprKind::Block(..) => return Some("it creates a block"),
+                CExprKind::ImplicitCast(_, _, PointerToIntegral, _, _) |
+                CExprKind::ExplicitCast(_, _, PointerToIntegral, _, _) =>
+                    return Some("it casts a pointer to an integer"),
+                // Conversions to closed enums are checked at runtime
+                CExprKind::ImplicitCast(typ, expr, _, _, _) |
+                CExprKind::ExplicitCast(typ, expr, _, _, _)
+                    if self.is_checked_enum_cast(typ.ctype, expr) =>
+                    return Some("it converts an integer to a checked enum"),
                 CExprKind::Binary(typ, op, _, _, _, _) => {
                     let problematic_op = match op {
                         Add | Subtract | Multiply | Divide | Modulus => true,
@@ -843,15 +993,21 @@ impl<'c> Translation<'c> {
 
                     if problematic_op {
                         let k = &self.ast_context.resolve_type(typ.ctype).kind;
-                        if k.is_unsigned_integral_type() || k.is_pointer() {
-                            return true;
+                        if k.is_pointer() {
+                            return Some("it does pointer arithmetic");
                         }
-                    }
-                },
-                CExprKind::Unary(_, AddressOf, expr_id, _) => {
-                    if let CExprKind::Member(_, expr_id, _, _, _) = self.ast_context[expr_id].kind {
-                        if let CExprKind::DeclRef(..) = self.ast_context[expr_id].kind {
-                            return true;
+                        // Wrapping addition, subtraction and multiplication are `const fn`s;
+                        // division and remainder aren't yet
+                        let div_op = match op {
+                            Divide => Some(BinOpKind::Div),
+                            Modulus => Some(BinOpKind::Rem),
+                            _ => None,
+                        };
+                        if let Some(div_op) = div_op {
+                            if k.is_integral_type()
+                                && self.overflow_method(true, div_op, k.is_unsigned_integral_type()).is_some() {
+                                return Some("it does wrapping division");
+                            }
                         }
                     }
                 },
@@ -867,7 +1023,7 @@ impl<'c> Translation<'c> {
                                     let field_decl = &self.ast_context[*field_id].kind;
 
                                     if let CDeclKind::Field { bitfield_width: Some(_), .. } = field_decl {
-                                        return true;
+                                        return Some("it initializes a struct with bit-fields");
                                     }
                                 }
                             }
@@ -880,10 +1036,64 @@ impl<'c> Translation<'c> {
             }
         }
 
-        false
+        None
     }
 
-    fn add_static_initializer_to_section(&self, name: &str, typ: CQualTypeId, init: &mut P<Expr>) -> Result<(), String> {
+    /// Is this a field or element of a named variable, like `x.f` or `x[1].f`?
+    fn is_static_interior(&self, expr_id: CExprId) -> bool {
+        let is_named = |expr_id: CExprId| match self.ast_context[expr_id].kind {
+            CExprKind::DeclRef(..) => true,
+            _ => self.is_static_interior(expr_id),
+        };
+        match self.ast_context[expr_id].kind {
+            CExprKind::Member(_, base, _, MemberKind::Dot, _) => is_named(base),
+            CExprKind::ArraySubscript(_, lhs, rhs, _) => [lhs, rhs].iter().any(|&e| {
+                match self.ast_context[e].kind {
+                    CExprKind::ImplicitCast(_, arr, CastKind::ArrayToPointerDecay, _, _) => is_named(arr),
+                    _ => false,
+                }
+            }),
+            _ => false,
+        }
+    }
+
+    /// Is `arr[index]` a constant index into a named array, which Rust can evaluate at compile
+    /// time?
+    fn is_constant_array_index(&self, arr: CExprId, index: CExprId) -> bool {
+        let arr = match self.ast_context[arr].kind {
+            CExprKind::ImplicitCast(_, arr, CastKind::ArrayToPointerDecay, _, _) => arr,
+            _ => return false,
+        };
+        let is_constant_array = self.ast_context[arr].kind.get_type()
+            .map_or(false, |ty| match self.ast_context.resolve_type(ty).kind {
+                CTypeKind::ConstantArray(..) => true,
+                _ => false,
+            });
+
+        let mut index = index;
+        while let CExprKind::ImplicitCast(_, e, _, _, _) = self.ast_context[index].kind {
+            index = e;
+        }
+        let is_literal_index = match self.ast_context[index].kind {
+            CExprKind::Literal(_, CLiteral::Integer(..)) => true,
+            _ => false,
+        };
+
+        is_constant_array && is_literal_index
+    }
+
+    fn add_static_initializer_to_section(
+        &self,
+        name: &str,
+        typ: CQualTypeId,
+        initializer: Option<CExprId>,
+        init: &mut P<Expr>,
+    ) -> Result<(), String> {
+        if let Some(reason) = self.static_initializer_runtime_reason(initializer) {
+            diag!(Diagnostic::StaticInitializers,
+                  "Initializing {} at startup instead of at compile time: {}", name, reason);
+        }
+
         let root_lhs_expr = mk().path_expr(vec![name]);
         let assign_expr = {
             let first_stmt = match &init.node {
@@ -940,10 +1150,8 @@ impl<'c> Translation<'c> {
 
     fn convert_decl(&self, ctx: ExprContext, toplevel: bool, decl_id: CDeclId) -> Result<ConvertedDecl, String> {
 
-        let mut s = {
-            let decl_cmt = self.comment_context.borrow_mut().remove_decl_comment(decl_id);
-            self.comment_store.borrow_mut().add_comment_lines(decl_cmt)
-        };
+        let extra_comment = self.static_assert_comment(decl_id);
+        let mut s = self.convert_decl_comments(decl_id, toplevel, extra_comment);
 
         match self.ast_context.c_decls.get(&decl_id)
             .ok_or_else(|| format!("Missing decl {:?}", decl_id))?
@@ -963,7 +1171,7 @@ impl<'c> Translation<'c> {
                 manual_alignment,
                 max_field_alignment,
                 platform_byte_size,
-                ..
+                platform_alignment,
             } => {
                 let name = self.type_converter.borrow().resolve_decl_name(decl_id).unwrap();
                 let mut has_bitfields = false;
@@ -990,7 +1198,20 @@ impl<'c> Translation<'c> {
                 }
 
                 if has_bitfields {
-                    return self.convert_bitfield_struct_decl(name, manual_alignment, platform_byte_size, s, field_info);
+                    let converted = self.convert_bitfield_struct_decl(name.clone(), manual_alignment, platform_byte_size, s, field_info)?;
+                    self.add_layout_assertions(decl_id, &name, platform_byte_size, None, vec![]);
+                    return Ok(converted);
+                }
+
+                let field_offsets = field_info.into_iter()
+                    .map(|(name, _, _, bit_offset, _)| (name, bit_offset / 8))
+                    .collect();
+                self.add_layout_assertions(decl_id, &name, platform_byte_size, Some(platform_alignment), field_offsets);
+
+                if let Some((field_id, elt)) = self.flexible_array_member(decl_id) {
+                    let field_name = self.type_converter.borrow().resolve_field_name(Some(decl_id), field_id)
+                        .ok_or_else(|| format!("Unnamed field {:?}", field_id))?;
+                    self.add_flexible_array_impl(decl_id, &name, &field_name, elt)?;
                 }
 
                 let mut reprs = vec![simple_metaitem("C")];
@@ -1018,7 +1239,7 @@ impl<'c> Translation<'c> {
                     .struct_item(name, field_entries)))
             }
 
-            CDeclKind::Union { fields: Some(ref fields), .. } => {
+            CDeclKind::Union { fields: Some(ref fields), platform_byte_size, platform_alignment, .. } => {
                 let name = self.type_converter.borrow().resolve_decl_name(decl_id).unwrap();
 
                 let mut field_syns = vec![];
@@ -1034,6 +1255,8 @@ impl<'c> Translation<'c> {
                     }
                 }
 
+                self.add_layout_assertions(decl_id, &name, platform_byte_size, Some(platform_alignment), vec![]);
+
                 Ok(if field_syns.is_empty() {
                     // Empty unions are a GNU extension, but Rust doesn't allow empty unions.
                     ConvertedDecl::Item(mk().span(s).pub_()
@@ -1050,8 +1273,17 @@ impl<'c> Translation<'c> {
 
             CDeclKind::Field { .. } => Err(format!("Field declarations should be handled inside structs/unions")),
 
+            CDeclKind::StaticAssert { assert_expr, .. } =>
+                self.convert_static_assert(ctx, s, assert_expr),
+
             CDeclKind::Enum { integral_type: Some(integral_type), .. } => {
                 let enum_name = &self.type_converter.borrow().resolve_decl_name(decl_id).expect("Enums should already be renamed");
+                match self.closed_enums.borrow().get(&decl_id) {
+                    Some(&Ok(repr)) => return self.convert_closed_enum(s, decl_id, repr),
+                    Some(&Err(ref reason)) =>
+                        diag!(Diagnostic::Enums, "Translating enum {} as an integer type alias: it {}", enum_name, reason),
+                    None => {}
+                }
                 let ty = self.convert_type(integral_type.ctype)?;
                 Ok(ConvertedDecl::Item(mk().span(s).pub_()
                     .type_item(enum_name, ty)))
@@ -1061,25 +1293,35 @@ impl<'c> Translation<'c> {
                 let name = self.renamer.borrow_mut().get(&decl_id).expect("Enum constant not named");
                 let enum_id = self.ast_context.parents[&decl_id];
                 let enum_name = self.type_converter.borrow().resolve_decl_name(enum_id).expect("Enums should already be renamed");
-                let ty = mk().path_ty(mk().path(vec![enum_name]));
-                let val = match value {
-                    ConstIntExpr::I(value) => signed_int_expr(value),
-                    ConstIntExpr::U(value) => mk().lit_expr(mk().int_lit(value as u128, LitIntType::Unsuffixed)),
+                let ty = mk().path_ty(mk().path(vec![enum_name.clone()]));
+                let val = if self.closed_enum(enum_id).is_some() {
+                    mk().path_expr(vec![enum_name, name.clone()])
+                } else {
+                    match value {
+                        ConstIntExpr::I(value) => signed_int_expr(value),
+                        ConstIntExpr::U(value) => mk().lit_expr(mk().int_lit(value as u128, LitIntType::Unsuffixed)),
+                    }
                 };
 
                 Ok(ConvertedDecl::Item(mk().span(s).pub_().const_item(name, ty, val)))
             }
 
             CDeclKind::Function { .. } if !toplevel => Err(format!("Function declarations must be top-level")),
-            CDeclKind::Function { is_extern, is_inline, typ, ref name, ref parameters, body, .. } => {
+            CDeclKind::Function { is_extern, is_inline, typ, ref name, ref parameters, body, ref attrs, .. } => {
                 let new_name = &self.renamer.borrow().get(&decl_id).expect("Functions should already be renamed");
 
+                if let Some(target) = linkage::alias_target(attrs) {
+                    return self.convert_function_alias(ctx, s, typ, new_name, name, target);
+                }
+
                 if self.import_simd_function(new_name)? {
                     return Ok(ConvertedDecl::NoItem);
                 }
 
+                // Functions that never return get the `!` return type
+                let is_noreturn = self.ast_context.is_noreturn_function(decl_id);
                 let (ret, is_var): (Option<CQualTypeId>, bool) = match self.ast_context.resolve_type(typ).kind {
-                    CTypeKind::Function(ret, _, is_var, is_noreturn, _) => (if is_noreturn { None } else { Some(ret) }, is_var),
+                    CTypeKind::Function(ret, _, is_var, _, _) => (if is_noreturn { None } else { Some(ret) }, is_var),
                     ref k => return Err(format!("Type of function {:?} was not a function type, got {:?}", decl_id, k))
                 };
 
@@ -1095,16 +1337,17 @@ impl<'c> Translation<'c> {
                 let is_main = self.ast_context.c_main == Some(decl_id);
 
                 let converted_function =
-                    self.convert_function(ctx, s, is_extern, is_inline, is_main, is_var,
+                    self.convert_function(ctx, s, decl_id, is_extern, is_inline, is_main, is_var,
                                           new_name, name, &args, ret, body);
 
                 converted_function.or_else(|e|
                     match self.tcfg.replace_unsupported_decls {
                         ReplaceMode::Extern if body.is_none() =>
-                            self.convert_function(ctx, s, is_extern, false, is_main, is_var,
+                            self.convert_function(ctx, s, decl_id, is_extern, false, is_main, is_var,
                                                   new_name, name, &args, ret, None),
                         _ => Err(e),
                     })
+                    .map(|converted| self.add_link_attrs(decl_id, new_name, attrs, converted))
             },
 
             CDeclKind::Typedef { ref typ, .. } => {
@@ -1119,8 +1362,14 @@ impl<'c> Translation<'c> {
             },
 
             // Extern variable without intializer (definition elsewhere)
-            CDeclKind::Variable { is_extern: true, is_static, is_defn: false, ref ident, initializer, typ } => {
+            CDeclKind::Variable { is_extern: true, is_static, is_defn: false, ref ident, initializer, typ, ref attrs } => {
                 assert!(is_static, "An extern variable must be static");
+
+                if let Some(target) = linkage::alias_target(attrs) {
+                    return Err(format!("Can't translate {} as an alias of {}: Rust can't give a \
+                                        second symbol name to a static", ident, target))
+                }
+
                 assert!(initializer.is_none(), "An extern variable that isn't a definition can't have an initializer");
 
                 let new_name = self.renamer.borrow().get(&decl_id).expect("Variables should already be renamed");
@@ -1137,11 +1386,11 @@ impl<'c> Translation<'c> {
                     .vis(visibility)
                     .static_foreign_item(&new_name, ty);
 
-                Ok(ConvertedDecl::ForeignItem(extern_item))
+                Ok(self.add_link_attrs(decl_id, &new_name, attrs, ConvertedDecl::ForeignItem(extern_item)))
             }
 
             // Extern variable with initializer (definition here)
-            CDeclKind::Variable { is_extern: true, is_static, ref ident, initializer, typ, .. } => {
+            CDeclKind::Variable { is_extern: true, is_static, ref ident, initializer, typ, ref attrs, .. } => {
                 assert!(is_static, "An extern variable must be static");
 
                 let new_name = &self.renamer.borrow().get(&decl_id).expect("Variables should already be renamed");
@@ -1162,14 +1411,15 @@ impl<'c> Translation<'c> {
                     // REVIEW: We might want to add the comment to the original span comments
                     s = self.comment_store.borrow_mut().add_comment_lines(vec![comment]);
 
-                    self.add_static_initializer_to_section(new_name, typ, &mut init)?;
+                    self.add_static_initializer_to_section(new_name, typ, initializer, &mut init)?;
 
-                    Ok(ConvertedDecl::Item(mk_linkage(false, new_name, ident)
-                    .span(s)
-                    .pub_()
-                    .abi("C")
-                    .mutbl()
-                    .static_item(new_name, ty, init)))
+                    let item = mk_linkage(false, new_name, ident)
+                        .span(s)
+                        .pub_()
+                        .abi("C")
+                        .mutbl()
+                        .static_item(new_name, ty, init);
+                    Ok(self.add_link_attrs(decl_id, new_name, attrs, ConvertedDecl::Item(item)))
                 } else {
                     let (ty, _, init) = self.convert_variable(ctx.set_static(is_static), initializer, typ)?;
 
@@ -1183,19 +1433,23 @@ impl<'c> Translation<'c> {
                         init?.val
                     };
 
-                    // Force mutability due to the potential for raw pointers occuring in the type
-                    // and because we're assigning to these variables in the external initializer
-                    Ok(ConvertedDecl::Item(mk_linkage(false, new_name, ident)
+                    let item = mk_linkage(false, new_name, ident)
                         .span(s)
                         .pub_()
-                        .abi("C")
-                        .mutbl()
-                        .static_item(new_name, ty, init)))
+                        .abi("C");
+
+                    // Globals that are never written don't need to be mutable; the rest might be
+                    // assigned to through pointers we can't track
+                    let item = match self.immutable_global(decl_id) {
+                        Some(repr) => self.immutable_global_item(item, repr, new_name, ty, init),
+                        None => item.mutbl().static_item(new_name, ty, init),
+                    };
+                    Ok(self.add_link_attrs(decl_id, new_name, attrs, ConvertedDecl::Item(item)))
                 }
             }
 
             // Static variable (definition here)
-            CDeclKind::Variable { is_static: true, initializer, typ, .. } => {
+            CDeclKind::Variable { is_static: true, initializer, typ, ref attrs, .. } => {
                 let new_name = &self.renamer.borrow().get(&decl_id).expect("Variables should already be renamed");
                 let (ty, _, init) = self.convert_variable(ctx.static_(), initializer, typ)?;
 
@@ -1216,12 +1470,17 @@ impl<'c> Translation<'c> {
                     // REVIEW: We might want to add the comment to the original span comments
                     s = self.comment_store.borrow_mut().add_comment_lines(vec![comment]);
 
-                    self.add_static_initializer_to_section(new_name, typ, &mut init)?;
+                    self.add_static_initializer_to_section(new_name, typ, initializer, &mut init)?;
                 }
 
                 // Force mutability due to the potential for raw pointers occurring in the type
-                // and because we're assigning to these variables in the external initializer
-                Ok(ConvertedDecl::Item(mk().span(s).mutbl().static_item(new_name, ty, init)))
+                // and because we're assigning to these variables in the external initializer,
+                // unless we know the global is never written
+                let item = match self.immutable_global(decl_id) {
+                    Some(repr) => self.immutable_global_item(mk().span(s), repr, new_name, ty, init),
+                    None => mk().span(s).mutbl().static_item(new_name, ty, init),
+                };
+                Ok(self.add_link_attrs(decl_id, new_name, attrs, ConvertedDecl::Item(item)))
             }
 
             CDeclKind::Variable { .. } => Err(format!("This should be handled in 'convert_decl_stmt'")),
@@ -1234,6 +1493,7 @@ impl<'c> Translation<'c> {
         &self,
         mut ctx: ExprContext,
         span: Span,
+        decl_id: CDeclId,
         is_extern: bool,
         is_inline: bool,
         is_main: bool,
@@ -1261,22 +1521,38 @@ impl<'c> Translation<'c> {
 
         self.with_scope(|| {
             let mut args: Vec<Arg> = vec![];
+            let mut reference_shadows: Vec<Stmt> = vec![];
+            let fn_decl_id = decl_id;
 
-            for &(decl_id, ref var, typ) in arguments {
+            for (index, &(decl_id, ref var, typ)) in arguments.iter().enumerate() {
 
 
-                let (ty, mutbl, _) = self.convert_variable(ctx, None, typ)?;
+                let (mut ty, mut mutbl, _) = self.convert_variable(ctx, None, typ)?;
+                let reference_param = self.reference_param(fn_decl_id, index);
+                if let Some(param) = reference_param {
+                    ty = self.reference_param_ty(param, typ.ctype)?;
+                }
 
                 let pat = if var.is_empty() {
                     mk().wild_pat()
                 } else {
-                    // extern function declarations don't support/require mut patterns
-                    let mutbl = if body.is_none() { Mutability::Immutable } else { mutbl };
-
                     let new_var = self.renamer.borrow_mut()
                         .insert(decl_id, var.as_str())
                         .expect(&format!("Failed to insert argument '{}' while converting '{}'", var, name));
 
+                    // The body sees reference parameters through a raw pointer of the same name
+                    if let Some(param) = reference_param {
+                        if body.is_some() {
+                            reference_shadows.push(
+                                self.reference_param_shadow(param, &new_var, mutbl, typ.ctype)?
+                            );
+                        }
+                        mutbl = Mutability::Immutable;
+                    }
+
+                    // extern function declarations don't support/require mut patterns
+                    let mutbl = if body.is_none() { Mutability::Immutable } else { mutbl };
+
                     mk().set_mutbl(mutbl).ident_pat(new_var)
                 };
 
@@ -1314,10 +1590,10 @@ impl<'c> Translation<'c> {
                             cfg::ImplicitReturnType::NoImplicitReturnType
                         }
                     }
-                    _ => cfg::ImplicitReturnType::Void,
+                    None => cfg::ImplicitReturnType::NoReturn,
                 };
 
-                let mut body_stmts = vec![];
+                let mut body_stmts = reference_shadows;
                 for &(_, _, typ) in arguments {
                     body_stmts.append(&mut self.compute_variable_array_sizes(ctx, typ.ctype)?);
                 }
@@ -1327,7 +1603,14 @@ impl<'c> Translation<'c> {
                     _ => panic!("function body expects to be a compound statement"),
                 };
                 body_stmts.append(&mut self.convert_function_body(ctx, name, body_ids, ret)?);
-                let block = stmts_block(body_stmts);
+                let mut block = stmts_block(body_stmts);
+
+                // Comments between the last statement and the closing brace of the body
+                let body_end = self.comment_context.borrow_mut().remove_block_end_comment(body);
+                if !body_end.is_empty() {
+                    let span = self.comment_store.borrow_mut().add_block_end_comment_lines(body_end);
+                    block = block.map(|b| Block { span, ..b });
+                }
 
                 // Only add linkage attributes if the function is `extern`
                 let mk_ = if is_main {
@@ -1367,12 +1650,16 @@ impl<'c> Translation<'c> {
     pub fn convert_cfg(
         &self,
         name: &str,
-        graph: cfg::Cfg<cfg::Label, cfg::StmtOrDecl>,
+        stmt_ids: &[CStmtId],
+        mut graph: cfg::Cfg<cfg::Label, cfg::StmtOrDecl>,
         store: cfg::DeclStmtStore,
         live_in: IndexSet<CDeclId>,
         cut_out_trailing_ret: bool,
     ) -> Result<Vec<Stmt>, String> {
 
+        let node_splitting = self.tcfg.node_splitting_budget
+            .map(|budget| graph.split_irreducible_nodes(budget));
+
         if self.tcfg.dump_function_cfgs {
             graph
                 .dump_dot_graph(
@@ -1387,6 +1674,11 @@ impl<'c> Translation<'c> {
             graph.dump_json_graph(&store, format!("{}_{}.json", "cfg", name))
                 .expect("Failed to write CFG .json file");
         }
+        let mut html = self.tcfg.dump_cfg_html.as_ref().map(|_| {
+            let mut html = cfg::html::CfgHtml::new(&self.ast_context, name, stmt_ids);
+            html.add_cfg(&graph, &self.ast_context, &store, self.tcfg.dump_cfg_liveness);
+            html
+        });
 
         let (lifted_stmts, relooped) = cfg::relooper::reloop(
             graph,
@@ -1404,10 +1696,37 @@ impl<'c> Translation<'c> {
             }
         }
 
+        if let Some(ref mut html) = html {
+            html.add_structures(&relooped);
+        }
+
         let current_block_ident = self.renamer.borrow_mut().pick_name("current_block");
         let current_block = mk().ident_expr(&current_block_ident);
         let mut stmts: Vec<Stmt> = lifted_stmts;
-        if cfg::structures::has_multiple(&relooped) {
+        let uses_current_block = cfg::structures::has_multiple(&relooped);
+        if cfg::structures::has_early_exit_block(&relooped) {
+            self.use_feature("label_break_value");
+        }
+
+        if let Some(report) = node_splitting {
+            if report.was_irreducible {
+                let outcome = if uses_current_block {
+                    "still dispatches on `current_block`"
+                } else {
+                    "avoids `current_block` dispatch"
+                };
+                let budget_note = if report.is_reducible {
+                    String::new()
+                } else {
+                    format!(" (code-growth budget of {}% exhausted)", self.tcfg.node_splitting_budget.unwrap())
+                };
+                diag!(Diagnostic::NodeSplitting,
+                      "`{}`: split {} blocks ({} statements copied), {}{}",
+                      name, report.split_blocks, report.copied_stmts, outcome, budget_note);
+            }
+        }
+
+        if uses_current_block {
 
             if self.tcfg.fail_on_multiple {
                 panic!("Uses of `current_block' are illegal with `--fail-on-multiple'.");
@@ -1432,6 +1751,11 @@ impl<'c> Translation<'c> {
             self.tcfg.debug_relooper_labels,
             cut_out_trailing_ret,
         )?);
+
+        if let (Some(mut html), Some(dir)) = (html, self.tcfg.dump_cfg_html.as_ref()) {
+            html.add_rust(&stmts);
+            html.write(dir).expect("Failed to write CFG .html file");
+        }
         Ok(stmts)
     }
 
@@ -1444,10 +1768,16 @@ impl<'c> Translation<'c> {
     ) -> Result<Vec<Stmt>, String> {
 
         // Function body scope
-        self.with_scope(|| {
+        self.block_slots.borrow_mut().push(vec![]);
+        let body = self.with_scope(|| {
             let (graph, store) = cfg::Cfg::from_stmts(self, ctx, body_ids, ret)?;
-            self.convert_cfg(name, graph, store, IndexSet::new(), true)
-        })
+            self.convert_cfg(name, body_ids, graph, store, IndexSet::new(), true)
+        });
+        let slots = self.block_slots.borrow_mut().pop().expect("Missing block slots of function body");
+
+        let mut stmts: Vec<Stmt> = slots.iter().map(|slot| blocks::block_slot_decl(slot)).collect();
+        stmts.extend(body?);
+        Ok(stmts)
     }
 
     /// Convert a C expression to a rust boolean expression
@@ -1568,7 +1898,7 @@ impl<'c> Translation<'c> {
                     let init = mk().unsafe_().block(init.stmts);
                     let mut init = mk().block_expr(init);
 
-                    self.add_static_initializer_to_section(&ident2, typ, &mut init)?;
+                    self.add_static_initializer_to_section(&ident2, typ, initializer, &mut init)?;
                     self.item_store.borrow_mut().items.push(static_item);
 
                     return Ok(cfg::DeclStmtInfo::empty());
@@ -1578,7 +1908,11 @@ impl<'c> Translation<'c> {
         };
 
         match self.ast_context.index(decl_id).kind {
-            CDeclKind::Variable { is_static, is_extern, is_defn, ref ident, initializer, typ } if !is_static && !is_extern => {
+            CDeclKind::Variable { is_static: false, is_extern: false, ref ident, initializer, typ, .. }
+                if self.is_block_byref(decl_id) =>
+                self.convert_block_byref_decl(ctx, decl_id, ident, initializer, typ),
+
+            CDeclKind::Variable { is_static, is_extern, is_defn, ref ident, initializer, typ, .. } if !is_static && !is_extern => {
                 assert!(is_defn, "Only local variable definitions should be extracted");
 
                 let has_self_reference =
@@ -1648,6 +1982,12 @@ impl<'c> Translation<'c> {
                 }
             }
 
+            // The lengths in a variably modified typedef are evaluated where the typedef is
+            CDeclKind::Typedef { typ, .. } if self.is_variable_array(typ.ctype) => {
+                let stmts = self.compute_variable_array_sizes(ctx, typ.ctype)?;
+                Ok(cfg::DeclStmtInfo::new(vec![], stmts.clone(), stmts))
+            }
+
             ref decl => {
                 let inserted = if let Some(ident) = decl.get_name() {
                     self.renamer.borrow_mut()
@@ -1775,12 +2115,15 @@ impl<'c> Translation<'c> {
             None => self.implicit_default_expr(typ.ctype, ctx.is_static).map(WithStmts::new),
         };
 
-        // Variable declarations for variable-length arrays use the type of a pointer to the
-        // underlying array element
-        let ty = if let CTypeKind::VariableArray(mut elt, _) = self.ast_context.resolve_type(typ.ctype).kind {
-            elt = self.variable_array_base_type(elt);
+        // Variable declarations for variable-length arrays use a flat buffer of the underlying
+        // array element
+        let ty = if self.is_variable_array(typ.ctype) {
+            let elt = self.variable_array_base_type(typ.ctype);
             let ty = self.convert_type(elt)?;
-            mk().path_ty(vec![mk().path_segment_with_args("Vec", mk().angle_bracketed_args(vec![ty]))])
+            match self.variable_array_capacity(typ.ctype) {
+                Some(capacity) => mk().array_ty(ty, mk().lit_expr(mk().int_lit(capacity as u128, LitIntType::Unsuffixed))),
+                None => mk().path_ty(vec![mk().path_segment_with_args("Vec", mk().angle_bracketed_args(vec![ty]))]),
+            }
         } else {
             self.convert_type(typ.ctype)?
         };
@@ -1794,6 +2137,20 @@ impl<'c> Translation<'c> {
         self.type_converter.borrow_mut().convert(&self.ast_context, type_id)
     }
 
+    /// The function type of a reference to a function that is declared `_Noreturn` although its
+    /// type doesn't say so. Only its Rust item returns `!`.
+    fn noreturn_decl_ref_type(&self, expr_id: CExprId) -> Option<CTypeId> {
+        match self.ast_context[expr_id].kind {
+            CExprKind::DeclRef(_, decl_id, _) => match self.ast_context[decl_id].kind {
+                CDeclKind::Function { typ, .. }
+                    if self.ast_context.is_noreturn_function(decl_id)
+                        && !self.ast_context.is_noreturn_function_type(typ) => Some(typ),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
     /// Construct an expression for a NULL at any type, including forward declarations,
     /// function pointers, and normal pointers.
     fn null_ptr(&self, type_id: CTypeId, is_static: bool) -> Result<P<Expr>, String> {
@@ -1802,12 +2159,14 @@ impl<'c> Translation<'c> {
             return Ok(mk().path_expr(vec!["None"]))
         }
 
+        let ty = self.convert_type(type_id)?;
+        let mut zero = mk().lit_expr(mk().int_lit(0, LitIntType::Unsuffixed));
         let pointee = match self.ast_context.resolve_type(type_id).kind {
             CTypeKind::Pointer(pointee) => pointee,
+            // Block pointers always point to a mutable `BlockLiteral`
+            CTypeKind::BlockPointer(_) => return Ok(mk().cast_expr(zero, ty)),
             _ => return Err(format!("null_ptr requires a pointer")),
         };
-        let ty = self.convert_type(type_id)?;
-        let mut zero = mk().lit_expr(mk().int_lit(0, LitIntType::Unsuffixed));
         if is_static && !pointee.qualifiers.is_const {
             let mut qtype = pointee;
             qtype.qualifiers.is_const = true;
@@ -1898,16 +2257,24 @@ impl<'c> Translation<'c> {
                 };
                 Some(val)
             }
+            CTypeKind::ConstantArray(elts, count) => {
+                let esize = self.compute_size_of_expr(elts)?;
+                let csize = mk().lit_expr(mk().int_lit(count as u128, LitIntType::Unsuffixed));
+                Some(mk().binary_expr(BinOpKind::Mul, csize, esize))
+            }
             _ => None,
         }
     }
 
     /// Variable element arrays are represented by a flat array of non-variable-length array
     /// elements. This function traverses potentially multiple levels of variable-length array
-    /// to find the underlying element type.
+    /// (and fixed-size arrays of them) to find the underlying element type.
     fn variable_array_base_type(&self, mut elt: CTypeId) -> CTypeId {
-        while let CTypeKind::VariableArray(elt_, _) = self.ast_context.resolve_type(elt).kind {
-            elt = elt_;
+        while self.is_variable_array(elt) {
+            elt = match self.ast_context.resolve_type(elt).kind {
+                CTypeKind::VariableArray(elt_, _) | CTypeKind::ConstantArray(elt_, _) => elt_,
+                _ => break,
+            };
         }
         elt
     }
@@ -1925,6 +2292,11 @@ impl<'c> Translation<'c> {
                 CTypeKind::VariableArray(elt, Some(expr_id)) => {
                     type_id = elt;
 
+                    // Lengths of array types named by a typedef are saved where the typedef is
+                    if self.renamer.borrow().get(&CDeclId(expr_id.0)).is_some() {
+                        continue;
+                    }
+
                     // Convert this expression
                     let mut expr = self.convert_expr(ctx.used(), expr_id)?;
                     stmts.append(&mut expr.stmts);
@@ -1951,19 +2323,32 @@ impl<'c> Translation<'c> {
             let len = len.expect("Sizeof a VLA type with count expression omitted");
 
             let mut elts = self.compute_size_of_type(ctx, elts)?;
-            let mut len = self.convert_expr(ctx.used().not_static(), len)?;
+
+            // The length of an array that was already declared is fixed when it is declared,
+            // even if the length expression has changed value since
+            let opt_saved_len = self.renamer.borrow().get(&CDeclId(len.0));
+            let mut len = match opt_saved_len {
+                Some(name) => WithStmts::new(mk().path_expr(vec![name])),
+                None => self.convert_expr(ctx.used().not_static(), len)?
+                    .map(|len| cast_int(len, "usize")),
+            };
 
             let mut stmts = elts.stmts;
             stmts.append(&mut len.stmts);
 
-            let lhs = elts.val;
-            let rhs = cast_int(len.val, "usize");
-
-            let val = mk().binary_expr(BinOpKind::Mul, lhs, rhs);
+            let val = mk().binary_expr(BinOpKind::Mul, elts.val, len.val);
 
             return Ok(WithStmts { stmts, val })
         }
 
+        if let CTypeKind::ConstantArray(elts, count) = self.ast_context.resolve_type(type_id).kind {
+            if self.is_variable_array(elts) {
+                let count = mk().lit_expr(mk().int_lit(count as u128, "usize"));
+                return Ok(self.compute_size_of_type(ctx, elts)?
+                    .map(|elts| mk().binary_expr(BinOpKind::Mul, elts, count)))
+            }
+        }
+
         let ty = self.convert_type(type_id)?;
         let name = "size_of";
         let params = mk().angle_bracketed_args(vec![ty]);
@@ -2004,7 +2389,35 @@ impl<'c> Translation<'c> {
     /// In the case that `use_` is unused, all side-effecting components will be in the
     /// `stmts` field of the output and it is expected that the `val` field of the output will be
     /// ignored.
-    pub fn convert_expr(&self, mut ctx: ExprContext, expr_id: CExprId) -> Result<WithStmts<P<Expr>>, String> {
+    pub fn convert_expr(&self, ctx: ExprContext, expr_id: CExprId) -> Result<WithStmts<P<Expr>>, String> {
+        let comments = self.comment_context.borrow_mut().remove_expr_comment(expr_id);
+        let mut converted = self.convert_expr_kind(ctx, expr_id)?;
+        if comments.is_empty() {
+            return Ok(converted)
+        }
+        let span = self.comment_store.borrow_mut().add_comment_lines(comments);
+
+        // The value of an unused expression is thrown away, so its comments go on the first
+        // statement it turned into
+        if ctx.is_unused() {
+            if let Some(stmt) = converted.stmts.first_mut() {
+                if stmt.span == DUMMY_SP {
+                    stmt.span = span;
+                    return Ok(converted)
+                }
+            }
+        }
+
+        // Comments inside of expressions are printed right before the translated expression. If
+        // that already has comments of its own, it gets wrapped in parentheses to hold ours.
+        Ok(converted.map(|e| if e.span == DUMMY_SP {
+            e.map(|e| Expr { span, ..e })
+        } else {
+            mk().span(span).paren_expr(e)
+        }))
+    }
+
+    fn convert_expr_kind(&self, mut ctx: ExprContext, expr_id: CExprId) -> Result<WithStmts<P<Expr>>, String> {
         match self.ast_context[expr_id].kind {
             CExprKind::DesignatedInitExpr(..) => Err(format!("Unexpected designated init expr")),
             CExprKind::BadExpr => Err(format!("convert_expr: expression kind not supported")),
@@ -2012,28 +2425,16 @@ impl<'c> Translation<'c> {
                 self.convert_shuffle_vector(ctx, child_expr_ids),
             CExprKind::ConvertVector(..) => Err(format!("convert vector not supported")),
 
-            CExprKind::UnaryType(_ty, kind, opt_expr, arg_ty) => {
+            CExprKind::UnaryType(ty, kind, _, arg_ty) => {
                 let result = match kind {
-                    UnTypeOp::SizeOf =>
-                        match opt_expr {
-                            None => self.compute_size_of_type(ctx, arg_ty.ctype)?,
-                            Some(_) =>
-                                {
-                                    let inner = self.variable_array_base_type(arg_ty.ctype);
-                                    let inner_size = self.compute_size_of_type(ctx, inner)?;
-
-                                    if let Some(sz) = self.compute_size_of_expr(arg_ty.ctype) {
-                                        inner_size.map(|x| mk().binary_expr(BinOpKind::Mul, sz, x))
-                                    } else {
-                                        // Otherwise, use the pointer and make a deref of a pointer offset expression
-                                        inner_size
-                                    }
-                                },
-                        }
+                    // The size of a variable-length array operand comes from its type, using the
+                    // lengths saved when the array was declared
+                    UnTypeOp::SizeOf => self.compute_size_of_type(ctx, arg_ty.ctype)?,
                     UnTypeOp::AlignOf => self.compute_align_of_type(arg_ty.ctype)?,
                 };
 
-                Ok(result.map(|x| mk().cast_expr(x, mk().path_ty(vec!["libc","c_ulong"]))))
+                let size_ty = self.convert_type(self.ast_context.resolve_type_id(ty.ctype))?;
+                Ok(result.map(|x| mk().cast_expr(x, size_ty)))
             }
 
             CExprKind::DeclRef(qual_ty, decl_id, lrvalue) => {
@@ -2047,7 +2448,15 @@ impl<'c> Translation<'c> {
                     .get(&decl_id)
                     .ok_or_else(|| format!("name not declared: '{}'", varname))?;
 
-                let mut val = mk().path_expr(vec![rustname]);
+                let mut val = if self.is_block_byref(decl_id) {
+                    blocks::block_byref_place(&rustname)
+                } else {
+                    mk().path_expr(vec![rustname])
+                };
+
+                if self.immutable_global(decl_id) == Some(statics::ImmutableGlobal::SyncStatic) {
+                    val = mk().field_expr(val, "0");
+                }
 
                 // If the variable is volatile and used as something that isn't an LValue, this
                 // constitutes a volatile read.
@@ -2063,7 +2472,7 @@ impl<'c> Translation<'c> {
                     val = mk().cast_expr(val, ty);
                 }
 
-                if let CTypeKind::VariableArray(..) = self.ast_context.resolve_type(qual_ty.ctype).kind {
+                if self.is_variable_array(qual_ty.ctype) {
                     val = mk().method_call_expr(val, "as_mut_ptr", vec![] as Vec<P<Expr>>);
                 }
 
@@ -2134,6 +2543,10 @@ impl<'c> Translation<'c> {
                 self.convert_binary_expr(ctx, type_id, op, lhs, rhs, opt_lhs_type_id, opt_res_type_id),
 
             CExprKind::ArraySubscript(_, ref lhs, ref rhs, _) => {
+                if let Some(val) = self.convert_variable_array_subscript(ctx, expr_id)? {
+                    return Ok(val)
+                }
+
                 let lhs_node = &self.ast_context.index(*lhs).kind;
                 let rhs_node = &self.ast_context.index(*rhs).kind;
 
@@ -2169,6 +2582,7 @@ impl<'c> Translation<'c> {
 
                     let t = self.ast_context[arr].kind.get_type().ok_or_else(|| format!("bad arr type"))?;
                     let var_elt_type_id = match self.ast_context.resolve_type(t).kind {
+                        CTypeKind::ConstantArray(elt, _) if self.is_variable_array(elt) => Some(elt),
                         CTypeKind::ConstantArray(..) => None,
                         CTypeKind::IncompleteArray(..) => None,
                         CTypeKind::VariableArray(elt, _) => Some(elt),
@@ -2213,6 +2627,31 @@ impl<'c> Translation<'c> {
             }
 
             CExprKind::Call(_, func, ref args) => {
+                let calls_block = self.ast_context[func].kind.get_type()
+                    .map_or(false, |ty| match self.ast_context.resolve_type(ty).kind {
+                        CTypeKind::BlockPointer(_) => true,
+                        _ => false,
+                    });
+                if calls_block {
+                    return self.convert_block_call(ctx, func, args);
+                }
+
+                // Direct calls may pass some arguments as references
+                let callee_decl = match self.ast_context[func].kind {
+                    CExprKind::ImplicitCast(_, fexp, CastKind::FunctionToPointerDecay, _, _) =>
+                        match self.ast_context[fexp].kind {
+                            CExprKind::DeclRef(_, decl_id, _) => Some(decl_id),
+                            _ => None,
+                        },
+                    _ => None,
+                };
+
+                if let (Some(callee), false) = (callee_decl, ctx.is_unused()) {
+                    if let Some(alloc) = self.convert_flexible_array_alloc(ctx, callee, args)? {
+                        return Ok(alloc)
+                    }
+                }
+
                 let is_variadic = self.fn_expr_is_variadic(func);
                 let WithStmts { mut stmts, val: func } = match self.ast_context.index(func).kind {
                     CExprKind::ImplicitCast(_, fexp, CastKind::FunctionToPointerDecay, _, _) =>
@@ -2228,10 +2667,13 @@ impl<'c> Translation<'c> {
                 let mut args_new: Vec<P<Expr>> = vec![];
                 ctx.decay_ref = DecayRef::from(is_variadic);
 
-                for arg in args {
+                for (index, arg) in args.iter().enumerate() {
                     // We want to decay refs only when function is variadic
-                    let WithStmts { stmts: ss, val } = self.convert_expr(ctx.used(), *arg)?;
+                    let WithStmts { stmts: ss, mut val } = self.convert_expr(ctx.used(), *arg)?;
                     stmts.extend(ss);
+                    if let Some(param) = callee_decl.and_then(|f| self.reference_param(f, index)) {
+                        val = self.convert_reference_arg(param, val);
+                    }
                     args_new.push(val);
                 }
 
@@ -2299,6 +2741,9 @@ impl<'c> Translation<'c> {
 
             CExprKind::VAArg(ty, val_id) =>
                 self.convert_vaarg(ctx, ty, val_id),
+
+            CExprKind::Block(ty, ref params, body) =>
+                self.convert_block_literal(ctx, ty, params, body),
         }
     }
 
@@ -2468,8 +2913,19 @@ impl<'c> Translation<'c> {
 
             CastKind::LValueToRValue | CastKind::NoOp | CastKind::ToVoid | CastKind::ConstCast => Ok(val),
 
-            CastKind::FunctionToPointerDecay =>
-                Ok(val.map(|x| mk().call_expr(mk().ident_expr("Some"), vec![x]))),
+            CastKind::FunctionToPointerDecay => {
+                // `_Noreturn` functions diverge, but pointers to them have the C function type
+                if let Some(typ) = self.noreturn_decl_ref_type(expr) {
+                    let source_ty = self.type_converter.borrow_mut()
+                        .convert_function_type(&self.ast_context, typ, true)?;
+                    let target_ty = self.convert_type(typ)?;
+                    return Ok(val.map(|x| mk().call_expr(
+                        mk().ident_expr("Some"),
+                        vec![transmute_expr(source_ty, target_ty, x)],
+                    )))
+                }
+                Ok(val.map(|x| mk().call_expr(mk().ident_expr("Some"), vec![x])))
+            }
 
             CastKind::BuiltinFnToFnPtr =>
                 Ok(val.map(|x| mk().call_expr(mk().ident_expr("Some"), vec![x]))),
@@ -2508,7 +2964,7 @@ impl<'c> Translation<'c> {
                     _ => {
                         // Variable length arrays are already represented as pointers.
                         let source_ty = self.ast_context[expr].kind.get_type().ok_or_else(|| format!("bad variable array source type"))?;
-                        if let CTypeKind::VariableArray(..) = self.ast_context.resolve_type(source_ty).kind {
+                        if self.is_variable_array(source_ty) {
                             Ok(val)
                         } else {
                             let method = if is_const || ctx.is_static {
@@ -2628,6 +3084,10 @@ impl<'c> Translation<'c> {
             _ => {},
         }
 
+        if let Some(repr) = self.closed_enum(enum_decl) {
+            return val.map(|x| self.closed_enum_from_int(enum_decl, repr, x))
+        }
+
         val.map(|x| mk().cast_expr(x, target_ty))
     }
 
@@ -2635,13 +3095,31 @@ impl<'c> Translation<'c> {
         let resolved_ty_id = self.ast_context.resolve_type_id(ty_id);
         let resolved_ty = &self.ast_context.index(resolved_ty_id).kind;
 
-        if resolved_ty.is_bool() {
+        if self.is_variable_array(resolved_ty_id) {
+
+            // Variable length arrays unnested and implemented as a flat array of the underlying
+            // element type.
+
+            // Find base element type of potentially nested arrays
+            let inner = self.variable_array_base_type(resolved_ty_id);
+            let val = self.implicit_default_expr(inner, is_static)?;
+            match self.variable_array_capacity(resolved_ty_id) {
+                Some(capacity) => {
+                    let capacity = mk().lit_expr(mk().int_lit(capacity as u128, LitIntType::Unsuffixed));
+                    Ok(mk().repeat_expr(val, capacity))
+                }
+                None => {
+                    let count = self.compute_size_of_expr(ty_id).unwrap();
+                    Ok(vec_expr(val, count))
+                }
+            }
+        } else if resolved_ty.is_bool() {
             Ok(mk().lit_expr(mk().bool_lit(false)))
         } else if resolved_ty.is_integral_type() {
             Ok(mk().lit_expr(mk().int_lit(0, LitIntType::Unsuffixed)))
         } else if resolved_ty.is_floating_type() {
             Ok(mk().lit_expr(mk().float_unsuffixed_lit("0.")))
-        } else if let &CTypeKind::Pointer(_) = resolved_ty {
+        } else if let &CTypeKind::Pointer(_) | &CTypeKind::BlockPointer(_) = resolved_ty {
             self.null_ptr(resolved_ty_id, is_static)
         } else if let &CTypeKind::ConstantArray(elt, sz) = resolved_ty {
             let sz = mk().lit_expr(mk().int_lit(sz as u128, LitIntType::Unsuffixed));
@@ -2651,16 +3129,6 @@ impl<'c> Translation<'c> {
             Ok(mk().array_expr(vec![] as Vec<P<Expr>>))
         } else if let Some(decl_id) = resolved_ty.as_underlying_decl() {
             self.zero_initializer(decl_id, ty_id, is_static)
-        } else if let &CTypeKind::VariableArray(elt, _) = resolved_ty {
-
-            // Variable length arrays unnested and implemented as a flat array of the underlying
-            // element type.
-
-            // Find base element type of potentially nested arrays
-            let inner = self.variable_array_base_type(elt);
-            let count = self.compute_size_of_expr(ty_id).unwrap();
-            let val = self.implicit_default_expr(inner, is_static)?;
-            Ok(vec_expr(val, count))
         } else if let &CTypeKind::Vector(CQualTypeId { ctype, .. }, len) = resolved_ty {
             self.implicit_vector_default(ctype, len)
         } else {
@@ -2754,6 +3222,12 @@ impl<'c> Translation<'c> {
     fn match_bool(&self, target: bool, ty_id: CTypeId, val: P<Expr>) -> P<Expr> {
         let ty = &self.ast_context.resolve_type(ty_id).kind;
 
+        // Rust enums have to be turned back into integers before comparing them against zero
+        let val = match self.closed_enum_type(ty_id) {
+            Some(repr) => mk().cast_expr(val, repr.ty()),
+            None => val,
+        };
+
         if self.ast_context.is_function_pointer(ty_id) {
             if target {
                 mk().method_call_expr(val, "is_some", vec![] as Vec<P<Expr>>)
@@ -2974,7 +3448,15 @@ impl<'c> Translation<'c> {
                 // rather than translating the original definition
             },
             CDeclKind::Variable { is_static: true, is_extern: false, .. } => {},
+            CDeclKind::StaticAssert { .. } => {},
             ref e => unimplemented!("{:?}", e),
         }
+
+        // Pointer-containing statics are wrapped in `SyncStatic`, which lives in the parent module
+        if self.immutable_global(decl_id) == Some(statics::ImmutableGlobal::SyncStatic) {
+            item_store.uses
+                .get_mut(vec!["super".into()])
+                .insert(statics::SYNC_STATIC);
+        }
     }
 }
@@ -2,6 +2,8 @@
 
 use super::*;
 
+use std::str::FromStr;
+
 fn neg_expr(arg: P<Expr>) -> P<Expr> {
     mk().unary_expr(ast::UnOp::Neg, arg)
 }
@@ -10,6 +12,73 @@ fn wrapping_neg_expr(arg: P<Expr>) -> P<Expr> {
     mk().method_call_expr(arg, "wrapping_neg", vec![] as Vec<P<Expr>>)
 }
 
+/// Panic with the same message as Rust's own overflow checks if a `checked_*` operation failed
+fn expect_no_overflow(checked: P<Expr>, op: &str) -> P<Expr> {
+    let msg = mk().lit_expr(mk().str_lit(&format!("attempt to {} with overflow", op)));
+    mk().method_call_expr(checked, "expect", vec![msg])
+}
+
+/// `panic!("...")`, for the division by zero checks that come with `checked_div` and
+/// `checked_rem`
+fn panic_expr(msg: &str) -> P<Expr> {
+    let msg = vec![
+        Token::interpolated(Nonterminal::NtExpr(mk().lit_expr(mk().str_lit(msg)))),
+    ].into_iter().collect::<TokenStream>();
+    mk().mac_expr(mk().mac(vec!["panic"], msg, MacDelimiter::Parenthesis))
+}
+
+/// How integer arithmetic that might overflow is translated
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum OverflowMode {
+    /// Unsigned arithmetic always uses `wrapping_*` methods, signed arithmetic uses plain
+    /// operators, which panic on overflow in debug builds
+    DebugAssert,
+    /// All integer arithmetic uses `wrapping_*` methods and never panics
+    Wrapping,
+    /// Signed arithmetic uses `checked_*` methods and panics on overflow, which is undefined
+    /// behavior in C. Unsigned arithmetic only wraps explicitly where it can overflow.
+    Checked,
+    /// All integer arithmetic uses plain operators
+    Plain,
+}
+
+impl FromStr for OverflowMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<OverflowMode, ()> {
+        match s {
+            "debug-assert" => Ok(OverflowMode::DebugAssert),
+            "wrapping" => Ok(OverflowMode::Wrapping),
+            "checked" => Ok(OverflowMode::Checked),
+            "plain" => Ok(OverflowMode::Plain),
+            _ => Err(()),
+        }
+    }
+}
+
+/// C does arithmetic on types narrower than `int` in `int`, so it can't overflow. Only increments
+/// and decrements are translated on the narrow type itself, where converting the `int` result
+/// back is implementation-defined rather than undefined, and wraps on every supported target.
+fn is_narrower_than_int(kind: &CTypeKind) -> bool {
+    match *kind {
+        CTypeKind::Char | CTypeKind::SChar | CTypeKind::UChar |
+        CTypeKind::Short | CTypeKind::UShort => true,
+        _ => false,
+    }
+}
+
+/// The name used by the `wrapping_*` and `checked_*` methods for an arithmetic operator
+fn arith_method_suffix(op: BinOpKind) -> Option<&'static str> {
+    match op {
+        BinOpKind::Add => Some("add"),
+        BinOpKind::Sub => Some("sub"),
+        BinOpKind::Mul => Some("mul"),
+        BinOpKind::Div => Some("div"),
+        BinOpKind::Rem => Some("rem"),
+        _ => None,
+    }
+}
+
 impl<'c> Translation<'c> {
     pub fn convert_binary_expr(
         &self,
@@ -140,6 +209,7 @@ impl<'c> Translation<'c> {
                     stmts.extend(rhs_stmts);
                     let expr_ids = Some((lhs, rhs));
                     let val = self.convert_binary_operator(
+                        ctx,
                         op,
                         ty,
                         type_id.ctype,
@@ -158,6 +228,7 @@ impl<'c> Translation<'c> {
 
     fn covert_assignment_operator_aux(
         &self,
+        ctx: ExprContext,
         bin_op_kind: BinOpKind,
         bin_op: c_ast::BinOp,
         read: P<Expr>,
@@ -180,6 +251,7 @@ impl<'c> Translation<'c> {
             let lhs = mk().cast_expr(read, lhs_type.clone());
             let ty = self.convert_type(compute_res_ty.ctype)?;
             let val = self.convert_binary_operator(
+                ctx,
                 bin_op,
                 ty,
                 compute_res_ty.ctype,
@@ -282,30 +354,19 @@ impl<'c> Translation<'c> {
         let is_volatile_compound_assign = op.underlying_assignment().is_some() && is_volatile;
 
         let qtype_kind = &self.ast_context.resolve_type(qtype.ctype).kind;
-        let compute_type_kind = &self
-            .ast_context
-            .resolve_type(compute_lhs_type_id.ctype)
-            .kind;
 
         let pointer_lhs = match qtype_kind {
             &CTypeKind::Pointer(pointee) => Some(pointee),
             _ => None,
         };
 
-        let is_unsigned_arith = match op {
-            c_ast::BinOp::AssignAdd
-            | c_ast::BinOp::AssignSubtract
-            | c_ast::BinOp::AssignMultiply
-            | c_ast::BinOp::AssignDivide
-            | c_ast::BinOp::AssignModulus => compute_type_kind.is_unsigned_integral_type(),
-            _ => false,
-        };
+        let is_method_arith = self.is_overflow_method_arith(op, compute_lhs_type_id.ctype);
 
         let (write, read, lhs_stmts) = if initial_lhs_type_id.ctype != compute_lhs_type_id.ctype
             || ctx.is_used()
             || pointer_lhs.is_some()
             || is_volatile_compound_assign
-            || is_unsigned_arith
+            || is_method_arith
         {
             let WithStmts {
                 val: (write, read),
@@ -341,13 +402,14 @@ impl<'c> Translation<'c> {
             c_ast::BinOp::Assign => self.volatile_write(&write, initial_lhs_type_id, rhs)?,
 
             // Anything volatile needs to be desugared into explicit reads and writes
-            op if is_volatile || is_unsigned_arith => {
+            op if is_volatile || is_method_arith => {
                 let op = op
                     .underlying_assignment()
                     .expect("Cannot convert non-assignment operator");
 
                 let val = if compute_lhs_type_id.ctype == initial_lhs_type_id.ctype {
                     self.convert_binary_operator(
+                        ctx,
                         op,
                         ty,
                         qtype.ctype,
@@ -363,6 +425,7 @@ impl<'c> Translation<'c> {
                     let lhs = mk().cast_expr(read.clone(), lhs_type.clone());
                     let ty = self.convert_type(result_type_id.ctype)?;
                     let val = self.convert_binary_operator(
+                        ctx,
                         op,
                         ty,
                         result_type_id.ctype,
@@ -424,6 +487,7 @@ impl<'c> Translation<'c> {
             }
 
             c_ast::BinOp::AssignAdd => self.covert_assignment_operator_aux(
+                ctx,
                 BinOpKind::Add,
                 c_ast::BinOp::Add,
                 read.clone(),
@@ -435,6 +499,7 @@ impl<'c> Translation<'c> {
                 rhs_type_id,
             )?,
             c_ast::BinOp::AssignSubtract => self.covert_assignment_operator_aux(
+                ctx,
                 BinOpKind::Sub,
                 c_ast::BinOp::Subtract,
                 read.clone(),
@@ -446,6 +511,7 @@ impl<'c> Translation<'c> {
                 rhs_type_id,
             )?,
             c_ast::BinOp::AssignMultiply => self.covert_assignment_operator_aux(
+                ctx,
                 BinOpKind::Mul,
                 c_ast::BinOp::Multiply,
                 read.clone(),
@@ -457,6 +523,7 @@ impl<'c> Translation<'c> {
                 rhs_type_id,
             )?,
             c_ast::BinOp::AssignDivide => self.covert_assignment_operator_aux(
+                ctx,
                 BinOpKind::Div,
                 c_ast::BinOp::Divide,
                 read.clone(),
@@ -468,6 +535,7 @@ impl<'c> Translation<'c> {
                 rhs_type_id,
             )?,
             c_ast::BinOp::AssignModulus => self.covert_assignment_operator_aux(
+                ctx,
                 BinOpKind::Rem,
                 c_ast::BinOp::Modulus,
                 read.clone(),
@@ -479,6 +547,7 @@ impl<'c> Translation<'c> {
                 rhs_type_id,
             )?,
             c_ast::BinOp::AssignBitXor => self.covert_assignment_operator_aux(
+                ctx,
                 BinOpKind::BitXor,
                 c_ast::BinOp::BitXor,
                 read.clone(),
@@ -490,6 +559,7 @@ impl<'c> Translation<'c> {
                 rhs_type_id,
             )?,
             c_ast::BinOp::AssignShiftLeft => self.covert_assignment_operator_aux(
+                ctx,
                 BinOpKind::Shl,
                 c_ast::BinOp::ShiftLeft,
                 read.clone(),
@@ -501,6 +571,7 @@ impl<'c> Translation<'c> {
                 rhs_type_id,
             )?,
             c_ast::BinOp::AssignShiftRight => self.covert_assignment_operator_aux(
+                ctx,
                 BinOpKind::Shr,
                 c_ast::BinOp::ShiftRight,
                 read.clone(),
@@ -512,6 +583,7 @@ impl<'c> Translation<'c> {
                 rhs_type_id,
             )?,
             c_ast::BinOp::AssignBitOr => self.covert_assignment_operator_aux(
+                ctx,
                 BinOpKind::BitOr,
                 c_ast::BinOp::BitOr,
                 read.clone(),
@@ -523,6 +595,7 @@ impl<'c> Translation<'c> {
                 rhs_type_id,
             )?,
             c_ast::BinOp::AssignBitAnd => self.covert_assignment_operator_aux(
+                ctx,
                 BinOpKind::BitAnd,
                 c_ast::BinOp::BitAnd,
                 read.clone(),
@@ -542,10 +615,103 @@ impl<'c> Translation<'c> {
         Ok(WithStmts { stmts, val: read })
     }
 
+    /// Which method, if any, implements an integer arithmetic operator under the selected
+    /// `OverflowMode`. Checked methods are not `const fn`s, so static initializers fall back to
+    /// plain operators, where overflow is a compile-time error anyway. `wrapping_div` and
+    /// `wrapping_rem` aren't `const fn`s either, so static initializers using them are moved to
+    /// `run_static_initializers`.
+    pub fn overflow_method(
+        &self,
+        is_static: bool,
+        op: BinOpKind,
+        is_unsigned: bool,
+    ) -> Option<String> {
+        let suffix = arith_method_suffix(op)?;
+        // Unsigned division and remainder can't overflow
+        let can_wrap = op != BinOpKind::Div && op != BinOpKind::Rem;
+        let prefix = match (self.tcfg.overflow_mode, is_unsigned) {
+            (OverflowMode::DebugAssert, true) | (OverflowMode::Wrapping, _) => "wrapping",
+            (OverflowMode::Checked, true) if can_wrap => "wrapping",
+            (OverflowMode::Checked, false) if !is_static => "checked",
+            _ => return None,
+        };
+        Some(format!("{}_{}", prefix, suffix))
+    }
+
+    /// Does an integer arithmetic operator on the given type translate to a method call rather
+    /// than a plain (compound assignment) operator?
+    fn is_overflow_method_arith(&self, op: c_ast::BinOp, ctype: CTypeId) -> bool {
+        let kind = &self.ast_context.resolve_type(ctype).kind;
+        if !kind.is_integral_type() {
+            return false
+        }
+        let wraps = kind.is_unsigned_integral_type() || is_narrower_than_int(kind);
+        let op = match op {
+            c_ast::BinOp::AssignAdd => BinOpKind::Add,
+            c_ast::BinOp::AssignSubtract => BinOpKind::Sub,
+            c_ast::BinOp::AssignMultiply => BinOpKind::Mul,
+            c_ast::BinOp::AssignDivide => BinOpKind::Div,
+            c_ast::BinOp::AssignModulus => BinOpKind::Rem,
+            _ => return false,
+        };
+        self.overflow_method(false, op, wraps).is_some()
+    }
+
+    /// Translate integer arithmetic according to the selected `OverflowMode`. Non-integral
+    /// operands always use the plain operator.
+    fn convert_overflow_arith(
+        &self,
+        ctx: ExprContext,
+        op: BinOpKind,
+        ctype: CTypeId,
+        lhs: P<Expr>,
+        rhs: P<Expr>,
+    ) -> P<Expr> {
+        let kind = &self.ast_context.resolve_type(ctype).kind;
+        let method = if kind.is_integral_type() {
+            let wraps = kind.is_unsigned_integral_type() || is_narrower_than_int(kind);
+            self.overflow_method(ctx.is_static, op, wraps)
+        } else {
+            None
+        };
+        match method {
+            // `checked_div` and `checked_rem` also fail on a zero divisor, which Rust reports
+            // separately from overflow
+            Some(ref method) if method.starts_with("checked_") && (op == BinOpKind::Div || op == BinOpKind::Rem) => {
+                let rhs_name = self.renamer.borrow_mut().pick_name("rhs");
+                let rhs_var = || mk().path_expr(vec![&rhs_name]);
+                let zero_msg = if op == BinOpKind::Div {
+                    "attempt to divide by zero"
+                } else {
+                    "attempt to calculate the remainder with a divisor of zero"
+                };
+                let is_zero = mk().binary_expr(
+                    BinOpKind::Eq,
+                    rhs_var(),
+                    mk().lit_expr(mk().int_lit(0, LitIntType::Unsuffixed)),
+                );
+                let zero_check = mk().ifte_expr(
+                    is_zero,
+                    mk().block(vec![mk().semi_stmt(panic_expr(zero_msg))]),
+                    None as Option<P<Expr>>,
+                );
+                let checked = mk().method_call_expr(lhs, mk().path_segment(method), vec![rhs_var()]);
+                mk().block_expr(mk().block(vec![
+                    mk().local_stmt(P(mk().local(mk().ident_pat(&rhs_name), None as Option<P<Ty>>, Some(rhs)))),
+                    mk().semi_stmt(zero_check),
+                    mk().expr_stmt(expect_no_overflow(checked, &method["checked_".len()..])),
+                ]))
+            }
+            Some(method) => mk().method_call_expr(lhs, mk().path_segment(method), vec![rhs]),
+            None => mk().binary_expr(op, lhs, rhs),
+        }
+    }
+
     /// Translate a non-assignment binary operator. It is expected that the `lhs` and `rhs`
     /// arguments be usable as rvalues.
     fn convert_binary_operator(
         &self,
+        ctx: ExprContext,
         op: c_ast::BinOp,
         ty: P<Ty>,
         ctype: CTypeId,
@@ -555,30 +721,13 @@ impl<'c> Translation<'c> {
         rhs: P<Expr>,
         lhs_rhs_ids: Option<(CExprId, CExprId)>,
     ) -> P<Expr> {
-        let is_unsigned_integral_type = self
-            .ast_context
-            .index(ctype)
-            .kind
-            .is_unsigned_integral_type();
-
         match op {
-            c_ast::BinOp::Add => self.convert_addition(lhs_type, rhs_type, lhs, rhs),
-            c_ast::BinOp::Subtract => self.convert_subtraction(ty, lhs_type, rhs_type, lhs, rhs),
+            c_ast::BinOp::Add => self.convert_addition(ctx, lhs_type, rhs_type, lhs, rhs),
+            c_ast::BinOp::Subtract => self.convert_subtraction(ctx, ty, lhs_type, rhs_type, lhs, rhs),
 
-            c_ast::BinOp::Multiply if is_unsigned_integral_type => {
-                mk().method_call_expr(lhs, mk().path_segment("wrapping_mul"), vec![rhs])
-            }
-            c_ast::BinOp::Multiply => mk().binary_expr(BinOpKind::Mul, lhs, rhs),
-
-            c_ast::BinOp::Divide if is_unsigned_integral_type => {
-                mk().method_call_expr(lhs, mk().path_segment("wrapping_div"), vec![rhs])
-            }
-            c_ast::BinOp::Divide => mk().binary_expr(BinOpKind::Div, lhs, rhs),
-
-            c_ast::BinOp::Modulus if is_unsigned_integral_type => {
-                mk().method_call_expr(lhs, mk().path_segment("wrapping_rem"), vec![rhs])
-            }
-            c_ast::BinOp::Modulus => mk().binary_expr(BinOpKind::Rem, lhs, rhs),
+            c_ast::BinOp::Multiply => self.convert_overflow_arith(ctx, BinOpKind::Mul, ctype, lhs, rhs),
+            c_ast::BinOp::Divide => self.convert_overflow_arith(ctx, BinOpKind::Div, ctype, lhs, rhs),
+            c_ast::BinOp::Modulus => self.convert_overflow_arith(ctx, BinOpKind::Rem, ctype, lhs, rhs),
 
             c_ast::BinOp::BitXor => mk().binary_expr(BinOpKind::BitXor, lhs, rhs),
 
@@ -643,6 +792,7 @@ impl<'c> Translation<'c> {
 
     fn convert_addition(
         &self,
+        ctx: ExprContext,
         lhs_type_id: CQualTypeId,
         rhs_type_id: CQualTypeId,
         lhs: P<Expr>,
@@ -675,15 +825,14 @@ impl<'c> Translation<'c> {
                 }
                 None => pointer_offset(rhs, lhs),
             }
-        } else if lhs_type.is_unsigned_integral_type() {
-            mk().method_call_expr(lhs, mk().path_segment("wrapping_add"), vec![rhs])
         } else {
-            mk().binary_expr(BinOpKind::Add, lhs, rhs)
+            self.convert_overflow_arith(ctx, BinOpKind::Add, lhs_type_id.ctype, lhs, rhs)
         }
     }
 
     fn convert_subtraction(
         &self,
+        ctx: ExprContext,
         ty: P<Ty>,
         lhs_type_id: CQualTypeId,
         rhs_type_id: CQualTypeId,
@@ -718,10 +867,8 @@ impl<'c> Translation<'c> {
                     ),
                 ),
             }
-        } else if lhs_type.is_unsigned_integral_type() {
-            mk().method_call_expr(lhs, mk().path_segment("wrapping_sub"), vec![rhs])
         } else {
-            mk().binary_expr(BinOpKind::Sub, lhs, rhs)
+            self.convert_overflow_arith(ctx, BinOpKind::Sub, lhs_type_id.ctype, lhs, rhs)
         }
     }
 
@@ -800,18 +947,8 @@ impl<'c> Translation<'c> {
                 };
                 mk().method_call_expr(read.clone(), "offset", vec![n])
             } else {
-                if self
-                    .ast_context
-                    .resolve_type(ty.ctype)
-                    .kind
-                    .is_unsigned_integral_type()
-                {
-                    let m = if up { "wrapping_add" } else { "wrapping_sub" };
-                    mk().method_call_expr(read.clone(), m, vec![one])
-                } else {
-                    let k = if up { BinOpKind::Add } else { BinOpKind::Sub };
-                    mk().binary_expr(k, read.clone(), one)
-                }
+                let k = if up { BinOpKind::Add } else { BinOpKind::Sub };
+                self.convert_overflow_arith(ctx, k, ty.ctype, read.clone(), one)
             };
 
         // *p = *p + rhs
@@ -944,10 +1081,23 @@ impl<'c> Translation<'c> {
             c_ast::UnOp::Negate => {
                 let val = self.convert_expr(ctx.used(), arg)?;
 
-                if resolved_ctype.kind.is_unsigned_integral_type() {
+                if resolved_ctype.kind.is_unsigned_integral_type() && ctx.is_static {
+                    // `wrapping_neg` isn't a `const fn`, but `wrapping_sub` is
+                    let zero = mk().cast_expr(mk().lit_expr(mk().int_lit(0, "")), ty);
+                    Ok(val.map(|v| mk().method_call_expr(mk().paren_expr(zero), "wrapping_sub", vec![v])))
+                } else if resolved_ctype.kind.is_unsigned_integral_type() {
                     Ok(val.map(wrapping_neg_expr))
-                } else {
+                } else if !resolved_ctype.kind.is_signed_integral_type() || ctx.is_static {
                     Ok(val.map(neg_expr))
+                } else {
+                    match self.tcfg.overflow_mode {
+                        OverflowMode::Wrapping => Ok(val.map(wrapping_neg_expr)),
+                        OverflowMode::Checked => Ok(val.map(|v| {
+                            let checked = mk().method_call_expr(v, "checked_neg", vec![] as Vec<P<Expr>>);
+                            expect_no_overflow(checked, "negate")
+                        })),
+                        OverflowMode::DebugAssert | OverflowMode::Plain => Ok(val.map(neg_expr)),
+                    }
                 }
             }
             c_ast::UnOp::Complement => Ok(self
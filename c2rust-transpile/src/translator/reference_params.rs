@@ -0,0 +1,158 @@
+#![deny(missing_docs)]
+//! With `--nonnull-references`, pointer parameters that C promises are valid are passed as Rust
+//! references instead of raw pointers.
+//!
+//! A parameter qualifies when it is declared `nonnull` (on the function or the parameter) or
+//! `_Nonnull`, which gives `&T`, or `_Nullable`, which gives `Option<&T>`. Pointers to `const`
+//! become shared references. Pointers to mutable data only become `&mut T` when they are also
+//! `restrict`, since mutable references may not alias; others stay raw pointers.
+//!
+//! A reference only covers a single object, so parameters the body subscripts, offsets, reassigns
+//! or passes on are kept as raw pointers, as are the parameters of functions without a body here.
+//!
+//! Function bodies keep working on raw pointers: each reference parameter is immediately
+//! shadowed by a pointer of its original C type. Direct calls convert their arguments with
+//! `&*p`/`&mut *p`, or `p.as_ref()`/`p.as_mut()` for nullable ones. Functions whose address is
+//! taken keep their C signature so that they still fit their function pointer types.
+
+use super::*;
+
+/// How a pointer parameter is passed
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ReferenceParam {
+    /// `&mut T` rather than `&T`
+    is_mut: bool,
+    /// `Option<&T>` rather than `&T`
+    is_nullable: bool,
+}
+
+impl<'c> Translation<'c> {
+    /// How parameter `index` of function `fn_decl_id` is passed, if it is translated as a
+    /// reference.
+    pub fn reference_param(&self, fn_decl_id: CDeclId, index: usize) -> Option<ReferenceParam> {
+        if !self.tcfg.nonnull_references || self.address_taken_functions.contains(&fn_decl_id) {
+            return None
+        }
+
+        // Without a body we can't tell whether the pointer is used as an array
+        let (parameters, attrs) = match self.ast_context[fn_decl_id].kind {
+            CDeclKind::Function { ref parameters, ref attrs, body: Some(_), .. } => (parameters, attrs),
+            _ => return None,
+        };
+        let param_id = *parameters.get(index)?;
+        if self.escaping_pointer_params.contains(&param_id) {
+            return None
+        }
+        let typ = match self.ast_context[param_id].kind {
+            CDeclKind::Variable { typ, .. } => typ,
+            _ => return None,
+        };
+
+        let declared_nonnull = attrs.iter().any(|attr| match *attr {
+            DeclAttribute::NonNull(ref indices) => indices.contains(&index),
+            _ => false,
+        });
+        let is_nullable = match self.pointer_nullability(typ.ctype) {
+            Some(Attribute::Nullable) => true,
+            Some(Attribute::NotNull) => false,
+            _ if declared_nonnull => false,
+            _ => return None,
+        };
+
+        let pointee = match self.ast_context.resolve_type(typ.ctype).kind {
+            CTypeKind::Pointer(pointee) => pointee,
+            _ => return None,
+        };
+        match self.ast_context.resolve_type(pointee.ctype).kind {
+            CTypeKind::Void | CTypeKind::Function(..) | CTypeKind::VariableArray(..) => return None,
+            _ => {}
+        }
+
+        let is_mut = if pointee.qualifiers.is_const {
+            false
+        } else if typ.qualifiers.is_restrict {
+            true
+        } else {
+            return None
+        };
+
+        Some(ReferenceParam { is_mut, is_nullable })
+    }
+
+    /// The `_Nonnull`/`_Nullable` attribute on a pointer type, if there is one
+    fn pointer_nullability(&self, ctype: CTypeId) -> Option<Attribute> {
+        match self.ast_context[ctype].kind {
+            CTypeKind::Attributed(_, Some(Attribute::NotNull)) => Some(Attribute::NotNull),
+            CTypeKind::Attributed(_, Some(Attribute::Nullable)) => Some(Attribute::Nullable),
+            CTypeKind::Attributed(ty, _) => self.pointer_nullability(ty.ctype),
+            CTypeKind::Paren(ty) => self.pointer_nullability(ty),
+            _ => None,
+        }
+    }
+
+    /// The Rust type of a reference parameter with the given C pointer type
+    pub fn reference_param_ty(&self, param: ReferenceParam, ctype: CTypeId) -> Result<P<Ty>, String> {
+        let pointee = match self.ast_context.resolve_type(ctype).kind {
+            CTypeKind::Pointer(pointee) => pointee,
+            ref t => return Err(format!("Reference parameter is not a pointer: {:?}", t)),
+        };
+        let pointee_ty = self.convert_type(pointee.ctype)?;
+        let ref_ty = if param.is_mut {
+            mk().mutbl().ref_ty(pointee_ty)
+        } else {
+            mk().ref_ty(pointee_ty)
+        };
+
+        if param.is_nullable {
+            let args = mk().angle_bracketed_args(vec![ref_ty]);
+            Ok(mk().path_ty(vec![mk().path_segment_with_args("Option", args)]))
+        } else {
+            Ok(ref_ty)
+        }
+    }
+
+    /// Rebind a reference parameter as the raw pointer the function body expects
+    pub fn reference_param_shadow(
+        &self,
+        param: ReferenceParam,
+        name: &str,
+        mutbl: Mutability,
+        ctype: CTypeId,
+    ) -> Result<Stmt, String> {
+        let ptr_ty = self.convert_type(ctype)?;
+        let val = if param.is_nullable {
+            let null = if param.is_mut { "null_mut" } else { "null" };
+            let null = mk().call_expr(
+                mk().path_expr(vec!["", "std", "ptr", null]),
+                vec![] as Vec<P<Expr>>,
+            );
+            mk().match_expr(mk().ident_expr(name), vec![
+                mk().arm(
+                    vec![mk().tuple_struct_pat(vec!["Some"], vec![mk().ident_pat("r")])],
+                    None as Option<P<Expr>>,
+                    mk().ident_expr("r"),
+                ),
+                mk().arm(
+                    vec![mk().ident_pat("None")],
+                    None as Option<P<Expr>>,
+                    null,
+                ),
+            ])
+        } else {
+            mk().ident_expr(name)
+        };
+
+        let local = mk().local(mk().set_mutbl(mutbl).ident_pat(name), Some(ptr_ty), Some(val));
+        Ok(mk().local_stmt(P(local)))
+    }
+
+    /// Convert a raw pointer argument into the reference a parameter expects
+    pub fn convert_reference_arg(&self, param: ReferenceParam, arg: P<Expr>) -> P<Expr> {
+        match (param.is_nullable, param.is_mut) {
+            (false, false) => mk().addr_of_expr(mk().unary_expr(ast::UnOp::Deref, arg)),
+            (false, true) => mk().mutbl().addr_of_expr(mk().unary_expr(ast::UnOp::Deref, arg)),
+            (true, false) => mk().method_call_expr(arg, "as_ref", vec![] as Vec<P<Expr>>),
+            (true, true) => mk().method_call_expr(arg, "as_mut", vec![] as Vec<P<Expr>>),
+        }
+    }
+}
@@ -0,0 +1,44 @@
+#![deny(missing_docs)]
+//! This module translates C11 `_Static_assert` declarations into compile-time checks.
+//!
+//! Each assertion becomes a `const` of type `[(); 1]` initialized with `[(); cond as usize]`.
+//! When the condition is false the array has length 0, so the assertion fails the build with a
+//! type mismatch, just like the C compiler would reject the declaration. The message of the
+//! assertion is kept as a comment on the item.
+
+use super::*;
+
+impl<'c> Translation<'c> {
+    /// Translate a `_Static_assert` declaration, in file scope or in a block. The `span` carries
+    /// the comments of the declaration, including the one from `static_assert_comment`.
+    pub fn convert_static_assert(
+        &self,
+        ctx: ExprContext,
+        span: Span,
+        assert_expr: CExprId,
+    ) -> Result<ConvertedDecl, String> {
+        let cond = self.convert_condition(ctx.used().static_(), true, assert_expr)?;
+        if !cond.stmts.is_empty() {
+            return Err(format!("Static assertion condition is not a constant expression"));
+        }
+
+        let unit_array = |len: P<Expr>| mk().array_ty(mk().tuple_ty(vec![] as Vec<P<Ty>>), len);
+        let one = mk().lit_expr(mk().int_lit(1, LitIntType::Unsuffixed));
+        let len = mk().cast_expr(mk().paren_expr(cond.val), mk().path_ty(vec!["usize"]));
+        let init = mk().repeat_expr(mk().tuple_expr(vec![] as Vec<P<Expr>>), len);
+
+        let name = self.renamer.borrow_mut().pick_name_root("STATIC_ASSERT");
+        Ok(ConvertedDecl::Item(mk().span(span)
+            .call_attr("allow", vec!["dead_code"])
+            .const_item(name, unit_array(one), init)))
+    }
+
+    /// The comment holding the message of a `_Static_assert` declaration, if it has one
+    pub fn static_assert_comment(&self, decl_id: CDeclId) -> Option<String> {
+        match self.ast_context.c_decls.get(&decl_id).map(|decl| &decl.kind) {
+            Some(&CDeclKind::StaticAssert { message: Some(ref message), .. }) =>
+                Some(format!("// _Static_assert: {:?}", message)),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,190 @@
+#![deny(missing_docs)]
+//! This module decides which C globals can be translated without `mut`.
+//!
+//! A `const`-qualified global can never be legally written, so unless its address escapes into a
+//! pointer that drops the qualifier, or its initializer has to be run at startup, it becomes an
+//! immutable `static`. Scalars with internal linkage whose address is never taken go one step
+//! further and become `const` items. Immutable statics must be `Sync`, which raw pointers are
+//! not, so globals whose type contains data pointers are wrapped in a `SyncStatic` newtype.
+
+use super::*;
+
+use std::collections::HashSet;
+
+/// How a C global that is never written is translated
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ImmutableGlobal {
+    /// A `const` item
+    Const,
+    /// An immutable `static`
+    Static,
+    /// An immutable `static` wrapped in `SyncStatic` because its type contains raw pointers
+    SyncStatic,
+}
+
+/// Name of the wrapper type making pointer-containing statics `Sync`
+pub const SYNC_STATIC: &str = "SyncStatic";
+
+impl<'c> Translation<'c> {
+    /// Decide which of the global variables can be translated as immutable items.
+    pub fn classify_immutable_globals(&self) -> IndexMap<CDeclId, ImmutableGlobal> {
+        let context = &self.ast_context;
+        let mut addressed: HashSet<CDeclId> = HashSet::new();
+        let mut casts_away_const: HashSet<CDeclId> = HashSet::new();
+
+        for expr in context.c_exprs.values() {
+            match expr.kind {
+                CExprKind::Unary(_, c_ast::UnOp::AddressOf, arg, _) => {
+                    if let Some(decl_id) = self.referenced_object(arg) {
+                        addressed.insert(decl_id);
+                    }
+                }
+                CExprKind::ImplicitCast(ty, arg, CastKind::BitCast, _, _) |
+                CExprKind::ImplicitCast(ty, arg, CastKind::NoOp, _, _) |
+                CExprKind::ExplicitCast(ty, arg, CastKind::BitCast, _, _) |
+                CExprKind::ExplicitCast(ty, arg, CastKind::NoOp, _, _) => {
+                    let drops_const = match context.resolve_type(ty.ctype).kind {
+                        CTypeKind::Pointer(pointee) => !pointee.qualifiers.is_const,
+                        _ => false,
+                    };
+                    if drops_const {
+                        if let Some(decl_id) = self.pointer_source(arg) {
+                            casts_away_const.insert(decl_id);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut globals = IndexMap::new();
+        for (&decl_id, decl) in &context.c_decls {
+            let (is_extern, initializer, typ, attrs) = match decl.kind {
+                CDeclKind::Variable { is_static: true, is_extern, is_defn, initializer, typ, ref attrs, .. }
+                    if is_defn || !is_extern => (is_extern, initializer, typ, attrs),
+                _ => continue,
+            };
+
+            if !typ.qualifiers.is_const || typ.qualifiers.is_volatile {
+                continue;
+            }
+            // Initializers run by `run_static_initializers` assign to the global
+            if casts_away_const.contains(&decl_id) || self.static_initializer_is_uncompilable(initializer) {
+                continue;
+            }
+
+            let kind = &context.resolve_type(typ.ctype).kind;
+            let is_scalar = kind.is_integral_type() || kind.is_floating_type() ||
+                match *kind { CTypeKind::Enum(..) => true, _ => false };
+
+            // Linkage attributes like `section` need an actual symbol
+            let repr = if is_scalar && !is_extern && attrs.is_empty() && !addressed.contains(&decl_id) &&
+                !self.static_initializer_is_unsafe(initializer) {
+                ImmutableGlobal::Const
+            } else if self.contains_raw_pointer(typ.ctype) {
+                ImmutableGlobal::SyncStatic
+            } else {
+                ImmutableGlobal::Static
+            };
+            globals.insert(decl_id, repr);
+        }
+
+        globals
+    }
+
+    /// How the given global is translated, if it is translated without `mut`.
+    pub fn immutable_global(&self, decl_id: CDeclId) -> Option<ImmutableGlobal> {
+        self.immutable_globals.borrow().get(&decl_id).cloned()
+    }
+
+    /// Build the item for a global that is never written, wrapping its type and initializer when
+    /// needed. `builder` carries the span, linkage and visibility of the item.
+    pub fn immutable_global_item(
+        &self,
+        builder: Builder,
+        repr: ImmutableGlobal,
+        name: &str,
+        ty: P<Ty>,
+        init: P<Expr>,
+    ) -> P<Item> {
+        match repr {
+            ImmutableGlobal::Const => builder.const_item(name, ty, init),
+            ImmutableGlobal::Static => builder.static_item(name, ty, init),
+            ImmutableGlobal::SyncStatic => {
+                let ty = mk().path_ty(vec![mk().path_segment_with_args(
+                    SYNC_STATIC,
+                    mk().angle_bracketed_args(vec![ty]),
+                )]);
+                let init = mk().call_expr(mk().path_expr(vec![SYNC_STATIC]), vec![init]);
+                builder.static_item(name, ty, init)
+            }
+        }
+    }
+
+    /// The definition of `SyncStatic` and its `Sync` implementation
+    pub fn sync_static_items(&self) -> Vec<P<Item>> {
+        let wrapper = mk().pub_()
+            .call_attr("derive", vec!["Copy", "Clone"])
+            .call_attr("repr", vec!["transparent"])
+            .generic_over(mk().ty_param("T"))
+            .tuple_struct_item(SYNC_STATIC, vec![mk().pub_().enum_field(mk().ident_ty("T"))]);
+        let wrapped_ty = mk().path_ty(vec![mk().path_segment_with_args(
+            SYNC_STATIC,
+            mk().angle_bracketed_args(vec![mk().ident_ty("T")]),
+        )]);
+        let sync_impl = mk().unsafe_()
+            .generic_over(mk().ty_param("T"))
+            .trait_impl_item(vec!["Sync"], wrapped_ty, vec![]);
+        vec![wrapper, sync_impl]
+    }
+
+    /// Does a value of this type contain a data pointer, making it `!Sync` in Rust?
+    fn contains_raw_pointer(&self, ty: CTypeId) -> bool {
+        let context = &self.ast_context;
+        match context.resolve_type(ty).kind {
+            CTypeKind::Pointer(_) => !context.is_function_pointer(ty),
+            CTypeKind::ConstantArray(elt, _) |
+            CTypeKind::IncompleteArray(elt) => self.contains_raw_pointer(elt),
+            CTypeKind::Struct(decl_id) | CTypeKind::Union(decl_id) => {
+                match context[decl_id].kind {
+                    CDeclKind::Struct { fields: Some(ref fields), .. } |
+                    CDeclKind::Union { fields: Some(ref fields), .. } => {
+                        fields.iter().any(|&field| match context[field].kind {
+                            CDeclKind::Field { typ, .. } => self.contains_raw_pointer(typ.ctype),
+                            _ => false,
+                        })
+                    }
+                    _ => false,
+                }
+            }
+            _ => false,
+        }
+    }
+
+    /// The variable whose storage an lvalue designates, looking through member accesses, array
+    /// subscripts and casts. This is conservative: `p[0]` is attributed to `p` itself.
+    fn referenced_object(&self, expr: CExprId) -> Option<CDeclId> {
+        match self.ast_context[expr].kind {
+            CExprKind::DeclRef(_, decl_id, _) => Some(decl_id),
+            CExprKind::Member(_, base, _, MemberKind::Dot, _) => self.referenced_object(base),
+            CExprKind::ArraySubscript(_, lhs, rhs, _) =>
+                self.referenced_object(lhs).or_else(|| self.referenced_object(rhs)),
+            CExprKind::ImplicitCast(_, arg, _, _, _) |
+            CExprKind::ExplicitCast(_, arg, _, _, _) => self.referenced_object(arg),
+            _ => None,
+        }
+    }
+
+    /// The variable a pointer-valued expression was directly derived from by taking its address
+    /// or letting it decay, if any.
+    fn pointer_source(&self, expr: CExprId) -> Option<CDeclId> {
+        match self.ast_context[expr].kind {
+            CExprKind::Unary(_, c_ast::UnOp::AddressOf, arg, _) |
+            CExprKind::ImplicitCast(_, arg, CastKind::ArrayToPointerDecay, _, _) =>
+                self.referenced_object(arg),
+            CExprKind::ImplicitCast(_, arg, _, _, _) |
+            CExprKind::ExplicitCast(_, arg, _, _, _) => self.pointer_source(arg),
+            _ => None,
+        }
+    }
+}
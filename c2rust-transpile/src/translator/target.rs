@@ -0,0 +1,283 @@
+#![deny(missing_docs)]
+//! When translating for an explicit target (`--target`), the generated code is only correct for
+//! that target: the preprocessor took the target's branches, clang laid out structs and evaluated
+//! `sizeof` in constant expressions for it, and `long` and `char` are translated to fixed-width
+//! types of the target's sizes. Each translated file therefore records its target and refuses to
+//! compile for a target with a different architecture, operating system or pointer width. The
+//! operating system stands in for the rest of the data model: `x86_64-pc-windows-msvc` and
+//! `x86_64-unknown-linux-gnu` only differ in the width of `long`.
+
+use super::*;
+
+use std::{env, mem};
+
+use c2rust_ast_exporter::clang_ast::TargetInfo;
+
+impl<'c> Translation<'c> {
+    /// The `compile_error!` item guarding against building the translation for a target other than
+    /// the one it was translated for, if translating for an explicit target
+    pub fn target_check_item(&self) -> Option<P<Item>> {
+        self.tcfg.target.as_ref()?;
+        let target = &self.ast_context.target;
+
+        let mut conditions = vec![];
+        if let Some(arch) = rust_target_arch(&target.triple) {
+            conditions.push(mk().meta_item(vec!["target_arch"], mk().str_lit(arch)));
+        }
+        if let Some(os) = rust_target_os(&target.triple) {
+            conditions.push(mk().meta_item(vec!["target_os"], mk().str_lit(os)));
+        }
+        let pointer_width = target.pointer_width.to_string();
+        conditions.push(mk().meta_item(vec!["target_pointer_width"], mk().str_lit(&pointer_width)));
+
+        // #[cfg(not(all(target_arch = "x86", target_os = "linux", target_pointer_width = "32")))]
+        let conditions = conditions
+            .into_iter()
+            .map(|cond| mk().nested_meta_item(cond))
+            .collect::<Vec<_>>();
+        let all = mk().meta_item(vec!["all"], MetaItemKind::List(conditions));
+        let not = mk().meta_item(vec!["not"], MetaItemKind::List(vec![mk().nested_meta_item(all)]));
+        let cfg = mk().meta_item(vec!["cfg"], MetaItemKind::List(vec![mk().nested_meta_item(not)]));
+
+        // compile_error!("translated for i686-unknown-linux-gnu");
+        let msg = format!("translated for {}", target.triple);
+        let msg = vec![
+            Token::interpolated(Nonterminal::NtExpr(mk().lit_expr(mk().str_lit(&msg)))),
+        ].into_iter().collect::<TokenStream>();
+        let mac = mk().mac(vec!["compile_error"], msg, MacDelimiter::Parenthesis);
+
+        Some(mk().meta_item_attr(AttrStyle::Outer, cfg).mac_item(mac))
+    }
+}
+
+/// Does an AST's target have the architecture, operating system and data model of the host? If
+/// so, translating it as for the host, with `libc` types and no target guard, is still correct.
+pub fn is_host_target(target: &TargetInfo) -> bool {
+    rust_target_arch(&target.triple) == Some(env::consts::ARCH)
+        && rust_target_os(&target.triple) == Some(env::consts::OS)
+        && target.pointer_width == 8 * mem::size_of::<usize>() as u64
+        && target.long_width == 8 * mem::size_of::<::libc::c_long>() as u64
+        && target.char_is_signed == (::libc::c_char::min_value() != 0)
+}
+
+/// The Rust `target_arch` corresponding to the architecture of an LLVM target triple
+fn rust_target_arch(triple: &str) -> Option<&'static str> {
+    let arch = triple.split('-').next()?;
+    let rust_arch = match arch {
+        "i386" | "i486" | "i586" | "i686" => "x86",
+        "x86_64" | "amd64" => "x86_64",
+        "aarch64" | "arm64" => "aarch64",
+        "mips" | "mipsel" => "mips",
+        "mips64" | "mips64el" => "mips64",
+        "powerpc" => "powerpc",
+        "powerpc64" | "powerpc64le" => "powerpc64",
+        "riscv32" => "riscv32",
+        "riscv64" => "riscv64",
+        "sparc64" | "sparcv9" => "sparc64",
+        "s390x" => "s390x",
+        "wasm32" => "wasm32",
+        _ if arch.starts_with("arm") || arch.starts_with("thumb") => "arm",
+        _ => return None,
+    };
+    Some(rust_arch)
+}
+
+/// The Rust `target_os` corresponding to the operating system of an LLVM target triple, which may
+/// omit the vendor (`x86_64-linux-gnu`) or carry a version (`x86_64-apple-darwin18.2.0`)
+fn rust_target_os(triple: &str) -> Option<&'static str> {
+    let parts = triple.split('-').skip(1).collect::<Vec<_>>();
+
+    // Android triples name Linux as their system and Android as their environment
+    if parts.iter().any(|part| part.starts_with("android")) {
+        return Some("android")
+    }
+
+    parts.into_iter().filter_map(|part| {
+        let rust_os = match part {
+            "linux" => "linux",
+            "windows" | "win32" | "mingw32" => "windows",
+            "none" => "none",
+            _ if part.starts_with("darwin") || part.starts_with("macos") => "macos",
+            _ if part.starts_with("ios") => "ios",
+            _ if part.starts_with("freebsd") => "freebsd",
+            _ if part.starts_with("netbsd") => "netbsd",
+            _ if part.starts_with("openbsd") => "openbsd",
+            _ if part.starts_with("dragonfly") => "dragonfly",
+            _ if part.starts_with("solaris") => "solaris",
+            _ => return None,
+        };
+        Some(rust_os)
+    }).next()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashSet;
+    use std::path::PathBuf;
+
+    use build_files::BuildDirectoryContents;
+
+    fn config(target: &str) -> TranspilerConfig {
+        TranspilerConfig {
+            dump_untyped_context: false,
+            dump_typed_context: false,
+            pretty_typed_context: false,
+            dump_function_cfgs: false,
+            json_function_cfgs: false,
+            dump_cfg_liveness: false,
+            dump_cfg_html: None,
+            dump_structures: false,
+            incremental_relooper: true,
+            fail_on_multiple: false,
+            filter: None,
+            debug_relooper_labels: false,
+            cross_checks: false,
+            cross_check_backend: String::new(),
+            cross_check_configs: vec![],
+            prefix_function_names: None,
+            translate_asm: false,
+            use_c_loop_info: true,
+            use_c_multiple_info: true,
+            simplify_structures: true,
+            panic_on_translator_failure: true,
+            emit_modules: false,
+            fail_on_error: true,
+            replace_unsupported_decls: ReplaceMode::Extern,
+            translate_valist: false,
+            overwrite_existing: false,
+            reduce_type_annotations: false,
+            reorganize_definitions: false,
+            emit_source_map: false,
+            annotate_source_locations: false,
+            vla_lowering: VlaLowering::Vec,
+            overflow_mode: OverflowMode::DebugAssert,
+            nonnull_references: false,
+            assert_field_offsets: false,
+            node_splitting_budget: None,
+            configurations: vec![],
+            target: Some(target.to_string()),
+            save_ast: None,
+            enabled_warnings: HashSet::new(),
+            emit_build_files: false,
+            build_directory_name: String::new(),
+            build_directory_contents: BuildDirectoryContents::Nothing,
+            main: None,
+        }
+    }
+
+    /// An AST context for `target` holding a `long`, an `unsigned long` and a plain `char`
+    fn context(target: TargetInfo) -> (TypedAstContext, [CTypeId; 3]) {
+        let mut context = TypedAstContext::new();
+        context.target = target;
+        let kinds = [CTypeKind::Long, CTypeKind::ULong, CTypeKind::Char];
+        let mut ids = [CTypeId(0); 3];
+        for (i, kind) in kinds.iter().enumerate() {
+            ids[i] = CTypeId(i as u64);
+            context.c_types.insert(ids[i], Located { loc: None, kind: kind.clone() });
+        }
+        (context, ids)
+    }
+
+    fn i686_linux() -> TargetInfo {
+        TargetInfo {
+            triple: "i686-unknown-linux-gnu".to_string(),
+            pointer_width: 32,
+            long_width: 32,
+            char_is_signed: true,
+        }
+    }
+
+    #[test]
+    fn translates_types_for_32_bit_target() {
+        with_globals(|| {
+            let tcfg = config("i686-unknown-linux-gnu");
+            let (context, [long, ulong, chr]) = context(i686_linux());
+            let t = Translation::new(context, &tcfg, PathBuf::from("test.c"));
+            let ctx = ExprContext {
+                used: true,
+                is_static: false,
+                decay_ref: DecayRef::Default,
+                va_decl: None,
+                is_bitfield_write: false,
+            };
+
+            assert_eq!(ty_to_string(&t.convert_type(long).unwrap()), "i32");
+            assert_eq!(ty_to_string(&t.convert_type(ulong).unwrap()), "u32");
+            assert_eq!(ty_to_string(&t.convert_type(chr).unwrap()), "i8");
+
+            let size = t.compute_size_of_type(ctx, long).unwrap();
+            assert_eq!(expr_to_string(&size.val), "::std::mem::size_of::<i32>()");
+        })
+    }
+
+    #[test]
+    fn translates_unsigned_char_target() {
+        with_globals(|| {
+            let tcfg = config("armv7-unknown-linux-gnueabihf");
+            let target = TargetInfo {
+                triple: "armv7-unknown-linux-gnueabihf".to_string(),
+                char_is_signed: false,
+                .. i686_linux()
+            };
+            let (context, [_, _, chr]) = context(target);
+            let t = Translation::new(context, &tcfg, PathBuf::from("test.c"));
+
+            assert_eq!(ty_to_string(&t.convert_type(chr).unwrap()), "u8");
+        })
+    }
+
+    #[test]
+    fn guards_on_architecture_os_and_pointer_width() {
+        with_globals(|| {
+            let tcfg = config("x86_64-pc-windows-msvc");
+            let target = TargetInfo {
+                triple: "x86_64-pc-windows-msvc".to_string(),
+                pointer_width: 64,
+                long_width: 32,
+                char_is_signed: true,
+            };
+            let (context, [long, _, _]) = context(target);
+            let t = Translation::new(context, &tcfg, PathBuf::from("test.c"));
+
+            assert_eq!(ty_to_string(&t.convert_type(long).unwrap()), "i32");
+            let guard = item_to_string(&t.target_check_item().unwrap());
+            for cond in &["target_arch = \"x86_64\"", "target_os = \"windows\"",
+                          "target_pointer_width = \"64\""] {
+                assert!(guard.contains(cond), "{}", guard);
+            }
+            assert!(guard.contains("compile_error!(\"translated for x86_64-pc-windows-msvc\")"),
+                    "{}", guard);
+        })
+    }
+
+    #[test]
+    fn maps_triples_to_rust_targets() {
+        assert_eq!(rust_target_arch("i686-unknown-linux-gnu"), Some("x86"));
+        assert_eq!(rust_target_os("i686-unknown-linux-gnu"), Some("linux"));
+        assert_eq!(rust_target_os("x86_64-linux-gnu"), Some("linux"));
+        assert_eq!(rust_target_os("x86_64-pc-windows-msvc"), Some("windows"));
+        assert_eq!(rust_target_os("x86_64-w64-mingw32"), Some("windows"));
+        assert_eq!(rust_target_os("x86_64-apple-darwin18.2.0"), Some("macos"));
+        assert_eq!(rust_target_os("aarch64-linux-android"), Some("android"));
+        assert_eq!(rust_target_os("armv7-none-eabi"), Some("none"));
+    }
+
+    #[test]
+    fn windows_is_not_linux_host() {
+        let windows = TargetInfo {
+            triple: "x86_64-pc-windows-msvc".to_string(),
+            pointer_width: 64,
+            long_width: 32,
+            char_is_signed: true,
+        };
+        let linux = TargetInfo {
+            triple: "x86_64-unknown-linux-gnu".to_string(),
+            long_width: 64,
+            .. windows.clone()
+        };
+        assert_eq!(is_host_target(&windows), cfg!(all(target_arch = "x86_64", windows)));
+        assert_eq!(is_host_target(&linux), cfg!(all(target_arch = "x86_64", target_os = "linux")));
+    }
+}
@@ -0,0 +1,257 @@
+#![deny(missing_docs)]
+//! This module decides how local variable-length arrays are stored and indexed.
+//!
+//! A VLA is flattened into storage for its innermost fixed-size elements, with the lengths of its
+//! variable dimensions saved in `vla` locals when the array is declared. That storage is a
+//! `Vec` by default. With `--vla-lowering bounded`, arrays whose length has an upper bound that
+//! can be read off the length expression (a `const` local, `n % 16`, `n & 7`, a `char` ...) are
+//! stored in a fixed-size stack array of that capacity instead, as long as that array is small
+//! enough in bytes.
+//!
+//! Subscripts that go all the way down to an element of a local VLA index the storage directly,
+//! so out-of-bounds accesses panic rather than corrupting memory.
+
+use super::*;
+
+use std::str::FromStr;
+
+/// Arrays whose bounded storage would take more bytes than this are always allocated on the heap
+const MAX_BOUNDED_VLA_BYTES: u64 = 16 * 1024;
+
+/// How local variable-length arrays are allocated
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum VlaLowering {
+    /// Always allocate a `Vec` of the requested length
+    Vec,
+    /// Use a fixed-size stack array when the length has a provable upper bound, and a `Vec`
+    /// otherwise
+    Bounded,
+}
+
+impl FromStr for VlaLowering {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<VlaLowering, ()> {
+        match s {
+            "vec" => Ok(VlaLowering::Vec),
+            "bounded" => Ok(VlaLowering::Bounded),
+            _ => Err(()),
+        }
+    }
+}
+
+impl<'c> Translation<'c> {
+    /// Is this a variable-length array type, or an array of them? Both are stored flattened.
+    pub fn is_variable_array(&self, type_id: CTypeId) -> bool {
+        match self.ast_context.resolve_type(type_id).kind {
+            CTypeKind::VariableArray(..) => true,
+            CTypeKind::ConstantArray(elt, _) => self.is_variable_array(elt),
+            _ => false,
+        }
+    }
+
+    /// The number of elements to reserve for a local variable-length array on the stack, if it
+    /// is small enough and stack allocation was requested. Arrays whose element size isn't known
+    /// go on the heap.
+    pub fn variable_array_capacity(&self, type_id: CTypeId) -> Option<u64> {
+        if self.tcfg.vla_lowering != VlaLowering::Bounded {
+            return None
+        }
+        let bound = self.variable_array_bound(type_id)?;
+        let elt_size = self.byte_size(self.variable_array_base_type(type_id))?;
+        bound.checked_mul(elt_size)
+            .filter(|&bytes| bytes <= MAX_BOUNDED_VLA_BYTES)
+            .map(|_| bound)
+    }
+
+    /// The size in bytes of a value of a fixed-size type on the translation's target
+    fn byte_size(&self, type_id: CTypeId) -> Option<u64> {
+        let target = &self.ast_context.target;
+        match self.ast_context.resolve_type(type_id).kind {
+            CTypeKind::Bool | CTypeKind::Char | CTypeKind::SChar | CTypeKind::UChar => Some(1),
+            CTypeKind::Short | CTypeKind::UShort | CTypeKind::Half => Some(2),
+            CTypeKind::Int | CTypeKind::UInt | CTypeKind::Float => Some(4),
+            CTypeKind::Long | CTypeKind::ULong => Some(target.long_width / 8),
+            CTypeKind::LongLong | CTypeKind::ULongLong | CTypeKind::Double => Some(8),
+            CTypeKind::LongDouble | CTypeKind::Int128 | CTypeKind::UInt128 => Some(16),
+            CTypeKind::Pointer(..) | CTypeKind::BlockPointer(..) => Some(target.pointer_width / 8),
+            CTypeKind::ConstantArray(elt, len) => self.byte_size(elt)?.checked_mul(len as u64),
+            CTypeKind::Enum(decl_id) => match self.ast_context[decl_id].kind {
+                CDeclKind::Enum { integral_type: Some(ty), .. } => self.byte_size(ty.ctype),
+                _ => None,
+            },
+            CTypeKind::Struct(decl_id) | CTypeKind::Union(decl_id) => match self.ast_context[decl_id].kind {
+                CDeclKind::Struct { fields: Some(_), platform_byte_size, .. } |
+                CDeclKind::Union { fields: Some(_), platform_byte_size, .. } => Some(platform_byte_size),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// An upper bound on the number of base elements of a variable-length array type
+    fn variable_array_bound(&self, type_id: CTypeId) -> Option<u64> {
+        let (elt, len) = match self.ast_context.resolve_type(type_id).kind {
+            CTypeKind::VariableArray(elt, Some(len)) => (elt, self.length_bound(len)?),
+            CTypeKind::ConstantArray(elt, len) => (elt, len as u64),
+            _ => return None,
+        };
+        if self.is_variable_array(elt) {
+            len.checked_mul(self.variable_array_bound(elt)?)
+        } else {
+            Some(len)
+        }
+    }
+
+    /// An upper bound on the value of an array length expression, if one is evident from its
+    /// structure. Lengths are never negative, so only upper bounds matter.
+    fn length_bound(&self, expr_id: CExprId) -> Option<u64> {
+        let bound = match self.ast_context[expr_id].kind {
+            CExprKind::Literal(_, CLiteral::Integer(n, _)) => Some(n),
+            CExprKind::ImplicitCast(_, expr, CastKind::IntegralCast, _, _) |
+            CExprKind::ImplicitCast(_, expr, CastKind::LValueToRValue, _, _) |
+            CExprKind::ImplicitCast(_, expr, CastKind::NoOp, _, _) |
+            CExprKind::ExplicitCast(_, expr, CastKind::IntegralCast, _, _) =>
+                self.length_bound(expr),
+            CExprKind::DeclRef(_, decl_id, _) => match self.ast_context[decl_id].kind {
+                CDeclKind::EnumConstant { value: ConstIntExpr::U(n), .. } => Some(n),
+                CDeclKind::EnumConstant { value: ConstIntExpr::I(n), .. } if n >= 0 => Some(n as u64),
+                // A `const` local can only ever hold its initial value
+                CDeclKind::Variable { is_static: false, is_extern: false, typ, initializer: Some(init), .. }
+                    if typ.qualifiers.is_const && !typ.qualifiers.is_volatile => self.length_bound(init),
+                _ => None,
+            },
+            CExprKind::Binary(_, c_ast::BinOp::Modulus, _, rhs, _, _) =>
+                self.length_bound(rhs).map(|n| n.saturating_sub(1)),
+            CExprKind::Binary(_, c_ast::BinOp::BitAnd, lhs, rhs, _, _) =>
+                match (self.length_bound(lhs), self.length_bound(rhs)) {
+                    (Some(l), Some(r)) => Some(l.min(r)),
+                    (l, r) => l.or(r),
+                },
+            CExprKind::Binary(_, c_ast::BinOp::Divide, lhs, rhs, _, _) =>
+                match self.ast_context[rhs].kind {
+                    CExprKind::Literal(_, CLiteral::Integer(n, _)) if n > 0 =>
+                        self.length_bound(lhs).map(|l| l / n),
+                    _ => None,
+                },
+            CExprKind::Binary(_, c_ast::BinOp::Add, lhs, rhs, _, _) =>
+                self.length_bound(lhs)?.checked_add(self.length_bound(rhs)?),
+            CExprKind::Binary(_, c_ast::BinOp::Multiply, lhs, rhs, _, _) =>
+                self.length_bound(lhs)?.checked_mul(self.length_bound(rhs)?),
+            CExprKind::Conditional(_, _, lhs, rhs) =>
+                Some(self.length_bound(lhs)?.max(self.length_bound(rhs)?)),
+            _ => None,
+        };
+
+        // Small integer types bound any expression of that type
+        bound.or_else(|| {
+            let ty = self.ast_context[expr_id].kind.get_type()?;
+            match self.ast_context.resolve_type(ty).kind {
+                CTypeKind::Bool => Some(1),
+                CTypeKind::Char if self.ast_context.target.char_is_signed => Some(127),
+                CTypeKind::SChar => Some(127),
+                CTypeKind::Char | CTypeKind::UChar => Some(255),
+                _ => None,
+            }
+        })
+    }
+
+    /// Translate a subscript that selects an element of a local variable-length array as an
+    /// index into the array's storage. Returns `None` for any other subscript, including ones
+    /// that select a row of a multi-dimensional VLA.
+    pub fn convert_variable_array_subscript(
+        &self,
+        ctx: ExprContext,
+        expr_id: CExprId,
+    ) -> Result<Option<WithStmts<P<Expr>>>, String> {
+        let (arr, index) = match self.subscripted_variable_array(expr_id) {
+            Some(parts) => parts,
+            None => return Ok(None),
+        };
+        let arr_ty = self.ast_context[arr].kind.get_type().ok_or_else(|| format!("bad arr type"))?;
+        if !self.is_variable_array(arr_ty) || self.is_variable_array(self.array_element(arr_ty)) {
+            return Ok(None)
+        }
+
+        let (decl_id, mut offset) = match self.variable_array_offset(ctx, arr)? {
+            Some(offset) => offset,
+            None => return Ok(None),
+        };
+        let index = self.convert_expr(ctx.used(), index)?;
+        offset.stmts.extend(index.stmts);
+        let index = match offset.val {
+            Some(offset) => mk().binary_expr(BinOpKind::Add, offset, cast_int(index.val, "usize")),
+            None => cast_int(index.val, "usize"),
+        };
+
+        let name = self.renamer.borrow().get(&decl_id)
+            .ok_or_else(|| format!("Variable-length array was not renamed"))?;
+        Ok(Some(WithStmts {
+            stmts: offset.stmts,
+            val: mk().index_expr(mk().path_expr(vec![name]), index),
+        }))
+    }
+
+    /// The offset, counted in base elements, of a row of a local variable-length array, along
+    /// with the array itself. `None` as the offset stands for the start of the array.
+    fn variable_array_offset(
+        &self,
+        ctx: ExprContext,
+        expr_id: CExprId,
+    ) -> Result<Option<(CDeclId, WithStmts<Option<P<Expr>>>)>, String> {
+        if let CExprKind::DeclRef(_, decl_id, _) = self.ast_context[expr_id].kind {
+            return Ok(match self.ast_context[decl_id].kind {
+                CDeclKind::Variable { is_static: false, is_extern: false, .. } =>
+                    Some((decl_id, WithStmts::new(None))),
+                _ => None,
+            })
+        }
+
+        let (arr, index) = match self.subscripted_variable_array(expr_id) {
+            Some(parts) => parts,
+            None => return Ok(None),
+        };
+        let (decl_id, mut offset) = match self.variable_array_offset(ctx, arr)? {
+            Some(offset) => offset,
+            None => return Ok(None),
+        };
+
+        let arr_ty = self.ast_context[arr].kind.get_type().ok_or_else(|| format!("bad arr type"))?;
+        let row_size = self.compute_size_of_expr(self.array_element(arr_ty))
+            .ok_or_else(|| format!("Row of a variable-length array has a fixed size"))?;
+        let index = self.convert_expr(ctx.used(), index)?;
+        offset.stmts.extend(index.stmts);
+        let row = mk().binary_expr(BinOpKind::Mul, row_size, cast_int(index.val, "usize"));
+        let val = match offset.val {
+            Some(offset) => mk().binary_expr(BinOpKind::Add, offset, row),
+            None => row,
+        };
+
+        Ok(Some((decl_id, WithStmts { stmts: offset.stmts, val: Some(val) })))
+    }
+
+    /// Split `arr[index]` (or `index[arr]`) into the array that decays to the subscripted pointer
+    /// and the index.
+    fn subscripted_variable_array(&self, expr_id: CExprId) -> Option<(CExprId, CExprId)> {
+        let (lhs, rhs) = match self.ast_context[expr_id].kind {
+            CExprKind::ArraySubscript(_, lhs, rhs, _) => (lhs, rhs),
+            _ => return None,
+        };
+        let decayed = |expr: CExprId| match self.ast_context[expr].kind {
+            CExprKind::ImplicitCast(_, arr, CastKind::ArrayToPointerDecay, _, _) => Some(arr),
+            _ => None,
+        };
+        decayed(lhs).map(|arr| (arr, rhs))
+            .or_else(|| decayed(rhs).map(|arr| (arr, lhs)))
+    }
+
+    /// The element type of an array type
+    fn array_element(&self, type_id: CTypeId) -> CTypeId {
+        match self.ast_context.resolve_type(type_id).kind {
+            CTypeKind::VariableArray(elt, _) |
+            CTypeKind::ConstantArray(elt, _) |
+            CTypeKind::IncompleteArray(elt) => elt,
+            _ => type_id,
+        }
+    }
+}
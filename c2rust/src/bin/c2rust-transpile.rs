@@ -3,12 +3,12 @@ extern crate clap;
 extern crate c2rust_transpile;
 
 use std::collections::HashSet;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use clap::{App, Values};
 use regex::Regex;
 
-use c2rust_transpile::{TranspilerConfig, ReplaceMode, Diagnostic};
+use c2rust_transpile::{TranspilerConfig, ReplaceMode, VlaLowering, OverflowMode, Diagnostic, Configuration};
 
 
 fn main() {
@@ -17,12 +17,6 @@ fn main() {
         .get_matches();
 
     // Build a TranspilerConfig from the command line
-    let cc_json_path = Path::new(matches.value_of("COMPILE_COMMANDS").unwrap()).canonicalize().unwrap();
-    let extra_args: Vec<&str> = match matches.values_of("extra-clang-args") {
-        Some(args) => args.collect(),
-        None => Vec::new(),
-    };
-
     let enabled_warnings: HashSet<Diagnostic> = matches.values_of("warn")
         .unwrap_or_else(|| Values::default())
         .map(|s| Diagnostic::from_str(s).unwrap())
@@ -35,6 +29,7 @@ fn main() {
         dump_function_cfgs:     matches.is_present("dump-function-cfgs"),
         json_function_cfgs:     matches.is_present("json-function-cfgs"),
         dump_cfg_liveness:      matches.is_present("dump-cfgs-liveness"),
+        dump_cfg_html:          matches.value_of("dump-cfg-html").map(PathBuf::from),
         dump_structures:        matches.is_present("dump-structures"),
 
         incremental_relooper:   !matches.is_present("no-incremental-relooper"),
@@ -66,6 +61,25 @@ fn main() {
         overwrite_existing:     matches.is_present("overwrite-existing"),
         reduce_type_annotations:matches.is_present("reduce-type-annotations"),
         reorganize_definitions: matches.is_present("reorganize-definitions"),
+        emit_source_map:        matches.is_present("emit-source-map"),
+        annotate_source_locations: matches.is_present("annotate-source-locations"),
+        vla_lowering:           matches.value_of("vla-lowering")
+            .and_then(|x| VlaLowering::from_str(x).ok())
+            .unwrap(),
+        overflow_mode:          matches.value_of("overflow-mode")
+            .and_then(|x| OverflowMode::from_str(x).ok())
+            .unwrap(),
+        nonnull_references:     matches.is_present("nonnull-references"),
+        assert_field_offsets:   matches.is_present("assert-field-offsets"),
+        node_splitting_budget:  matches.value_of("node-splitting-budget")
+            .map(|x| x.parse().expect("--node-splitting-budget expects a percentage")),
+        configurations:         matches.values_of("configuration")
+            .map(|vals| vals
+                .map(|x| x.parse::<Configuration>().unwrap_or_else(|e| panic!("{}", e)))
+                .collect::<Vec<_>>())
+            .unwrap_or_default(),
+        target:                 matches.value_of("target").map(String::from),
+        save_ast:               matches.value_of("save-ast").map(PathBuf::from),
         emit_modules:           matches.is_present("emit-modules"),
         emit_build_files:       matches.is_present("emit-build-files"),
         build_directory_name:   matches.value_of("build-directory-name")
@@ -97,5 +111,16 @@ fn main() {
     // emit-build-files implies emit-modules
     if tcfg.emit_build_files { tcfg.emit_modules = true };
 
+    if let Some(ast_path) = matches.value_of("from-ast") {
+        c2rust_transpile::transpile_from_ast(tcfg, Path::new(ast_path));
+        return;
+    }
+
+    let cc_json_path = Path::new(matches.value_of("COMPILE_COMMANDS").unwrap()).canonicalize().unwrap();
+    let extra_args: Vec<&str> = match matches.values_of("extra-clang-args") {
+        Some(args) => args.collect(),
+        None => Vec::new(),
+    };
+
     c2rust_transpile::transpile(tcfg, &cc_json_path, &extra_args);
 }
@@ -149,8 +149,12 @@ def _main():
     except cbor2.CBORDecodeError as de:
         die("CBOR decoding error:" + str(de))
 
+    header = array[0]
+    if not isinstance(header, list) or header[:1] != ["c2rust-ast"]:
+        die("not an AST exported by c2rust")
+
     # translate tags
-    for e in array:
+    for e in array[1]:
         assert len(e) >= 2
         e[1] = TAGS[e[1]] if e[1] in TAGS else "MissingTag"
 
@@ -44,7 +44,8 @@ cargo = get_cmd_or_die("cargo")
 
 # Intermediate files
 intermediate_files = [
-    'cc_db', 'cbor', 'c_obj', 'c_lib', 'rust_src', 'rust_test_exec',
+    'cc_db', 'cbor', 'c_obj', 'c_lib', 'rust_src', 'source_map',
+    'rust_test_exec',
 ]
 
 
@@ -69,22 +70,25 @@ class CFile:
             flags = set()
 
         self.path = path
+        self.flags = frozenset(flags)
         self.enable_incremental_relooper = "incremental_relooper" in flags
         self.disallow_current_block = "disallow_current_block" in flags
-
-    def translate(self, cc_db, extra_args: List[str] = []) -> RustFile:
-        extensionless_file, _ = os.path.splitext(self.path)
-
-        # help plumbum find rust
-        ld_lib_path = get_rust_toolchain_libpath()
-        if 'LD_LIBRARY_PATH' in pb.local.env:
-            ld_lib_path += ':' + pb.local.env['LD_LIBRARY_PATH']
-
-        # run the transpiler
-        transpiler = get_cmd_or_die(c.TRANSPILER)
-
+        self.bounded_vlas = "bounded_vlas" in flags
+        self.blocks = "blocks" in flags
+        self.checked_overflow = "checked_overflow" in flags
+        self.wrapping_overflow = "wrapping_overflow" in flags
+        self.nonnull_references = "nonnull_references" in flags
+        self.node_splitting = "node_splitting" in flags
+        self.round_trip_ast = "round_trip_ast" in flags
+        self.source_map = "source_map" in flags
+        self.field_offsets = "field_offsets" in flags
+        self.reorganize_definitions = "reorganize_definitions" in flags
+
+    def _translation_args(self) -> List[str]:
+        """
+        Transpiler options selected by this file's flags.
+        """
         args = [
-            cc_db,
             "--prefix-function-names",
             "rust_",
             "--overwrite-existing",
@@ -94,17 +98,33 @@ class CFile:
             args.append("--no-incremental-relooper")
         if self.disallow_current_block:
             args.append("--fail-on-multiple")
+        if self.bounded_vlas:
+            args.extend(["--vla-lowering", "bounded"])
+        if self.checked_overflow:
+            args.extend(["--overflow-mode", "checked"])
+        if self.wrapping_overflow:
+            args.extend(["--overflow-mode", "wrapping"])
+        if self.nonnull_references:
+            args.append("--nonnull-references")
+        if self.node_splitting:
+            args.extend(["--node-splitting-budget", "100"])
+        if self.source_map:
+            args.append("--emit-source-map")
+        if self.field_offsets:
+            args.append("--assert-field-offsets")
+        if self.reorganize_definitions:
+            args.append("--reorganize-definitions")
+
+        return args
+
+    def _run_transpiler(self, args: List[str]) -> None:
+        # help plumbum find rust
+        ld_lib_path = get_rust_toolchain_libpath()
+        if 'LD_LIBRARY_PATH' in pb.local.env:
+            ld_lib_path += ':' + pb.local.env['LD_LIBRARY_PATH']
 
-        args.append("--")
-        args.extend(extra_args)
-
-        # Add -isysroot on MacOS to get SDK directory
-        if on_mac():
-            try:
-                xcrun = pb.local["xcrun"]
-                args.append("-isysroot" + xcrun("--show-sdk-path").strip())
-            except pb.CommandNotFound:
-                pass
+        # run the transpiler
+        transpiler = get_cmd_or_die(c.TRANSPILER)
 
         with pb.local.env(RUST_BACKTRACE='1', LD_LIBRARY_PATH=ld_lib_path):
             # log the command in a format that's easy to re-run
@@ -120,6 +140,58 @@ class CFile:
         if retcode != 0:
             raise NonZeroReturn(stderr)
 
+    def translate(self, cc_db, extra_args: List[str] = [],
+                  ast_dir: Optional[str] = None) -> None:
+        """
+        Translate every file in `cc_db` with this file's flags, so the files
+        listed there must all have the same flags. The ASTs of the files are
+        saved into `ast_dir` if it is given.
+        """
+        args = [cc_db] + self._translation_args()
+        if ast_dir:
+            args.extend(["--save-ast", ast_dir])
+
+        args.append("--")
+        args.extend(extra_args)
+
+        if self.blocks:
+            args.append("-fblocks")
+
+        # Add -isysroot on MacOS to get SDK directory
+        if on_mac():
+            try:
+                xcrun = pb.local["xcrun"]
+                args.append("-isysroot" + xcrun("--show-sdk-path").strip())
+            except pb.CommandNotFound:
+                pass
+
+        self._run_transpiler(args)
+
+    def check_ast_round_trip(self, ast_dir: str) -> RustFile:
+        """
+        Translate the AST of this file saved in `ast_dir` and check that the
+        result is the same as the translation from the C file. Returns the
+        translation from the saved AST.
+        """
+        ast_path = os.path.join(ast_dir, os.path.basename(self.path) + ".cbor")
+        self._run_transpiler(self._translation_args() + ["--from-ast", ast_path])
+
+        extensionless_ast_file, _ = os.path.splitext(os.path.splitext(ast_path)[0])
+        round_trip_file = RustFile(extensionless_ast_file + ".rs")
+
+        retcode, stdout, _ = diff[self.rust_file().path,
+                                  round_trip_file.path].run(retcode=None)
+        if retcode != 0:
+            raise NonZeroReturn("translation from the saved AST differs:\n" + stdout)
+
+        return round_trip_file
+
+    def source_map_path(self) -> str:
+        extensionless_file, _ = os.path.splitext(self.path)
+        return extensionless_file + ".srcmap.json"
+
+    def rust_file(self) -> RustFile:
+        extensionless_file, _ = os.path.splitext(self.path)
         return RustFile(extensionless_file + ".rs")
 
 
@@ -132,6 +204,9 @@ def build_static_library(c_files: Iterable[CFile],
     # create .o files
     args = ["-c", "-fPIC", "-march=native"]
 
+    if any(c_file.blocks for c_file in c_files):
+        args.append("-fblocks")
+
     args.extend(c_file.path for c_file in c_files)
 
     if len(args) == 2:
@@ -196,6 +271,7 @@ class TestDirectory:
         self.generated_files = {
             "rust_src": [],
             "cbor": [],
+            "source_map": [],
             "c_obj": [],
             "c_lib": [],
             "rust_test_exec": [],
@@ -270,18 +346,17 @@ class TestDirectory:
         if message:
             sys.stdout.write(message)
 
-    def _generate_cc_db(self, c_file_path: str) -> None:
-        directory, cfile = os.path.split(c_file_path)
+    def _generate_cc_db(self, c_file_paths: List[str]) -> None:
+        directory = os.path.dirname(c_file_paths[0])
 
-        compile_commands = """ \
-        [
+        entries = ",".join("""
           {{
             "arguments": [ "cc", "-D_FORTIFY_SOURCE=0", "-c", "{0}" ],
             "directory": "{1}",
             "file": "{0}"
-          }}
-        ]
-        """.format(cfile, directory)
+          }}""".format(os.path.basename(path), directory)
+            for path in c_file_paths)
+        compile_commands = "[{}\n]\n".format(entries)
 
         cc_db = os.path.join(directory, "compile_commands.json")
 
@@ -293,6 +368,18 @@ class TestDirectory:
         with open(cc_db, 'w') as fh:
             fh.write(compile_commands)
 
+    def _group_by_flags(self) -> List[List[CFile]]:
+        """
+        Group the C files that can be translated by a single run of the
+        transpiler, which exercises translating several files at once.
+        """
+        groups = {}
+
+        for c_file in self.c_files:
+            groups.setdefault(c_file.flags, []).append(c_file)
+
+        return list(groups.values())
+
     def run(self) -> List[TestOutcome]:
         outcomes = []
 
@@ -335,38 +422,58 @@ class TestDirectory:
         self.generated_files["c_obj"].extend(static_library.obj_files)
 
         rust_file_builder = RustFileBuilder()
-        rust_file_builder.add_features(["libc", "extern_types", "simd_ffi", "stdsimd", "const_transmute", "nll"])
+        rust_file_builder.add_features(["libc", "extern_types", "simd_ffi", "stdsimd", "const_transmute", "nll",
+                                       "const_raw_ptr_deref", "const_raw_ptr_to_usize_cast"])
 
         # .c -> .rs
-        for c_file in self.c_files:
-            _, c_file_short = os.path.split(c_file.path)
-            description = "{}: translating the C file into Rust...".format(
-                c_file_short)
+        for c_files in self._group_by_flags():
+            c_files_short = ", ".join(os.path.basename(c_file.path)
+                                      for c_file in c_files)
+            description = "{}: translating the C files into Rust...".format(
+                c_files_short)
 
             # Run the step
             self.print_status(Colors.WARNING, "RUNNING", description)
 
-            self._generate_cc_db(c_file.path)
+            self._generate_cc_db([c_file.path for c_file in c_files])
+
+            # Files marked `round_trip_ast` are also translated from their
+            # saved ASTs, which has to give the same Rust code
+            ast_dir = None
+            if c_files[0].round_trip_ast:
+                ast_dir = os.path.join(self.full_path, "saved_asts")
 
             try:
-                translated_rust_file = c_file.translate(self.generated_files["cc_db"],
-                                                        extra_args=["-march=native"])
+                c_files[0].translate(self.generated_files["cc_db"],
+                                     extra_args=["-march=native"],
+                                     ast_dir=ast_dir)
+                if ast_dir:
+                    for c_file in c_files:
+                        self.generated_files["cbor"].append(os.path.join(
+                            ast_dir, os.path.basename(c_file.path) + ".cbor"))
+                        self.generated_files["rust_src"].append(
+                            c_file.check_ast_round_trip(ast_dir))
             except NonZeroReturn as exception:
                 self.print_status(Colors.FAIL, "FAILED", "translate " +
-                                  c_file_short)
+                                  c_files_short)
                 sys.stdout.write('\n')
                 sys.stdout.write(str(exception))
 
                 outcomes.append(TestOutcome.UnexpectedFailure)
                 continue
 
-            self.generated_files["rust_src"].append(translated_rust_file)
+            for c_file in c_files:
+                translated_rust_file = c_file.rust_file()
+                self.generated_files["rust_src"].append(translated_rust_file)
+                if c_file.source_map:
+                    self.generated_files["source_map"].append(
+                        c_file.source_map_path())
 
-            _, rust_file_short = os.path.split(translated_rust_file.path)
-            extensionless_rust_file, _ = os.path.splitext(rust_file_short)
+                _, rust_file_short = os.path.split(translated_rust_file.path)
+                extensionless_rust_file, _ = os.path.splitext(rust_file_short)
 
-            rust_file_builder.add_mod(RustMod(extensionless_rust_file,
-                                              RustVisibility.Public))
+                rust_file_builder.add_mod(RustMod(extensionless_rust_file,
+                                                  RustVisibility.Public))
 
         match_arms = []
         rustc_extra_args = ["-C", "target-cpu=native"]
@@ -0,0 +1,44 @@
+//! bounded_vlas
+
+struct frame {
+    double samples[512];
+};
+
+void bounded_vlas(const unsigned buffer_size, int buffer[]) {
+    if (buffer_size < 9) return;
+
+    const int n = 5;
+    int fixed[n];
+    for (int i = 0; i < n; i++)
+        fixed[i] = i * i;
+
+    unsigned k = buffer_size + 5;
+    int wrapped[k % 8][n];
+    for (unsigned i = 0; i < k % 8; i++)
+        for (int j = 0; j < n; j++)
+            wrapped[i][j] = fixed[j] + (int) i;
+
+    unsigned char c = (unsigned char) buffer_size;
+    int small[c];
+    for (int i = 0; i < c; i++)
+        small[i] = 2 * i;
+
+    // No evident bound, so this one is still allocated on the heap
+    int large[buffer_size];
+    for (unsigned i = 0; i < buffer_size; i++)
+        large[i] = (int) i + 100;
+
+    // Bounded, but too many bytes to put on the stack, so this one is on the heap as well
+    struct frame frames[k % 8];
+    frames[k % 8 - 1].samples[511] = 2.5;
+
+    buffer[0] = fixed[4];
+    buffer[1] = (int) sizeof(fixed);
+    buffer[2] = wrapped[3][2];
+    buffer[3] = (int) sizeof(wrapped);
+    buffer[4] = (int) sizeof(wrapped[0]);
+    buffer[5] = small[7];
+    buffer[6] = (int) sizeof(small);
+    buffer[7] = large[buffer_size - 1];
+    buffer[8] = (int) (frames[k % 8 - 1].samples[511] * 2) + (int) (sizeof(frames) / sizeof(struct frame));
+}
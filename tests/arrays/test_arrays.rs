@@ -3,6 +3,8 @@ extern crate libc;
 use arrays::rust_entry;
 use incomplete_arrays::{rust_test_sized_array,rust_entry2,rust_check_some_ints};
 use variable_arrays::{rust_variable_arrays, rust_alloca_arrays};
+use vla_sizes::rust_vla_sizes;
+use bounded_vlas::rust_bounded_vlas;
 use self::libc::{c_int, c_uint};
 
 #[link(name = "test")]
@@ -24,6 +26,12 @@ extern "C" {
 
     #[no_mangle]
     fn check_some_ints() -> bool;
+
+    #[no_mangle]
+    fn vla_sizes(_: c_uint, _: *mut c_int);
+
+    #[no_mangle]
+    fn bounded_vlas(_: c_uint, _: *mut c_int);
 }
 
 #[no_mangle]
@@ -34,6 +42,8 @@ pub static rust_SOME_INTS: [u32; 4] = [2,0,1,8];
 const BUFFER_SIZE: usize = 49;
 const BUFFER_SIZE2: usize = 2;
 const BUFFER_SIZEV: usize = 88;
+const BUFFER_SIZE_VLA: usize = 11;
+const BUFFER_SIZE_BOUNDED: usize = 9;
 
 pub fn test_sized_array_impls() {
     unsafe {
@@ -134,3 +144,31 @@ pub fn test_alloca_arrays() {
         assert_eq!(buffer[index], rust_buffer[index], "index: {}", index);
     }
 }
+
+pub fn test_vla_sizes() {
+    let mut buffer = [0; BUFFER_SIZE_VLA];
+    let mut rust_buffer = [0; BUFFER_SIZE_VLA];
+    let expected_buffer = [23, 48, 16, 16, 28, 24, 21, 12, 13, 138, 37];
+
+    unsafe {
+        vla_sizes(BUFFER_SIZE_VLA as u32, buffer.as_mut_ptr());
+        rust_vla_sizes(BUFFER_SIZE_VLA as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
+
+pub fn test_bounded_vlas() {
+    let mut buffer = [0; BUFFER_SIZE_BOUNDED];
+    let mut rust_buffer = [0; BUFFER_SIZE_BOUNDED];
+    let expected_buffer = [16, 20, 7, 120, 20, 14, 36, 108, 11];
+
+    unsafe {
+        bounded_vlas(BUFFER_SIZE_BOUNDED as u32, buffer.as_mut_ptr());
+        rust_bounded_vlas(BUFFER_SIZE_BOUNDED as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
@@ -0,0 +1,45 @@
+static int sum_grid(int n, int m, int grid[n][m]) {
+    int sum = 0;
+    for (int i = 0; i < n; i++)
+        for (int j = 0; j < m; j++)
+            sum += grid[i][j];
+    return sum;
+}
+
+void vla_sizes(const unsigned buffer_size, int buffer[]) {
+    if (buffer_size < 11) return;
+
+    int n = 3, m = 4;
+    int grid[n][m];
+    typedef int row[m];
+
+    for (int i = 0; i < n; i++)
+        for (int j = 0; j < m; j++)
+            grid[i][j] = i * 10 + j;
+
+    // Changing the lengths doesn't resize arrays and types that were already declared
+    m = 7;
+
+    int mixed[2][n];
+    for (int i = 0; i < 2; i++)
+        for (int j = 0; j < n; j++)
+            mixed[i][j] = grid[j][i];
+
+    row last;
+    for (int j = 0; j < 4; j++)
+        last[j] = grid[n - 1][j];
+
+    int *second = grid[1];
+
+    buffer[0] = grid[2][3];
+    buffer[1] = (int) sizeof(grid);
+    buffer[2] = (int) sizeof(grid[0]);
+    buffer[3] = (int) sizeof(row);
+    buffer[4] = (int) sizeof(int[m]);
+    buffer[5] = (int) sizeof(mixed);
+    buffer[6] = mixed[1][2];
+    buffer[7] = *(*(grid + 1) + 2);
+    buffer[8] = second[3];
+    buffer[9] = sum_grid(n, 4, grid);
+    buffer[10] = last[1] + (int) sizeof(last);
+}
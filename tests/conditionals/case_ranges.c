@@ -0,0 +1,35 @@
+static int classify(int c) {
+    switch (c) {
+    case 'a' ... 'z':
+        return 1;
+    case 'A' ... 'Z':
+        return 2;
+    case '0' ... '9':
+        return 3;
+    case -10 ... -1:
+        return 4;
+    default:
+        return 0;
+    }
+}
+
+static int table[6] = { [1 ... 3] = 9 };
+
+void case_ranges(const unsigned sz, int buf[const]) {
+    int x = 6;
+    int arr[8] = { [2 ... 5] = x + 1, [7] = 1 };
+    int sum = 0;
+
+    for (int i = 0; i < 8; i++) {
+        sum += arr[i];
+    }
+
+    buf[0] = classify('q');
+    buf[1] = classify('Q');
+    buf[2] = classify('5');
+    buf[3] = classify(-3);
+    buf[4] = classify('!');
+    buf[5] = sum;
+    buf[6] = table[1] + table[3] + table[4];
+    buf[7] = arr[5] * 10 + arr[6];
+}
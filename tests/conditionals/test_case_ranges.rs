@@ -0,0 +1,26 @@
+extern crate libc;
+
+use case_ranges::rust_case_ranges;
+use self::libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn case_ranges(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 8;
+
+pub fn test_case_ranges() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [1, 2, 3, 4, 0, 29, 18, 70];
+
+    unsafe {
+        case_ranges(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_case_ranges(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
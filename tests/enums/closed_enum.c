@@ -0,0 +1,26 @@
+enum Direction { North, East, South, West };
+
+static enum Direction turn_right(enum Direction d) {
+        switch (d) {
+        case North: return East;
+        case East: return South;
+        case South: return West;
+        case West: return North;
+        }
+        return North;
+}
+
+void entry6(const unsigned buffer_size, int buffer[]) {
+        enum Direction d = North;
+
+        for (unsigned i = 0; i < buffer_size; i++) {
+                buffer[i] = d;
+                d = turn_right(d);
+        }
+
+        // Integer constants naming a variant convert without a runtime check
+        d = (enum Direction)2;
+        if (buffer_size > 0) {
+                buffer[0] = d == South;
+        }
+}
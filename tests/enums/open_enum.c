@@ -0,0 +1,32 @@
+enum Mode { Off, On, Auto };
+
+// Other translation units can pass any integer as a `Mode` here
+int mode_is_on(enum Mode m) {
+        return m == On;
+}
+
+enum Level { Low, High };
+
+struct Setting {
+        enum Level level;
+};
+
+// Other translation units can store any integer in the `level` field before calling this
+int setting_is_high(struct Setting *setting) {
+        return setting->level == High;
+}
+
+void entry7(const unsigned buffer_size, int buffer[]) {
+        if (buffer_size < 3) {
+                return;
+        }
+
+        buffer[0] = mode_is_on(On);
+        buffer[1] = mode_is_on(Off);
+        buffer[2] = mode_is_on(Auto);
+
+        struct Setting setting = { High };
+        if (buffer_size > 3) {
+                buffer[3] = setting_is_high(&setting);
+        }
+}
@@ -5,6 +5,8 @@ use enum_ret::{Color, rust_entry2};
 use enum_duplicate::{e, rust_entry3};
 use top_enum::{E as otherE, rust_entry4};
 use big_enum::{E1, E2, E3, rust_entry5};
+use closed_enum::{Direction, rust_entry6};
+use open_enum::{Mode, Setting, rust_entry7, rust_mode_is_on, rust_setting_is_high};
 
 use self::libc::{c_int, c_uint};
 
@@ -24,6 +26,12 @@ extern "C" {
 
     #[no_mangle]
     fn entry5(_: c_uint, _: *mut c_int);
+
+    #[no_mangle]
+    fn entry6(_: c_uint, _: *mut c_int);
+
+    #[no_mangle]
+    fn entry7(_: c_uint, _: *mut c_int);
 }
 
 const BUFFER_SIZE: usize = 10;
@@ -31,6 +39,8 @@ const BUFFER_SIZE2: usize = 7;
 const BUFFER_SIZE3: usize = 4;
 const BUFFER_SIZE4: usize = 1;
 const BUFFER_SIZE5: usize = 6;
+const BUFFER_SIZE6: usize = 6;
+const BUFFER_SIZE7: usize = 4;
 
 
 pub fn test_variants() {
@@ -108,3 +118,51 @@ pub fn test_buffer5() {
     assert_eq!(buffer, rust_buffer);
     assert_eq!(buffer, expected_buffer);
 }
+
+pub fn test_closed_enum() {
+    // `Direction` only ever holds its own constants, so it becomes a Rust enum
+    assert_eq!(Direction::South as u32, 2);
+    assert_eq!(Direction::try_from(3), Ok(Direction::West));
+    assert_eq!(Direction::try_from(4), Err(4));
+
+    let mut buffer = [0; BUFFER_SIZE6];
+    let mut rust_buffer = [0; BUFFER_SIZE6];
+    let expected_buffer = [1, 1, 2, 3, 0, 1];
+
+    unsafe {
+        entry6(BUFFER_SIZE6 as u32, buffer.as_mut_ptr());
+        rust_entry6(BUFFER_SIZE6 as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
+
+pub fn test_open_enum() {
+    // `Mode` crosses the signature of an exported function, so it stays an integer type and
+    // values outside of its constants are passed through
+    let unknown: Mode = 7;
+    assert_eq!(unsafe { rust_mode_is_on(unknown) }, 0);
+
+    let mut buffer = [0; BUFFER_SIZE7];
+    let mut rust_buffer = [0; BUFFER_SIZE7];
+    let expected_buffer = [1, 0, 0, 1];
+
+    unsafe {
+        entry7(BUFFER_SIZE7 as u32, buffer.as_mut_ptr());
+        rust_entry7(BUFFER_SIZE7 as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
+
+pub fn test_open_enum_field() {
+    // `Level` is reachable through the `struct Setting *` parameter of an exported function, so
+    // the field keeps the integer type and can hold values outside of its constants
+    let mut setting = Setting { level: 9 };
+    assert_eq!(unsafe { rust_setting_is_high(&mut setting) }, 0);
+
+    setting.level = 1;
+    assert_eq!(unsafe { rust_setting_is_high(&mut setting) }, 1);
+}
@@ -0,0 +1,31 @@
+//! disallow_current_block
+
+int cleanup_for(int n) {
+    int total = 0;
+    int i;
+
+    for (i = 0; i < n; i++) {
+        if (i == 7)
+            goto out;
+        total += i;
+    }
+    total *= 2;
+
+out:
+    return total;
+}
+
+int cleanup_while(int x) {
+    int steps = 0;
+
+    while (x > 1) {
+        if (steps > 50)
+            goto out;
+        x = x % 2 ? 3 * x + 1 : x / 2;
+        steps++;
+    }
+    steps = steps * 10;
+
+out:
+    return steps;
+}
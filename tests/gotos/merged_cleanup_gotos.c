@@ -0,0 +1,16 @@
+// The labeled block for `goto fail` is followed by a `Multiple` dispatching between `fail` and
+// `out`, so breaking out of it has to record which of the two comes next
+int merged_cleanups(int x) {
+    int r = 0;
+
+    if (x < 0)
+        goto fail;
+    r += x;
+    if (x > 10)
+        goto out;
+
+fail:
+    r -= 1;
+out:
+    return r;
+}
@@ -0,0 +1,32 @@
+//! node_splitting, disallow_current_block
+
+int split_jump_into_while(int x, int n) {
+    int total = 0;
+
+    if (x & 1)
+        goto middle;
+
+    while (n > 0) {
+        total += n;
+    middle:
+        total += 2;
+        n -= 3;
+    }
+
+    return total;
+}
+
+int split_jump_into_for(int n) {
+    int acc = 0;
+
+    if (n > 5)
+        goto inner;
+
+    for (; n > 0; n--) {
+        acc += 10;
+    inner:
+        acc += n;
+    }
+
+    return acc;
+}
@@ -0,0 +1,39 @@
+extern crate libc;
+
+use self::libc::c_int;
+use cleanup_gotos::{rust_cleanup_for, rust_cleanup_while};
+use merged_cleanup_gotos::rust_merged_cleanups;
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn cleanup_for(_: c_int) -> c_int;
+    #[no_mangle]
+    fn cleanup_while(_: c_int) -> c_int;
+    #[no_mangle]
+    fn merged_cleanups(_: c_int) -> c_int;
+}
+
+pub fn test_cleanup_for() {
+    unsafe {
+        for n in -1..12 {
+            assert_eq!(rust_cleanup_for(n), cleanup_for(n));
+        }
+    }
+}
+
+pub fn test_cleanup_while() {
+    unsafe {
+        for x in 0..40 {
+            assert_eq!(rust_cleanup_while(x), cleanup_while(x));
+        }
+    }
+}
+
+pub fn test_merged_cleanups() {
+    unsafe {
+        for x in -3..15 {
+            assert_eq!(rust_merged_cleanups(x), merged_cleanups(x));
+        }
+    }
+}
@@ -0,0 +1,30 @@
+extern crate libc;
+
+use self::libc::c_int;
+use split_irreducible::{rust_split_jump_into_for, rust_split_jump_into_while};
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn split_jump_into_while(_: c_int, _: c_int) -> c_int;
+    #[no_mangle]
+    fn split_jump_into_for(_: c_int) -> c_int;
+}
+
+pub fn test_split_jump_into_while() {
+    unsafe {
+        for x in 0..2 {
+            for n in -2..12 {
+                assert_eq!(rust_split_jump_into_while(x, n), split_jump_into_while(x, n));
+            }
+        }
+    }
+}
+
+pub fn test_split_jump_into_for() {
+    unsafe {
+        for n in -2..12 {
+            assert_eq!(rust_split_jump_into_for(n), split_jump_into_for(n));
+        }
+    }
+}
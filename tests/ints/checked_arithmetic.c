@@ -0,0 +1,46 @@
+//! checked_overflow
+
+void checked_arithmetic(const unsigned sz, int buf[const]) {
+    int a = 1000, b = -7;
+    unsigned u = 3, v = 5;
+    char c = 100;
+    int i = 0;
+
+    buf[0] = a + b;
+    buf[1] = a - b;
+    buf[2] = a * b;
+    buf[3] = a / b;
+    buf[4] = a % b;
+    buf[5] = -b;
+    buf[6] = (int)(u - v);
+    buf[7] = (int)(v / u + v % u);
+
+    a += 5;
+    a -= 10;
+    a *= 2;
+    a /= 3;
+    a %= 100;
+    buf[8] = a;
+
+    c += 20;
+    buf[9] = c;
+
+    i++;
+    ++i;
+    i--;
+    buf[10] = i++;
+    buf[11] = i;
+
+    u -= 4;
+    buf[12] = (int)u;
+
+    // Increments of types narrower than int are done in int and converted back, which wraps
+    signed char sc = 127;
+    sc++;
+    buf[13] = sc;
+    short s = -32768;
+    buf[14] = s--;
+    buf[15] = s;
+    signed char t = 127;
+    buf[16] = ++t;
+}
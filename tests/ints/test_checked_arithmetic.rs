@@ -0,0 +1,27 @@
+extern crate libc;
+
+use checked_arithmetic::rust_checked_arithmetic;
+use self::libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn checked_arithmetic(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 17;
+
+pub fn test_checked_arithmetic() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [993, 1007, -7000, -142, 6, 7, -2, 3, 63, 120, 1, 2, -1,
+                           -128, -32768, 32767, -128];
+
+    unsafe {
+        checked_arithmetic(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_checked_arithmetic(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
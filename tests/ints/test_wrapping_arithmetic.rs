@@ -0,0 +1,30 @@
+extern crate libc;
+
+use wrapping_arithmetic::{rust_wrapping_sum, rust_wrapping_product, rust_wrapping_negation,
+                          rust_wrapping_increment, rust_wrapping_compound,
+                          rust_wrapping_static_division};
+use self::libc::c_int;
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn wrapping_sum(_: c_int, _: c_int) -> c_int;
+
+    #[no_mangle]
+    fn wrapping_static_division() -> c_int;
+}
+
+pub fn test_wrapping_arithmetic() {
+    unsafe {
+        assert_eq!(wrapping_sum(40, 2), rust_wrapping_sum(40, 2));
+        assert_eq!(wrapping_static_division(), rust_wrapping_static_division());
+        assert_eq!(rust_wrapping_static_division(), 131);
+
+        // Signed overflow is undefined in C, so only the translation is checked here
+        assert_eq!(rust_wrapping_sum(c_int::max_value(), 1), c_int::min_value());
+        assert_eq!(rust_wrapping_product(c_int::max_value(), 2), -2);
+        assert_eq!(rust_wrapping_negation(c_int::min_value()), c_int::min_value());
+        assert_eq!(rust_wrapping_increment(c_int::max_value()), c_int::min_value());
+        assert_eq!(rust_wrapping_compound(c_int::min_value(), 1), c_int::max_value());
+    }
+}
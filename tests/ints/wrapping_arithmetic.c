@@ -0,0 +1,29 @@
+//! wrapping_overflow
+
+int wrapping_sum(int x, int y) {
+    return x + y;
+}
+
+int wrapping_product(int x, int y) {
+    return x * y;
+}
+
+int wrapping_negation(int x) {
+    return -x;
+}
+
+int wrapping_increment(int x) {
+    x++;
+    return x;
+}
+
+int wrapping_compound(int x, int y) {
+    x -= y;
+    return x;
+}
+
+static int static_quotient = (40 / 3) * 10 + 40 % 3;
+
+int wrapping_static_division(void) {
+    return static_quotient;
+}
@@ -0,0 +1,24 @@
+static int constructed = 0;
+
+__attribute__((constructor))
+static void set_constructed(void) {
+    constructed = 42;
+}
+
+__attribute__((section(".data.tagged")))
+int tagged = 7;
+
+int sum_tagged(int x) {
+    return x + tagged;
+}
+
+int sum_alias(int x) __attribute__((alias("sum_tagged")));
+
+void attributes(const unsigned buffer_size, int buffer[]) {
+    if (buffer_size < 4) return;
+
+    buffer[0] = constructed;
+    buffer[1] = tagged;
+    buffer[2] = sum_tagged(1);
+    buffer[3] = sum_alias(2);
+}
@@ -0,0 +1,62 @@
+//! blocks
+
+// Just enough of the blocks runtime for blocks that are never copied
+void *_NSConcreteGlobalBlock[32];
+void *_NSConcreteStackBlock[32];
+void _Block_object_assign(void *dst, const void *src, const int flags) {}
+void _Block_object_dispose(const void *src, const int flags) {}
+
+typedef int (^int_op)(int);
+
+static int apply(int_op op, int x) {
+    return op(x);
+}
+
+static void repeat(int n, void (^body)(int)) {
+    for (int i = 0; i < n; i++) {
+        body(i);
+    }
+}
+
+void blocks(const unsigned buffer_size, int buffer[]) {
+    if (buffer_size < 7) return;
+
+    // No captures
+    int_op twice = ^(int x) { return 2 * x; };
+    buffer[0] = twice(21);
+
+    // Captured by copy: later changes aren't seen by the block
+    int offset = 10;
+    int_op add_offset = ^(int x) { return x + offset; };
+    offset = 100;
+    buffer[1] = apply(add_offset, 5);
+
+    // __block variables are shared with the block
+    __block int sum = 0;
+    repeat(5, ^(int i) { sum += i; });
+    buffer[2] = sum;
+
+    // Recursion through a __block variable
+    __block int_op fact = 0;
+    fact = ^(int n) { return n <= 1 ? 1 : n * fact(n - 1); };
+    buffer[3] = fact(5);
+
+    // Blocks without parameters, called from other blocks
+    __block int counter = 0;
+    void (^tick)(void) = ^{ counter++; };
+    tick();
+    repeat(3, ^(int i) { tick(); });
+    buffer[4] = counter;
+
+    // Null block pointers
+    int_op none = 0;
+    buffer[5] = none == 0 ? 7 : 8;
+
+    // A block literal evaluated on every iteration reuses its storage
+    int total = 0;
+    for (int i = 0; i < 4; i++) {
+        int_op add_i = ^(int x) { return x + i; };
+        total = add_i(total);
+    }
+    buffer[6] = total;
+}
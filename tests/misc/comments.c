@@ -0,0 +1,58 @@
+// Comments in all kinds of positions must not break the translation
+
+static int weigh(int kind) {
+    int weight = 0;
+
+    switch (kind) {
+    // Light things
+    case 0:
+        weight = 1;
+        break;
+
+    /* Heavy things */
+    case 1:
+        weight = 10 /* per item */ * 2;
+        break;
+
+    // Anything else
+    default:
+        weight = -1;
+        // nothing more to do
+    }
+
+    return weight;
+}
+
+static int sum(int n) {
+    int total = 0;
+    for (int i = 0; i < n; i++) {
+        total += i;
+        /* end of the loop body */
+    }
+    return total + /* the offset */ 1;
+}
+
+static int pick(int a,
+                /* the second argument */ int b) {
+    int result = a > b
+        // the larger one
+        ? a
+        : b;
+    if (result > 100) {
+        result = 100;
+        // clamped
+    } else {
+        // left alone
+    }
+    return result;
+}
+
+void comments(const unsigned sz, int buf[const]) {
+    buf[0] = weigh(0);
+    buf[1] = weigh(1);
+    buf[2] = weigh(2);
+    buf[3] = sum(/* four */ 4);
+    buf[4] = pick(3, 7);
+    buf[5] = pick(300, 7);
+    // end of the function
+}
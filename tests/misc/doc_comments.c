@@ -0,0 +1,44 @@
+/**
+ * @brief A point on the integer grid.
+ */
+struct point {
+    int x;
+    int y;
+};
+
+/// The number of points in a test run
+static const int NUM_POINTS = 3;
+
+/**
+ * @brief Computes the Manhattan distance between two points.
+ *
+ * Example:
+ * @code
+ *     struct point a = { 1, 2 }, b = { 4, 0 };
+ *     int d = manhattan(a, b); // 5
+ * @endcode
+ *
+ * @param a the first point
+ * @param[in] b the second point
+ * @return the sum of the absolute differences of
+ *         the coordinates
+ */
+static int manhattan(struct point a, struct point b) {
+    int dx = a.x > b.x ? a.x - b.x : b.x - a.x;
+    int dy = a.y > b.y ? a.y - b.y : b.y - a.y;
+    return dx + dy;
+}
+
+/*!
+ * \brief Fills a buffer with distances from the origin.
+ * \param sz number of elements in \p buf
+ * \param buf the output buffer
+ * \retval void nothing is returned
+ */
+void doc_comments(const unsigned sz, int buf[const]) {
+    struct point origin = { 0, 0 };
+    struct point points[3] = { { 1, 2 }, { -3, 4 }, { 5, -6 } };
+
+    for (int i = 0; i < NUM_POINTS && i < sz; i++)
+        buf[i] = manhattan(origin, points[i]);
+}
@@ -0,0 +1,63 @@
+#include <stdlib.h>
+
+static _Noreturn void die(void) {
+    abort();
+}
+
+__attribute__((noreturn)) static void die_with_message(const char *msg) {
+    (void)msg;
+    __builtin_trap();
+}
+
+typedef void fatal_fn(const char *) __attribute__((noreturn));
+
+/* A pointer to a `_Noreturn` function doesn't diverge by its type */
+static void (*on_error)(void) = die;
+
+static int checked_div(int a, int b) {
+    if (b == 0)
+        die();
+    return a / b;
+}
+
+static int classify(int x) {
+    switch (x) {
+    case 0:
+        return 10;
+    case 1:
+        return 20;
+    default:
+        if (x < 0)
+            die_with_message("negative");
+        return 30;
+    }
+}
+
+static int checked_mod(int a, int b, fatal_fn *fatal) {
+    if (b == 0)
+        fatal("zero");
+    return a % b;
+}
+
+static int sign(int x) {
+    if (x > 0)
+        return 1;
+    if (x < 0)
+        return -1;
+    if (x == 0)
+        return 0;
+    __builtin_unreachable();
+}
+
+void noreturn_calls(const unsigned sz, int buf[const]) {
+    buf[0] = checked_div(10, 2);
+    buf[1] = checked_div(-9, 3);
+    buf[2] = classify(0);
+    buf[3] = classify(1);
+    buf[4] = classify(5);
+    buf[5] = sign(7);
+    buf[6] = sign(-2);
+    buf[7] = sign(0);
+    buf[8] = checked_mod(7, 3, die_with_message);
+    buf[9] = on_error != 0;
+}
@@ -0,0 +1,46 @@
+//! round_trip_ast
+
+// Exercises enough kinds of declarations, statements and expressions that a
+// lossy saved AST would show up as a different translation
+
+enum shape { CIRCLE, SQUARE = 4, TRIANGLE };
+
+struct point {
+    int x;
+    int y;
+};
+
+typedef struct point point_t;
+
+static const char *const shape_names[] = { "circle", "square", "triangle" };
+
+static int counter = 10;
+
+static int manhattan(point_t p, point_t q) {
+    int dx = p.x > q.x ? p.x - q.x : q.x - p.x;
+    int dy = p.y > q.y ? p.y - q.y : q.y - p.y;
+    return dx + dy;
+}
+
+void round_trip(const unsigned buffer_size, int buffer[]) {
+    if (buffer_size < 6) return;
+
+    point_t a = { 1, 2 };
+    point_t b = { .x = -3, .y = 5 };
+    unsigned long mask = 0xf0UL;
+
+    buffer[0] = manhattan(a, b);
+    buffer[1] = TRIANGLE;
+    buffer[2] = shape_names[SQUARE - 3][1];
+    buffer[3] = (int)(mask >> 4);
+
+    for (int i = 0; i < 3; i++) {
+        counter += i;
+    }
+    buffer[4] = counter;
+
+    switch (a.y) {
+        case 2: buffer[5] = sizeof(struct point); break;
+        default: buffer[5] = -1;
+    }
+}
@@ -0,0 +1,12 @@
+//! source_map
+
+int source_map_add(int a, int b) {
+    int sum = a + b;
+    return sum;
+}
+
+void source_map(const unsigned buffer_size, int buffer[]) {
+    if (buffer_size < 1) return;
+
+    buffer[0] = source_map_add(1, 2);
+}
@@ -0,0 +1,20 @@
+struct pair {
+    int first;
+    int second;
+};
+
+enum { PAIR_FIELDS = 2 };
+
+_Static_assert(sizeof(struct pair) == PAIR_FIELDS * sizeof(int), "pair is not packed");
+_Static_assert(sizeof(char) == 1, "char is not a byte");
+_Static_assert(PAIR_FIELDS > 1 && PAIR_FIELDS < 3, "pair has two fields");
+
+void static_assertions(const unsigned buffer_size, int buffer[]) {
+    _Static_assert(sizeof(buffer[0]) == sizeof(int), "elements are ints");
+
+    if (buffer_size < 2) return;
+
+    struct pair p = { 1, 2 };
+    buffer[0] = p.first + PAIR_FIELDS;
+    buffer[1] = (int) sizeof(struct pair);
+}
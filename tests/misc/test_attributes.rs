@@ -0,0 +1,29 @@
+extern crate libc;
+
+use attributes::{rust_attributes, rust_sum_alias};
+use self::libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn attributes(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 4;
+
+pub fn test_attributes() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [42, 7, 8, 9];
+
+    unsafe {
+        attributes(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_attributes(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+
+    // The alias is an exported function of its own that calls its target
+    assert_eq!(unsafe { rust_sum_alias(3) }, 10);
+}
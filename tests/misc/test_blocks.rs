@@ -0,0 +1,26 @@
+extern crate libc;
+
+use blocks::rust_blocks;
+use self::libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn blocks(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 7;
+
+pub fn test_blocks() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [42, 15, 10, 120, 4, 7, 6];
+
+    unsafe {
+        blocks(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_blocks(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
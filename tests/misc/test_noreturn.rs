@@ -0,0 +1,26 @@
+extern crate libc;
+
+use noreturn::rust_noreturn_calls;
+use self::libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn noreturn_calls(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 10;
+
+pub fn test_noreturn() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [5, -3, 10, 20, 30, 1, -1, 0, 1, 1];
+
+    unsafe {
+        noreturn_calls(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_noreturn_calls(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
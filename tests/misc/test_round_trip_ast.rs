@@ -0,0 +1,26 @@
+extern crate libc;
+
+use round_trip_ast::rust_round_trip;
+use self::libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn round_trip(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 6;
+
+pub fn test_round_trip_ast() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [7, 5, 113, 15, 13, 8];
+
+    unsafe {
+        round_trip(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_round_trip(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
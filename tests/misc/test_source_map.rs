@@ -0,0 +1,96 @@
+extern crate libc;
+
+use source_map::rust_source_map;
+use self::libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn source_map(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 1;
+
+const TRANSLATION: &str = include_str!("source_map.rs");
+const SOURCE_MAP: &str = include_str!("source_map.srcmap.json");
+
+/// One entry of the `.srcmap.json` sidecar
+struct Entry {
+    rust_lines: (usize, usize),
+    c_file: String,
+    c_line: u64,
+    kind: String,
+}
+
+/// Read the entries out of the pretty-printed JSON, which has one field per line
+fn entries() -> Vec<Entry> {
+    let mut entries = vec![];
+    let mut entry = Entry { rust_lines: (0, 0), c_file: String::new(), c_line: 0, kind: String::new() };
+    for line in SOURCE_MAP.lines() {
+        let mut field = line.trim().trim_end_matches(',').splitn(2, ": ");
+        let (key, value) = match (field.next(), field.next()) {
+            (Some(key), Some(value)) => (key.trim_matches('"'), value.trim_matches('"')),
+            _ => continue,
+        };
+        match key {
+            "rust_start_line" => entry.rust_lines.0 = value.parse().unwrap(),
+            "rust_end_line" => entry.rust_lines.1 = value.parse().unwrap(),
+            "c_file" => entry.c_file = value.to_owned(),
+            "c_line" => entry.c_line = value.parse().unwrap(),
+            "kind" => {
+                entry.kind = value.to_owned();
+                entries.push(entry);
+                entry = Entry { rust_lines: (0, 0), c_file: String::new(), c_line: 0, kind: String::new() };
+            }
+            _ => {}
+        }
+    }
+    entries
+}
+
+/// The Rust lines the entry for a C line of the given kind maps to
+fn rust_lines(entries: &[Entry], c_line: u64, kind: &str) -> Vec<&'static str> {
+    let entry = entries.iter().find(|e| e.c_line == c_line && e.kind == kind)
+        .unwrap_or_else(|| panic!("no {} entry for C line {}", kind, c_line));
+    let (start, end) = entry.rust_lines;
+    assert!(1 <= start && start <= end);
+    TRANSLATION.lines().skip(start - 1).take(end - start + 1).collect()
+}
+
+pub fn test_source_map() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [3];
+
+    unsafe {
+        source_map(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_source_map(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
+
+pub fn test_source_map_sidecar() {
+    let entries = entries();
+    assert!(entries.iter().all(|e| e.c_file.ends_with("source_map.c")));
+    // The markers are stripped from the translation
+    assert!(!TRANSLATION.contains("c2rust-source-map"));
+
+    // A function maps to its whole item, ending with its closing brace
+    let add = rust_lines(&entries, 3, "decl");
+    assert!(add.iter().any(|line| line.contains("fn rust_source_map_add(")));
+    assert_eq!(add.last().unwrap().trim(), "}");
+
+    let main = rust_lines(&entries, 8, "decl");
+    assert!(main.iter().any(|line| line.contains("fn rust_source_map(")));
+    assert!(!main.iter().any(|line| line.contains("fn rust_source_map_add(")));
+
+    // Statements map to the lines translated from them
+    let decl = rust_lines(&entries, 4, "stmt");
+    assert!(decl.iter().any(|line| line.contains("sum")));
+    let ret = rust_lines(&entries, 5, "stmt");
+    assert!(ret.iter().any(|line| line.contains("return sum")));
+    let call = rust_lines(&entries, 11, "stmt");
+    assert!(call.iter().any(|line| line.contains("rust_source_map_add(1")));
+}
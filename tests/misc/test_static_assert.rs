@@ -0,0 +1,26 @@
+extern crate libc;
+
+use static_assert::rust_static_assertions;
+use self::libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn static_assertions(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 2;
+
+pub fn test_static_assert() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [3, 8];
+
+    unsafe {
+        static_assertions(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_static_assertions(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
@@ -0,0 +1,26 @@
+extern crate libc;
+
+use wide_strings::rust_wide_strings;
+use self::libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn wide_strings(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 10;
+
+pub fn test_wide_strings() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [119, 0, 955, 33, 128512, 98, 233, 195, 363, 3];
+
+    unsafe {
+        wide_strings(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_wide_strings(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
@@ -0,0 +1,23 @@
+#include <stddef.h>
+#include <uchar.h>
+
+static const wchar_t greeting[] = L"héllo";
+
+void wide_strings(const unsigned sz, int buf[const]) {
+    const wchar_t *w = L"wide";
+    const char16_t *u = u"λ!";
+    const char32_t *U = U"\U0001F600";
+    wchar_t padded[6] = L"ab";
+    const char *u8s = u8"é";
+
+    buf[0] = w[0];
+    buf[1] = w[4];
+    buf[2] = u[0];
+    buf[3] = u[1];
+    buf[4] = U[0];
+    buf[5] = padded[1] + padded[5];
+    buf[6] = greeting[1];
+    buf[7] = (unsigned char)u8s[0];
+    buf[8] = L'x' + u'y' + U'z';
+    buf[9] = sizeof(L"ab") / sizeof(wchar_t);
+}
@@ -0,0 +1,51 @@
+//! nonnull_references
+
+#include <stddef.h>
+
+struct point {
+    int x;
+    int y;
+};
+
+static int manhattan(const struct point * _Nonnull p) {
+    return p->x + p->y;
+}
+
+__attribute__((nonnull(1, 2)))
+static void swap(int * restrict a, int * restrict b) {
+    int t = *a;
+    *a = *b;
+    *b = t;
+}
+
+static int value_or(const int * _Nullable p, int fallback) {
+    if (!p)
+        return fallback;
+    return *p;
+}
+
+static void store(int * restrict _Nonnull out, int v) {
+    *out = v;
+}
+
+static int first(const int * _Nonnull p) {
+    return *p;
+}
+
+void nonnull_params(const unsigned sz, int buf[const]) {
+    int values[4] = { 1, 2, 3, 4 };
+    struct point pt = { 3, 7 };
+    int x = 5, y = 9;
+    int stored;
+    int (*get)(const int *) = first;
+
+    buf[0] = manhattan(&pt);
+    swap(&x, &y);
+    buf[1] = x;
+    buf[2] = y;
+    buf[3] = value_or(&x, -1);
+    buf[4] = value_or(NULL, -1);
+    store(&stored, 7);
+    buf[5] = stored * 2;
+    buf[6] = get(&values[2]);
+}
@@ -0,0 +1,26 @@
+extern crate libc;
+
+use nonnull_params::rust_nonnull_params;
+use self::libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn nonnull_params(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 7;
+
+pub fn test_nonnull_params() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [10, 9, 5, 9, -1, 14, 3];
+
+    unsafe {
+        nonnull_params(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_nonnull_params(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
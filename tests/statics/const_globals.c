@@ -0,0 +1,40 @@
+typedef int (*binop)(int, int);
+
+static int add(int a, int b) { return a + b; }
+static int sub(int a, int b) { return a - b; }
+
+// Internal scalar whose address is never taken
+static const int offset = 3;
+
+// External scalar whose address is taken
+const int scale = 2;
+
+// Pointer table: needs to be made Sync
+static const char *const names[] = { "zero", "one", "two" };
+
+// Function pointer table
+static const binop ops[2] = { add, sub };
+
+static const struct {
+    int id;
+    const char *label;
+} entries[2] = { { 10, "ten" }, { 20, "twenty" } };
+
+static int read_through(const int *p) {
+    return *p;
+}
+
+void entry3(const unsigned buffer_size, int buffer[]) {
+    if (buffer_size < 8) return;
+
+    static const int local_limit = 7;
+
+    buffer[0] = offset;
+    buffer[1] = read_through(&scale);
+    buffer[2] = names[1][0];
+    buffer[3] = ops[0](offset, scale);
+    buffer[4] = ops[1](offset, scale);
+    buffer[5] = entries[1].id;
+    buffer[6] = entries[0].label[1];
+    buffer[7] = local_limit;
+}
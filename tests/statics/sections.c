@@ -31,19 +31,25 @@ int *dont_section_d = &dont_section_d;
 Foo dont_section_foo;
 unsigned dont_section_me = 1 + 1;
 Bar bar;
+int dont_section_foo_array[3] = {1, 2, 3};
 
-// These should be initialized via sections:
+// These are evaluated at compile time, using const wrapping arithmetic:
 unsigned section_me = -1U;
 int section_me2 = NULL;
 unsigned section_me3 = 1U + 2U;
 unsigned section_me4 = 1 + 1U;
 unsigned section_me5 = 1U + 1;
 Foo section_foo_b_field = {1, -1U, 1.2};
-const unsigned int section_num_params = sizeof(params) / sizeof(params[0]);
-const size_t if_expr = sizeof(size_t) == 4 ? 30 : 31;
+unsigned section_me6 = 2U - 3U;
+unsigned section_me7 = 0x80000001U * 2U;
+
+// These should be initialized via sections:
 stat s = {&bar.bar};
 stat2 s2 = {&bar.bar};
 static stat3 selfref = { NULL, &selfref.first };
+int *element_ptr = &dont_section_foo_array[1];
+const unsigned int section_num_params = sizeof(params) / sizeof(params[0]);
+const size_t if_expr = sizeof(size_t) == 4 ? 30 : 31;
 
 size_t fn_scoped_static_init(void) {
     static size_t sectioned_scoped_init = &section_me;
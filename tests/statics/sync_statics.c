@@ -0,0 +1,16 @@
+//! reorganize_definitions
+
+#include "sync_statics.h"
+
+const char *const words[3] = { "alpha", "beta", "gamma" };
+
+const struct greeting greetings[2] = { { 1, "hello" }, { 2, "bonjour" } };
+
+void entry4(const unsigned buffer_size, int buffer[]) {
+    if (buffer_size < 4) return;
+
+    buffer[0] = words[0][0];
+    buffer[1] = words[2][1];
+    buffer[2] = greetings[1].id;
+    buffer[3] = greetings[1].text[3];
+}
@@ -0,0 +1,8 @@
+struct greeting {
+    int id;
+    const char *text;
+};
+
+// Defined in sync_statics.c, but first declared here, so their imports go in this header's submodule
+extern const char *const words[3];
+extern const struct greeting greetings[2];
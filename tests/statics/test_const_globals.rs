@@ -0,0 +1,26 @@
+extern crate libc;
+
+use const_globals::rust_entry3;
+use self::libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn entry3(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 8;
+
+pub fn test_const_globals() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [3, 2, 111, 5, 1, 20, 101, 7];
+
+    unsafe {
+        entry3(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_entry3(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
@@ -13,8 +13,12 @@ pub fn test_sectioned_statics() {
         assert_eq!(rust_section_foo_b_field.a, 1);
         assert_eq!(rust_section_foo_b_field.b, -1);
         assert_eq!(rust_section_foo_b_field.c, 1.2);
+        assert_eq!(rust_section_me6, c_uint::max_value());
+        assert_eq!(rust_section_me7, 2u32);
         assert_eq!(rust_section_num_params, 2);
         assert!(rust_if_expr == 30 || rust_if_expr == 31);
+        assert_eq!(*rust_element_ptr, 2);
+        assert_eq!(rust_s.f, &mut rust_bar.bar as *mut *mut i32);
 
         // There's not really a way to test the function scoped static
         // directly since it's (rightly) private. But this does prove
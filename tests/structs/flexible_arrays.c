@@ -0,0 +1,48 @@
+#include <stdlib.h>
+
+struct int_buffer {
+    unsigned len;
+    int data[];
+};
+
+struct chars {
+    char tag;
+    char text[];
+};
+
+static struct int_buffer *make_buffer(unsigned len) {
+    struct int_buffer *buf = malloc(sizeof(struct int_buffer) + len * sizeof(int));
+    buf->len = len;
+    for (unsigned i = 0; i < len; i++)
+        buf->data[i] = (int)(i * i);
+    return buf;
+}
+
+static int sum(const struct int_buffer *buf) {
+    int total = 0;
+    for (unsigned i = 0; i < buf->len; i++)
+        total += buf->data[i];
+    return total;
+}
+
+void flexible_arrays(unsigned buffer_size, int buffer[]) {
+    int i = 0;
+
+    struct int_buffer *buf = make_buffer(5);
+    buffer[i++] = buf->len;
+    buffer[i++] = buf->data[4];
+    buffer[i++] = sum(buf);
+    free(buf);
+
+    struct chars *c = (struct chars *)malloc(sizeof(char) * 3 + sizeof(struct chars));
+    c->tag = 'x';
+    c->text[0] = 'a';
+    c->text[1] = 'b';
+    c->text[2] = 0;
+    buffer[i++] = c->tag;
+    buffer[i++] = c->text[0] + c->text[1];
+    free(c);
+
+    buffer[i++] = sizeof(struct int_buffer);
+    buffer[i++] = sizeof(struct chars);
+}
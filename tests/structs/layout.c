@@ -0,0 +1,41 @@
+//! field_offsets
+
+#include <stddef.h>
+
+struct __attribute__((packed)) packed_header {
+    char tag;
+    int length;
+    short flags;
+};
+
+struct __attribute__((aligned(16))) aligned_vec {
+    float x;
+    float y;
+    float z;
+};
+
+struct mixed {
+    char c;
+    double d;
+    short s;
+    struct packed_header header;
+};
+
+union number {
+    int i;
+    double d;
+    char bytes[12];
+};
+
+void layout_info(const unsigned buffer_size, size_t buffer[]) {
+    if (buffer_size < 8) return;
+
+    buffer[0] = sizeof(struct packed_header);
+    buffer[1] = offsetof(struct packed_header, flags);
+    buffer[2] = sizeof(struct aligned_vec);
+    buffer[3] = _Alignof(struct aligned_vec);
+    buffer[4] = sizeof(struct mixed);
+    buffer[5] = offsetof(struct mixed, header);
+    buffer[6] = sizeof(union number);
+    buffer[7] = _Alignof(union number);
+}
@@ -0,0 +1,42 @@
+extern crate libc;
+
+use flexible_arrays::{int_buffer, rust_flexible_arrays};
+use self::libc::{c_int, c_uint};
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn flexible_arrays(_: c_uint, _: *mut c_int);
+}
+
+const BUFFER_SIZE: usize = 7;
+
+pub fn test_flexible_arrays() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+    let expected_buffer = [5, 16, 30, 120, 195, 4, 1];
+
+    unsafe {
+        flexible_arrays(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_flexible_arrays(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer, expected_buffer);
+}
+
+pub fn test_flexible_array_accessors() {
+    unsafe {
+        let buf = int_buffer::alloc_with_data(3);
+        (*buf).len = 3;
+        int_buffer::data_mut(buf, 3).copy_from_slice(&[7, 8, 9]);
+
+        assert_eq!(int_buffer::data(buf, 3), &[7, 8, 9]);
+        assert_eq!((*buf).data.as_ptr().offset(2).read(), 9);
+
+        libc::free(buf as *mut libc::c_void);
+
+        assert!(int_buffer::alloc_with_data(usize::max_value()).is_null());
+        assert!(int_buffer::alloc_with_data(usize::max_value() / 4).is_null());
+    }
+}
@@ -0,0 +1,31 @@
+extern crate libc;
+
+use std::mem::{align_of, size_of};
+use layout::{aligned_vec, mixed, number, packed_header, rust_layout_info};
+use self::libc::{c_uint, size_t};
+
+#[link(name = "test")]
+extern "C" {
+    #[no_mangle]
+    fn layout_info(_: c_uint, _: *mut size_t);
+}
+
+const BUFFER_SIZE: usize = 8;
+
+pub fn test_layout() {
+    let mut buffer = [0; BUFFER_SIZE];
+    let mut rust_buffer = [0; BUFFER_SIZE];
+
+    unsafe {
+        layout_info(BUFFER_SIZE as u32, buffer.as_mut_ptr());
+        rust_layout_info(BUFFER_SIZE as u32, rust_buffer.as_mut_ptr());
+    }
+
+    assert_eq!(buffer, rust_buffer);
+    assert_eq!(buffer[0], size_of::<packed_header>());
+    assert_eq!(buffer[2], size_of::<aligned_vec>());
+    assert_eq!(buffer[3], align_of::<aligned_vec>());
+    assert_eq!(buffer[4], size_of::<mixed>());
+    assert_eq!(buffer[6], size_of::<number>());
+    assert_eq!(buffer[7], align_of::<number>());
+}